@@ -0,0 +1,1013 @@
+use crate::bindings::wasi::http::types::IncomingBody;
+use anyhow::Result;
+use bytes::Bytes;
+
+pub trait FromBody: Sized {
+    /// Media types accepted in the request `Content-Type`, empty accepts anything.
+    const MEDIA_TYPES: &'static [&'static str] = &[];
+
+    fn from_data(data: Bytes) -> Result<Self>;
+
+    /// Like `from_data`, for extractors whose parsing depends on the request `Content-Type`.
+    fn from_content(data: Bytes, _content_type: Option<&str>) -> Result<Self> {
+        Self::from_data(data)
+    }
+
+    /// `content_length` is the declared `Content-Length`, used to size the
+    /// read buffer up front.
+    fn from_body(
+        body: IncomingBody,
+        content_type: Option<&str>,
+        content_length: Option<usize>,
+        limit: Option<usize>,
+    ) -> Result<Self> {
+        let capacity = preallocation(content_length, limit);
+        Self::from_content(body.read_with_capacity(limit, capacity)?, content_type)
+    }
+}
+
+/// Response body chunks, written one by one to the outgoing stream.
+pub type BodyChunks = Box<dyn Iterator<Item = Result<Bytes>>>;
+
+pub fn single_chunk(data: Bytes) -> BodyChunks {
+    Box::new(std::iter::once(Ok(data)))
+}
+
+// Without a body limit, a bogus Content-Length must not reserve gigabytes
+const MAX_PREALLOCATION: usize = 1024 * 1024;
+
+/// Read buffer capacity for a body declaring `content_length`, capped by the
+/// body limit.
+fn preallocation(content_length: Option<usize>, limit: Option<usize>) -> usize {
+    let cap = limit.unwrap_or(MAX_PREALLOCATION);
+    content_length.map_or(0, |length| length.min(cap))
+}
+
+pub trait IntoBody: Sized {
+    fn into_body(self) -> Result<Bytes>;
+
+    /// Chunks written by `run`, the whole body at once by default.
+    fn into_chunks(self) -> Result<BodyChunks> {
+        Ok(single_chunk(self.into_body()?))
+    }
+
+    #[allow(unused_variables)]
+    fn extend_response_parts(&self, parts: &mut http::response::Parts) {}
+}
+
+impl FromBody for IncomingBody {
+    fn from_data(_: Bytes) -> Result<Self> {
+        unimplemented!("Should never be called")
+    }
+
+    fn from_body(
+        body: IncomingBody,
+        _: Option<&str>,
+        _: Option<usize>,
+        _: Option<usize>,
+    ) -> Result<Self> {
+        Ok(body)
+    }
+}
+
+impl FromBody for Bytes {
+    fn from_data(data: Bytes) -> Result<Self> {
+        Ok(data)
+    }
+}
+
+impl IntoBody for Bytes {
+    fn into_body(self) -> Result<Bytes> {
+        Ok(self)
+    }
+}
+
+impl FromBody for () {
+    fn from_data(_: Bytes) -> Result<Self> {
+        Ok(())
+    }
+
+    fn from_body(
+        _: IncomingBody,
+        _: Option<&str>,
+        _: Option<usize>,
+        _: Option<usize>,
+    ) -> Result<Self> {
+        Ok(())
+    }
+}
+
+impl IntoBody for () {
+    fn into_body(self) -> Result<Bytes> {
+        Ok(Bytes::new())
+    }
+}
+
+impl FromBody for String {
+    fn from_data(data: Bytes) -> Result<Self> {
+        String::from_utf8(data.into()).map_err(Into::into)
+    }
+}
+
+impl IntoBody for String {
+    fn into_body(self) -> Result<Bytes> {
+        Ok(Bytes::from(self))
+    }
+}
+
+impl<T: FromBody> FromBody for Option<T> {
+    const MEDIA_TYPES: &'static [&'static str] = T::MEDIA_TYPES;
+
+    fn from_data(data: Bytes) -> Result<Self> {
+        Self::from_content(data, None)
+    }
+
+    fn from_content(data: Bytes, content_type: Option<&str>) -> Result<Self> {
+        if data.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(T::from_content(data, content_type)?))
+        }
+    }
+}
+
+impl<T: IntoBody> IntoBody for Option<T> {
+    fn into_body(self) -> Result<Bytes> {
+        match self {
+            Some(value) => value.into_body(),
+            None => Ok(Bytes::new()),
+        }
+    }
+
+    fn into_chunks(self) -> Result<BodyChunks> {
+        match self {
+            Some(value) => value.into_chunks(),
+            None => Ok(single_chunk(Bytes::new())),
+        }
+    }
+
+    fn extend_response_parts(&self, parts: &mut http::response::Parts) {
+        if let Some(value) = self {
+            value.extend_response_parts(parts);
+        }
+    }
+}
+
+// Errors
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayloadTooLarge {
+    pub limit: usize,
+}
+
+impl std::fmt::Display for PayloadTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Request body exceeds the {} bytes limit", self.limit)
+    }
+}
+
+impl std::error::Error for PayloadTooLarge {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedMediaType {
+    pub expected: &'static [&'static str],
+}
+
+impl std::fmt::Display for UnsupportedMediaType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let expected = self
+            .expected
+            .iter()
+            .map(|media_type| format!("'{media_type}'"))
+            .collect::<Vec<_>>()
+            .join(" or ");
+        write!(f, "Unsupported content type, expected {expected}")
+    }
+}
+
+impl std::error::Error for UnsupportedMediaType {}
+
+/// Checks a `Content-Type` value against an expected media type, ignoring
+/// parameters and accepting structured syntax suffixes (`application/x+json`).
+pub fn matches_media_type(content_type: &str, expected: &str) -> bool {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    if essence == expected {
+        return true;
+    }
+
+    match (essence.split_once('/'), expected.split_once('/')) {
+        (Some((kind, subtype)), Some((expected_kind, expected_subtype))) => {
+            kind == expected_kind
+                && subtype
+                    .strip_suffix(expected_subtype)
+                    .is_some_and(|prefix| prefix.ends_with('+'))
+        }
+        _ => false,
+    }
+}
+
+pub fn matches_any_media_type(content_type: &str, expected: &[&str]) -> bool {
+    expected
+        .iter()
+        .any(|expected| matches_media_type(content_type, expected))
+}
+
+// Data types
+
+pub use super::multipart::Multipart;
+
+#[derive(Debug, Clone)]
+pub struct Json<T>(pub T);
+
+impl<T: serde::de::DeserializeOwned> FromBody for Json<T> {
+    const MEDIA_TYPES: &'static [&'static str] = &["application/json"];
+
+    fn from_data(bytes: Bytes) -> Result<Self> {
+        let data = serde_json::from_slice(&bytes)?;
+        Ok(Self(data))
+    }
+}
+
+impl<T> AsRef<T> for Json<T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: serde::Serialize> IntoBody for Json<T> {
+    fn into_body(self) -> Result<Bytes> {
+        use bytes::{BufMut, BytesMut};
+
+        let mut buf = BytesMut::with_capacity(128).writer();
+        serde_json::to_writer(&mut buf, &self.0)?;
+        Ok(buf.into_inner().freeze())
+    }
+
+    fn extend_response_parts(&self, parts: &mut http::response::Parts) {
+        parts
+            .headers
+            .entry(http::header::CONTENT_TYPE)
+            .or_insert(http::HeaderValue::from_static("application/json"));
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Form<T>(pub T);
+
+impl<T: serde::de::DeserializeOwned> FromBody for Form<T> {
+    const MEDIA_TYPES: &'static [&'static str] = &["application/x-www-form-urlencoded"];
+
+    fn from_data(bytes: Bytes) -> Result<Self> {
+        let data = serde_urlencoded::from_bytes(&bytes)?;
+        Ok(Self(data))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Yaml<T>(pub T);
+
+impl<T: serde::de::DeserializeOwned> FromBody for Yaml<T> {
+    const MEDIA_TYPES: &'static [&'static str] =
+        &["application/yaml", "application/x-yaml", "text/yaml"];
+
+    fn from_data(bytes: Bytes) -> Result<Self> {
+        let data = serde_yaml::from_slice(&bytes)?;
+        Ok(Self(data))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MsgPack<T>(pub T);
+
+impl<T: serde::de::DeserializeOwned> FromBody for MsgPack<T> {
+    const MEDIA_TYPES: &'static [&'static str] = &["application/msgpack", "application/x-msgpack"];
+
+    fn from_data(bytes: Bytes) -> Result<Self> {
+        let data = rmp_serde::from_slice(&bytes)?;
+        Ok(Self(data))
+    }
+}
+
+impl<T: serde::Serialize> IntoBody for MsgPack<T> {
+    fn into_body(self) -> Result<Bytes> {
+        // Named fields keep the output readable by non-Rust decoders
+        Ok(rmp_serde::to_vec_named(&self.0)?.into())
+    }
+
+    fn extend_response_parts(&self, parts: &mut http::response::Parts) {
+        parts
+            .headers
+            .entry(http::header::CONTENT_TYPE)
+            .or_insert(http::HeaderValue::from_static("application/msgpack"));
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Proto<T>(pub T);
+
+impl<T: prost::Message + Default> FromBody for Proto<T> {
+    const MEDIA_TYPES: &'static [&'static str] =
+        &["application/x-protobuf", "application/protobuf"];
+
+    fn from_data(bytes: Bytes) -> Result<Self> {
+        let data = T::decode(bytes)?;
+        Ok(Self(data))
+    }
+}
+
+impl<T: prost::Message> IntoBody for Proto<T> {
+    fn into_body(self) -> Result<Bytes> {
+        Ok(self.0.encode_to_vec().into())
+    }
+
+    fn extend_response_parts(&self, parts: &mut http::response::Parts) {
+        parts
+            .headers
+            .entry(http::header::CONTENT_TYPE)
+            .or_insert(http::HeaderValue::from_static("application/x-protobuf"));
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RawJson<T>(pub T);
+
+impl<T: Into<Bytes>> IntoBody for RawJson<T> {
+    fn into_body(self) -> Result<Bytes> {
+        Ok(self.0.into())
+    }
+
+    fn extend_response_parts(&self, parts: &mut http::response::Parts) {
+        Json(()).extend_response_parts(parts)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Html<T>(pub T);
+
+impl<T: Into<Bytes>> IntoBody for Html<T> {
+    fn into_body(self) -> Result<Bytes> {
+        Ok(self.0.into())
+    }
+
+    fn extend_response_parts(&self, parts: &mut http::response::Parts) {
+        parts
+            .headers
+            .entry(http::header::CONTENT_TYPE)
+            .or_insert(http::HeaderValue::from_static("text/html; charset=utf-8"));
+    }
+}
+
+/// Body produced chunk by chunk, `run` writes each chunk as soon as it is
+/// produced instead of holding the whole response in memory.
+#[derive(Debug, Clone)]
+pub struct Stream<I>(pub I);
+
+impl<I> IntoBody for Stream<I>
+where
+    I: IntoIterator<Item = Result<Bytes>>,
+    I::IntoIter: 'static,
+{
+    fn into_body(self) -> Result<Bytes> {
+        let mut buf = bytes::BytesMut::new();
+        for chunk in self.0 {
+            buf.extend_from_slice(&chunk?);
+        }
+        Ok(buf.freeze())
+    }
+
+    fn into_chunks(self) -> Result<BodyChunks> {
+        Ok(Box::new(self.0.into_iter()))
+    }
+}
+
+// Status wrappers, they set the response status on top of the wrapped body
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoContent;
+
+impl IntoBody for NoContent {
+    fn into_body(self) -> Result<Bytes> {
+        Ok(Bytes::new())
+    }
+
+    fn extend_response_parts(&self, parts: &mut http::response::Parts) {
+        parts.status = http::StatusCode::NO_CONTENT;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Created<T> {
+    pub location: http::HeaderValue,
+    pub body: T,
+}
+
+impl<T> Created<T> {
+    pub fn new(location: &str, body: T) -> Result<Self> {
+        Ok(Self {
+            location: location.parse()?,
+            body,
+        })
+    }
+}
+
+impl<T: IntoBody> IntoBody for Created<T> {
+    fn into_body(self) -> Result<Bytes> {
+        self.body.into_body()
+    }
+
+    fn into_chunks(self) -> Result<BodyChunks> {
+        self.body.into_chunks()
+    }
+
+    fn extend_response_parts(&self, parts: &mut http::response::Parts) {
+        self.body.extend_response_parts(parts);
+        parts.status = http::StatusCode::CREATED;
+        parts
+            .headers
+            .insert(http::header::LOCATION, self.location.clone());
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Accepted<T>(pub T);
+
+impl<T: IntoBody> IntoBody for Accepted<T> {
+    fn into_body(self) -> Result<Bytes> {
+        self.0.into_body()
+    }
+
+    fn into_chunks(self) -> Result<BodyChunks> {
+        self.0.into_chunks()
+    }
+
+    fn extend_response_parts(&self, parts: &mut http::response::Parts) {
+        self.0.extend_response_parts(parts);
+        parts.status = http::StatusCode::ACCEPTED;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Redirect {
+    pub status: http::StatusCode,
+    pub location: http::HeaderValue,
+}
+
+impl Redirect {
+    /// 301 Moved Permanently
+    pub fn permanent(location: &str) -> Result<Self> {
+        Self::with_status(http::StatusCode::MOVED_PERMANENTLY, location)
+    }
+
+    /// 302 Found
+    pub fn to(location: &str) -> Result<Self> {
+        Self::with_status(http::StatusCode::FOUND, location)
+    }
+
+    /// 307 Temporary Redirect, the method and body are kept by the client
+    pub fn temporary(location: &str) -> Result<Self> {
+        Self::with_status(http::StatusCode::TEMPORARY_REDIRECT, location)
+    }
+
+    fn with_status(status: http::StatusCode, location: &str) -> Result<Self> {
+        Ok(Self {
+            status,
+            location: location.parse()?,
+        })
+    }
+}
+
+impl IntoBody for Redirect {
+    fn into_body(self) -> Result<Bytes> {
+        Ok(Bytes::new())
+    }
+
+    fn extend_response_parts(&self, parts: &mut http::response::Parts) {
+        parts.status = self.status;
+        parts
+            .headers
+            .insert(http::header::LOCATION, self.location.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_payload_too_large_display() {
+        let err = PayloadTooLarge { limit: 1024 };
+        assert_eq!(err.to_string(), "Request body exceeds the 1024 bytes limit");
+    }
+
+    #[test]
+    fn test_unsupported_media_type_display() {
+        let err = UnsupportedMediaType {
+            expected: &["application/json"],
+        };
+        assert_eq!(
+            err.to_string(),
+            "Unsupported content type, expected 'application/json'"
+        );
+
+        let err = UnsupportedMediaType {
+            expected: &["application/json", "text/plain"],
+        };
+        assert_eq!(
+            err.to_string(),
+            "Unsupported content type, expected 'application/json' or 'text/plain'"
+        );
+    }
+
+    #[test]
+    fn test_matches_media_type() {
+        assert!(matches_media_type("application/json", "application/json"));
+        assert!(matches_media_type(
+            "Application/JSON; charset=utf-8",
+            "application/json"
+        ));
+        assert!(matches_media_type(
+            "application/cloudevents+json",
+            "application/json"
+        ));
+        assert!(!matches_media_type("text/plain", "application/json"));
+        assert!(!matches_media_type("application/xjson", "application/json"));
+        assert!(!matches_media_type("", "application/json"));
+    }
+
+    #[test]
+    fn test_media_types() {
+        assert_eq!(Json::<()>::MEDIA_TYPES, ["application/json"]);
+        assert_eq!(Option::<Json<()>>::MEDIA_TYPES, ["application/json"]);
+        assert_eq!(
+            Form::<()>::MEDIA_TYPES,
+            ["application/x-www-form-urlencoded"]
+        );
+        assert!(String::MEDIA_TYPES.is_empty());
+    }
+
+    #[test]
+    fn test_form_from_data() {
+        #[derive(serde::Deserialize, PartialEq, Debug)]
+        struct Test {
+            message: String,
+        }
+        let data = Bytes::from("message=hello+world%21");
+        let form = Form::<Test>::from_data(data).unwrap();
+        assert_eq!(form.0.message, "hello world!");
+    }
+
+    #[test]
+    fn test_yaml_from_data() {
+        #[derive(serde::Deserialize, PartialEq, Debug)]
+        struct Test {
+            message: String,
+            tags: Vec<String>,
+        }
+        let data = Bytes::from("message: build passed\ntags:\n  - ci\n  - main\n");
+        let yaml = Yaml::<Test>::from_data(data).unwrap();
+        assert_eq!(
+            yaml.0,
+            Test {
+                message: "build passed".to_string(),
+                tags: vec!["ci".to_string(), "main".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_yaml_from_data_invalid() {
+        let data = Bytes::from("message: [unclosed");
+        assert!(Yaml::<serde_json::Value>::from_data(data).is_err());
+    }
+
+    #[test]
+    fn test_msgpack_from_data_and_into_body() {
+        #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+        struct Test {
+            a: i32,
+        }
+        let obj = Test { a: 42 };
+        let body = MsgPack(Test { a: 42 }).into_body().unwrap();
+        let decoded = MsgPack::<Test>::from_data(body).unwrap();
+        assert_eq!(decoded.0, obj);
+    }
+
+    #[test]
+    fn test_msgpack_into_body_uses_named_fields() {
+        #[derive(serde::Serialize)]
+        struct Test {
+            message: String,
+        }
+        let body = MsgPack(Test {
+            message: "hi".to_string(),
+        })
+        .into_body()
+        .unwrap();
+        let decoded = MsgPack::<serde_json::Value>::from_data(body).unwrap();
+        assert_eq!(decoded.0, serde_json::json!({ "message": "hi" }));
+    }
+
+    #[test]
+    fn test_msgpack_from_data_invalid() {
+        let data = Bytes::from_static(&[0xc1]);
+        assert!(MsgPack::<serde_json::Value>::from_data(data).is_err());
+    }
+
+    #[test]
+    fn test_msgpack_extend_response_parts_sets_content_type() {
+        let msgpack = MsgPack(1);
+        let (mut parts, _) = http::response::Response::new("ok").into_parts();
+        msgpack.extend_response_parts(&mut parts);
+        let content_type = parts.headers.get(http::header::CONTENT_TYPE).unwrap();
+        assert_eq!(content_type, "application/msgpack");
+    }
+
+    #[test]
+    fn test_proto_from_data_and_into_body() {
+        #[derive(Clone, PartialEq, prost::Message)]
+        struct Test {
+            #[prost(int32, tag = "1")]
+            a: i32,
+        }
+        let body = Proto(Test { a: 42 }).into_body().unwrap();
+        let decoded = Proto::<Test>::from_data(body).unwrap();
+        assert_eq!(decoded.0, Test { a: 42 });
+    }
+
+    #[test]
+    fn test_proto_from_data_invalid() {
+        #[derive(Clone, PartialEq, prost::Message)]
+        struct Test {
+            #[prost(string, tag = "1")]
+            a: String,
+        }
+        let data = Bytes::from_static(&[0x0a, 0x05, b'a']);
+        assert!(Proto::<Test>::from_data(data).is_err());
+    }
+
+    #[test]
+    fn test_matches_any_media_type() {
+        assert!(matches_any_media_type(
+            "application/x-yaml",
+            Yaml::<()>::MEDIA_TYPES
+        ));
+        assert!(!matches_any_media_type(
+            "application/json",
+            Yaml::<()>::MEDIA_TYPES
+        ));
+        assert!(!matches_any_media_type("application/json", &[]));
+    }
+
+    #[test]
+    fn test_form_from_data_invalid() {
+        #[derive(serde::Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Test {
+            message: String,
+        }
+        let data = Bytes::from("other=value");
+        assert!(Form::<Test>::from_data(data).is_err());
+    }
+
+    #[test]
+    fn test_bytes_from_data() {
+        let data = Bytes::from("hello");
+        let result = Bytes::from_data(data.clone()).unwrap();
+        assert_eq!(result, data);
+    }
+
+    #[test]
+    fn test_bytes_into_body() {
+        let data = Bytes::from("world");
+        let result = data.clone().into_body().unwrap();
+        assert_eq!(result, data);
+    }
+
+    #[test]
+    fn test_unit_from_data() {
+        let data = Bytes::from("ignored");
+        assert!(<()>::from_data(data).is_ok());
+    }
+
+    #[test]
+    fn test_unit_into_body() {
+        let result = ().into_body().unwrap();
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_string_from_data() {
+        let data = Bytes::from("hello world");
+        let result = String::from_data(data).unwrap();
+        assert_eq!(result, "hello world");
+    }
+
+    #[test]
+    fn test_string_into_body() {
+        let s = String::from("abc");
+        let result = s.into_body().unwrap();
+        assert_eq!(result, Bytes::from("abc"));
+    }
+
+    #[test]
+    fn test_option_from_data_some() {
+        let data = Bytes::from("foo");
+        let result: Option<String> = Option::<String>::from_data(data).unwrap();
+        assert_eq!(result, Some("foo".to_string()));
+    }
+
+    #[test]
+    fn test_option_from_data_none() {
+        let data = Bytes::new();
+        let result: Option<String> = Option::<String>::from_data(data).unwrap();
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn test_option_into_body_some() {
+        let opt = Some(Bytes::from("bar"));
+        let result = opt.into_body().unwrap();
+        assert_eq!(result, Bytes::from("bar"));
+    }
+
+    #[test]
+    fn test_option_into_body_none() {
+        let opt: Option<Bytes> = None;
+        let result = opt.into_body().unwrap();
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_json_from_data_and_into_body() {
+        #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+        struct Test {
+            a: i32,
+        }
+        let obj = Test { a: 42 };
+        let json_bytes = serde_json::to_vec(&obj).unwrap();
+        let json = Json::<Test>::from_data(Bytes::from(json_bytes)).unwrap();
+        assert_eq!(json.0, obj);
+
+        let body = json.into_body().unwrap();
+        let decoded: Test = serde_json::from_slice(&body).unwrap();
+        assert_eq!(decoded, obj);
+    }
+
+    #[test]
+    fn test_raw_json_into_body() {
+        let raw = RawJson(Bytes::from("{\"x\":1}"));
+        let result = raw.into_body().unwrap();
+        assert_eq!(result, Bytes::from("{\"x\":1}"));
+    }
+
+    #[test]
+    fn test_html_into_body() {
+        let html = Html(Bytes::from("<h1>hi</h1>"));
+        let result = html.into_body().unwrap();
+        assert_eq!(result, Bytes::from("<h1>hi</h1>"));
+    }
+
+    #[test]
+    fn test_json_extend_response_parts_sets_content_type() {
+        #[derive(serde::Serialize)]
+        struct Dummy {
+            x: i32,
+        }
+        let json = Json(Dummy { x: 1 });
+        let (mut parts, _) = http::response::Response::new("ok").into_parts();
+        json.extend_response_parts(&mut parts);
+        let content_type = parts.headers.get(http::header::CONTENT_TYPE).unwrap();
+        assert_eq!(content_type, "application/json");
+    }
+
+    #[test]
+    fn test_html_extend_response_parts_sets_content_type() {
+        let html = Html(Bytes::from("<p>test</p>"));
+        let (mut parts, _) = http::response::Response::new("ok").into_parts();
+        html.extend_response_parts(&mut parts);
+        let content_type = parts.headers.get(http::header::CONTENT_TYPE).unwrap();
+        assert_eq!(content_type, "text/html; charset=utf-8");
+    }
+
+    #[test]
+    fn test_raw_json_extend_response_parts_sets_content_type() {
+        let raw = RawJson(Bytes::from("{}"));
+        let (mut parts, _) = http::response::Response::new("ok").into_parts();
+        raw.extend_response_parts(&mut parts);
+        let content_type = parts.headers.get(http::header::CONTENT_TYPE).unwrap();
+        assert_eq!(content_type, "application/json");
+    }
+
+    #[test]
+    fn test_option_extend_response_parts_some() {
+        #[derive(serde::Serialize)]
+        struct Dummy {
+            x: i32,
+        }
+        let json = Some(Json(Dummy { x: 2 }));
+        let (mut parts, _) = http::response::Response::new("ok").into_parts();
+        json.extend_response_parts(&mut parts);
+        let content_type = parts.headers.get(http::header::CONTENT_TYPE).unwrap();
+        assert_eq!(content_type, "application/json");
+    }
+
+    #[test]
+    fn test_option_extend_response_parts_none() {
+        let json: Option<Json<()>> = None;
+        let (mut parts, _) = http::response::Response::new("ok").into_parts();
+        json.extend_response_parts(&mut parts);
+        assert!(parts.headers.get(http::header::CONTENT_TYPE).is_none());
+    }
+
+    #[test]
+    fn test_bytes_extend_response_parts_does_nothing() {
+        let bytes = Bytes::from("data");
+        let (mut parts, _) = http::response::Response::new("ok").into_parts();
+        bytes.extend_response_parts(&mut parts);
+        assert!(parts.headers.get(http::header::CONTENT_TYPE).is_none());
+    }
+
+    #[test]
+    fn test_unit_extend_response_parts_does_nothing() {
+        let (mut parts, _) = http::response::Response::new("ok").into_parts();
+        ().extend_response_parts(&mut parts);
+        assert!(parts.headers.get(http::header::CONTENT_TYPE).is_none());
+    }
+
+    #[test]
+    fn test_string_extend_response_parts_does_nothing() {
+        let s = String::from("abc");
+        let (mut parts, _) = http::response::Response::new("ok").into_parts();
+        s.extend_response_parts(&mut parts);
+        assert!(parts.headers.get(http::header::CONTENT_TYPE).is_none());
+    }
+
+    #[test]
+    fn test_json_extend_response_parts_doesnt_overwrite_existing_content_type() {
+        #[derive(serde::Serialize)]
+        struct Dummy {
+            x: i32,
+        }
+        let json = Json(Dummy { x: 1 });
+        let (mut parts, _) = http::response::Response::new("ok").into_parts();
+        parts.headers.insert(
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::from_static("text/plain"),
+        );
+        json.extend_response_parts(&mut parts);
+        let content_type = parts.headers.get(http::header::CONTENT_TYPE).unwrap();
+        // Should remain as "text/plain" since or_insert does not overwrite
+        assert_eq!(content_type, "text/plain");
+    }
+
+    #[test]
+    fn test_html_extend_response_parts_doesnt_overwrites_existing_content_type() {
+        let html = Html(Bytes::from("<p>test</p>"));
+        let (mut parts, _) = http::response::Response::new("ok").into_parts();
+        parts.headers.insert(
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::from_static("application/json"),
+        );
+        html.extend_response_parts(&mut parts);
+        let content_type = parts.headers.get(http::header::CONTENT_TYPE).unwrap();
+        // Should remain as "application/json" since or_insert does not overwrite
+        assert_eq!(content_type, "application/json");
+    }
+
+    #[test]
+    fn test_json_extend_response_parts_adds_content_type() {
+        #[derive(serde::Serialize)]
+        struct Dummy {
+            x: i32,
+        }
+        let json = Json(Dummy { x: 1 });
+        let (mut parts, _) = http::response::Response::new("ok").into_parts();
+        json.extend_response_parts(&mut parts);
+        let content_type = parts.headers.get(http::header::CONTENT_TYPE).unwrap();
+        // Should remain as "text/plain" since or_insert does not overwrite
+        assert_eq!(content_type, "application/json");
+    }
+
+    #[test]
+    fn test_html_extend_response_parts_adds_content_type() {
+        let html = Html(Bytes::from("<p>test</p>"));
+        let (mut parts, _) = http::response::Response::new("ok").into_parts();
+        html.extend_response_parts(&mut parts);
+        let content_type = parts.headers.get(http::header::CONTENT_TYPE).unwrap();
+        // Should remain as "application/json" since or_insert does not overwrite
+        assert_eq!(content_type, "text/html; charset=utf-8");
+    }
+
+    #[test]
+    fn test_no_content() {
+        let (mut parts, _) = http::response::Response::new(()).into_parts();
+        NoContent.extend_response_parts(&mut parts);
+        assert_eq!(parts.status, http::StatusCode::NO_CONTENT);
+        assert!(NoContent.into_body().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_created_sets_location() {
+        let created = Created::new("/messages/42", Json(serde_json::json!({ "id": 42 }))).unwrap();
+        let (mut parts, _) = http::response::Response::new(()).into_parts();
+        created.extend_response_parts(&mut parts);
+        assert_eq!(parts.status, http::StatusCode::CREATED);
+        assert_eq!(
+            parts.headers.get(http::header::LOCATION).unwrap(),
+            "/messages/42"
+        );
+        assert_eq!(
+            parts.headers.get(http::header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(created.into_body().unwrap(), Bytes::from(r#"{"id":42}"#));
+    }
+
+    #[test]
+    fn test_created_invalid_location() {
+        assert!(Created::new("/messages/\n", ()).is_err());
+    }
+
+    #[test]
+    fn test_accepted_keeps_body() {
+        let accepted = Accepted(Json(serde_json::json!({ "queued": true })));
+        let (mut parts, _) = http::response::Response::new(()).into_parts();
+        accepted.extend_response_parts(&mut parts);
+        assert_eq!(parts.status, http::StatusCode::ACCEPTED);
+        assert_eq!(
+            accepted.into_body().unwrap(),
+            Bytes::from(r#"{"queued":true}"#)
+        );
+    }
+
+    #[test]
+    fn test_redirect_statuses() {
+        let cases = [
+            (
+                Redirect::permanent("/docs"),
+                http::StatusCode::MOVED_PERMANENTLY,
+            ),
+            (Redirect::to("/docs"), http::StatusCode::FOUND),
+            (
+                Redirect::temporary("/docs"),
+                http::StatusCode::TEMPORARY_REDIRECT,
+            ),
+        ];
+        for (redirect, status) in cases {
+            let redirect = redirect.unwrap();
+            let (mut parts, _) = http::response::Response::new(()).into_parts();
+            redirect.extend_response_parts(&mut parts);
+            assert_eq!(parts.status, status);
+            assert_eq!(parts.headers.get(http::header::LOCATION).unwrap(), "/docs");
+            assert!(redirect.into_body().unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_redirect_invalid_location() {
+        assert!(Redirect::to("https://example.com/\n").is_err());
+    }
+
+    #[test]
+    fn test_into_chunks_default_single_chunk() {
+        let chunks: Vec<_> = Bytes::from("hello")
+            .into_chunks()
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(chunks, vec![Bytes::from("hello")]);
+    }
+
+    #[test]
+    fn test_stream_chunks() {
+        let chunks = || vec![Ok(Bytes::from("a")), Ok(Bytes::from("b"))];
+        let streamed: Vec<_> = Stream(chunks())
+            .into_chunks()
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(streamed, vec![Bytes::from("a"), Bytes::from("b")]);
+        assert_eq!(Stream(chunks()).into_body().unwrap(), Bytes::from("ab"));
+    }
+
+    #[test]
+    fn test_stream_into_body_error() {
+        let stream = Stream(vec![Ok(Bytes::from("a")), Err(anyhow::anyhow!("boom"))]);
+        assert_eq!(stream.into_body().unwrap_err().to_string(), "boom");
+    }
+
+    #[test]
+    fn test_preallocation() {
+        assert_eq!(preallocation(None, Some(1024)), 0);
+        assert_eq!(preallocation(Some(512), Some(1024)), 512);
+        // Oversized bodies are rejected anyway, never reserve past the limit
+        assert_eq!(preallocation(Some(4096), Some(1024)), 1024);
+        assert_eq!(preallocation(Some(usize::MAX), None), MAX_PREALLOCATION);
+    }
+}
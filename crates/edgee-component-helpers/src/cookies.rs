@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::Result;
+use http::{HeaderMap, HeaderValue, Request, Response};
+
+/// Cookies sent with the request, by name.
+///
+/// Values are kept as sent, only the optional surrounding quotes are
+/// removed. When a name is sent twice the first value wins, browsers send
+/// the most specific path first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Cookies(HashMap<String, String>);
+
+impl Cookies {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut cookies = HashMap::new();
+        let pairs = headers
+            .get_all(http::header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'))
+            .filter_map(|pair| pair.trim().split_once('='));
+        for (name, value) in pairs {
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(value);
+            cookies
+                .entry(name.to_string())
+                .or_insert_with(|| value.to_string());
+        }
+        Self(cookies)
+    }
+
+    pub fn from_req<B>(req: &Request<B>) -> Self {
+        Self::from_headers(req.headers())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+/// Cookie sent back in a `Set-Cookie` response header.
+///
+/// Cookies are `HttpOnly`, `Secure` and `SameSite=Lax` unless told
+/// otherwise, components are served over HTTPS and their cookies are
+/// rarely meant for scripts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetCookie {
+    pub name: String,
+    pub value: String,
+    pub path: Option<String>,
+    pub max_age: Option<Duration>,
+    pub http_only: bool,
+    pub secure: bool,
+    pub same_site: Option<SameSite>,
+}
+
+impl SetCookie {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            path: None,
+            max_age: None,
+            http_only: true,
+            secure: true,
+            same_site: Some(SameSite::Lax),
+        }
+    }
+
+    /// Expires the cookie named `name` on the client.
+    pub fn removal(name: impl Into<String>) -> Self {
+        Self::new(name, "").max_age(Duration::ZERO)
+    }
+
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: Option<SameSite>) -> Self {
+        self.same_site = same_site;
+        self
+    }
+
+    pub fn to_header_value(&self) -> Result<HeaderValue> {
+        let mut cookie = format!("{}={}", self.name, self.value);
+        if let Some(path) = &self.path {
+            cookie.push_str(&format!("; Path={path}"));
+        }
+        if let Some(max_age) = self.max_age {
+            cookie.push_str(&format!("; Max-Age={}", max_age.as_secs()));
+        }
+        if self.http_only {
+            cookie.push_str("; HttpOnly");
+        }
+        if self.secure {
+            cookie.push_str("; Secure");
+        }
+        match self.same_site {
+            Some(SameSite::Strict) => cookie.push_str("; SameSite=Strict"),
+            Some(SameSite::Lax) => cookie.push_str("; SameSite=Lax"),
+            Some(SameSite::None) => cookie.push_str("; SameSite=None"),
+            None => {}
+        }
+        HeaderValue::from_str(&cookie).map_err(|_| anyhow::anyhow!("Invalid cookie {}", self.name))
+    }
+}
+
+/// Adds `Set-Cookie` headers to a response, one per cookie.
+pub trait ResponseCookies {
+    fn set_cookie(&mut self, cookie: &SetCookie) -> Result<()>;
+}
+
+impl<T> ResponseCookies for Response<T> {
+    fn set_cookie(&mut self, cookie: &SetCookie) -> Result<()> {
+        let value = cookie.to_header_value()?;
+        self.headers_mut().append(http::header::SET_COOKIE, value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cookies_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            http::header::COOKIE,
+            HeaderValue::from_static("state=abc123; theme=\"dark\"; state=stale"),
+        );
+        headers.append(http::header::COOKIE, HeaderValue::from_static("lang=fr"));
+
+        let cookies = Cookies::from_headers(&headers);
+        assert_eq!(cookies.get("state"), Some("abc123"));
+        assert_eq!(cookies.get("theme"), Some("dark"));
+        assert_eq!(cookies.get("lang"), Some("fr"));
+        assert_eq!(cookies.get("missing"), None);
+    }
+
+    #[test]
+    fn test_set_cookie_header_value() {
+        let cookie = SetCookie::new("state", "abc123")
+            .path("/oauth")
+            .max_age(Duration::from_secs(600));
+        assert_eq!(
+            cookie.to_header_value().unwrap(),
+            "state=abc123; Path=/oauth; Max-Age=600; HttpOnly; Secure; SameSite=Lax"
+        );
+
+        let cookie = SetCookie::removal("state").same_site(None).secure(false);
+        assert_eq!(
+            cookie.to_header_value().unwrap(),
+            "state=; Max-Age=0; HttpOnly"
+        );
+
+        assert!(SetCookie::new("state", "a\nb").to_header_value().is_err());
+    }
+
+    #[test]
+    fn test_response_set_cookie() {
+        let mut res = Response::new(());
+        res.set_cookie(&SetCookie::new("a", "1")).unwrap();
+        res.set_cookie(&SetCookie::new("b", "2")).unwrap();
+
+        let values = res
+            .headers()
+            .get_all(http::header::SET_COOKIE)
+            .iter()
+            .collect::<Vec<_>>();
+        assert_eq!(values.len(), 2);
+    }
+}
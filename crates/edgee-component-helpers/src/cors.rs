@@ -0,0 +1,193 @@
+use bytes::Bytes;
+use http::header::{self, HeaderMap, HeaderValue};
+use http::{Method, Response, StatusCode};
+
+#[derive(Debug, Clone, Default)]
+pub struct Cors {
+    /// Allowed origins, `*` allows any origin.
+    pub allowed_origins: Vec<String>,
+    /// Methods advertised on preflight, defaults to the route's methods when empty.
+    pub allowed_methods: Vec<Method>,
+    pub allowed_headers: Vec<String>,
+    pub max_age: Option<u32>,
+}
+
+impl Cors {
+    fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|origin| origin == "*")
+    }
+
+    fn allows_origin(&self, origin: &str) -> bool {
+        self.allows_any_origin()
+            || self
+                .allowed_origins
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(origin))
+    }
+
+    /// Builds the answer to a preflight request. The allowed origin itself is
+    /// added by `apply`, like for any other response.
+    pub fn preflight_response(&self, route_methods: &[Method]) -> Response<Bytes> {
+        let methods = if self.allowed_methods.is_empty() {
+            route_methods
+        } else {
+            &self.allowed_methods
+        };
+        let methods = methods
+            .iter()
+            .map(Method::as_str)
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let mut builder = Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .header(header::ACCESS_CONTROL_ALLOW_METHODS, methods);
+        if !self.allowed_headers.is_empty() {
+            builder = builder.header(
+                header::ACCESS_CONTROL_ALLOW_HEADERS,
+                self.allowed_headers.join(", "),
+            );
+        }
+        if let Some(max_age) = self.max_age {
+            builder = builder.header(header::ACCESS_CONTROL_MAX_AGE, max_age);
+        }
+        builder.body(Bytes::new()).unwrap()
+    }
+
+    /// Adds `Access-Control-Allow-Origin` to a response if `origin` is allowed.
+    pub fn apply(&self, origin: &HeaderValue, headers: &mut HeaderMap) {
+        if self.allows_any_origin() {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                HeaderValue::from_static("*"),
+            );
+            return;
+        }
+
+        if origin
+            .to_str()
+            .is_ok_and(|origin| self.allows_origin(origin))
+        {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+            headers.append(header::VARY, HeaderValue::from_static("Origin"));
+        }
+    }
+}
+
+pub fn is_preflight(parts: &http::request::Parts) -> bool {
+    parts.method == Method::OPTIONS
+        && parts.headers.contains_key(header::ORIGIN)
+        && parts
+            .headers
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cors(origins: &[&str]) -> Cors {
+        Cors {
+            allowed_origins: origins.iter().map(ToString::to_string).collect(),
+            allowed_headers: vec!["Content-Type".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_is_preflight() {
+        let (parts, _) = http::Request::builder()
+            .method(Method::OPTIONS)
+            .header(header::ORIGIN, "https://app.example.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .body(())
+            .unwrap()
+            .into_parts();
+        assert!(is_preflight(&parts));
+    }
+
+    #[test]
+    fn test_is_not_preflight() {
+        let (parts, _) = http::Request::builder()
+            .method(Method::OPTIONS)
+            .body(())
+            .unwrap()
+            .into_parts();
+        assert!(!is_preflight(&parts));
+
+        let (parts, _) = http::Request::builder()
+            .method(Method::POST)
+            .header(header::ORIGIN, "https://app.example.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .body(())
+            .unwrap()
+            .into_parts();
+        assert!(!is_preflight(&parts));
+    }
+
+    #[test]
+    fn test_preflight_response_defaults_to_route_methods() {
+        let response = cors(&["*"]).preflight_response(&[Method::POST]);
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let headers = response.headers();
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_METHODS], "POST");
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_HEADERS],
+            "Content-Type"
+        );
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_MAX_AGE));
+    }
+
+    #[test]
+    fn test_preflight_response_with_configured_methods() {
+        let cors = Cors {
+            allowed_methods: vec![Method::POST, Method::OPTIONS],
+            max_age: Some(600),
+            ..cors(&["*"])
+        };
+        let response = cors.preflight_response(&[Method::POST]);
+
+        let headers = response.headers();
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_METHODS],
+            "POST, OPTIONS"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+    }
+
+    #[test]
+    fn test_apply_wildcard_origin() {
+        let mut headers = HeaderMap::new();
+        cors(&["*"]).apply(
+            &HeaderValue::from_static("https://app.example.com"),
+            &mut headers,
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(!headers.contains_key(header::VARY));
+    }
+
+    #[test]
+    fn test_apply_allowed_origin() {
+        let mut headers = HeaderMap::new();
+        cors(&["https://app.example.com"]).apply(
+            &HeaderValue::from_static("https://APP.example.com"),
+            &mut headers,
+        );
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://APP.example.com"
+        );
+        assert_eq!(headers[header::VARY], "Origin");
+    }
+
+    #[test]
+    fn test_apply_disallowed_origin() {
+        let mut headers = HeaderMap::new();
+        cors(&["https://app.example.com"]).apply(
+            &HeaderValue::from_static("https://evil.example.com"),
+            &mut headers,
+        );
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+}
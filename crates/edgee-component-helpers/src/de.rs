@@ -0,0 +1,192 @@
+use std::marker::PhantomData;
+
+use serde::de::value::SeqDeserializer;
+use serde::de::{self, Deserializer, IntoDeserializer, Visitor};
+
+// Deserializer for plain string values (headers, settings) parsed into the
+// requested type: numbers and booleans are parsed, sequences are comma
+// separated and empty strings are treated as missing for `Option`s. Values
+// not parsing say what was expected, e.g. `expected u32, got "many"`.
+
+pub struct StrDeserializer<'a, E> {
+    value: &'a str,
+    marker: PhantomData<E>,
+}
+
+impl<'a, E> StrDeserializer<'a, E> {
+    pub fn new(value: &'a str) -> Self {
+        Self {
+            value,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, E: de::Error> IntoDeserializer<'de, E> for StrDeserializer<'_, E> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, E> {
+                match self.value.trim().parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(_) => Err(de::Error::custom(format_args!(
+                        "expected {}, got {:?}",
+                        &visitor as &dyn de::Expected,
+                        self.value,
+                    ))),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de, E: de::Error> Deserializer<'de> for StrDeserializer<'_, E> {
+    type Error = E;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, E> {
+        visitor.visit_str(self.value)
+    }
+
+    // Typos are reported rather than read as false, empty values are unset
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, E> {
+        match self.value.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => visitor.visit_bool(true),
+            "false" | "0" | "no" | "off" | "" => visitor.visit_bool(false),
+            _ => Err(de::Error::custom(format_args!(
+                "expected true or false, got {:?}",
+                self.value
+            ))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, E> {
+        if self.value.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, E> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Unit variants by name, e.g. "v1"
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, E> {
+        visitor.visit_enum(self.value.trim().into_deserializer())
+    }
+
+    // Comma separated values, e.g. "https://a.com, https://b.com"
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, E> {
+        let items = self
+            .value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(StrDeserializer::new);
+        let mut seq = SeqDeserializer::new(items);
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::de::value::Error;
+    use serde::Deserialize;
+
+    use super::*;
+
+    fn parse<'de, T: Deserialize<'de>>(value: &'de str) -> Result<T, Error> {
+        T::deserialize(StrDeserializer::<Error>::new(value))
+    }
+
+    #[test]
+    fn test_parse_numbers() {
+        assert_eq!(parse::<u32>(" 42 ").unwrap(), 42);
+        assert_eq!(
+            parse::<u32>("many").unwrap_err().to_string(),
+            "expected u32, got \"many\""
+        );
+        assert_eq!(
+            parse::<u8>("300").unwrap_err().to_string(),
+            "expected u8, got \"300\""
+        );
+    }
+
+    #[test]
+    fn test_parse_bool() {
+        assert!(parse::<bool>("true").unwrap());
+        assert!(!parse::<bool>("false").unwrap());
+        assert!(!parse::<bool>("").unwrap());
+        assert!(parse::<bool>(" Yes").unwrap());
+        assert!(!parse::<bool>("0").unwrap());
+        assert_eq!(
+            parse::<bool>("ture").unwrap_err().to_string(),
+            "expected true or false, got \"ture\""
+        );
+    }
+
+    #[test]
+    fn test_parse_enum() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Mode {
+            Webhook,
+            Bot,
+        }
+
+        assert_eq!(parse::<Mode>(" bot").unwrap(), Mode::Bot);
+        assert_eq!(
+            parse::<Mode>("chat").unwrap_err().to_string(),
+            "unknown variant `chat`, expected `webhook` or `bot`"
+        );
+    }
+
+    #[test]
+    fn test_parse_option() {
+        assert_eq!(parse::<Option<String>>("").unwrap(), None);
+        assert_eq!(parse::<Option<String>>("a").unwrap().as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(parse::<Vec<String>>(" a, ,b ,").unwrap(), vec!["a", "b"]);
+        assert!(parse::<Vec<String>>("").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,186 @@
+use bytes::Bytes;
+use http::StatusCode;
+
+use super::body::Json;
+
+/// Handler error carrying the HTTP status and a machine readable code.
+///
+/// Handlers keep returning `anyhow::Result`, `run` looks for an `ApiError`
+/// in the returned error to pick the response status. Any other error is
+/// answered with a 500.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "bad_request", message)
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, "unauthorized", message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "not_found", message)
+    }
+
+    pub fn method_not_allowed(message: impl Into<String>) -> Self {
+        Self::new(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "method_not_allowed",
+            message,
+        )
+    }
+
+    pub fn unprocessable(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNPROCESSABLE_ENTITY, "unprocessable", message)
+    }
+
+    pub fn too_many_requests(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::TOO_MANY_REQUESTS, "too_many_requests", message)
+    }
+
+    pub fn bad_gateway(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_GATEWAY, "bad_gateway", message)
+    }
+
+    pub fn gateway_timeout(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::GATEWAY_TIMEOUT, "gateway_timeout", message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", message)
+    }
+
+    /// Error body sent to the client.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "error": self.message,
+            "code": self.code,
+        })
+    }
+
+    /// Response `run` sends for this error, for handlers answering it
+    /// themselves, e.g. to add headers.
+    pub fn into_response(self) -> http::Response<Json<serde_json::Value>> {
+        let mut res = http::Response::new(Json(self.to_json()));
+        *res.status_mut() = self.status;
+        res
+    }
+
+    /// RFC 9457 problem details of this error, keeping its `code`.
+    pub fn to_problem(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "about:blank",
+            "title": self.status.canonical_reason().unwrap_or("Error"),
+            "status": self.status.as_u16(),
+            "detail": self.message,
+            "code": self.code,
+        })
+    }
+
+    /// `application/problem+json` response of this error.
+    pub fn into_problem_response(self) -> http::Response<Bytes> {
+        http::Response::builder()
+            .status(self.status)
+            .header(http::header::CONTENT_TYPE, "application/problem+json")
+            .body(Bytes::from(self.to_problem().to_string()))
+            .unwrap()
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+// Errors that were not raised as an `ApiError` are internal ones
+impl From<anyhow::Error> for ApiError {
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<ApiError>() {
+            Ok(err) => err,
+            Err(err) => Self::internal(err.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_error_display() {
+        let err = ApiError::unauthorized("Unauthorized");
+        assert_eq!(err.status, StatusCode::UNAUTHORIZED);
+        assert_eq!(err.to_string(), "Unauthorized");
+    }
+
+    #[test]
+    fn test_api_error_to_json() {
+        let err = ApiError::too_many_requests("Slow down");
+        assert_eq!(
+            err.to_json(),
+            serde_json::json!({ "error": "Slow down", "code": "too_many_requests" })
+        );
+    }
+
+    #[test]
+    fn test_api_error_into_response() {
+        let res = ApiError::unauthorized("Unauthorized").into_response();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            res.body().0,
+            serde_json::json!({ "error": "Unauthorized", "code": "unauthorized" })
+        );
+    }
+
+    #[test]
+    fn test_api_error_into_problem_response() {
+        let res = ApiError::internal("Internal server error").into_problem_response();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            res.headers().get(http::header::CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+        let body: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "type": "about:blank",
+                "title": "Internal Server Error",
+                "status": 500,
+                "detail": "Internal server error",
+                "code": "internal_error",
+            })
+        );
+    }
+
+    #[test]
+    fn test_api_error_from_anyhow_keeps_api_error() {
+        let err: anyhow::Error = ApiError::unprocessable("Bad payload").into();
+        assert_eq!(ApiError::from(err), ApiError::unprocessable("Bad payload"));
+    }
+
+    #[test]
+    fn test_api_error_from_anyhow_falls_back_to_internal() {
+        let err = ApiError::from(anyhow::anyhow!("boom"));
+        assert_eq!(err.status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(err.code, "internal_error");
+        assert_eq!(err.message, "boom");
+    }
+}
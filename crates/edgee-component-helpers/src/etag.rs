@@ -0,0 +1,92 @@
+use bytes::Bytes;
+use http::{HeaderValue, StatusCode};
+
+// Conditional GET support
+//
+// Handlers of cacheable routes mark their response with `Cacheable`, `run`
+// then tags the serialized body and answers a matching `If-None-Match` with
+// an empty 304.
+
+/// Response extension asking `run` to send an `ETag` for a 200 response.
+#[derive(Debug, Clone, Copy)]
+pub struct Cacheable;
+
+/// Strong validator derived from the body bytes (64-bit FNV-1a).
+pub fn etag(data: &[u8]) -> HeaderValue {
+    let hash = data.iter().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    });
+    HeaderValue::from_str(&format!("\"{hash:016x}\"")).unwrap()
+}
+
+/// Whether an `If-None-Match` header value lists `etag`, weakly compared
+/// as the header requires.
+pub fn matches(if_none_match: &HeaderValue, etag: &HeaderValue) -> bool {
+    let Ok(if_none_match) = if_none_match.to_str() else {
+        return false;
+    };
+    let etag = etag.to_str().unwrap_or_default();
+    let etag = etag.strip_prefix("W/").unwrap_or(etag);
+
+    if_none_match.trim() == "*"
+        || if_none_match.split(',').any(|tag| {
+            let tag = tag.trim();
+            tag.strip_prefix("W/").unwrap_or(tag) == etag
+        })
+}
+
+/// Tags a cacheable response, emptying its body when the client copy is
+/// still fresh.
+pub(crate) fn apply(
+    if_none_match: Option<&HeaderValue>,
+    parts: &mut http::response::Parts,
+    body: Bytes,
+) -> Bytes {
+    let etag = etag(&body);
+    let fresh = if_none_match.is_some_and(|value| matches(value, &etag));
+    parts.headers.insert(http::header::ETAG, etag);
+
+    if !fresh {
+        return body;
+    }
+    parts.status = StatusCode::NOT_MODIFIED;
+    parts.headers.remove(http::header::CONTENT_LENGTH);
+    Bytes::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_etag_is_stable() {
+        assert_eq!(etag(b"{\"ok\":true}"), etag(b"{\"ok\":true}"));
+        assert_ne!(etag(b"{\"ok\":true}"), etag(b"{\"ok\":false}"));
+        assert_eq!(etag(b""), "\"cbf29ce484222325\"");
+    }
+
+    #[test]
+    fn test_matches() {
+        let tag = HeaderValue::from_static("\"abc\"");
+        assert!(matches(&HeaderValue::from_static("\"abc\""), &tag));
+        assert!(matches(&HeaderValue::from_static("\"x\", W/\"abc\""), &tag));
+        assert!(matches(&HeaderValue::from_static("*"), &tag));
+        assert!(!matches(&HeaderValue::from_static("\"abd\""), &tag));
+    }
+
+    #[test]
+    fn test_apply() {
+        let body = Bytes::from_static(b"{\"ok\":true}");
+        let tag = etag(&body);
+
+        let (mut parts, ()) = http::Response::new(()).into_parts();
+        assert_eq!(apply(None, &mut parts, body.clone()), body);
+        assert_eq!(parts.status, StatusCode::OK);
+        assert_eq!(parts.headers.get(http::header::ETAG), Some(&tag));
+
+        let (mut parts, ()) = http::Response::new(()).into_parts();
+        assert_eq!(apply(Some(&tag), &mut parts, body), Bytes::new());
+        assert_eq!(parts.status, StatusCode::NOT_MODIFIED);
+        assert_eq!(parts.headers.get(http::header::ETAG), Some(&tag));
+    }
+}
@@ -1,8 +1,12 @@
-use bytes::Bytes;
+use std::ops::ControlFlow;
+
+use bytes::{Bytes, BytesMut};
 use http::header::{HeaderName, HeaderValue};
 use http::uri;
 use serde::de::DeserializeOwned;
 
+use super::body::PayloadTooLarge;
+
 use crate::bindings::wasi::http::types::{
     ErrorCode, Headers, IncomingBody, IncomingRequest, Method, ResponseOutparam, Scheme,
 };
@@ -98,8 +102,16 @@ impl TryFrom<Headers> for http::header::HeaderMap {
     }
 }
 
-impl From<http::header::HeaderMap> for Headers {
-    fn from(headers: http::header::HeaderMap) -> Self {
+impl TryFrom<http::header::HeaderMap> for Headers {
+    type Error = anyhow::Error;
+
+    fn try_from(headers: http::header::HeaderMap) -> anyhow::Result<Self, Self::Error> {
+        if let Some(name) = headers
+            .keys()
+            .find(|name| super::headers::is_forbidden(name.as_str()))
+        {
+            anyhow::bail!("Header '{name}' can't be sent");
+        }
         let entries: Vec<_> = headers
             .into_iter()
             .filter_map(|(name, value)| Some((name?, value)))
@@ -110,52 +122,93 @@ impl From<http::header::HeaderMap> for Headers {
                 (name, value)
             })
             .collect();
-        Headers::from_list(&entries).unwrap()
+        Headers::from_list(&entries).map_err(|err| anyhow::anyhow!("Invalid headers: {err:?}"))
     }
 }
 
 impl IncomingBody {
-    pub fn read(&self) -> anyhow::Result<Bytes> {
-        use bytes::BytesMut;
-
+    /// Hands the body to `on_chunk` as it is received, without buffering it.
+    ///
+    /// Reading stops as soon as `on_chunk` breaks or fails, e.g. when a
+    /// signature prefix already mismatches, or once `limit` is crossed.
+    pub fn read_chunks<F>(&self, limit: Option<usize>, on_chunk: F) -> anyhow::Result<()>
+    where
+        F: FnMut(&[u8]) -> anyhow::Result<ControlFlow<()>>,
+    {
         use crate::bindings::wasi::io::streams::StreamError;
 
         let stream = self
             .stream()
             .map_err(|_| anyhow::anyhow!("Missing request body stream"))?;
 
-        let mut bytes = BytesMut::new();
+        let frames = std::iter::from_fn(|| match stream.blocking_read(4096) {
+            Ok(frame) => Some(Ok(frame)),
+            Err(StreamError::Closed) => None,
+            Err(err) => Some(Err(anyhow::anyhow!("Failed reading request body: {err}"))),
+        });
+        consume_chunks(frames, limit, on_chunk)
+    }
 
-        loop {
-            match stream.read(4096) {
-                Ok(frame) => {
-                    bytes.extend_from_slice(&frame);
-                }
-                Err(StreamError::Closed) => break,
-                Err(err) => anyhow::bail!("Failed reading request body: {err}"),
-            }
-        }
+    pub fn read(&self, limit: Option<usize>) -> anyhow::Result<Bytes> {
+        self.read_with_capacity(limit, 0)
+    }
+
+    /// Like `read`, with the buffer sized for `capacity` bytes up front.
+    pub fn read_with_capacity(
+        &self,
+        limit: Option<usize>,
+        capacity: usize,
+    ) -> anyhow::Result<Bytes> {
+        let mut bytes = BytesMut::with_capacity(capacity);
+        self.read_chunks(limit, |chunk| {
+            bytes.extend_from_slice(chunk);
+            Ok(ControlFlow::Continue(()))
+        })?;
 
         Ok(bytes.freeze())
     }
 
     pub fn read_json<T: DeserializeOwned>(&self) -> anyhow::Result<T> {
-        let bytes = self.read()?;
+        let bytes = self.read(None)?;
         Ok(serde_json::from_slice(&bytes)?)
     }
 }
 
+fn consume_chunks<I, F>(frames: I, limit: Option<usize>, mut on_chunk: F) -> anyhow::Result<()>
+where
+    I: Iterator<Item = anyhow::Result<Vec<u8>>>,
+    F: FnMut(&[u8]) -> anyhow::Result<ControlFlow<()>>,
+{
+    let mut read = 0;
+    for frame in frames {
+        let frame = frame?;
+        // Bail out as soon as the limit is crossed instead of reading the
+        // rest of the stream
+        read += frame.len();
+        if let Some(limit) = limit {
+            if read > limit {
+                return Err(PayloadTooLarge { limit }.into());
+            }
+        }
+        if on_chunk(&frame)?.is_break() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
 impl ResponseOutparam {
     pub fn error(self, code: ErrorCode) {
         ResponseOutparam::set(self, Err(code));
     }
 
-    pub fn send(self, res: http::Response<Bytes>) -> anyhow::Result<()> {
+    pub fn send(self, res: http::Response<super::body::BodyChunks>) -> anyhow::Result<()> {
         use crate::bindings::wasi::http::types::{OutgoingBody, OutgoingResponse};
 
         let (parts, body) = res.into_parts();
 
-        let res = OutgoingResponse::new(parts.headers.into());
+        let res = OutgoingResponse::new(parts.headers.try_into()?);
         let _ = res.set_status_code(parts.status.into());
 
         let resp_body = res
@@ -167,7 +220,14 @@ impl ResponseOutparam {
         let out = resp_body
             .write()
             .map_err(|_| anyhow::anyhow!("Could not get response body writer"))?;
-        out.blocking_write_and_flush(&body)?;
+        for chunk in body {
+            // A body left unfinished tells the client the response is truncated
+            let chunk = chunk?;
+            // Blocking writes are capped at 4096 bytes per call
+            for piece in chunk.chunks(4096) {
+                out.blocking_write_and_flush(piece)?;
+            }
+        }
         drop(out);
 
         OutgoingBody::finish(resp_body, None)?;
@@ -178,6 +238,8 @@ impl ResponseOutparam {
 
 #[cfg(test)]
 mod tests {
+    use std::ops::ControlFlow;
+
     use crate::bindings::wasi::http::types::{Method as WasiMethod, Scheme as WasiScheme};
     use http::Method as HttpMethod;
 
@@ -279,4 +341,56 @@ mod tests {
         let result = super::to_http_request_builder(scheme, authority, path_and_query, method);
         assert!(result.is_err());
     }
+
+    fn frames(frames: &[&str]) -> impl Iterator<Item = anyhow::Result<Vec<u8>>> {
+        frames
+            .iter()
+            .map(|frame| Ok(frame.as_bytes().to_vec()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_consume_chunks_reads_everything() {
+        let mut seen = Vec::new();
+        super::consume_chunks(frames(&["ab", "cd"]), Some(4), |chunk| {
+            seen.push(chunk.to_vec());
+            Ok(ControlFlow::Continue(()))
+        })
+        .unwrap();
+        assert_eq!(seen, vec![b"ab".to_vec(), b"cd".to_vec()]);
+    }
+
+    #[test]
+    fn test_consume_chunks_over_limit() {
+        let mut calls = 0;
+        let err = super::consume_chunks(frames(&["ab", "cd", "ef"]), Some(3), |_| {
+            calls += 1;
+            Ok(ControlFlow::Continue(()))
+        })
+        .unwrap_err();
+        assert!(err.is::<super::PayloadTooLarge>());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_consume_chunks_early_break() {
+        let mut calls = 0;
+        super::consume_chunks(frames(&["ab", "cd"]), None, |_| {
+            calls += 1;
+            Ok(ControlFlow::Break(()))
+        })
+        .unwrap();
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_consume_chunks_abort_error() {
+        let err = super::consume_chunks(frames(&["bad", "rest"]), None, |chunk| {
+            anyhow::ensure!(chunk.starts_with(b"sha256="), "Invalid signature");
+            Ok(ControlFlow::Continue(()))
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Invalid signature");
+    }
 }
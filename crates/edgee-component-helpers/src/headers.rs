@@ -0,0 +1,212 @@
+use anyhow::Result;
+use http::{HeaderMap, HeaderName, Request};
+use serde::de::value::{Error, MapDeserializer};
+use serde::de::{self, Deserializer, Visitor};
+
+use super::de::StrDeserializer;
+
+/// Selected request headers deserialized into a struct.
+///
+/// Field names are looked up case-insensitively, with `_` also matching `-`
+/// so `x_request_id` reads the `X-Request-Id` header. Values are parsed into
+/// the field type (see `StrDeserializer`), missing headers leave `Option`
+/// fields to `None`.
+#[derive(Debug, Clone)]
+pub struct TypedHeaders<T>(pub T);
+
+impl<T: serde::de::DeserializeOwned> TypedHeaders<T> {
+    pub fn from_headers(headers: &HeaderMap) -> Result<Self> {
+        let data = T::deserialize(HeadersDeserializer(headers))
+            .map_err(|err| anyhow::anyhow!("Invalid request headers: {err}"))?;
+        Ok(Self(data))
+    }
+
+    pub fn from_req<B>(req: &Request<B>) -> Result<Self> {
+        Self::from_headers(req.headers())
+    }
+}
+
+/// Headers the WASI HTTP host refuses on outgoing requests and responses,
+/// connection-level ones it manages itself.
+const FORBIDDEN: &[&str] = &[
+    "connection",
+    "host",
+    "http2-settings",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Whether `name` is a header the host would refuse to send.
+pub fn is_forbidden(name: &str) -> bool {
+    FORBIDDEN
+        .iter()
+        .any(|forbidden| forbidden.eq_ignore_ascii_case(name))
+}
+
+/// Headers copied from one header map onto another, possibly renamed, e.g.
+/// the `x-request-id` of an upstream response onto the response sent back.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderMapping(Vec<(HeaderName, HeaderName)>);
+
+impl HeaderMapping {
+    /// Mapping of `(from, to)` header names, failing on the first invalid
+    /// one.
+    pub fn new<'a>(names: impl IntoIterator<Item = (&'a str, &'a str)>) -> Result<Self> {
+        names
+            .into_iter()
+            .map(|(from, to)| {
+                let name = |name: &str| {
+                    HeaderName::try_from(name)
+                        .map_err(|_| anyhow::anyhow!("Invalid header name '{name}'"))
+                };
+                Ok((name(from)?, name(to)?))
+            })
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+
+    /// Copies every value of the mapped headers of `from` onto `to`,
+    /// replacing the values `to` had.
+    pub fn apply(&self, from: &HeaderMap, to: &mut HeaderMap) {
+        for (source, target) in &self.0 {
+            let values: Vec<_> = from.get_all(source).iter().cloned().collect();
+            if values.is_empty() {
+                continue;
+            }
+            to.remove(target);
+            for value in values {
+                to.append(target.clone(), value);
+            }
+        }
+    }
+}
+
+struct HeadersDeserializer<'a>(&'a HeaderMap);
+
+impl<'de> Deserializer<'de> for HeadersDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(de::Error::custom("headers can only be read into a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let headers = self.0;
+        let entries = fields.iter().filter_map(|field| {
+            let value = headers
+                .get(*field)
+                .or_else(|| headers.get(field.replace('_', "-")))?;
+            Some((*field, StrDeserializer::new(value.to_str().ok()?)))
+        });
+        visitor.visit_map(MapDeserializer::new(entries))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Headers {
+        x_request_id: Option<String>,
+        #[serde(rename = "Content-Length")]
+        content_length: u64,
+        dnt: Option<bool>,
+    }
+
+    fn headers(entries: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in entries {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_header_mapping() {
+        let mapping = HeaderMapping::new([
+            ("x-slack-req-id", "x-upstream-id"),
+            ("x-missing", "x-missing"),
+        ])
+        .unwrap();
+        let mut to = headers(&[
+            ("x-upstream-id", "old"),
+            ("content-type", "application/json"),
+        ]);
+        mapping.apply(&headers(&[("X-Slack-Req-Id", "r1")]), &mut to);
+        assert_eq!(to["x-upstream-id"], "r1");
+        assert_eq!(to.len(), 2);
+
+        let err = HeaderMapping::new([("bad header", "x")]).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid header name 'bad header'");
+    }
+
+    #[test]
+    fn test_is_forbidden() {
+        assert!(is_forbidden("Host"));
+        assert!(is_forbidden("transfer-encoding"));
+        assert!(!is_forbidden("x-request-id"));
+        assert!(!is_forbidden("hosts"));
+    }
+
+    #[test]
+    fn test_typed_headers() {
+        let headers = headers(&[
+            ("x-request-id", "abc"),
+            ("content-length", "42"),
+            ("dnt", "true"),
+            ("user-agent", "curl"),
+        ]);
+
+        let TypedHeaders(parsed) = TypedHeaders::<Headers>::from_headers(&headers).unwrap();
+        assert_eq!(
+            parsed,
+            Headers {
+                x_request_id: Some("abc".to_string()),
+                content_length: 42,
+                dnt: Some(true),
+            }
+        );
+    }
+
+    #[test]
+    fn test_typed_headers_missing_optional() {
+        let headers = headers(&[("content-length", "0")]);
+
+        let TypedHeaders(parsed) = TypedHeaders::<Headers>::from_headers(&headers).unwrap();
+        assert_eq!(parsed.x_request_id, None);
+        assert_eq!(parsed.dnt, None);
+    }
+
+    #[test]
+    fn test_typed_headers_errors() {
+        let err = TypedHeaders::<Headers>::from_headers(&HeaderMap::new()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid request headers: missing field `Content-Length`"
+        );
+
+        let headers = headers(&[("content-length", "many")]);
+        let err = TypedHeaders::<Headers>::from_headers(&headers).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid request headers: expected u64, got \"many\""
+        );
+    }
+}
@@ -0,0 +1,1131 @@
+//! Request handling framework for Edgee edge function components: the `run`
+//! pipeline, body and header extractors, settings parsing, routing and
+//! response types, on top of the WASI HTTP bindings.
+//!
+//! Components implement the exported `Guest` trait from [`bindings`] and
+//! hand each request to [`run`] or [`run_router`].
+#![allow(dead_code)]
+use anyhow::Result;
+use bytes::Bytes;
+use http::{Method, Request, Response, StatusCode};
+use std::time::Duration;
+
+use crate::bindings::wasi::http::types::{IncomingBody, IncomingRequest, ResponseOutparam};
+use body::{
+    single_chunk, BodyChunks, FromBody, Html, IntoBody, Json, PayloadTooLarge, UnsupportedMediaType,
+};
+pub use cookies::{Cookies, SetCookie};
+pub use cors::Cors;
+pub use error::ApiError;
+pub use headers::{is_forbidden as is_forbidden_header, HeaderMapping, TypedHeaders};
+pub use query::Query;
+pub use router::Router;
+pub use settings::{EdgeeSettings, SettingsErrors, Validate};
+
+pub mod bindings {
+    wit_bindgen::generate!({
+        path: "../../.edgee/wit",
+        world: "edge-function",
+        generate_all,
+        pub_export_macro: true,
+        default_bindings_module: "$crate::bindings",
+    });
+}
+
+pub mod body;
+pub mod cookies;
+pub mod cors;
+mod de;
+mod error;
+pub mod etag;
+mod extensions;
+mod headers;
+pub mod middleware;
+mod multipart;
+pub mod negotiate;
+mod query;
+pub mod router;
+mod settings;
+
+// Request handling helpers
+
+/// Builds the response of a failed request from the status picked by `run`.
+pub type ErrorHandler = fn(StatusCode, &anyhow::Error) -> Response<Bytes>;
+
+/// Per-request options resolved before the body is read.
+///
+/// They are built by the `configure` callback of `run`, which can also stash
+/// what it parsed in the request extensions for the handler to reuse.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    pub max_body_bytes: Option<usize>,
+    pub cors: Option<Cors>,
+    /// Defaults to `json_error_response`, use `html_error_response` for
+    /// browser-facing routes or `negotiate::error_handler` to follow the
+    /// request `Accept` header.
+    pub error_handler: Option<ErrorHandler>,
+    /// Answers a 504 instead of the handler response when the handler,
+    /// outbound calls included, runs longer than this. Handlers can't be
+    /// interrupted, `remaining_time` lets outbound calls be bounded by it.
+    pub handler_timeout: Option<Duration>,
+}
+
+pub fn run<I, O, C, F>(
+    req: IncomingRequest,
+    response_out: ResponseOutparam,
+    allowed_methods: &[Method],
+    configure: C,
+    handler: F,
+) where
+    C: FnOnce(&mut http::request::Parts) -> RunOptions,
+    F: FnOnce(Request<I>) -> Result<Response<O>>,
+    I: FromBody,
+    O: IntoBody,
+{
+    let req: Request<IncomingBody> = req.try_into().unwrap();
+    serve(
+        req,
+        Box::new(move |res| response_out.send(res)),
+        allowed_methods,
+        configure,
+        read_incoming_body,
+        handler,
+    );
+}
+
+/// Same as `run`, with the allowed methods and the handler picked by the
+/// router from the request path. Unrouted paths get a 404.
+pub fn run_router<I, O, C>(
+    req: IncomingRequest,
+    response_out: ResponseOutparam,
+    router: &Router<I, O>,
+    configure: C,
+) where
+    C: FnOnce(&mut http::request::Parts) -> RunOptions,
+    I: FromBody,
+    O: IntoBody,
+{
+    let req: Request<IncomingBody> = req.try_into().unwrap();
+    let allowed_methods = router.allowed_methods(req.uri().path());
+    serve(
+        req,
+        Box::new(move |res| response_out.send(res)),
+        &allowed_methods,
+        configure,
+        read_incoming_body,
+        |req| router.dispatch(req),
+    );
+}
+
+/// Writes the response of `run`, through the WASI response outparam.
+type Sender = Box<dyn FnOnce(Response<BodyChunks>) -> Result<()>>;
+
+thread_local! {
+    // Sender of the request being served, for the panic hook to answer
+    static SENDER: std::cell::RefCell<Option<Sender>> = const { std::cell::RefCell::new(None) };
+}
+
+// wasm components abort on panic and the instance traps: the hook answers a
+// problem+json 500 through the stashed sender first, so the client doesn't
+// get a bare host error. Native builds answer the same way, the panic being
+// caught by `call_handler` afterwards.
+fn install_panic_hook() {
+    static INSTALLED: std::sync::Once = std::sync::Once::new();
+    INSTALLED.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            previous(info);
+            if let Some(send) = SENDER.with_borrow_mut(Option::take) {
+                let res = ApiError::internal("Internal server error").into_problem_response();
+                if let Err(err) = send(res.map(single_chunk)) {
+                    eprintln!("Failed to send response: {err}");
+                }
+            }
+        }));
+    });
+}
+
+fn read_incoming_body<I: FromBody>(
+    body: IncomingBody,
+    content_type: Option<&str>,
+    length: Option<usize>,
+    limit: Option<usize>,
+) -> Result<I> {
+    I::from_body(body, content_type, length, limit)
+}
+
+fn serve<B, I, O, C, R, F>(
+    req: Request<B>,
+    send: Sender,
+    allowed_methods: &[Method],
+    configure: C,
+    read_body: R,
+    handler: F,
+) where
+    C: FnOnce(&mut http::request::Parts) -> RunOptions,
+    R: FnOnce(B, Option<&str>, Option<usize>, Option<usize>) -> Result<I>,
+    F: FnOnce(Request<I>) -> Result<Response<O>>,
+    I: FromBody,
+    O: IntoBody,
+{
+    install_panic_hook();
+    SENDER.set(Some(send));
+
+    let (mut parts, body) = req.into_parts();
+    let options = configure(&mut parts);
+    let origin = parts.headers.get(http::header::ORIGIN).cloned();
+
+    let mut res = match &options.cors {
+        Some(cors) if cors::is_preflight(&parts) => {
+            cors.preflight_response(allowed_methods).map(single_chunk)
+        }
+        _ => {
+            let length = content_length(&parts.headers);
+            let limit = options.max_body_bytes;
+            handle_with(
+                parts,
+                allowed_methods,
+                &options,
+                |content_type| read_body(body, content_type, length, limit),
+                handler,
+            )
+        }
+    };
+
+    // Already answered by the panic hook
+    let Some(send) = SENDER.take() else {
+        return;
+    };
+    if let (Some(cors), Some(origin)) = (&options.cors, &origin) {
+        cors.apply(origin, res.headers_mut());
+    }
+
+    // Headers are gone once streaming started, a failing chunk can only be logged
+    if let Err(err) = send(res) {
+        eprintln!("Failed to send response: {err}");
+    }
+}
+
+/// Same pipeline as `run` for a body that was already read, so the handler
+/// can be served outside of a WASI runtime.
+pub fn handle_bytes<I, O, F>(
+    req: Request<Bytes>,
+    allowed_methods: &[Method],
+    options: &RunOptions,
+    handler: F,
+) -> Response<Bytes>
+where
+    F: FnOnce(Request<I>) -> Result<Response<O>>,
+    I: FromBody,
+    O: IntoBody,
+{
+    let (parts, data) = req.into_parts();
+    let res = handle_with(
+        parts,
+        allowed_methods,
+        options,
+        |content_type| match options.max_body_bytes {
+            Some(limit) if data.len() > limit => Err(PayloadTooLarge { limit }.into()),
+            _ => I::from_content(data, content_type),
+        },
+        handler,
+    );
+
+    let (parts, chunks) = res.into_parts();
+    let mut body = bytes::BytesMut::new();
+    for chunk in chunks {
+        match chunk {
+            Ok(chunk) => body.extend_from_slice(&chunk),
+            Err(err) => {
+                eprintln!("Errored during response streaming: {err}");
+
+                let on_error = options.error_handler.unwrap_or(json_error_response);
+                return on_error(StatusCode::INTERNAL_SERVER_ERROR, &err);
+            }
+        }
+    }
+    Response::from_parts(parts, body.freeze())
+}
+
+fn handle_with<I, O, R, F>(
+    parts: http::request::Parts,
+    allowed_methods: &[Method],
+    options: &RunOptions,
+    read_body: R,
+    handler: F,
+) -> Response<BodyChunks>
+where
+    R: FnOnce(Option<&str>) -> Result<I>,
+    F: FnOnce(Request<I>) -> Result<Response<O>>,
+    I: FromBody,
+    O: IntoBody,
+{
+    let head = parts.method == Method::HEAD;
+    let res = respond(parts, allowed_methods, options, read_body, handler);
+    match head {
+        true => without_body(res),
+        false => res,
+    }
+}
+
+fn respond<I, O, R, F>(
+    parts: http::request::Parts,
+    allowed_methods: &[Method],
+    options: &RunOptions,
+    read_body: R,
+    handler: F,
+) -> Response<BodyChunks>
+where
+    R: FnOnce(Option<&str>) -> Result<I>,
+    F: FnOnce(Request<I>) -> Result<Response<O>>,
+    I: FromBody,
+    O: IntoBody,
+{
+    let on_error = options.error_handler.unwrap_or(json_error_response);
+
+    if allowed_methods.is_empty() {
+        return on_error(
+            StatusCode::NOT_FOUND,
+            &anyhow::anyhow!("No route for {}", parts.uri.path()),
+        )
+        .map(single_chunk);
+    }
+
+    if parts.method == Method::OPTIONS && !allowed_methods.contains(&Method::OPTIONS) {
+        return options_response(allowed_methods).map(single_chunk);
+    }
+
+    if !allowed_methods.contains(&parts.method) {
+        eprintln!("Rejected request with method {}", parts.method);
+
+        return method_not_allowed_response(&parts.method, allowed_methods, on_error)
+            .map(single_chunk);
+    }
+
+    let content_type = parts
+        .headers
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    let body = match check_content_type(&parts.headers, I::MEDIA_TYPES)
+        .and_then(|()| check_content_length(&parts.headers, options.max_body_bytes))
+        .and_then(|()| read_body(content_type))
+    {
+        Ok(body) => body,
+        Err(err) => {
+            eprintln!("Errored during body parsing: {err}");
+
+            return on_error(body_error_status(&err), &err).map(single_chunk);
+        }
+    };
+    // Only safe requests can be answered from the client cache
+    let if_none_match = match parts.method {
+        Method::GET | Method::HEAD => parts.headers.get(http::header::IF_NONE_MATCH).cloned(),
+        _ => None,
+    };
+    let req = Request::from_parts(parts, body);
+
+    let deadline = options
+        .handler_timeout
+        .map(|timeout| monotonic_now() + timeout);
+    let previous = DEADLINE.replace(deadline);
+    let res = call_handler(handler, req);
+    DEADLINE.set(previous);
+    let res = match res {
+        Ok(res) => res,
+        Err(panicked) => return panicked.into_problem_response().map(single_chunk),
+    };
+    // Guests can't be interrupted, a handler running past its deadline is
+    // only noticed once it returns
+    let res = res.and_then(|res| match (deadline, options.handler_timeout) {
+        (Some(deadline), Some(timeout)) if monotonic_now() > deadline => {
+            Err(ApiError::gateway_timeout(format!(
+                "Handler timed out after {} ms",
+                timeout.as_millis()
+            ))
+            .into())
+        }
+        _ => Ok(res),
+    });
+    let res = match res {
+        Ok(res) => res,
+        Err(err) => {
+            eprintln!("Errored during request handling: {err}");
+
+            let status = err
+                .downcast_ref::<ApiError>()
+                .map_or(StatusCode::INTERNAL_SERVER_ERROR, |err| err.status);
+            return on_error(status, &err).map(single_chunk);
+        }
+    };
+
+    let (mut parts, data) = res.into_parts();
+    data.extend_response_parts(&mut parts);
+    if parts.status == StatusCode::OK && parts.extensions.get::<etag::Cacheable>().is_some() {
+        return match data.into_body() {
+            Ok(body) => {
+                let body = etag::apply(if_none_match.as_ref(), &mut parts, body);
+                Response::from_parts(parts, single_chunk(body))
+            }
+            Err(err) => {
+                eprintln!("Errored during response serialization: {err}");
+
+                on_error(StatusCode::INTERNAL_SERVER_ERROR, &err).map(single_chunk)
+            }
+        };
+    }
+    match data.into_chunks() {
+        Ok(chunks) => Response::from_parts(parts, chunks),
+        Err(err) => {
+            eprintln!("Errored during response serialization: {err}");
+
+            on_error(StatusCode::INTERNAL_SERVER_ERROR, &err).map(single_chunk)
+        }
+    }
+}
+
+// HEAD responses keep the headers of the GET one, with the length of the
+// body they don't send
+fn without_body(res: Response<BodyChunks>) -> Response<BodyChunks> {
+    let (mut parts, chunks) = res.into_parts();
+    let mut length = Some(0);
+    for chunk in chunks {
+        match chunk {
+            Ok(chunk) => length = length.map(|length| length + chunk.len()),
+            Err(err) => {
+                eprintln!("Errored during response streaming: {err}");
+                length = None;
+                break;
+            }
+        }
+    }
+
+    if let Some(length) = length.filter(|_| parts.status != StatusCode::NOT_MODIFIED) {
+        parts
+            .headers
+            .entry(http::header::CONTENT_LENGTH)
+            .or_insert(length.into());
+    }
+    Response::from_parts(parts, single_chunk(Bytes::new()))
+}
+
+// A panicking handler is answered with a problem+json 500, the panic
+// message staying in the logs. Only native builds (tests, local-dev) unwind,
+// under `run` wasm ones are answered by the panic hook before aborting.
+#[cfg(not(target_arch = "wasm32"))]
+fn call_handler<I, O, F>(handler: F, req: Request<I>) -> Result<Result<Response<O>>, ApiError>
+where
+    F: FnOnce(Request<I>) -> Result<Response<O>>,
+{
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| handler(req))).map_err(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown panic");
+        eprintln!("Handler panicked: {message}");
+
+        ApiError::internal("Internal server error")
+    })
+}
+
+#[cfg(target_arch = "wasm32")]
+fn call_handler<I, O, F>(handler: F, req: Request<I>) -> Result<Result<Response<O>>, ApiError>
+where
+    F: FnOnce(Request<I>) -> Result<Response<O>>,
+{
+    Ok(handler(req))
+}
+
+/// Time elapsed since an arbitrary point, from the WASI monotonic clock.
+#[cfg(target_arch = "wasm32")]
+pub fn monotonic_now() -> Duration {
+    Duration::from_nanos(bindings::wasi::clocks::monotonic_clock::now())
+}
+
+/// Time elapsed since an arbitrary point, from the WASI monotonic clock.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn monotonic_now() -> Duration {
+    static START: std::sync::LazyLock<std::time::Instant> =
+        std::sync::LazyLock::new(std::time::Instant::now);
+    START.elapsed()
+}
+
+thread_local! {
+    static DEADLINE: std::cell::Cell<Option<Duration>> = const { std::cell::Cell::new(None) };
+}
+
+/// Time left before the `handler_timeout` of the request being handled, zero
+/// once it passed, `None` without one or outside of a handler. Outbound
+/// clients bound their calls with it.
+pub fn remaining_time() -> Option<Duration> {
+    DEADLINE
+        .get()
+        .map(|deadline| deadline.saturating_sub(monotonic_now()))
+}
+
+// Requests without a Content-Type are let through and left to the body parser
+fn check_content_type(headers: &http::HeaderMap, expected: &'static [&'static str]) -> Result<()> {
+    if expected.is_empty() {
+        return Ok(());
+    }
+
+    match headers.get(http::header::CONTENT_TYPE) {
+        Some(value)
+            if !value
+                .to_str()
+                .is_ok_and(|value| body::matches_any_media_type(value, expected)) =>
+        {
+            Err(UnsupportedMediaType { expected }.into())
+        }
+        _ => Ok(()),
+    }
+}
+
+// Reject declared oversized bodies before reading a single byte
+fn check_content_length(headers: &http::HeaderMap, limit: Option<usize>) -> Result<()> {
+    let Some(limit) = limit else {
+        return Ok(());
+    };
+
+    match content_length(headers) {
+        Some(length) if length > limit => Err(PayloadTooLarge { limit }.into()),
+        _ => Ok(()),
+    }
+}
+
+fn content_length(headers: &http::HeaderMap) -> Option<usize> {
+    headers
+        .get(http::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok())
+}
+
+fn body_error_status(err: &anyhow::Error) -> StatusCode {
+    if err.is::<PayloadTooLarge>() {
+        StatusCode::PAYLOAD_TOO_LARGE
+    } else if err.is::<UnsupportedMediaType>() {
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    } else {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+fn method_not_allowed_response(
+    method: &Method,
+    allowed_methods: &[Method],
+    on_error: ErrorHandler,
+) -> Response<Bytes> {
+    let mut res = on_error(
+        StatusCode::METHOD_NOT_ALLOWED,
+        &anyhow::anyhow!("Method {method} not allowed"),
+    );
+    res.headers_mut()
+        .insert(http::header::ALLOW, allow_header(allowed_methods));
+    res
+}
+
+// OPTIONS on a known path lists its methods, unless a route handles it
+fn options_response(allowed_methods: &[Method]) -> Response<Bytes> {
+    let mut methods = allowed_methods.to_vec();
+    methods.push(Method::OPTIONS);
+
+    Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header(http::header::ALLOW, allow_header(&methods))
+        .body(Bytes::new())
+        .unwrap()
+}
+
+fn allow_header(methods: &[Method]) -> http::HeaderValue {
+    let allow = methods
+        .iter()
+        .map(Method::as_str)
+        .collect::<Vec<_>>()
+        .join(", ");
+    allow.parse().unwrap()
+}
+
+/// Answers `{"error": "..."}`, with the `code` of an `ApiError`.
+pub fn json_error_response(status_code: StatusCode, err: &anyhow::Error) -> Response<Bytes> {
+    let mut body = match err.downcast_ref::<ApiError>() {
+        Some(err) => err.to_json(),
+        None => serde_json::json!({
+            "error": err.to_string(),
+        }),
+    };
+    // List settings problems one by one, not only joined in `error`
+    if let Some(SettingsErrors(errors)) = err.downcast_ref() {
+        body["errors"] = serde_json::json!(errors);
+    }
+
+    let (mut parts, ()) = Response::builder()
+        .status(status_code)
+        .body(())
+        .unwrap()
+        .into_parts();
+    let body = Json(body);
+    body.extend_response_parts(&mut parts);
+    Response::from_parts(parts, body.into_body().unwrap())
+}
+
+/// Answers a minimal HTML page, for routes opened in a browser.
+pub fn html_error_response(status_code: StatusCode, err: &anyhow::Error) -> Response<Bytes> {
+    let title = status_code.canonical_reason().unwrap_or("Error");
+    let page = format!(
+        "<!DOCTYPE html>\n<html>\n<head><title>{title}</title></head>\n<body>\n<h1>{title}</h1>\n<p>{}</p>\n</body>\n</html>\n",
+        escape_html(&err.to_string()),
+    );
+
+    let (mut parts, ()) = Response::builder()
+        .status(status_code)
+        .body(())
+        .unwrap()
+        .into_parts();
+    let page = Html(page);
+    page.extend_response_parts(&mut parts);
+    Response::from_parts(parts, page.into_body().unwrap())
+}
+
+/// Answers the bare error message, for command line clients.
+pub fn text_error_response(status_code: StatusCode, err: &anyhow::Error) -> Response<Bytes> {
+    Response::builder()
+        .status(status_code)
+        .header(http::header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(Bytes::from(format!("{err}\n")))
+        .unwrap()
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_json_error_response_bad_request() {
+        let err = anyhow::anyhow!("invalid input");
+        let response = json_error_response(StatusCode::BAD_REQUEST, &err);
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body_bytes = response.body();
+        let body_str = std::str::from_utf8(body_bytes).unwrap();
+        assert!(body_str.contains("\"error\":\"invalid input\""));
+    }
+
+    #[test]
+    fn test_json_error_response_internal_server_error() {
+        let err = anyhow::anyhow!("something went wrong");
+        let response = json_error_response(StatusCode::INTERNAL_SERVER_ERROR, &err);
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let body_bytes = response.body();
+        let body_str = std::str::from_utf8(body_bytes).unwrap();
+        assert!(body_str.contains("\"error\":\"something went wrong\""));
+    }
+
+    #[test]
+    fn test_json_error_response_empty_error() {
+        let err = anyhow::anyhow!("");
+        let response = json_error_response(StatusCode::NOT_FOUND, &err);
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let body_bytes = response.body();
+        let body_str = std::str::from_utf8(body_bytes).unwrap();
+        assert!(body_str.contains("\"error\":\"\""));
+    }
+
+    #[test]
+    fn test_check_content_length_within_limit() {
+        let mut headers = http::HeaderMap::new();
+        headers.insert(http::header::CONTENT_LENGTH, "10".parse().unwrap());
+        assert!(check_content_length(&headers, Some(10)).is_ok());
+    }
+
+    #[test]
+    fn test_check_content_length_exceeds_limit() {
+        let mut headers = http::HeaderMap::new();
+        headers.insert(http::header::CONTENT_LENGTH, "11".parse().unwrap());
+        let err = check_content_length(&headers, Some(10)).unwrap_err();
+        assert_eq!(body_error_status(&err), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn test_check_content_length_without_limit_or_header() {
+        let mut headers = http::HeaderMap::new();
+        assert!(check_content_length(&headers, Some(10)).is_ok());
+
+        headers.insert(http::header::CONTENT_LENGTH, "11".parse().unwrap());
+        assert!(check_content_length(&headers, None).is_ok());
+    }
+
+    #[test]
+    fn test_body_error_status_bad_request() {
+        let err = anyhow::anyhow!("expected value at line 1 column 1");
+        assert_eq!(body_error_status(&err), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_check_content_type_matching() {
+        let mut headers = http::HeaderMap::new();
+        headers.insert(
+            http::header::CONTENT_TYPE,
+            "application/json; charset=utf-8".parse().unwrap(),
+        );
+        assert!(check_content_type(&headers, &["application/json"]).is_ok());
+    }
+
+    #[test]
+    fn test_check_content_type_mismatch() {
+        let mut headers = http::HeaderMap::new();
+        headers.insert(
+            http::header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded".parse().unwrap(),
+        );
+        let err = check_content_type(&headers, &["application/json"]).unwrap_err();
+        assert_eq!(body_error_status(&err), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(
+            err.to_string(),
+            "Unsupported content type, expected 'application/json'"
+        );
+    }
+
+    #[test]
+    fn test_check_content_type_missing_or_unconstrained() {
+        let mut headers = http::HeaderMap::new();
+        assert!(check_content_type(&headers, &["application/json"]).is_ok());
+
+        headers.insert(http::header::CONTENT_TYPE, "text/plain".parse().unwrap());
+        assert!(check_content_type(&headers, &[]).is_ok());
+    }
+
+    #[test]
+    fn test_method_not_allowed_response() {
+        let response =
+            method_not_allowed_response(&Method::GET, &[Method::POST], json_error_response);
+
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers().get(http::header::ALLOW).unwrap(), "POST");
+
+        let body_str = std::str::from_utf8(response.body()).unwrap();
+        assert!(body_str.contains("\"error\":\"Method GET not allowed\""));
+    }
+
+    #[test]
+    fn test_method_not_allowed_response_lists_all_methods() {
+        let response = method_not_allowed_response(
+            &Method::DELETE,
+            &[Method::GET, Method::POST],
+            json_error_response,
+        );
+        assert_eq!(
+            response.headers().get(http::header::ALLOW).unwrap(),
+            "GET, POST"
+        );
+    }
+
+    #[test]
+    fn test_handle_bytes_options() {
+        let req = |uri| {
+            Request::builder()
+                .method(Method::OPTIONS)
+                .uri(uri)
+                .body(Bytes::new())
+                .unwrap()
+        };
+        let handler = |_req: Request<()>| -> Result<Response<()>> { unreachable!() };
+
+        let allowed_methods = [Method::GET, Method::HEAD];
+        let response = handle_bytes(
+            req("/health"),
+            &allowed_methods,
+            &RunOptions::default(),
+            handler,
+        );
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            response.headers().get(http::header::ALLOW).unwrap(),
+            "GET, HEAD, OPTIONS"
+        );
+
+        let response = handle_bytes(req("/unknown"), &[], &RunOptions::default(), handler);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_handle_bytes() {
+        let req = Request::builder()
+            .method(Method::POST)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(Bytes::from_static(br#"{"message":"hi"}"#))
+            .unwrap();
+
+        let options = RunOptions {
+            max_body_bytes: Some(64),
+            ..Default::default()
+        };
+        let response = handle_bytes(req, &[Method::POST], &options, |req| {
+            let Json(value): Json<serde_json::Value> = req.into_body();
+            Ok(Response::new(Json(value)))
+        });
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), &Bytes::from_static(br#"{"message":"hi"}"#));
+    }
+
+    #[test]
+    fn test_handle_bytes_stream() {
+        let req = Request::builder()
+            .method(Method::GET)
+            .body(Bytes::new())
+            .unwrap();
+
+        let response = handle_bytes(
+            req,
+            &[Method::GET],
+            &RunOptions::default(),
+            |_req: Request<()>| {
+                let chunks = (1..=3).map(|i| Ok(Bytes::from(format!("line {i}\n"))));
+                Ok(Response::new(body::Stream(chunks)))
+            },
+        );
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), &Bytes::from("line 1\nline 2\nline 3\n"));
+    }
+
+    #[test]
+    fn test_handle_bytes_stream_error() {
+        let req = Request::builder()
+            .method(Method::GET)
+            .body(Bytes::new())
+            .unwrap();
+
+        let response = handle_bytes(
+            req,
+            &[Method::GET],
+            &RunOptions::default(),
+            |_req: Request<()>| {
+                let chunks = vec![Ok(Bytes::from("partial")), Err(anyhow::anyhow!("boom"))];
+                Ok(Response::new(body::Stream(chunks)))
+            },
+        );
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body_str = std::str::from_utf8(response.body()).unwrap();
+        assert!(body_str.contains("\"error\":\"boom\""));
+    }
+
+    #[test]
+    fn test_handle_bytes_over_limit() {
+        let req = Request::builder()
+            .method(Method::POST)
+            .body(Bytes::from_static(br#"{"message":"hi"}"#))
+            .unwrap();
+
+        let options = RunOptions {
+            max_body_bytes: Some(4),
+            ..Default::default()
+        };
+        let response = handle_bytes(req, &[Method::POST], &options, |req| {
+            let Json(value): Json<serde_json::Value> = req.into_body();
+            Ok(Response::new(Json(value)))
+        });
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn test_handle_bytes_no_route() {
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/unknown")
+            .body(Bytes::new())
+            .unwrap();
+
+        let response = handle_bytes(
+            req,
+            &[],
+            &RunOptions::default(),
+            |req: Request<Json<serde_json::Value>>| Ok(Response::new(req.into_body())),
+        );
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body_str = std::str::from_utf8(response.body()).unwrap();
+        assert!(body_str.contains("\"error\":\"No route for /unknown\""));
+    }
+
+    #[test]
+    fn test_handle_bytes_api_error() {
+        let req = Request::builder()
+            .method(Method::POST)
+            .body(Bytes::from_static(br#"{}"#))
+            .unwrap();
+
+        let response = handle_bytes(
+            req,
+            &[Method::POST],
+            &RunOptions::default(),
+            |_req: Request<Json<serde_json::Value>>| -> Result<Response<Json<serde_json::Value>>> {
+                Err(ApiError::too_many_requests("Slow down").into())
+            },
+        );
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({ "error": "Slow down", "code": "too_many_requests" })
+        );
+    }
+
+    #[test]
+    fn test_handle_bytes_custom_error_handler() {
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri("/missing")
+            .body(Bytes::new())
+            .unwrap();
+        let options = RunOptions {
+            error_handler: Some(html_error_response),
+            ..Default::default()
+        };
+
+        let response = handle_bytes(
+            req,
+            &[],
+            &options,
+            |req: Request<Json<serde_json::Value>>| Ok(Response::new(req.into_body())),
+        );
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.headers().get(http::header::CONTENT_TYPE).unwrap(),
+            "text/html; charset=utf-8"
+        );
+        let body_str = std::str::from_utf8(response.body()).unwrap();
+        assert!(body_str.contains("<h1>Not Found</h1>"));
+        assert!(body_str.contains("<p>No route for /missing</p>"));
+    }
+
+    #[test]
+    fn test_handle_bytes_handler_panic() {
+        let req = Request::builder()
+            .method(Method::GET)
+            .body(Bytes::new())
+            .unwrap();
+
+        let response = handle_bytes(
+            req,
+            &[Method::GET],
+            &RunOptions::default(),
+            |_req: Request<()>| -> Result<Response<Json<serde_json::Value>>> {
+                panic!("secret detail")
+            },
+        );
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            response.headers().get(http::header::CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            body,
+            ApiError::internal("Internal server error").to_problem()
+        );
+    }
+
+    // `run` over a body already read, with a sender recording the responses
+    // in place of the WASI outparam
+    fn run_bytes<F>(handler: F) -> Vec<Response<Vec<u8>>>
+    where
+        F: FnOnce(Request<()>) -> Result<Response<Json<serde_json::Value>>>,
+    {
+        let sent = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let sink = sent.clone();
+        let send: Sender = Box::new(move |res: Response<BodyChunks>| {
+            let (parts, chunks) = res.into_parts();
+            let body: Vec<Bytes> = chunks.collect::<Result<_>>()?;
+            sink.borrow_mut()
+                .push(Response::from_parts(parts, body.concat()));
+            Ok(())
+        });
+        let req = Request::builder()
+            .method(Method::GET)
+            .body(Bytes::new())
+            .unwrap();
+
+        serve(
+            req,
+            send,
+            &[Method::GET],
+            |_parts| RunOptions::default(),
+            |data: Bytes, content_type, _length, _limit| <()>::from_content(data, content_type),
+            handler,
+        );
+        sent.take()
+    }
+
+    #[test]
+    fn test_run_sends_response() {
+        let sent = run_bytes(|_req| Ok(Response::new(Json(serde_json::json!({"ok": true})))));
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].status(), StatusCode::OK);
+        assert_eq!(sent[0].body(), br#"{"ok":true}"#);
+    }
+
+    #[test]
+    fn test_run_handler_panic() {
+        let sent = run_bytes(|_req| panic!("secret detail"));
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            sent[0].headers().get(http::header::CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+        let body: serde_json::Value = serde_json::from_slice(sent[0].body()).unwrap();
+        assert_eq!(
+            body,
+            ApiError::internal("Internal server error").to_problem()
+        );
+        assert!(SENDER.with_borrow(Option::is_none));
+    }
+
+    #[test]
+    fn test_handle_bytes_handler_timeout() {
+        let req = || {
+            Request::builder()
+                .method(Method::GET)
+                .body(Bytes::new())
+                .unwrap()
+        };
+        let handler = |_req: Request<()>| {
+            let before = remaining_time().unwrap();
+            std::thread::sleep(Duration::from_millis(20));
+            let after = remaining_time().unwrap();
+            Ok(Response::new(Json(serde_json::json!({
+                "started": !before.is_zero(),
+                "expired": after.is_zero(),
+            }))))
+        };
+
+        let options = RunOptions {
+            handler_timeout: Some(Duration::from_millis(10)),
+            ..Default::default()
+        };
+        let response = handle_bytes(req(), &[Method::GET], &options, handler);
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["code"], "gateway_timeout");
+        assert_eq!(remaining_time(), None);
+
+        let options = RunOptions {
+            handler_timeout: Some(Duration::from_secs(10)),
+            ..Default::default()
+        };
+        let response = handle_bytes(req(), &[Method::GET], &options, handler);
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body, serde_json::json!({"started": true, "expired": false}));
+    }
+
+    #[test]
+    fn test_handle_bytes_cacheable() {
+        let req = |if_none_match: Option<&http::HeaderValue>| {
+            let mut req = Request::builder()
+                .method(Method::GET)
+                .body(Bytes::new())
+                .unwrap();
+            if let Some(value) = if_none_match {
+                req.headers_mut()
+                    .insert(http::header::IF_NONE_MATCH, value.clone());
+            }
+            req
+        };
+        let handler = |_req: Request<()>| {
+            let mut res = Response::new(Json(serde_json::json!({"version": "1.0.0"})));
+            res.extensions_mut().insert(etag::Cacheable);
+            Ok(res)
+        };
+
+        let options = RunOptions::default();
+        let response = handle_bytes(req(None), &[Method::GET], &options, handler);
+        assert_eq!(response.status(), StatusCode::OK);
+        let tag = response.headers().get(http::header::ETAG).unwrap().clone();
+
+        let response = handle_bytes(req(Some(&tag)), &[Method::GET], &options, handler);
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert!(response.body().is_empty());
+    }
+
+    #[test]
+    fn test_handle_bytes_head() {
+        let req = Request::builder()
+            .method(Method::HEAD)
+            .body(Bytes::new())
+            .unwrap();
+
+        let response = handle_bytes(req, &[Method::GET, Method::HEAD], &RunOptions::default(), {
+            |_req: Request<()>| Ok(Response::new(Json(serde_json::json!({"ok": true}))))
+        });
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(http::header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(
+            response
+                .headers()
+                .get(http::header::CONTENT_LENGTH)
+                .unwrap(),
+            "11"
+        );
+        assert!(response.body().is_empty());
+    }
+
+    #[test]
+    fn test_html_error_response_escapes_message() {
+        let err = anyhow::anyhow!("<script>alert('x')</script>");
+        let response = html_error_response(StatusCode::BAD_REQUEST, &err);
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body_str = std::str::from_utf8(response.body()).unwrap();
+        assert!(body_str.contains("<p>&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt;</p>"));
+    }
+
+    #[test]
+    fn test_json_error_response_settings_errors() {
+        let err = SettingsErrors(vec![
+            "Missing webhook_url setting".to_string(),
+            "Invalid max_body_bytes setting: expected usize, got \"lots\"".to_string(),
+        ]);
+        let response = json_error_response(StatusCode::INTERNAL_SERVER_ERROR, &err.into());
+
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "error": "Missing webhook_url setting; Invalid max_body_bytes setting: expected usize, got \"lots\"",
+                "errors": ["Missing webhook_url setting", "Invalid max_body_bytes setting: expected usize, got \"lots\""],
+            })
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use anyhow::Result;
+use http::{Request, Response};
+
+// Middleware wrapping routed handlers
+//
+// Layers run in registration order around the matched handler and decide
+// whether to call `next`, so cross-cutting concerns stay out of handlers.
+
+pub trait Middleware<I, O> {
+    fn handle(&self, req: Request<I>, next: Next<'_, I, O>) -> Result<Response<O>>;
+}
+
+impl<I, O, F> Middleware<I, O> for F
+where
+    F: Fn(Request<I>, Next<'_, I, O>) -> Result<Response<O>>,
+{
+    fn handle(&self, req: Request<I>, next: Next<'_, I, O>) -> Result<Response<O>> {
+        self(req, next)
+    }
+}
+
+pub(super) type BoxedMiddleware<'a, I, O> = Box<dyn Middleware<I, O> + 'a>;
+
+/// Remaining layers and the handler they wrap.
+pub struct Next<'a, I, O> {
+    layers: &'a [BoxedMiddleware<'a, I, O>],
+    handler: &'a dyn Fn(Request<I>) -> Result<Response<O>>,
+}
+
+impl<'a, I, O> Next<'a, I, O> {
+    pub(super) fn new(
+        layers: &'a [BoxedMiddleware<'a, I, O>],
+        handler: &'a dyn Fn(Request<I>) -> Result<Response<O>>,
+    ) -> Self {
+        Self { layers, handler }
+    }
+
+    pub fn run(self, req: Request<I>) -> Result<Response<O>> {
+        match self.layers.split_first() {
+            Some((layer, layers)) => layer.handle(
+                req,
+                Next {
+                    layers,
+                    handler: self.handler,
+                },
+            ),
+            None => (self.handler)(req),
+        }
+    }
+}
+
+/// Logs every routed request with its outcome.
+pub struct Logger;
+
+impl<I, O> Middleware<I, O> for Logger {
+    fn handle(&self, req: Request<I>, next: Next<'_, I, O>) -> Result<Response<O>> {
+        let method = req.method().clone();
+        let path = req.uri().path().to_string();
+
+        let res = next.run(req);
+        match &res {
+            Ok(res) => eprintln!("{method} {path} -> {}", res.status()),
+            Err(err) => eprintln!("{method} {path} -> error: {err}"),
+        }
+        res
+    }
+}
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Echoes the caller's `x-request-id` header on the response.
+pub struct RequestId;
+
+impl<I, O> Middleware<I, O> for RequestId {
+    fn handle(&self, req: Request<I>, next: Next<'_, I, O>) -> Result<Response<O>> {
+        let request_id = req.headers().get(REQUEST_ID_HEADER).cloned();
+
+        let mut res = next.run(req)?;
+        if let Some(request_id) = request_id {
+            res.headers_mut().insert(REQUEST_ID_HEADER, request_id);
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    fn handler(req: Request<()>) -> Result<Response<String>> {
+        Ok(Response::new(req.uri().path().to_string()))
+    }
+
+    fn request() -> Request<()> {
+        Request::builder()
+            .uri("/message")
+            .header(REQUEST_ID_HEADER, "abc-123")
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_next_runs_layers_in_order() {
+        let calls = RefCell::new(Vec::new());
+        let outer = |req: Request<()>, next: Next<'_, (), String>| {
+            calls.borrow_mut().push("outer");
+            next.run(req)
+        };
+        let inner = |req: Request<()>, next: Next<'_, (), String>| {
+            calls.borrow_mut().push("inner");
+            next.run(req)
+        };
+        let layers: Vec<BoxedMiddleware<(), String>> = vec![Box::new(outer), Box::new(inner)];
+
+        let res = Next::new(&layers, &handler).run(request()).unwrap();
+        assert_eq!(res.body(), "/message");
+        assert_eq!(*calls.borrow(), vec!["outer", "inner"]);
+    }
+
+    #[test]
+    fn test_layer_short_circuits() {
+        let deny = |_req: Request<()>, _next: Next<'_, (), String>| {
+            let mut res = Response::new("denied".to_string());
+            *res.status_mut() = http::StatusCode::UNAUTHORIZED;
+            Ok(res)
+        };
+        let layers: Vec<BoxedMiddleware<(), String>> = vec![Box::new(deny)];
+
+        let res = Next::new(&layers, &handler).run(request()).unwrap();
+        assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED);
+        assert_eq!(res.body(), "denied");
+    }
+
+    #[test]
+    fn test_request_id() {
+        let layers: Vec<BoxedMiddleware<(), String>> = vec![Box::new(RequestId)];
+
+        let res = Next::new(&layers, &handler).run(request()).unwrap();
+        assert_eq!(res.headers().get(REQUEST_ID_HEADER).unwrap(), "abc-123");
+    }
+
+    #[test]
+    fn test_logger_passes_through() {
+        let layers: Vec<BoxedMiddleware<(), String>> = vec![Box::new(Logger)];
+
+        let res = Next::new(&layers, &handler).run(request()).unwrap();
+        assert_eq!(res.body(), "/message");
+    }
+}
@@ -0,0 +1,221 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use super::body::FromBody;
+
+// multipart/form-data parsing (RFC 7578)
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Part {
+    pub name: Option<String>,
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    pub data: Bytes,
+}
+
+impl Part {
+    pub fn is_file(&self) -> bool {
+        self.filename.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Multipart {
+    pub parts: Vec<Part>,
+}
+
+impl Multipart {
+    pub fn parse(data: Bytes, boundary: &str) -> Result<Self> {
+        let delimiter = format!("--{boundary}");
+        let next_delimiter = format!("\r\n{delimiter}");
+
+        let mut pos = find(&data, delimiter.as_bytes(), 0)
+            .ok_or_else(|| anyhow::anyhow!("Missing multipart boundary in body"))?;
+        let mut parts = vec![];
+        loop {
+            pos += delimiter.len();
+            if data[pos..].starts_with(b"--") {
+                break;
+            }
+            if !data[pos..].starts_with(b"\r\n") {
+                anyhow::bail!("Malformed multipart body");
+            }
+            pos += 2;
+
+            // A part may have no headers at all, in which case the blank
+            // line immediately follows the delimiter
+            let (headers, content_start) = if data[pos..].starts_with(b"\r\n") {
+                ("", pos + 2)
+            } else {
+                let headers_end = find(&data, b"\r\n\r\n", pos)
+                    .ok_or_else(|| anyhow::anyhow!("Malformed multipart part headers"))?;
+                (
+                    std::str::from_utf8(&data[pos..headers_end])?,
+                    headers_end + 4,
+                )
+            };
+            let content_end = find(&data, next_delimiter.as_bytes(), content_start)
+                .ok_or_else(|| anyhow::anyhow!("Unterminated multipart body"))?;
+
+            let mut part = Part {
+                name: None,
+                filename: None,
+                content_type: None,
+                data: data.slice(content_start..content_end),
+            };
+            for line in headers.split("\r\n") {
+                let Some((name, value)) = line.split_once(':') else {
+                    continue;
+                };
+                if name.trim().eq_ignore_ascii_case("content-disposition") {
+                    part.name = parameter(value, "name");
+                    part.filename = parameter(value, "filename");
+                } else if name.trim().eq_ignore_ascii_case("content-type") {
+                    part.content_type = Some(value.trim().to_string());
+                }
+            }
+            parts.push(part);
+
+            pos = content_end + 2;
+        }
+
+        Ok(Self { parts })
+    }
+
+    /// Text fields, i.e. named parts which aren't files.
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &Bytes)> {
+        self.parts
+            .iter()
+            .filter(|part| !part.is_file())
+            .filter_map(|part| Some((part.name.as_deref()?, &part.data)))
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = &Part> {
+        self.parts.iter().filter(|part| part.is_file())
+    }
+}
+
+impl FromBody for Multipart {
+    const MEDIA_TYPES: &'static [&'static str] = &["multipart/form-data"];
+
+    fn from_data(_: Bytes) -> Result<Self> {
+        anyhow::bail!("Missing multipart boundary in Content-Type")
+    }
+
+    fn from_content(data: Bytes, content_type: Option<&str>) -> Result<Self> {
+        let boundary = content_type
+            .and_then(|content_type| parameter(content_type, "boundary"))
+            .ok_or_else(|| anyhow::anyhow!("Missing multipart boundary in Content-Type"))?;
+        Self::parse(data, &boundary)
+    }
+}
+
+// Reads a `key=value` parameter from a header value such as
+// `form-data; name="file"; filename="a.txt"`
+fn parameter(value: &str, key: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case(key) {
+            return None;
+        }
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(value);
+        Some(value.to_string())
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|index| index + from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = "--XYZ\r\n\
+        Content-Disposition: form-data; name=\"message\"\r\n\
+        \r\n\
+        build failed\r\n\
+        --XYZ\r\n\
+        Content-Disposition: form-data; name=\"log\"; filename=\"build.log\"\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        line 1\r\nline 2\r\n\
+        --XYZ--\r\n";
+
+    #[test]
+    fn test_parse() {
+        let multipart = Multipart::parse(Bytes::from(BODY), "XYZ").unwrap();
+        assert_eq!(multipart.parts.len(), 2);
+
+        let fields: Vec<_> = multipart.fields().collect();
+        assert_eq!(fields, vec![("message", &Bytes::from("build failed"))]);
+
+        let files: Vec<_> = multipart.files().collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name.as_deref(), Some("log"));
+        assert_eq!(files[0].filename.as_deref(), Some("build.log"));
+        assert_eq!(files[0].content_type.as_deref(), Some("text/plain"));
+        assert_eq!(files[0].data, Bytes::from("line 1\r\nline 2"));
+    }
+
+    #[test]
+    fn test_parse_part_without_headers() {
+        let body = "--XYZ\r\n\r\nanonymous\r\n--XYZ--";
+        let multipart = Multipart::parse(Bytes::from(body), "XYZ").unwrap();
+        assert_eq!(multipart.parts[0].name, None);
+        assert_eq!(multipart.parts[0].data, Bytes::from("anonymous"));
+    }
+
+    #[test]
+    fn test_parse_missing_boundary() {
+        let result = Multipart::parse(Bytes::from("no boundary here"), "XYZ");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_unterminated() {
+        let body = "--XYZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nvalue";
+        let result = Multipart::parse(Bytes::from(body), "XYZ");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Unterminated multipart body"
+        );
+    }
+
+    #[test]
+    fn test_from_content() {
+        let multipart = Multipart::from_content(
+            Bytes::from(BODY),
+            Some("multipart/form-data; boundary=\"XYZ\""),
+        )
+        .unwrap();
+        assert_eq!(multipart.parts.len(), 2);
+    }
+
+    #[test]
+    fn test_from_content_without_boundary() {
+        let result = Multipart::from_content(Bytes::from(BODY), Some("multipart/form-data"));
+        assert!(result.is_err());
+        assert!(Multipart::from_data(Bytes::from(BODY)).is_err());
+    }
+
+    #[test]
+    fn test_parameter() {
+        let value = "form-data; name=\"file\"; filename=\"a.txt\"";
+        assert_eq!(parameter(value, "name").as_deref(), Some("file"));
+        assert_eq!(parameter(value, "filename").as_deref(), Some("a.txt"));
+        assert_eq!(parameter(value, "missing"), None);
+        assert_eq!(
+            parameter("multipart/form-data; BOUNDARY=abc", "boundary").as_deref(),
+            Some("abc")
+        );
+    }
+}
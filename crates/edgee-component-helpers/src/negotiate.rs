@@ -0,0 +1,266 @@
+use anyhow::Result;
+use bytes::Bytes;
+use http::HeaderMap;
+
+use super::body::{Html, IntoBody, Json};
+use super::ErrorHandler;
+
+// Accept header content negotiation
+//
+// Callers that sent no Accept header, or accept nothing we offer, get the
+// first offered format rather than a 406.
+
+/// Representation picked for a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Html,
+    Text,
+}
+
+impl Format {
+    pub const ALL: &'static [Format] = &[Format::Json, Format::Html, Format::Text];
+
+    pub fn media_type(self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Html => "text/html",
+            Format::Text => "text/plain",
+        }
+    }
+
+    /// Picks the format of `offered` the request's `Accept` header prefers,
+    /// ties going to the earliest offered one.
+    pub fn negotiate(headers: &HeaderMap, offered: &[Format]) -> Format {
+        let ranges = headers
+            .get_all(http::header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(MediaRange::parse)
+            .collect::<Vec<_>>();
+
+        let mut best: Option<(Format, f32)> = None;
+        for &format in offered {
+            let quality = match ranges.is_empty() {
+                true => 1.0,
+                false => format.quality(&ranges),
+            };
+            if quality > 0.0 && best.is_none_or(|(_, best)| quality > best) {
+                best = Some((format, quality));
+            }
+        }
+        best.map_or(offered[0], |(format, _)| format)
+    }
+
+    // The most specific range matching the media type sets its quality
+    fn quality(self, ranges: &[MediaRange<'_>]) -> f32 {
+        let (kind, subtype) = self.media_type().split_once('/').unwrap();
+        ranges
+            .iter()
+            .filter_map(|range| {
+                let specificity = match (range.kind, range.subtype) {
+                    ("*", "*") => 0,
+                    (k, "*") if k.eq_ignore_ascii_case(kind) => 1,
+                    (k, s) if k.eq_ignore_ascii_case(kind) && s.eq_ignore_ascii_case(subtype) => 2,
+                    _ => return None,
+                };
+                Some((specificity, range.quality))
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map_or(0.0, |(_, quality)| quality)
+    }
+}
+
+struct MediaRange<'a> {
+    kind: &'a str,
+    subtype: &'a str,
+    quality: f32,
+}
+
+impl<'a> MediaRange<'a> {
+    fn parse(range: &'a str) -> Option<Self> {
+        let mut params = range.split(';');
+        let (kind, subtype) = params.next()?.trim().split_once('/')?;
+        let quality = params
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|quality| quality.parse::<f32>().ok())
+            .unwrap_or(1.0);
+        Some(Self {
+            kind,
+            subtype,
+            quality,
+        })
+    }
+}
+
+/// Plain text and HTML renderings of a result also served as JSON.
+pub trait Represent: serde::Serialize {
+    fn to_text(&self) -> String;
+
+    /// The text rendering in a minimal page by default.
+    fn to_html(&self) -> String {
+        format!(
+            "<!DOCTYPE html>\n<html>\n<body>\n<pre>{}</pre>\n</body>\n</html>\n",
+            super::escape_html(&self.to_text()),
+        )
+    }
+}
+
+/// Objects are rendered one `key: value` line per field.
+impl Represent for serde_json::Value {
+    fn to_text(&self) -> String {
+        match self {
+            serde_json::Value::Object(map) => map
+                .iter()
+                .map(|(key, value)| match value {
+                    serde_json::Value::String(value) => format!("{key}: {value}\n"),
+                    value => format!("{key}: {value}\n"),
+                })
+                .collect(),
+            serde_json::Value::String(value) => format!("{value}\n"),
+            value => format!("{value}\n"),
+        }
+    }
+}
+
+/// Response body rendered in the format negotiated with the caller.
+#[derive(Debug, Clone)]
+pub struct Negotiated<T> {
+    pub format: Format,
+    pub value: T,
+}
+
+impl<T> Negotiated<T> {
+    /// Negotiates between every format.
+    pub fn new(headers: &HeaderMap, value: T) -> Self {
+        Self {
+            format: Format::negotiate(headers, Format::ALL),
+            value,
+        }
+    }
+}
+
+impl<T> From<Json<T>> for Negotiated<T> {
+    fn from(Json(value): Json<T>) -> Self {
+        Self {
+            format: Format::Json,
+            value,
+        }
+    }
+}
+
+impl<T> AsRef<T> for Negotiated<T> {
+    fn as_ref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: Represent> IntoBody for Negotiated<T> {
+    fn into_body(self) -> Result<Bytes> {
+        match self.format {
+            Format::Json => Json(self.value).into_body(),
+            Format::Html => Html(self.value.to_html()).into_body(),
+            Format::Text => Ok(Bytes::from(self.value.to_text())),
+        }
+    }
+
+    fn extend_response_parts(&self, parts: &mut http::response::Parts) {
+        let content_type = match self.format {
+            Format::Json => "application/json",
+            Format::Html => "text/html; charset=utf-8",
+            Format::Text => "text/plain; charset=utf-8",
+        };
+        parts
+            .headers
+            .entry(http::header::CONTENT_TYPE)
+            .or_insert(http::HeaderValue::from_static(content_type));
+        parts
+            .headers
+            .append(http::header::VARY, http::HeaderValue::from_static("Accept"));
+    }
+}
+
+/// Error handler answering in the format the request accepts.
+pub fn error_handler(headers: &HeaderMap) -> ErrorHandler {
+    match Format::negotiate(headers, Format::ALL) {
+        Format::Json => super::json_error_response,
+        Format::Html => super::html_error_response,
+        Format::Text => super::text_error_response,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accept(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(http::header::ACCEPT, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_negotiate_defaults_to_first_offered() {
+        assert_eq!(
+            Format::negotiate(&HeaderMap::new(), Format::ALL),
+            Format::Json
+        );
+        assert_eq!(Format::negotiate(&accept("*/*"), Format::ALL), Format::Json);
+        assert_eq!(
+            Format::negotiate(&accept("image/png"), Format::ALL),
+            Format::Json
+        );
+    }
+
+    #[test]
+    fn test_negotiate_quality() {
+        let browser = accept("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8");
+        assert_eq!(Format::negotiate(&browser, Format::ALL), Format::Html);
+
+        let headers = accept("application/json;q=0.5, text/plain");
+        assert_eq!(Format::negotiate(&headers, Format::ALL), Format::Text);
+
+        let headers = accept("text/*;q=0.3, text/html;q=0, application/json;q=0.1");
+        assert_eq!(Format::negotiate(&headers, Format::ALL), Format::Text);
+    }
+
+    #[test]
+    fn test_negotiated_body() {
+        let value = serde_json::json!({ "ok": true, "name": "slack" });
+
+        let (mut parts, ()) = http::Response::new(()).into_parts();
+        let body = Negotiated {
+            format: Format::Text,
+            value: value.clone(),
+        };
+        body.extend_response_parts(&mut parts);
+        assert_eq!(
+            parts.headers.get(http::header::CONTENT_TYPE).unwrap(),
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(parts.headers.get(http::header::VARY).unwrap(), "Accept");
+        assert_eq!(body.into_body().unwrap(), "name: slack\nok: true\n");
+
+        let body = Negotiated::new(&accept("text/html"), value);
+        assert_eq!(
+            body.into_body().unwrap(),
+            "<!DOCTYPE html>\n<html>\n<body>\n<pre>name: slack\nok: true\n</pre>\n</body>\n</html>\n"
+        );
+    }
+
+    #[test]
+    fn test_error_handler() {
+        let err = anyhow::anyhow!("Missing request body");
+
+        let res = error_handler(&accept("text/plain"))(http::StatusCode::BAD_REQUEST, &err);
+        assert_eq!(res.status(), http::StatusCode::BAD_REQUEST);
+        assert_eq!(res.body(), "Missing request body\n");
+
+        let res = error_handler(&HeaderMap::new())(http::StatusCode::BAD_REQUEST, &err);
+        assert_eq!(
+            res.headers().get(http::header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use http::{Request, Uri};
+
+/// URI query string deserialized via serde, e.g. `Query<SendParams>`.
+#[derive(Debug, Clone)]
+pub struct Query<T>(pub T);
+
+impl<T: serde::de::DeserializeOwned> Query<T> {
+    pub fn from_uri(uri: &Uri) -> Result<Self> {
+        let data = serde_urlencoded::from_str(uri.query().unwrap_or_default())
+            .map_err(|err| anyhow::anyhow!("Invalid query string: {err}"))?;
+        Ok(Self(data))
+    }
+
+    pub fn from_req<B>(req: &Request<B>) -> Result<Self> {
+        Self::from_uri(req.uri())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Params {
+        message: String,
+        limit: Option<u32>,
+    }
+
+    #[test]
+    fn test_query_from_uri() {
+        let uri: Uri = "/send?message=disk%20full&limit=3".parse().unwrap();
+        let Query(params) = Query::<Params>::from_uri(&uri).unwrap();
+        assert_eq!(
+            params,
+            Params {
+                message: "disk full".to_string(),
+                limit: Some(3),
+            }
+        );
+    }
+
+    #[test]
+    fn test_query_without_query_string() {
+        let req = Request::builder().uri("/send").body(()).unwrap();
+        let Query(params) =
+            Query::<std::collections::HashMap<String, String>>::from_req(&req).unwrap();
+        assert!(params.is_empty());
+
+        let err = Query::<Params>::from_req(&req).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid query string: missing field `message`"
+        );
+    }
+
+    #[test]
+    fn test_query_invalid_value() {
+        let uri: Uri = "/send?message=hi&limit=many".parse().unwrap();
+        let err = Query::<Params>::from_uri(&uri).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid query string: "));
+    }
+}
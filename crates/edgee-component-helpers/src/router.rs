@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::Result;
+use http::{Method, Request, Response};
+
+use super::middleware::{BoxedMiddleware, Middleware, Next};
+
+// Minimal method + path router
+//
+// Patterns are `/`-separated segments where `:name` captures a single segment
+// and a trailing `*name` captures the rest of the path, e.g. `PUT /message/:ts`.
+
+type Handler<'a, I, O> = Box<dyn Fn(Request<I>) -> Result<Response<O>> + 'a>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Static(String),
+    Param(String),
+    Wildcard(String),
+}
+
+struct Route<'a, I, O> {
+    method: Method,
+    segments: Vec<Segment>,
+    handler: Handler<'a, I, O>,
+}
+
+pub struct Router<'a, I, O> {
+    routes: Vec<Route<'a, I, O>>,
+    layers: Vec<BoxedMiddleware<'a, I, O>>,
+}
+
+impl<I, O> Default for Router<'_, I, O> {
+    fn default() -> Self {
+        Self {
+            routes: Vec::new(),
+            layers: Vec::new(),
+        }
+    }
+}
+
+impl<'a, I, O> Router<'a, I, O> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a handler, routes are matched in registration order.
+    pub fn route<F>(mut self, method: Method, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Request<I>) -> Result<Response<O>> + 'a,
+    {
+        self.routes.push(Route {
+            method,
+            segments: parse_pattern(pattern),
+            handler: Box::new(handler),
+        });
+        self
+    }
+
+    /// Wraps every route with a middleware, the first layer added being the
+    /// outermost.
+    pub fn layer<M>(mut self, middleware: M) -> Self
+    where
+        M: Middleware<I, O> + 'a,
+    {
+        self.layers.push(Box::new(middleware));
+        self
+    }
+
+    /// Methods registered for a path, empty when no route matches it. GET
+    /// routes also answer HEAD.
+    pub fn allowed_methods(&self, path: &str) -> Vec<Method> {
+        let mut methods: Vec<Method> = Vec::new();
+        for route in &self.routes {
+            if match_path(&route.segments, path).is_some() && !methods.contains(&route.method) {
+                methods.push(route.method.clone());
+            }
+        }
+        if methods.contains(&Method::GET) && !methods.contains(&Method::HEAD) {
+            methods.push(Method::HEAD);
+        }
+        methods
+    }
+
+    /// Calls the first matching handler with its `PathParams` set as a
+    /// request extension. HEAD requests without a HEAD route go to the GET
+    /// one, `run` drops the body it answers.
+    pub fn dispatch(&self, mut req: Request<I>) -> Result<Response<O>> {
+        let path = req.uri().path();
+        let route = self
+            .find(req.method(), path)
+            .or_else(|| match req.method() {
+                &Method::HEAD => self.find(&Method::GET, path),
+                _ => None,
+            });
+        match route {
+            Some((route, params)) => {
+                req.extensions_mut().insert(params);
+                Next::new(&self.layers, &route.handler).run(req)
+            }
+            None => anyhow::bail!("No route for {} {}", req.method(), req.uri().path()),
+        }
+    }
+
+    fn find(&self, method: &Method, path: &str) -> Option<(&Route<'a, I, O>, PathParams)> {
+        self.routes
+            .iter()
+            .filter(|route| route.method == method)
+            .find_map(|route| Some((route, match_path(&route.segments, path)?)))
+    }
+}
+
+/// Path parameters captured by the matched route.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PathParams(HashMap<String, String>);
+
+impl PathParams {
+    pub fn from_req<B>(req: &Request<B>) -> Option<&Self> {
+        req.extensions().get()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    /// Parses a parameter into `T`, e.g. `params.parse::<u32>("id")`.
+    pub fn parse<T>(&self, name: &str) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        let value = self
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("Missing path parameter '{name}'"))?;
+        value
+            .parse()
+            .map_err(|err| anyhow::anyhow!("Invalid path parameter '{name}': {err}"))
+    }
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+fn parse_pattern(pattern: &str) -> Vec<Segment> {
+    split_path(pattern)
+        .map(|segment| {
+            if let Some(name) = segment.strip_prefix(':') {
+                Segment::Param(name.to_string())
+            } else if let Some(name) = segment.strip_prefix('*') {
+                Segment::Wildcard(name.to_string())
+            } else {
+                Segment::Static(segment.to_string())
+            }
+        })
+        .collect()
+}
+
+fn match_path(segments: &[Segment], path: &str) -> Option<PathParams> {
+    let parts: Vec<&str> = split_path(path).collect();
+    let mut params = HashMap::new();
+
+    for (index, segment) in segments.iter().enumerate() {
+        match segment {
+            Segment::Wildcard(name) => {
+                let rest = parts.get(index..).unwrap_or_default().join("/");
+                params.insert(name.clone(), rest);
+                return Some(PathParams(params));
+            }
+            Segment::Static(expected) if parts.get(index) != Some(&expected.as_str()) => {
+                return None;
+            }
+            Segment::Static(_) => {}
+            Segment::Param(name) => {
+                params.insert(name.clone(), parts.get(index)?.to_string());
+            }
+        }
+    }
+
+    (parts.len() == segments.len()).then_some(PathParams(params))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router() -> Router<'static, (), String> {
+        Router::new()
+            .route(Method::GET, "/health", |_| {
+                Ok(Response::new("ok".to_string()))
+            })
+            .route(Method::POST, "/message", |_| {
+                Ok(Response::new("created".to_string()))
+            })
+            .route(Method::PUT, "/message/:ts", |req| {
+                let ts = PathParams::from_req(&req).unwrap().get("ts").unwrap();
+                Ok(Response::new(format!("updated {ts}")))
+            })
+    }
+
+    fn request(method: Method, uri: &str) -> Request<()> {
+        Request::builder().method(method).uri(uri).body(()).unwrap()
+    }
+
+    #[test]
+    fn test_dispatch_static_route() {
+        let response = router().dispatch(request(Method::GET, "/health")).unwrap();
+        assert_eq!(response.body(), "ok");
+    }
+
+    #[test]
+    fn test_dispatch_path_param() {
+        let response = router()
+            .dispatch(request(Method::PUT, "/message/1700000000.000100"))
+            .unwrap();
+        assert_eq!(response.body(), "updated 1700000000.000100");
+    }
+
+    #[test]
+    fn test_dispatch_no_route() {
+        let err = router()
+            .dispatch(request(Method::DELETE, "/message"))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "No route for DELETE /message");
+    }
+
+    #[test]
+    fn test_dispatch_head_to_get_route() {
+        let response = router().dispatch(request(Method::HEAD, "/health")).unwrap();
+        assert_eq!(response.body(), "ok");
+
+        let err = router()
+            .dispatch(request(Method::HEAD, "/message"))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "No route for HEAD /message");
+    }
+
+    #[test]
+    fn test_allowed_methods() {
+        let router = router();
+        assert_eq!(
+            router.allowed_methods("/health"),
+            vec![Method::GET, Method::HEAD]
+        );
+        assert_eq!(router.allowed_methods("/message"), vec![Method::POST]);
+        assert_eq!(router.allowed_methods("/message/1"), vec![Method::PUT]);
+        assert!(router.allowed_methods("/unknown").is_empty());
+        assert!(router.allowed_methods("/message/1/2").is_empty());
+    }
+
+    #[test]
+    fn test_match_wildcard() {
+        let segments = parse_pattern("/files/*path");
+        let params = match_path(&segments, "/files/a/b.txt").unwrap();
+        assert_eq!(params.get("path"), Some("a/b.txt"));
+
+        let params = match_path(&parse_pattern("/*path"), "/").unwrap();
+        assert_eq!(params.get("path"), Some(""));
+    }
+
+    #[test]
+    fn test_match_trailing_slash() {
+        assert!(match_path(&parse_pattern("/health"), "/health/").is_some());
+        assert!(match_path(&parse_pattern("/"), "/").is_some());
+    }
+
+    #[test]
+    fn test_path_params_parse() {
+        let params = match_path(&parse_pattern("/items/:id"), "/items/42").unwrap();
+        assert_eq!(params.parse::<u32>("id").unwrap(), 42);
+        assert_eq!(
+            params.parse::<u32>("name").unwrap_err().to_string(),
+            "Missing path parameter 'name'"
+        );
+
+        let params = match_path(&parse_pattern("/items/:id"), "/items/abc").unwrap();
+        assert_eq!(
+            params.parse::<u32>("id").unwrap_err().to_string(),
+            "Invalid path parameter 'id': invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn test_dispatch_through_layers() {
+        let router = router().layer(|req: Request<()>, next: Next<'_, (), String>| {
+            let mut res = next.run(req)?;
+            res.body_mut().push('!');
+            Ok(res)
+        });
+
+        let response = router.dispatch(request(Method::GET, "/health")).unwrap();
+        assert_eq!(response.body(), "ok!");
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use anyhow::Result;
+use http::{HeaderMap, Request};
+use serde::de::{self, DeserializeSeed, MapAccess, Visitor};
+
+use super::de::StrDeserializer;
+use super::TypedHeaders;
+
+pub const SETTINGS_HEADER: &str = "x-edgee-component-settings";
+
+#[derive(serde::Deserialize)]
+struct SettingsHeader {
+    #[serde(rename = "x-edgee-component-settings")]
+    settings: Option<String>,
+}
+
+pub const SETTINGS_ENV: &str = "EDGEE_COMPONENT_SETTINGS";
+pub const SETTING_ENV_PREFIX: &str = "EDGEE_SETTING_";
+
+/// Settings for hosts without Edgee's header injection (wasmtime, Spin...):
+/// either the whole JSON object in `EDGEE_COMPONENT_SETTINGS`, or one
+/// `EDGEE_SETTING_<NAME>` variable per setting, e.g. `EDGEE_SETTING_WEBHOOK_URL`.
+pub fn settings_from_env(
+    vars: impl Iterator<Item = (String, String)>,
+) -> Result<Option<HashMap<String, String>>> {
+    let mut data = HashMap::new();
+    for (name, value) in vars {
+        if name == SETTINGS_ENV {
+            let data = serde_json::from_str(&value)
+                .map_err(|err| anyhow::anyhow!("Invalid {SETTINGS_ENV} variable: {err}"))?;
+            return Ok(Some(data));
+        }
+        if let Some(key) = name.strip_prefix(SETTING_ENV_PREFIX) {
+            data.insert(key.to_lowercase(), value);
+        }
+    }
+    Ok((!data.is_empty()).then_some(data))
+}
+
+// Reading the environment imports `wasi:cli/environment`, which isn't part of
+// the `wasi:http/proxy` world Edgee provides, so it's opt-in
+#[cfg(feature = "standalone")]
+fn settings_from_host() -> Result<Option<HashMap<String, String>>> {
+    settings_from_env(std::env::vars())
+}
+
+#[cfg(not(feature = "standalone"))]
+fn settings_from_host() -> Result<Option<HashMap<String, String>>> {
+    Ok(None)
+}
+
+/// Checks run once settings are deserialized, pushing a message for every
+/// broken rule so they can all be reported at once.
+pub trait Validate {
+    fn validate(&self, _errors: &mut Vec<String>) {}
+}
+
+/// Every problem found in the settings, displayed as a `; ` separated list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SettingsErrors(pub Vec<String>);
+
+impl fmt::Display for SettingsErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0.join("; "))
+    }
+}
+
+impl std::error::Error for SettingsErrors {}
+
+/// Component settings deserialized from the `x-edgee-component-settings`
+/// header into any struct.
+///
+/// Edgee sends every value as a string, they're parsed into the field types
+/// (see `StrDeserializer`) and `#[serde(default)]` applies to missing ones.
+/// Problems name the offending setting and what was expected, e.g.
+/// `Invalid retries setting: expected u8, got "many"`, and are all returned
+/// together as `SettingsErrors`.
+#[derive(Debug, Clone)]
+pub struct EdgeeSettings<T>(pub T);
+
+impl<T: de::DeserializeOwned + Validate> EdgeeSettings<T> {
+    /// Reads the settings header, falling back to the environment when
+    /// running outside of Edgee (see `settings_from_env`).
+    pub fn from_headers(headers: &HeaderMap) -> Result<Self> {
+        let TypedHeaders(SettingsHeader { settings }) = TypedHeaders::from_headers(headers)?;
+        let data: HashMap<String, String> = match settings {
+            Some(value) => serde_json::from_str(&value)?,
+            None => settings_from_host()?
+                .ok_or_else(|| anyhow::anyhow!("Missing '{SETTINGS_HEADER}' header"))?,
+        };
+
+        Self::from_map(&data)
+    }
+
+    pub fn from_req<B>(req: &Request<B>) -> Result<Self> {
+        Self::from_headers(req.headers())
+    }
+
+    // serde stops at the first error, so offending values are dropped (and
+    // missing ones stubbed) and deserialization retried until every problem
+    // is known
+    pub fn from_map(data: &HashMap<String, String>) -> Result<Self> {
+        let mut data: BTreeMap<String, String> = data.clone().into_iter().collect();
+        let mut errors = Vec::new();
+        let mut invalid = HashSet::new();
+        let mut missing = HashSet::new();
+
+        let settings = loop {
+            match T::deserialize(SettingsDeserializer(&data)) {
+                Ok(settings) => break Some(settings),
+                Err(SettingsError::Invalid(key, reason)) => {
+                    if !missing.contains(key.as_str()) {
+                        errors.push(format!("Invalid {key} setting: {reason}"));
+                    }
+                    data.remove(&key);
+                    invalid.insert(key);
+                }
+                Err(SettingsError::Missing(field)) => {
+                    if !invalid.contains(field) && !missing.contains(field) {
+                        errors.push(format!("Missing {field} setting"));
+                    }
+                    if !missing.insert(field) {
+                        break None;
+                    }
+                    data.insert(field.to_string(), String::new());
+                }
+                Err(SettingsError::Custom(message)) => {
+                    errors.push(message);
+                    break None;
+                }
+            }
+        };
+
+        // Cross-field rules are meaningless on stubbed settings
+        if let Some(settings) = &settings {
+            if missing.is_empty() {
+                settings.validate(&mut errors);
+            }
+        }
+
+        match settings {
+            Some(settings) if errors.is_empty() => Ok(Self(settings)),
+            _ => Err(SettingsErrors(errors).into()),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum SettingsError {
+    Missing(&'static str),
+    /// Setting name and why its value doesn't parse.
+    Invalid(String, String),
+    Custom(String),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(field) => write!(f, "Missing {field} setting"),
+            Self::Invalid(key, reason) => write!(f, "Invalid {key} setting: {reason}"),
+            Self::Custom(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl de::Error for SettingsError {
+    fn custom<M: fmt::Display>(msg: M) -> Self {
+        Self::Custom(msg.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Self::Missing(field)
+    }
+}
+
+struct SettingsDeserializer<'a>(&'a BTreeMap<String, String>);
+
+impl<'de> de::Deserializer<'de> for SettingsDeserializer<'_> {
+    type Error = SettingsError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SettingsError> {
+        visitor.visit_map(SettingsAccess {
+            entries: self.0.iter(),
+            value: None,
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct SettingsAccess<'a> {
+    entries: std::collections::btree_map::Iter<'a, String, String>,
+    value: Option<(&'a str, &'a str)>,
+}
+
+impl<'de> MapAccess<'de> for SettingsAccess<'_> {
+    type Error = SettingsError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, SettingsError> {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        self.value = Some((key, value));
+        seed.deserialize(StrDeserializer::new(key)).map(Some)
+    }
+
+    // Any value error is reported against its setting name, with the reason
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, SettingsError> {
+        let (key, value) = self.value.take().expect("value requested before key");
+        seed.deserialize(StrDeserializer::<SettingsError>::new(value))
+            .map_err(|err| SettingsError::Invalid(key.to_string(), err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, serde::Deserialize)]
+    struct TestSettings {
+        webhook_url: String,
+        #[serde(default)]
+        retries: u8,
+        #[serde(default)]
+        channels: Vec<String>,
+        #[serde(default)]
+        token: Option<String>,
+    }
+
+    impl Validate for TestSettings {
+        fn validate(&self, errors: &mut Vec<String>) {
+            if self.retries > 5 && self.token.is_none() {
+                errors.push("retries above 5 require a token".to_string());
+            }
+            if self.channels.len() > 3 {
+                errors.push("At most 3 channels are supported".to_string());
+            }
+        }
+    }
+
+    fn settings(json: &'static str) -> Result<TestSettings> {
+        let mut headers = HeaderMap::new();
+        headers.insert(SETTINGS_HEADER, json.parse().unwrap());
+        EdgeeSettings::from_headers(&headers).map(|EdgeeSettings(settings)| settings)
+    }
+
+    #[test]
+    fn test_edgee_settings() {
+        let settings = settings(
+            r#"{"webhook_url": "https://example.com", "retries": "3", "channels": "a, b", "token": ""}"#,
+        )
+        .unwrap();
+        assert_eq!(settings.webhook_url, "https://example.com");
+        assert_eq!(settings.retries, 3);
+        assert_eq!(settings.channels, vec!["a", "b"]);
+        assert_eq!(settings.token, None);
+    }
+
+    #[test]
+    fn test_edgee_settings_defaults() {
+        let settings = settings(r#"{"webhook_url": "https://example.com"}"#).unwrap();
+        assert_eq!(settings.retries, 0);
+        assert!(settings.channels.is_empty());
+    }
+
+    #[test]
+    fn test_edgee_settings_errors() {
+        assert_eq!(
+            settings("{}").unwrap_err().to_string(),
+            "Missing webhook_url setting"
+        );
+        assert_eq!(
+            settings(r#"{"webhook_url": "x", "retries": "many"}"#)
+                .unwrap_err()
+                .to_string(),
+            "Invalid retries setting: expected u8, got \"many\""
+        );
+        assert_eq!(
+            settings(r#"{"webhook_url": "x", "retries": "9"}"#)
+                .unwrap_err()
+                .to_string(),
+            "retries above 5 require a token"
+        );
+    }
+
+    #[test]
+    fn test_edgee_settings_missing_header() {
+        let err = EdgeeSettings::<TestSettings>::from_headers(&HeaderMap::new()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Missing 'x-edgee-component-settings' header"
+        );
+    }
+
+    #[test]
+    fn test_edgee_settings_aggregates_errors() {
+        let err = settings(r#"{"retries": "many", "channels": "a,b,c,d"}"#).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<SettingsErrors>().unwrap().0,
+            vec![
+                "Invalid retries setting: expected u8, got \"many\"",
+                "Missing webhook_url setting"
+            ]
+        );
+
+        let err =
+            settings(r#"{"webhook_url": "x", "retries": "9", "channels": "a,b,c,d"}"#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "retries above 5 require a token; At most 3 channels are supported"
+        );
+    }
+
+    fn vars(vars: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_settings_from_env_json() {
+        let data = settings_from_env(vars(&[
+            ("EDGEE_SETTING_RETRIES", "2"),
+            (SETTINGS_ENV, r#"{"webhook_url": "https://example.com"}"#),
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            data,
+            HashMap::from([("webhook_url".to_string(), "https://example.com".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_settings_from_env_prefixed() {
+        let data = settings_from_env(vars(&[
+            ("PATH", "/usr/bin"),
+            ("EDGEE_SETTING_WEBHOOK_URL", "https://example.com"),
+            ("EDGEE_SETTING_RETRIES", "2"),
+        ]))
+        .unwrap()
+        .unwrap();
+        let EdgeeSettings(settings) = EdgeeSettings::<TestSettings>::from_map(&data).unwrap();
+        assert_eq!(settings.webhook_url, "https://example.com");
+        assert_eq!(settings.retries, 2);
+    }
+
+    #[test]
+    fn test_settings_from_env_empty_or_invalid() {
+        assert_eq!(
+            settings_from_env(vars(&[("PATH", "/usr/bin")])).unwrap(),
+            None
+        );
+
+        let err = settings_from_env(vars(&[(SETTINGS_ENV, "{")])).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Invalid EDGEE_COMPONENT_SETTINGS variable: "));
+    }
+}
@@ -0,0 +1,68 @@
+use http::HeaderMap;
+use subtle::ConstantTimeEq;
+
+/// Checks the inbound token, sent either as `Authorization: Bearer <token>`
+/// or, for callers unable to set headers, as a `token` query parameter.
+pub fn is_authorized(headers: &HeaderMap, query_token: Option<&str>, expected: &str) -> bool {
+    let bearer = headers
+        .get(http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match bearer.or(query_token) {
+        Some(token) => constant_time_eq(token.trim().as_bytes(), expected.as_bytes()),
+        None => false,
+    }
+}
+
+// Avoid leaking how much of the token matched through response timings
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    #[test]
+    fn test_is_authorized_bearer() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            http::header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer s3cret"),
+        );
+        assert!(is_authorized(&headers, None, "s3cret"));
+        assert!(!is_authorized(&headers, None, "other"));
+    }
+
+    #[test]
+    fn test_is_authorized_query_token() {
+        let headers = HeaderMap::new();
+        assert!(is_authorized(&headers, Some("s3cret"), "s3cret"));
+        assert!(!is_authorized(&headers, Some("s3cre"), "s3cret"));
+    }
+
+    #[test]
+    fn test_is_authorized_missing_token() {
+        let headers = HeaderMap::new();
+        assert!(!is_authorized(&headers, None, "s3cret"));
+    }
+
+    #[test]
+    fn test_is_authorized_prefers_header() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            http::header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer wrong"),
+        );
+        assert!(!is_authorized(&headers, Some("s3cret"), "s3cret"));
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"abcd"));
+    }
+}
@@ -0,0 +1,40 @@
+//! Serves the component natively for local development:
+//!
+//! ```sh
+//! EDGEE_COMPONENT_SETTINGS='{"webhook_url": "https://hooks.slack.com/services/..."}' \
+//!     cargo run --features local-dev --bin local-dev
+//! ```
+//!
+//! Slack payloads are printed to stdout instead of being posted.
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::response::Response;
+use slack_message_component::local_dev::{self, StdoutClient};
+
+const DEFAULT_ADDR: &str = "127.0.0.1:3000";
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let addr = std::env::var("LOCAL_DEV_ADDR").unwrap_or_else(|_| DEFAULT_ADDR.to_string());
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    println!("Listening on http://{addr}");
+
+    axum::serve(listener, axum::Router::new().fallback(handle)).await?;
+    Ok(())
+}
+
+async fn handle(req: Request) -> Response {
+    let (parts, body) = req.into_parts();
+    let data = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(data) => data,
+        Err(err) => {
+            return Response::builder()
+                .status(http::StatusCode::BAD_REQUEST)
+                .body(Body::from(err.to_string()))
+                .unwrap();
+        }
+    };
+
+    local_dev::handle(http::Request::from_parts(parts, data), &StdoutClient).map(Body::from)
+}
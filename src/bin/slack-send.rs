@@ -0,0 +1,180 @@
+//! Posts a message through the component's payload builder, to validate a
+//! webhook or a template from the command line:
+//!
+//! ```sh
+//! cargo run --features cli --bin slack-send -- \
+//!     --webhook https://hooks.slack.com/services/... --severity error "Disk full"
+//! ```
+
+use std::collections::HashMap;
+use std::process::ExitCode;
+
+use slack_message_component::request::Event;
+use slack_message_component::{
+    handle_notification, render, ClientResponse, NotificationRequest, OutboundClient, Settings,
+};
+
+const USAGE: &str = "Usage: slack-send --webhook <url> [--severity <level>] [--channel <channel>] \
+[--scrub-pii] [--dry-run] <text>";
+
+struct UreqClient(ureq::Agent);
+
+impl OutboundClient for UreqClient {
+    fn send(
+        &self,
+        method: http::Method,
+        url: &str,
+        headers: &http::HeaderMap,
+        body: Vec<u8>,
+    ) -> anyhow::Result<ClientResponse> {
+        let mut request = http::Request::builder().method(method).uri(url);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        let mut response = self.0.run(request.body(body)?)?;
+
+        Ok(ClientResponse {
+            status: response.status().as_u16(),
+            headers: response.headers().clone(),
+            body: response.body_mut().read_to_vec()?,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Args {
+    settings: HashMap<String, String>,
+    request: NotificationRequest,
+    dry_run: bool,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> anyhow::Result<Args> {
+    let mut settings = HashMap::new();
+    let mut request = NotificationRequest::default();
+    let mut dry_run = false;
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| anyhow::anyhow!("Missing value for {name}"))
+        };
+        match arg.as_str() {
+            "--webhook" => {
+                settings.insert("webhook_url".to_string(), value("--webhook")?);
+            }
+            "--severity" => request.severity = Some(value("--severity")?),
+            "--channel" => request.channel = Some(value("--channel")?),
+            "--scrub-pii" => {
+                settings.insert("scrub_pii".to_string(), "true".to_string());
+            }
+            "--dry-run" => dry_run = true,
+            "-h" | "--help" => anyhow::bail!(USAGE),
+            flag if flag.starts_with("--") => anyhow::bail!("Unknown option {flag}\n{USAGE}"),
+            text if request.message.is_none() => request.message = Some(text.to_string()),
+            _ => anyhow::bail!("Unexpected argument {arg}\n{USAGE}"),
+        }
+    }
+
+    let Some(message) = &request.message else {
+        anyhow::bail!("Missing message text\n{USAGE}");
+    };
+    // A plain message has no place for the severity, events show it under
+    // their title
+    if let Some(severity) = &request.severity {
+        request.event = Some(Event {
+            title: message.clone(),
+            description: None,
+            severity: Some(severity.clone()),
+            source: None,
+            url: None,
+            fields: Default::default(),
+            timestamp: None,
+        });
+    }
+    Ok(Args {
+        settings,
+        request,
+        dry_run,
+    })
+}
+
+fn run() -> anyhow::Result<bool> {
+    let args = parse_args(std::env::args().skip(1))?;
+    if args.dry_run {
+        // webhook_url is required by the settings but unused without sending
+        let mut settings = args.settings;
+        settings
+            .entry("webhook_url".to_string())
+            .or_insert_with(|| "https://hooks.slack.com/services/dry-run".to_string());
+        let payload = render(&Settings::from_map(&settings)?, args.request)?;
+        println!("{}", payload.to_json_pretty()?);
+        return Ok(true);
+    }
+
+    let settings = Settings::from_map(&args.settings)?;
+    let agent = ureq::Agent::config_builder()
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let response = handle_notification(&settings, args.request, &UreqClient(agent))?;
+    println!("{} {}", response.status, response.body);
+    Ok(response.status.is_success())
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> anyhow::Result<Args> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = args(&[
+            "--webhook",
+            "http://example.com/webhook",
+            "--severity",
+            "error",
+            "--scrub-pii",
+            "Disk full",
+        ])
+        .unwrap();
+
+        assert_eq!(args.settings["webhook_url"], "http://example.com/webhook");
+        assert_eq!(args.settings["scrub_pii"], "true");
+        assert_eq!(args.request.message.as_deref(), Some("Disk full"));
+        assert_eq!(args.request.severity.as_deref(), Some("error"));
+        assert!(!args.dry_run);
+
+        let payload = render(&Settings::from_map(&args.settings).unwrap(), args.request).unwrap();
+        assert!(payload.to_json_pretty().unwrap().contains("*error*"));
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert!(args(&["--webhook"])
+            .unwrap_err()
+            .to_string()
+            .starts_with("Missing value for --webhook"));
+        assert!(args(&["--verbose", "hi"])
+            .unwrap_err()
+            .to_string()
+            .starts_with("Unknown option --verbose"));
+        assert!(args(&["--dry-run"])
+            .unwrap_err()
+            .to_string()
+            .starts_with("Missing message text"));
+    }
+}
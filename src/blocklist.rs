@@ -0,0 +1,136 @@
+use regex::Regex;
+use serde_json::Value;
+use std::sync::LazyLock;
+
+// Word blocklist
+//
+// Components relaying user-generated content, like support forms or chat
+// widgets, can mask words and phrases of the `blocklist` setting before
+// they reach internal channels. Matches are case insensitive, on whole
+// words, and keep their first letter and length, e.g. "d***". The labels
+// of Slack links like `<https://example.com|label>` are masked too, their
+// targets and URL fields being left as written.
+
+static LINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<([^>|]*)\|([^>]*)>").unwrap());
+
+pub struct Blocklist(Regex);
+
+// First letter of each match kept, other non-blank characters starred
+fn stars(matched: &str) -> String {
+    matched
+        .chars()
+        .enumerate()
+        .map(|(index, c)| match index == 0 || c.is_whitespace() {
+            true => c,
+            false => '*',
+        })
+        .collect()
+}
+
+impl Blocklist {
+    /// Blocklist of `words`, `None` when there are none.
+    pub fn new(words: &[String]) -> Option<Self> {
+        let mut words: Vec<&str> = words.iter().map(|word| word.trim()).collect();
+        words.retain(|word| !word.is_empty());
+        if words.is_empty() {
+            return None;
+        }
+        // Longest first, for phrases to win over the words they contain
+        words.sort_by_key(|word| std::cmp::Reverse(word.chars().count()));
+        let alternatives: Vec<String> = words
+            .iter()
+            .map(|word| {
+                let boundary = |c: Option<char>| match c.is_some_and(char::is_alphanumeric) {
+                    true => r"\b",
+                    false => "",
+                };
+                let phrase: Vec<String> = word.split_whitespace().map(regex::escape).collect();
+                format!(
+                    "{}{}{}",
+                    boundary(word.chars().next()),
+                    phrase.join(r"\s+"),
+                    boundary(word.chars().last())
+                )
+            })
+            .collect();
+        let pattern = format!("(?i)(?:{})", alternatives.join("|"));
+        Some(Self(Regex::new(&pattern).expect("escaped words")))
+    }
+
+    fn mask_words(&self, text: &str) -> String {
+        self.0
+            .replace_all(text, |caps: &regex::Captures| stars(&caps[0]))
+            .into_owned()
+    }
+
+    /// Masks the blocked words of `text`, outside of link targets.
+    pub fn mask(&self, text: &str) -> String {
+        let mut masked = String::with_capacity(text.len());
+        let mut end = 0;
+        for caps in LINK.captures_iter(text) {
+            let link = caps.get(0).unwrap();
+            masked.push_str(&self.mask_words(&text[end..link.start()]));
+            masked.push_str(&format!("<{}|{}>", &caps[1], self.mask_words(&caps[2])));
+            end = link.end();
+        }
+        masked.push_str(&self.mask_words(&text[end..]));
+        masked
+    }
+
+    /// Masks the blocked words of every string of a JSON document, except
+    /// URL fields like `image_url` or `title_link`.
+    pub fn mask_json(&self, value: &mut Value) {
+        match value {
+            Value::String(text) => *text = self.mask(text),
+            Value::Array(items) => items.iter_mut().for_each(|item| self.mask_json(item)),
+            Value::Object(map) => map
+                .iter_mut()
+                .filter(|(key, _)| !key.ends_with("url") && !key.ends_with("link"))
+                .for_each(|(_, value)| self.mask_json(value)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn blocklist() -> Blocklist {
+        Blocklist::new(&["darn".to_string(), "go  away".to_string(), "".to_string()]).unwrap()
+    }
+
+    #[test]
+    fn test_mask() {
+        assert_eq!(
+            blocklist().mask("DARN it, go away! Darnell stays"),
+            "D*** it, g* ****! Darnell stays"
+        );
+        assert!(Blocklist::new(&[" ".to_string()]).is_none());
+    }
+
+    #[test]
+    fn test_mask_keeps_link_targets() {
+        assert_eq!(
+            blocklist().mask("See <https://darn.example.com/darn|darn page>"),
+            "See <https://darn.example.com/darn|d*** page>"
+        );
+    }
+
+    #[test]
+    fn test_mask_json() {
+        let mut value = json!([
+            { "type": "section", "text": { "type": "mrkdwn", "text": "darn" } },
+            { "type": "image", "image_url": "https://example.com/darn.png", "alt_text": "darn" },
+        ]);
+        blocklist().mask_json(&mut value);
+        assert_eq!(
+            value,
+            json!([
+                { "type": "section", "text": { "type": "mrkdwn", "text": "d***" } },
+                { "type": "image", "image_url": "https://example.com/darn.png", "alt_text": "d***" },
+            ])
+        );
+    }
+}
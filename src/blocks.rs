@@ -0,0 +1,324 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::helpers::ApiError;
+use crate::request::{Author, Event, Image, RawField};
+use crate::{dates, is_http_url, pii};
+
+// Block Kit blocks built from request fields
+//
+// Image and context blocks are added after the `blocks` of the request, or
+// after a section holding the message when there are none, Slack showing
+// the blocks of a message instead of its text. Blocks are checked against
+// the Slack limits before sending, Slack only answering `invalid_blocks`.
+
+const MAX_BLOCKS: usize = 50;
+const MAX_SECTION_TEXT: usize = 3000;
+const MAX_SECTION_FIELDS: usize = 10;
+const MAX_FIELD_TEXT: usize = 2000;
+const MAX_HEADER_TEXT: usize = 150;
+
+/// Section showing `text` as mrkdwn.
+pub fn section(text: &str) -> Value {
+    json!({ "type": "section", "text": { "type": "mrkdwn", "text": text } })
+}
+
+/// Blocks of the request, which must be an array to be added to.
+pub fn to_vec(blocks: &RawField) -> Result<Vec<Value>> {
+    match blocks.to_value()? {
+        Value::Array(blocks) => Ok(blocks),
+        _ => Err(ApiError::bad_request(
+            "Invalid 'blocks' field in request body, an array is needed",
+        )
+        .into()),
+    }
+}
+
+/// Plain text of `blocks`, one line per block, for the notifications and
+/// clients not showing blocks.
+pub fn fallback_text(blocks: &RawField) -> Result<Option<String>> {
+    let lines: Vec<String> = to_vec(blocks)?
+        .iter()
+        .map(|block| {
+            let mut texts = Vec::new();
+            collect_texts(block, &mut texts);
+            texts.join(" ")
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+    Ok((!lines.is_empty()).then(|| lines.join("\n")))
+}
+
+// Text objects and rich text elements all keep their content in `text`,
+// which comes before the `fields` or `elements` of the same block
+fn collect_texts(value: &Value, texts: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            match map.get("text") {
+                Some(Value::String(text)) if !text.trim().is_empty() => {
+                    texts.push(text.trim().to_string())
+                }
+                Some(text) => collect_texts(text, texts),
+                None => {}
+            }
+            for (_, value) in map.iter().filter(|(key, _)| *key != "text") {
+                collect_texts(value, texts);
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| collect_texts(item, texts)),
+        _ => {}
+    }
+}
+
+/// Rejects `blocks` with a 422 listing every Slack limit they exceed.
+pub fn check_limits(blocks: &Value) -> Result<()> {
+    let Value::Array(blocks) = blocks else {
+        return Ok(());
+    };
+
+    let mut violations = Vec::new();
+    if blocks.len() > MAX_BLOCKS {
+        violations.push(format!(
+            "/blocks: {} blocks, {MAX_BLOCKS} at most",
+            blocks.len()
+        ));
+    }
+    let length = |text: &Value| text["text"].as_str().map_or(0, |text| text.chars().count());
+    for (index, block) in blocks.iter().enumerate() {
+        match block["type"].as_str() {
+            Some("section") => {
+                let text = length(&block["text"]);
+                if text > MAX_SECTION_TEXT {
+                    violations.push(format!(
+                        "/blocks/{index}/text: {text} characters, {MAX_SECTION_TEXT} at most"
+                    ));
+                }
+                let fields = block["fields"]
+                    .as_array()
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                if fields.len() > MAX_SECTION_FIELDS {
+                    violations.push(format!(
+                        "/blocks/{index}/fields: {} fields, {MAX_SECTION_FIELDS} at most",
+                        fields.len()
+                    ));
+                }
+                for (field_index, field) in fields.iter().enumerate() {
+                    let text = length(field);
+                    if text > MAX_FIELD_TEXT {
+                        violations.push(format!(
+                            "/blocks/{index}/fields/{field_index}: {text} characters, {MAX_FIELD_TEXT} at most"
+                        ));
+                    }
+                }
+            }
+            Some("header") => {
+                let text = length(&block["text"]);
+                if text > MAX_HEADER_TEXT {
+                    violations.push(format!(
+                        "/blocks/{index}/text: {text} characters, {MAX_HEADER_TEXT} at most"
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    match violations.is_empty() {
+        true => Ok(()),
+        false => Err(ApiError::unprocessable(format!(
+            "Blocks exceed Slack limits: {}",
+            violations.join("; ")
+        ))
+        .into()),
+    }
+}
+
+fn scrubbed(text: String, scrub_pii: bool) -> String {
+    match scrub_pii {
+        true => pii::scrub(&text),
+        false => text,
+    }
+}
+
+/// One image block per image, alt texts defaulting to "Image".
+pub fn images(images: Vec<Image>, scrub_pii: bool) -> Result<Vec<Value>> {
+    if images.iter().any(|image| !is_http_url(&image.url)) {
+        return Err(ApiError::bad_request(
+            "Invalid 'images' field in request body, http(s) URLs are needed",
+        )
+        .into());
+    }
+
+    Ok(images
+        .into_iter()
+        .map(|image| {
+            let alt = image.alt.unwrap_or_else(|| "Image".to_string());
+            json!({
+                "type": "image",
+                "image_url": image.url,
+                "alt_text": scrubbed(alt, scrub_pii),
+            })
+        })
+        .collect())
+}
+
+/// Header, description, fields, context and link button of `event`.
+pub fn event(event: Event, scrub_pii: bool) -> Result<Vec<Value>> {
+    if event.url.as_deref().is_some_and(|url| !is_http_url(url)) {
+        return Err(ApiError::bad_request(
+            "Invalid 'event' field in request body, an http(s) url is needed",
+        )
+        .into());
+    }
+
+    // Scrubbed part by part, the unix times of date tokens look like phone
+    // numbers
+    let text = |value: String| scrubbed(value, scrub_pii);
+    let mut title = text(event.title);
+    if title.chars().count() > MAX_HEADER_TEXT {
+        title = title.chars().take(MAX_HEADER_TEXT - 1).collect::<String>() + "…";
+    }
+    let mut blocks = vec![json!({
+        "type": "header",
+        "text": { "type": "plain_text", "text": title },
+    })];
+    if let Some(description) = event.description {
+        blocks.push(section(&text(description)));
+    }
+
+    let fields: Vec<Value> = event
+        .fields
+        .into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::String(value) => value,
+                value => value.to_string(),
+            };
+            let field = text(format!("*{key}*\n{value}"));
+            json!({ "type": "mrkdwn", "text": field })
+        })
+        .collect();
+    for fields in fields.chunks(MAX_SECTION_FIELDS) {
+        blocks.push(json!({ "type": "section", "fields": fields }));
+    }
+
+    let context: Vec<Value> = [
+        event.severity.map(|severity| text(format!("*{severity}*"))),
+        event.source.map(text),
+        event
+            .timestamp
+            .map(|timestamp| dates::format(&text(timestamp))),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|text| json!({ "type": "mrkdwn", "text": text }))
+    .collect();
+    if !context.is_empty() {
+        blocks.push(json!({ "type": "context", "elements": context }));
+    }
+    if let Some(url) = event.url {
+        blocks.push(json!({
+            "type": "actions",
+            "elements": [{
+                "type": "button",
+                "text": { "type": "plain_text", "text": "View details" },
+                "url": url,
+            }],
+        }));
+    }
+    Ok(blocks)
+}
+
+/// Context block with the avatar and name of `author`.
+pub fn author(author: Author, scrub_pii: bool) -> Result<Value> {
+    if author.name.is_empty()
+        || author
+            .icon_url
+            .as_deref()
+            .is_some_and(|url| !is_http_url(url))
+    {
+        return Err(ApiError::bad_request(
+            "Invalid 'author' field in request body, a name and an http(s) icon_url are needed",
+        )
+        .into());
+    }
+
+    let name = scrubbed(author.name, scrub_pii);
+    let mut elements = Vec::new();
+    if let Some(icon_url) = author.icon_url {
+        elements.push(json!({ "type": "image", "image_url": icon_url, "alt_text": name }));
+    }
+    elements.push(json!({ "type": "mrkdwn", "text": name }));
+    Ok(json!({ "type": "context", "elements": elements }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_text() {
+        let blocks = RawField::from_value(&json!([
+            { "type": "header", "text": { "type": "plain_text", "text": "Deploy finished" } },
+            { "type": "divider" },
+            {
+                "type": "section",
+                "text": { "type": "mrkdwn", "text": "*api* 1.2.0" },
+                "fields": [{ "type": "mrkdwn", "text": "by jane" }],
+            },
+            { "type": "rich_text", "elements": [{ "type": "rich_text_section", "elements": [{ "type": "text", "text": "All green " }] }] },
+        ]))
+        .unwrap();
+        assert_eq!(
+            fallback_text(&blocks).unwrap().as_deref(),
+            Some("Deploy finished\n*api* 1.2.0 by jane\nAll green")
+        );
+
+        let blocks = RawField::from_value(&json!([{ "type": "divider" }])).unwrap();
+        assert_eq!(fallback_text(&blocks).unwrap(), None);
+    }
+
+    #[test]
+    fn test_check_limits() {
+        let mut blocks = vec![json!({ "type": "divider" }); 50];
+        assert!(check_limits(&json!(blocks)).is_ok());
+
+        blocks[1] = json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": "a".repeat(3001) },
+            "fields": vec![json!({ "type": "mrkdwn", "text": "b" }); 11],
+        });
+        blocks[2] =
+            json!({ "type": "header", "text": { "type": "plain_text", "text": "c".repeat(151) } });
+        blocks.push(json!({ "type": "divider" }));
+        let err = check_limits(&json!(blocks)).unwrap_err();
+        let err = err.downcast_ref::<ApiError>().unwrap();
+        assert_eq!(err.status, http::StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            err.message,
+            "Blocks exceed Slack limits: /blocks: 51 blocks, 50 at most; \
+             /blocks/1/text: 3001 characters, 3000 at most; \
+             /blocks/1/fields: 11 fields, 10 at most; \
+             /blocks/2/text: 151 characters, 150 at most"
+        );
+    }
+
+    #[test]
+    fn test_author() {
+        let jane = Author {
+            name: "jane@example.com".to_string(),
+            icon_url: None,
+        };
+        assert_eq!(
+            author(jane.clone(), true).unwrap(),
+            json!({ "type": "context", "elements": [{ "type": "mrkdwn", "text": "[email]" }] })
+        );
+
+        let jane = Author {
+            icon_url: Some("avatar.png".to_string()),
+            ..jane
+        };
+        assert!(author(jane, false).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use anyhow::Result;
+use serde_json::json;
+
+use crate::client::OutboundClient;
+use crate::request::NotificationRequest;
+use crate::slack_api;
+use crate::Settings;
+
+// Canvas posting mode
+//
+// Messages are appended to the `canvas_id` canvas as markdown, in addition
+// to or instead of being posted, to keep a log next to the alerts. With
+// `canvas_section` set, entries go right under the first heading containing
+// that text, newest first, otherwise at the end of the canvas.
+
+/// Whether the canvas entry replaces the message or comes with it.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CanvasMode {
+    #[default]
+    Also,
+    Instead,
+}
+
+/// Markdown entry for a request, severity first and fields as a list.
+pub fn entry(request: &NotificationRequest) -> Result<String> {
+    let Some(message) = &request.message else {
+        anyhow::bail!("Missing 'message' field in request body");
+    };
+
+    let mut entry = match &request.severity {
+        Some(severity) => format!("**{severity}** {message}\n"),
+        None => format!("{message}\n"),
+    };
+    for (key, value) in request.fields.iter().flatten() {
+        let value = match value {
+            serde_json::Value::String(value) => value.clone(),
+            value => value.to_string(),
+        };
+        entry.push_str(&format!("- **{key}**: {value}\n"));
+    }
+    Ok(entry)
+}
+
+/// Adds `entry` to the configured canvas.
+pub fn append(
+    settings: &Settings,
+    client: &dyn OutboundClient,
+    bot_token: &str,
+    entry: &str,
+) -> Result<()> {
+    let Some(canvas_id) = settings.canvas_id.as_deref() else {
+        return Ok(());
+    };
+
+    let document_content = json!({ "type": "markdown", "markdown": entry });
+    let change = match &settings.canvas_section {
+        Some(section) => json!({
+            "operation": "insert_after",
+            "section_id": find_section(client, bot_token, canvas_id, section)?,
+            "document_content": document_content,
+        }),
+        None => json!({
+            "operation": "insert_at_end",
+            "document_content": document_content,
+        }),
+    };
+    slack_api::call(
+        client,
+        bot_token,
+        "canvases.edit",
+        &json!({ "canvas_id": canvas_id, "changes": [change] }),
+    )?;
+    Ok(())
+}
+
+fn find_section(
+    client: &dyn OutboundClient,
+    bot_token: &str,
+    canvas_id: &str,
+    heading: &str,
+) -> Result<String> {
+    let response = slack_api::call(
+        client,
+        bot_token,
+        "canvases.sections.lookup",
+        &json!({
+            "canvas_id": canvas_id,
+            "criteria": { "section_types": ["any_header"], "contains_text": heading },
+        }),
+    )?;
+    response["sections"][0]["id"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("No section '{heading}' in canvas {canvas_id}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockClient;
+    use serde_json::Value;
+
+    fn settings(section: Option<&str>) -> Settings {
+        let mut data = std::collections::HashMap::from([
+            ("bot_token".to_string(), "xoxb-1".to_string()),
+            ("canvas_id".to_string(), "F1".to_string()),
+        ]);
+        if let Some(section) = section {
+            data.insert("canvas_section".to_string(), section.to_string());
+        }
+        Settings::from_map(&data).unwrap()
+    }
+
+    #[test]
+    fn test_entry() {
+        let request = NotificationRequest::from_value(
+            json!({ "message": "Disk full", "severity": "error", "fields": { "host": "db-1", "usage": 97 } }),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            entry(&request).unwrap(),
+            "**error** Disk full\n- **host**: db-1\n- **usage**: 97\n"
+        );
+    }
+
+    #[test]
+    fn test_append_at_end() {
+        let client = MockClient::new().respond_with_body(200, r#"{"ok": true}"#);
+        append(&settings(None), &client, "xoxb-1", "Disk full\n").unwrap();
+
+        let requests = client.requests();
+        assert_eq!(requests[0].url, "https://slack.com/api/canvases.edit");
+        assert_eq!(
+            serde_json::from_slice::<Value>(&requests[0].body).unwrap(),
+            json!({
+                "canvas_id": "F1",
+                "changes": [{
+                    "operation": "insert_at_end",
+                    "document_content": { "type": "markdown", "markdown": "Disk full\n" },
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_append_under_section() {
+        let client = MockClient::new()
+            .respond_with_body(200, r#"{"ok": true, "sections": [{"id": "temp:C:1"}]}"#)
+            .respond_with_body(200, r#"{"ok": true}"#);
+        append(
+            &settings(Some("Incident log")),
+            &client,
+            "xoxb-1",
+            "Disk full\n",
+        )
+        .unwrap();
+
+        let requests = client.requests();
+        let edit: Value = serde_json::from_slice(&requests[1].body).unwrap();
+        assert_eq!(edit["changes"][0]["operation"], "insert_after");
+        assert_eq!(edit["changes"][0]["section_id"], "temp:C:1");
+
+        let client = MockClient::new().respond_with_body(200, r#"{"ok": true, "sections": []}"#);
+        let err = append(&settings(Some("Incident log")), &client, "xoxb-1", "x").unwrap_err();
+        assert_eq!(err.to_string(), "No section 'Incident log' in canvas F1");
+    }
+}
@@ -0,0 +1,582 @@
+use anyhow::Result;
+use http::{HeaderMap, Method};
+use std::cell::{Cell, RefCell};
+use std::time::Duration;
+
+use crate::helpers;
+
+// Outbound HTTP helpers
+//
+// Everything that talks to Slack goes through `OutboundClient` so the whole
+// flow can run natively in tests, without a WASI HTTP runtime.
+
+/// Response returned by an [`OutboundClient`].
+#[derive(Debug, Clone, Default)]
+pub struct ClientResponse {
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+/// Request sent as part of a batch, see [`OutboundClient::send_all`].
+#[derive(Debug, Clone)]
+pub struct OutboundRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+pub trait OutboundClient {
+    fn send(
+        &self,
+        method: Method,
+        url: &str,
+        headers: &HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<ClientResponse>;
+
+    fn post(&self, url: &str, headers: &HeaderMap, body: Vec<u8>) -> Result<ClientResponse> {
+        self.send(Method::POST, url, headers, body)
+    }
+
+    fn get(&self, url: &str, headers: &HeaderMap) -> Result<ClientResponse> {
+        self.send(Method::GET, url, headers, Vec::new())
+    }
+
+    /// Sends every request with at most `max_concurrency` of them in flight,
+    /// responses are returned in request order. Sequential by default.
+    fn send_all(
+        &self,
+        requests: Vec<OutboundRequest>,
+        max_concurrency: usize,
+    ) -> Vec<Result<ClientResponse>> {
+        let _ = max_concurrency;
+        requests
+            .into_iter()
+            .map(|request| self.send(request.method, &request.url, &request.headers, request.body))
+            .collect()
+    }
+}
+
+/// Client adding `headers` to the requests sent to Slack, e.g. the
+/// credentials of an egress gateway. Other hosts (secrets endpoint, token
+/// store, callbacks) don't get them, nor are the headers the component sets
+/// itself replaced.
+pub struct SlackHeadersClient<'a> {
+    client: &'a dyn OutboundClient,
+    headers: HeaderMap,
+}
+
+impl<'a> SlackHeadersClient<'a> {
+    pub fn new(client: &'a dyn OutboundClient, headers: HeaderMap) -> Self {
+        Self { client, headers }
+    }
+
+    fn with_headers(&self, url: &str, headers: &HeaderMap) -> HeaderMap {
+        let mut headers = headers.clone();
+        if is_slack_url(url) {
+            for (name, value) in &self.headers {
+                headers.entry(name).or_insert_with(|| value.clone());
+            }
+        }
+        headers
+    }
+}
+
+fn is_slack_url(url: &str) -> bool {
+    url.parse::<http::Uri>()
+        .ok()
+        .and_then(|uri| uri.host().map(str::to_ascii_lowercase))
+        .is_some_and(|host| host == "slack.com" || host.ends_with(".slack.com"))
+}
+
+impl OutboundClient for SlackHeadersClient<'_> {
+    fn send(
+        &self,
+        method: Method,
+        url: &str,
+        headers: &HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<ClientResponse> {
+        let headers = self.with_headers(url, headers);
+        self.client.send(method, url, &headers, body)
+    }
+
+    fn send_all(
+        &self,
+        requests: Vec<OutboundRequest>,
+        max_concurrency: usize,
+    ) -> Vec<Result<ClientResponse>> {
+        let requests = requests
+            .into_iter()
+            .map(|request| OutboundRequest {
+                headers: self.with_headers(&request.url, &request.headers),
+                ..request
+            })
+            .collect();
+        self.client.send_all(requests, max_concurrency)
+    }
+}
+
+/// Client adding up the time spent waiting on Slack, see [`crate::timings`],
+/// and keeping the headers of the last Slack response.
+pub struct TimedClient<'a> {
+    client: &'a dyn OutboundClient,
+    slack: Cell<Duration>,
+    slack_headers: RefCell<HeaderMap>,
+}
+
+impl<'a> TimedClient<'a> {
+    pub fn new(client: &'a dyn OutboundClient) -> Self {
+        Self {
+            client,
+            slack: Cell::default(),
+            slack_headers: RefCell::default(),
+        }
+    }
+
+    /// Time spent in the requests sent to Slack so far.
+    pub fn slack(&self) -> Duration {
+        self.slack.get()
+    }
+
+    /// Headers of the last response Slack answered with.
+    pub fn slack_headers(&self) -> HeaderMap {
+        self.slack_headers.borrow().clone()
+    }
+
+    fn timed<T>(&self, to_slack: bool, send: impl FnOnce() -> T) -> T {
+        let started = helpers::monotonic_now();
+        let result = send();
+        if to_slack {
+            let elapsed = helpers::monotonic_now().saturating_sub(started);
+            self.slack.set(self.slack.get() + elapsed);
+        }
+        result
+    }
+
+    fn keep_headers(&self, response: &Result<ClientResponse>) {
+        if let Ok(response) = response {
+            *self.slack_headers.borrow_mut() = response.headers.clone();
+        }
+    }
+}
+
+impl OutboundClient for TimedClient<'_> {
+    fn send(
+        &self,
+        method: Method,
+        url: &str,
+        headers: &HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<ClientResponse> {
+        let to_slack = is_slack_url(url);
+        let response = self.timed(to_slack, || self.client.send(method, url, headers, body));
+        if to_slack {
+            self.keep_headers(&response);
+        }
+        response
+    }
+
+    // Batches are sent concurrently, their wall time is what's waited
+    fn send_all(
+        &self,
+        requests: Vec<OutboundRequest>,
+        max_concurrency: usize,
+    ) -> Vec<Result<ClientResponse>> {
+        let to_slack: Vec<bool> = requests
+            .iter()
+            .map(|request| is_slack_url(&request.url))
+            .collect();
+        let responses = self.timed(to_slack.contains(&true), || {
+            self.client.send_all(requests, max_concurrency)
+        });
+        for (response, _) in responses.iter().zip(to_slack).filter(|(_, slack)| *slack) {
+            self.keep_headers(response);
+        }
+        responses
+    }
+}
+
+/// Client backed by the WASI HTTP outgoing handler.
+///
+/// Build it once per invocation and share it: every send (secrets, fan-out,
+/// retries) then goes through the same handle, leaving connection reuse to
+/// the runtime.
+#[cfg(not(test))]
+#[derive(Default)]
+pub struct WasiClient;
+
+#[cfg(not(test))]
+impl WasiClient {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[cfg(not(test))]
+impl OutboundClient for WasiClient {
+    fn send(
+        &self,
+        method: Method,
+        url: &str,
+        headers: &HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<ClientResponse> {
+        wasi::send(OutboundRequest {
+            method,
+            url: url.to_string(),
+            headers: headers.clone(),
+            body,
+        })
+    }
+
+    // Batches are driven on the outgoing handler so several requests can
+    // wait on Slack together
+    fn send_all(
+        &self,
+        requests: Vec<OutboundRequest>,
+        max_concurrency: usize,
+    ) -> Vec<Result<ClientResponse>> {
+        wasi::send_all(requests, max_concurrency)
+    }
+}
+
+#[cfg(not(test))]
+mod wasi {
+    use anyhow::Result;
+    use std::time::Duration;
+
+    use super::{ClientResponse, OutboundRequest};
+    use crate::bindings::wasi::http::outgoing_handler;
+    use crate::bindings::wasi::http::types::{
+        ErrorCode, FutureIncomingResponse, Headers, IncomingResponse, Method, OutgoingBody,
+        OutgoingRequest, RequestOptions, Scheme,
+    };
+    use crate::bindings::wasi::io::poll;
+    use crate::helpers::{self, ApiError};
+
+    /// Sends `request` and waits for its response.
+    pub fn send(request: OutboundRequest) -> Result<ClientResponse> {
+        let future = start(request)?;
+        // The pollable must be dropped before the future it comes from
+        {
+            let pollable = future.subscribe();
+            pollable.block();
+        }
+        finish(&future)
+    }
+
+    pub fn send_all(
+        requests: Vec<OutboundRequest>,
+        max_concurrency: usize,
+    ) -> Vec<Result<ClientResponse>> {
+        let mut results: Vec<Option<Result<ClientResponse>>> =
+            requests.iter().map(|_| None).collect();
+        let mut pending = requests.into_iter().enumerate();
+        let mut in_flight: Vec<(usize, FutureIncomingResponse)> = Vec::new();
+
+        loop {
+            while in_flight.len() < max_concurrency.max(1) {
+                let Some((index, request)) = pending.next() else {
+                    break;
+                };
+                match start(request) {
+                    Ok(future) => in_flight.push((index, future)),
+                    Err(err) => results[index] = Some(Err(err)),
+                }
+            }
+            if in_flight.is_empty() {
+                break;
+            }
+
+            // Pollables must be dropped before the futures they come from
+            let ready = {
+                let pollables: Vec<_> = in_flight
+                    .iter()
+                    .map(|(_, future)| future.subscribe())
+                    .collect();
+                let pollables: Vec<_> = pollables.iter().collect();
+                poll::poll(&pollables)
+            };
+
+            let mut waiting = Vec::with_capacity(in_flight.len());
+            for (position, (index, future)) in in_flight.into_iter().enumerate() {
+                if ready.contains(&(position as u32)) {
+                    results[index] = Some(finish(&future));
+                } else {
+                    waiting.push((index, future));
+                }
+            }
+            in_flight = waiting;
+        }
+
+        results
+            .into_iter()
+            .map(|result| result.expect("Every request gets a result"))
+            .collect()
+    }
+
+    fn start(request: OutboundRequest) -> Result<FutureIncomingResponse> {
+        let uri: http::Uri = request.url.parse()?;
+        let scheme = match uri.scheme_str() {
+            Some("http") => Scheme::Http,
+            Some("https") => Scheme::Https,
+            _ => anyhow::bail!("Unsupported URL {}", request.url),
+        };
+        let authority = uri
+            .authority()
+            .ok_or_else(|| anyhow::anyhow!("Missing host in URL {}", request.url))?;
+        // The handler can't be interrupted, the time it has left bounds its
+        // calls instead
+        let options = match helpers::remaining_time() {
+            Some(remaining) if remaining.is_zero() => {
+                return Err(ApiError::gateway_timeout(format!(
+                    "Handler timed out before calling {authority}"
+                ))
+                .into())
+            }
+            Some(remaining) => Some(request_options(remaining)),
+            None => None,
+        };
+
+        let outgoing = OutgoingRequest::new(Headers::try_from(request.headers)?);
+        let path_with_query = uri.path_and_query().map(|value| value.as_str());
+        outgoing
+            .set_method(&method(&request.method))
+            .and(outgoing.set_scheme(Some(&scheme)))
+            .and(outgoing.set_authority(Some(authority.as_str())))
+            .and(outgoing.set_path_with_query(path_with_query))
+            .map_err(|()| anyhow::anyhow!("Invalid request to {}", request.url))?;
+
+        let body = outgoing
+            .body()
+            .map_err(|()| anyhow::anyhow!("Could not get request body"))?;
+        {
+            let stream = body
+                .write()
+                .map_err(|()| anyhow::anyhow!("Could not get request body writer"))?;
+            // Blocking writes are capped at 4096 bytes per call
+            for piece in request.body.chunks(4096) {
+                stream.blocking_write_and_flush(piece)?;
+            }
+        }
+        OutgoingBody::finish(body, None)?;
+
+        Ok(outgoing_handler::handle(outgoing, options)?)
+    }
+
+    // Runtimes refusing one of the timeouts still apply the others
+    fn request_options(timeout: Duration) -> RequestOptions {
+        let nanos = Some(u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX));
+        let options = RequestOptions::new();
+        let _ = options.set_connect_timeout(nanos);
+        let _ = options.set_first_byte_timeout(nanos);
+        let _ = options.set_between_bytes_timeout(nanos);
+        options
+    }
+
+    fn finish(future: &FutureIncomingResponse) -> Result<ClientResponse> {
+        let response: IncomingResponse = match future.get() {
+            Some(Ok(Ok(response))) => response,
+            Some(Ok(Err(
+                code @ (ErrorCode::DnsTimeout
+                | ErrorCode::ConnectionTimeout
+                | ErrorCode::ConnectionReadTimeout
+                | ErrorCode::ConnectionWriteTimeout
+                | ErrorCode::HttpResponseTimeout),
+            ))) => {
+                return Err(ApiError::gateway_timeout(format!("Request timed out: {code}")).into())
+            }
+            Some(Ok(Err(code))) => anyhow::bail!("Request failed: {code}"),
+            Some(Err(())) => anyhow::bail!("Response already consumed"),
+            None => anyhow::bail!("Response not ready"),
+        };
+
+        let status = response.status();
+        let headers = http::HeaderMap::try_from(response.headers())?;
+        let body = response
+            .consume()
+            .map_err(|()| anyhow::anyhow!("Could not consume response body"))?;
+        let data = body.read(None)?;
+
+        Ok(ClientResponse {
+            status,
+            headers,
+            body: data.into(),
+        })
+    }
+
+    fn method(method: &http::Method) -> Method {
+        match *method {
+            http::Method::GET => Method::Get,
+            http::Method::POST => Method::Post,
+            http::Method::PUT => Method::Put,
+            http::Method::PATCH => Method::Patch,
+            http::Method::DELETE => Method::Delete,
+            http::Method::HEAD => Method::Head,
+            http::Method::OPTIONS => Method::Options,
+            _ => Method::Other(method.to_string()),
+        }
+    }
+}
+
+/// Request captured by a [`MockClient`].
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+/// In-memory client replaying queued responses, 200 once the queue is empty.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MockClient {
+    responses: std::cell::RefCell<std::collections::VecDeque<Result<ClientResponse>>>,
+    requests: std::cell::RefCell<Vec<RecordedRequest>>,
+}
+
+#[cfg(test)]
+impl MockClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn respond_with(self, status: u16) -> Self {
+        self.responses.borrow_mut().push_back(Ok(ClientResponse {
+            status,
+            ..Default::default()
+        }));
+        self
+    }
+
+    pub fn respond_with_body(self, status: u16, body: &str) -> Self {
+        self.responses.borrow_mut().push_back(Ok(ClientResponse {
+            status,
+            body: body.as_bytes().to_vec(),
+            ..Default::default()
+        }));
+        self
+    }
+
+    pub fn respond_with_headers(self, status: u16, headers: &[(&'static str, &str)]) -> Self {
+        let headers = headers
+            .iter()
+            .map(|(name, value)| (http::HeaderName::from_static(name), value.parse().unwrap()))
+            .collect();
+        self.responses.borrow_mut().push_back(Ok(ClientResponse {
+            status,
+            headers,
+            ..Default::default()
+        }));
+        self
+    }
+
+    pub fn fail_with(self, err: anyhow::Error) -> Self {
+        self.responses.borrow_mut().push_back(Err(err));
+        self
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.borrow().clone()
+    }
+}
+
+#[cfg(test)]
+impl OutboundClient for MockClient {
+    fn send(
+        &self,
+        method: Method,
+        url: &str,
+        headers: &HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<ClientResponse> {
+        self.requests.borrow_mut().push(RecordedRequest {
+            method,
+            url: url.to_string(),
+            headers: headers.clone(),
+            body,
+        });
+
+        self.responses.borrow_mut().pop_front().unwrap_or_else(|| {
+            Ok(ClientResponse {
+                status: 200,
+                ..Default::default()
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_client_records_requests() {
+        let client = MockClient::new();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            http::header::CONTENT_TYPE,
+            "application/json".parse().unwrap(),
+        );
+
+        let response = client
+            .post("http://example.com/webhook", &headers, b"{}".to_vec())
+            .unwrap();
+        assert_eq!(response.status, 200);
+
+        let requests = client.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, Method::POST);
+        assert_eq!(requests[0].url, "http://example.com/webhook");
+        assert_eq!(requests[0].headers, headers);
+        assert_eq!(requests[0].body, b"{}");
+    }
+
+    #[test]
+    fn test_slack_headers_client() {
+        let mock = MockClient::new();
+        let mut extra = HeaderMap::new();
+        extra.insert("x-gateway-key", "k3y".parse().unwrap());
+        extra.insert(http::header::CONTENT_TYPE, "text/plain".parse().unwrap());
+        let client = SlackHeadersClient::new(&mock, extra);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            http::header::CONTENT_TYPE,
+            "application/json".parse().unwrap(),
+        );
+
+        client
+            .post("https://hooks.slack.com/services/T/B/x", &headers, vec![])
+            .unwrap();
+        client
+            .get("https://secrets.example.com/slack.com", &headers)
+            .unwrap();
+        let requests = mock.requests();
+        assert_eq!(requests[0].headers["x-gateway-key"], "k3y");
+        assert_eq!(
+            requests[0].headers[http::header::CONTENT_TYPE],
+            "application/json"
+        );
+        assert!(!requests[1].headers.contains_key("x-gateway-key"));
+    }
+
+    #[test]
+    fn test_mock_client_replays_queued_responses() {
+        let client = MockClient::new()
+            .respond_with(429)
+            .fail_with(anyhow::anyhow!("connection reset"));
+        let headers = HeaderMap::new();
+
+        let first = client.post("http://example.com", &headers, vec![]).unwrap();
+        assert_eq!(first.status, 429);
+        let second = client.post("http://example.com", &headers, vec![]);
+        assert_eq!(second.unwrap_err().to_string(), "connection reset");
+        let third = client.post("http://example.com", &headers, vec![]).unwrap();
+        assert_eq!(third.status, 200);
+    }
+}
@@ -0,0 +1,239 @@
+use anyhow::Result;
+use http::HeaderMap;
+use serde_json::{json, Map, Value};
+
+// CloudEvents 1.0 ingestion
+//
+// https://github.com/cloudevents/spec/blob/v1.0.2/cloudevents/spec.md
+
+pub const MEDIA_TYPE: &str = "application/cloudevents+json";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CloudEvent {
+    pub id: Option<String>,
+    pub event_type: String,
+    pub source: String,
+    pub subject: Option<String>,
+    pub time: Option<String>,
+    pub data: Value,
+}
+
+impl CloudEvent {
+    /// Parses an event sent in structured mode, attributes and data in the body.
+    pub fn from_structured(value: Value) -> Result<Self> {
+        let Value::Object(mut event) = value else {
+            anyhow::bail!("Invalid CloudEvent: expected a JSON object");
+        };
+
+        let attribute = |event: &Map<String, Value>, name: &str| {
+            event
+                .get(name)
+                .and_then(Value::as_str)
+                .map(ToString::to_string)
+        };
+        check_spec_version(attribute(&event, "specversion"))?;
+
+        Ok(Self {
+            id: attribute(&event, "id"),
+            event_type: required(attribute(&event, "type"), "type")?,
+            source: required(attribute(&event, "source"), "source")?,
+            subject: attribute(&event, "subject"),
+            time: attribute(&event, "time"),
+            data: event.remove("data").unwrap_or_default(),
+        })
+    }
+
+    /// Parses an event sent in binary mode, attributes in `ce-*` headers and
+    /// data in the body. Returns `None` when the request isn't a CloudEvent.
+    pub fn from_headers(headers: &HeaderMap, data: Value) -> Option<Result<Self>> {
+        let attribute = |name: &str| {
+            headers
+                .get(format!("ce-{name}"))
+                .and_then(|value| value.to_str().ok())
+                .map(ToString::to_string)
+        };
+        let spec_version = attribute("specversion")?;
+
+        let event = check_spec_version(Some(spec_version)).and_then(|()| {
+            Ok(Self {
+                id: attribute("id"),
+                event_type: required(attribute("type"), "type")?,
+                source: required(attribute("source"), "source")?,
+                subject: attribute("subject"),
+                time: attribute("time"),
+                data,
+            })
+        });
+        Some(event)
+    }
+
+    /// Turns the event into the message context: data fields are kept at the
+    /// top level and attributes are exposed under `cloudevent`.
+    pub fn into_context(self) -> Value {
+        let message = match &self.data {
+            Value::Object(data) if data.contains_key("message") => None,
+            Value::String(text) => Some(text.clone()),
+            _ => Some(format!("{} from {}", self.event_type, self.source)),
+        };
+
+        let mut context = match self.data.clone() {
+            Value::Object(data) => data,
+            _ => Map::new(),
+        };
+        if let Some(message) = message {
+            context.insert("message".to_string(), Value::String(message));
+        }
+        context.insert(
+            "cloudevent".to_string(),
+            json!({
+                "id": self.id,
+                "type": self.event_type,
+                "source": self.source,
+                "subject": self.subject,
+                "time": self.time,
+                "data": self.data,
+            }),
+        );
+        Value::Object(context)
+    }
+}
+
+fn check_spec_version(spec_version: Option<String>) -> Result<()> {
+    match spec_version.as_deref() {
+        Some(version) if version.starts_with("1.") => Ok(()),
+        Some(version) => anyhow::bail!("Unsupported CloudEvents specversion '{version}'"),
+        None => anyhow::bail!("Invalid CloudEvent: missing 'specversion' attribute"),
+    }
+}
+
+fn required(value: Option<String>, name: &str) -> Result<String> {
+    value.ok_or_else(|| anyhow::anyhow!("Invalid CloudEvent: missing '{name}' attribute"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    #[test]
+    fn test_from_structured() {
+        let event = CloudEvent::from_structured(json!({
+            "specversion": "1.0",
+            "id": "42",
+            "type": "com.example.deploy.finished",
+            "source": "/ci/pipelines/7",
+            "data": { "message": "deploy done", "env": "prod" }
+        }))
+        .unwrap();
+
+        assert_eq!(event.id.as_deref(), Some("42"));
+        assert_eq!(event.event_type, "com.example.deploy.finished");
+        assert_eq!(event.source, "/ci/pipelines/7");
+        assert_eq!(
+            event.data,
+            json!({ "message": "deploy done", "env": "prod" })
+        );
+    }
+
+    #[test]
+    fn test_from_structured_missing_type() {
+        let result = CloudEvent::from_structured(json!({
+            "specversion": "1.0",
+            "source": "/ci",
+        }));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid CloudEvent: missing 'type' attribute"
+        );
+    }
+
+    #[test]
+    fn test_from_structured_unsupported_version() {
+        let result = CloudEvent::from_structured(json!({
+            "specversion": "0.3",
+            "type": "t",
+            "source": "s",
+        }));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Unsupported CloudEvents specversion '0.3'"
+        );
+    }
+
+    #[test]
+    fn test_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("ce-specversion", HeaderValue::from_static("1.0"));
+        headers.insert("ce-type", HeaderValue::from_static("com.example.alert"));
+        headers.insert("ce-source", HeaderValue::from_static("/monitoring"));
+        headers.insert("ce-id", HeaderValue::from_static("abc"));
+
+        let event = CloudEvent::from_headers(&headers, json!({ "message": "cpu high" }))
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.event_type, "com.example.alert");
+        assert_eq!(event.source, "/monitoring");
+        assert_eq!(event.id.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn test_from_headers_not_a_cloudevent() {
+        let headers = HeaderMap::new();
+        assert!(CloudEvent::from_headers(&headers, json!({})).is_none());
+    }
+
+    #[test]
+    fn test_from_headers_missing_source() {
+        let mut headers = HeaderMap::new();
+        headers.insert("ce-specversion", HeaderValue::from_static("1.0"));
+        headers.insert("ce-type", HeaderValue::from_static("com.example.alert"));
+
+        let result = CloudEvent::from_headers(&headers, json!({})).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_into_context_keeps_data_message() {
+        let event = CloudEvent::from_structured(json!({
+            "specversion": "1.0",
+            "type": "t",
+            "source": "s",
+            "data": { "message": "hello", "env": "prod" }
+        }))
+        .unwrap();
+
+        let context = event.into_context();
+        assert_eq!(context["message"], "hello");
+        assert_eq!(context["env"], "prod");
+        assert_eq!(context["cloudevent"]["type"], "t");
+        assert_eq!(context["cloudevent"]["source"], "s");
+    }
+
+    #[test]
+    fn test_into_context_default_message() {
+        let event = CloudEvent::from_structured(json!({
+            "specversion": "1.0",
+            "type": "com.example.order.created",
+            "source": "/shop",
+            "data": { "order": 1 }
+        }))
+        .unwrap();
+
+        let context = event.into_context();
+        assert_eq!(context["message"], "com.example.order.created from /shop");
+        assert_eq!(context["order"], 1);
+    }
+
+    #[test]
+    fn test_into_context_string_data() {
+        let event = CloudEvent::from_structured(json!({
+            "specversion": "1.0",
+            "type": "t",
+            "source": "s",
+            "data": "plain text"
+        }))
+        .unwrap();
+
+        assert_eq!(event.into_context()["message"], "plain text");
+    }
+}
@@ -0,0 +1,209 @@
+use regex::{Captures, Regex};
+use serde_json::Value;
+use std::sync::LazyLock;
+
+// Slack date tokens
+//
+// ISO-8601 timestamps are replaced with `<!date^<unix>^{date_short} {time}|<original>>`,
+// which Slack shows in each reader's own timezone, the original text being
+// the fallback for clients that can't. Timestamps without an offset are
+// left alone, there is no telling which timezone they are in.
+
+static TIMESTAMP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"\b(\d{4})-(\d{2})-(\d{2})[T ](\d{2}):(\d{2})(?::(\d{2})(?:\.\d+)?)?(Z|[+-]\d{2}:?\d{2})",
+    )
+    .unwrap()
+});
+
+const TOKEN_FORMAT: &str = "{date_short} {time}";
+
+/// Replaces the ISO-8601 timestamps of `text` with Slack date tokens.
+pub fn format(text: &str) -> String {
+    TIMESTAMP
+        .replace_all(text, |caps: &Captures| {
+            let matched = &caps[0];
+            // Already the fallback of a token
+            let start = caps.get(0).unwrap().start();
+            if text[..start].ends_with('|') {
+                return matched.to_string();
+            }
+            match unix_time(caps) {
+                Some(seconds) => format!("<!date^{seconds}^{TOKEN_FORMAT}|{matched}>"),
+                None => matched.to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Token of the unix time `seconds`, falling back to its UTC timestamp.
+pub fn token(seconds: i64) -> String {
+    let (days, time) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "<!date^{seconds}^{TOKEN_FORMAT}|{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z>",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Replaces timestamps in the mrkdwn text of blocks or attachments: the
+/// `text` of mrkdwn text objects, and the `text` and `pretext` of
+/// attachments. URLs, values and `plain_text`, which shows tokens as
+/// written, are left alone.
+pub fn format_json(value: &mut Value) {
+    match value {
+        Value::Array(items) => items.iter_mut().for_each(format_json),
+        Value::Object(map) => {
+            // Blocks and elements are typed, attachments aren't
+            let mrkdwn = map
+                .get("type")
+                .and_then(Value::as_str)
+                .is_none_or(|kind| kind == "mrkdwn");
+            for (key, value) in map.iter_mut() {
+                match (key.as_str(), value) {
+                    ("text" | "pretext", Value::String(text)) if mrkdwn => *text = format(text),
+                    (_, value) => format_json(value),
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn unix_time(caps: &Captures) -> Option<i64> {
+    let number = |index: usize| caps.get(index).map_or(Some(0), |m| m.as_str().parse().ok());
+    let (year, month, day) = (number(1)?, number(2)?, number(3)?);
+    let (hour, minute, second) = (number(4)?, number(5)?, number(6)?);
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    let offset = match &caps[7] {
+        "Z" => 0,
+        offset => {
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            let digits = offset[1..].replace(':', "");
+            let (hours, minutes): (i64, i64) =
+                (digits[..2].parse().ok()?, digits[2..].parse().ok()?);
+            sign * (hours * 3600 + minutes * 60)
+        }
+    };
+    Some(days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second - offset)
+}
+
+// Days since 1970-01-01 of a proleptic Gregorian date, Howard Hinnant's
+// `days_from_civil`
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// Date of a number of days since 1970-01-01, Howard Hinnant's
+// `civil_from_days`
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            format("Deployed at 2024-01-31T10:30:00Z"),
+            "Deployed at <!date^1706697000^{date_short} {time}|2024-01-31T10:30:00Z>"
+        );
+        assert_eq!(
+            format("Started 2024-03-01 01:00+02:00, done"),
+            "Started <!date^1709247600^{date_short} {time}|2024-03-01 01:00+02:00>, done"
+        );
+        assert_eq!(
+            format("at 1970-01-01T00:00:00.250-0130"),
+            "at <!date^5400^{date_short} {time}|1970-01-01T00:00:00.250-0130>"
+        );
+    }
+
+    #[test]
+    fn test_format_keeps_other_text() {
+        assert_eq!(format("Deployed on 2024-01-31"), "Deployed on 2024-01-31");
+        assert_eq!(
+            format("Local time 2024-01-31T10:30:00"),
+            "Local time 2024-01-31T10:30:00"
+        );
+        assert_eq!(
+            format("Invalid 2024-13-31T10:30:00Z"),
+            "Invalid 2024-13-31T10:30:00Z"
+        );
+        let token = "<!date^1706697000^{date_short} {time}|2024-01-31T10:30:00Z>";
+        assert_eq!(format(token), token);
+    }
+
+    #[test]
+    fn test_token() {
+        assert_eq!(
+            token(1706697000),
+            "<!date^1706697000^{date_short} {time}|2024-01-31T10:30:00Z>"
+        );
+        assert_eq!(
+            token(951868799),
+            "<!date^951868799^{date_short} {time}|2000-02-29T23:59:59Z>"
+        );
+    }
+
+    #[test]
+    fn test_format_json() {
+        let since = "Since 2024-01-31T10:30:00Z";
+        let token = "Since <!date^1706697000^{date_short} {time}|2024-01-31T10:30:00Z>";
+        let url = "https://grafana.example.com/d/1?from=2024-01-31T10:30:00Z";
+        let mut value = serde_json::json!([
+            { "type": "header", "text": { "type": "plain_text", "text": since } },
+            {
+                "type": "section",
+                "text": { "type": "mrkdwn", "text": since },
+                "accessory": { "type": "button", "text": { "type": "plain_text", "text": since }, "url": url },
+            },
+            { "type": "image", "image_url": url, "alt_text": since },
+            { "pretext": since, "title": since, "title_link": url, "ts": 1700000000 },
+        ]);
+        format_json(&mut value);
+        assert_eq!(
+            value,
+            serde_json::json!([
+                { "type": "header", "text": { "type": "plain_text", "text": since } },
+                {
+                    "type": "section",
+                    "text": { "type": "mrkdwn", "text": token },
+                    "accessory": { "type": "button", "text": { "type": "plain_text", "text": since }, "url": url },
+                },
+                { "type": "image", "image_url": url, "alt_text": since },
+                { "pretext": token, "title": since, "title_link": url, "ts": 1700000000 },
+            ])
+        );
+    }
+}
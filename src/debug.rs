@@ -0,0 +1,127 @@
+use anyhow::Result;
+use http::Request;
+use serde_json::{json, Value};
+
+use crate::helpers::ApiError;
+use crate::request::NotificationRequest;
+use crate::{auth, selftest, template, Settings};
+
+// Debug echo
+//
+// Requests sent with `X-Debug: true` and the `auth_token` as a bearer token
+// are handled as usual, their response also holding a `debug` object: the
+// Slack payload rendered for them, where it was sent, the filter rules and
+// source they went through, and the timings. What is sent is unchanged,
+// the point is to debug routing settings against production traffic.
+
+pub const DEBUG_HEADER: &str = "x-debug";
+
+/// Whether the request asks for the debug echo, which needs the
+/// `auth_token`.
+pub fn requested<B>(req: &Request<B>, settings: &Settings) -> Result<bool> {
+    let asked = req
+        .headers()
+        .get(DEBUG_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| matches!(value.trim(), "true" | "1"));
+    if !asked {
+        return Ok(false);
+    }
+
+    let Some(expected) = settings.auth_token.as_deref() else {
+        return Err(ApiError::not_found("The x-debug header needs an auth_token").into());
+    };
+    if !auth::is_authorized(req.headers(), None, expected) {
+        return Err(ApiError::unauthorized("Unauthorized").into());
+    }
+    Ok(true)
+}
+
+/// Debug details of `request`, sent with `settings`.
+pub fn details(
+    settings: &Settings,
+    request: &NotificationRequest,
+    source: Option<&str>,
+) -> Result<Value> {
+    let rules = match &settings.filters {
+        Some(filters) => filters.matched(request)?,
+        None => Vec::new(),
+    };
+
+    // Rendered again, errors being in the response already
+    let mut rendered = request.clone();
+    if !settings.interpolated_fields.is_empty() {
+        template::interpolate(&mut rendered, &settings.interpolated_fields)?;
+    }
+    let destination = selftest::destination(settings, &rendered);
+    let payload = crate::render(settings, rendered)
+        .ok()
+        .map(serde_json::to_value)
+        .transpose()?;
+
+    Ok(json!({
+        "source": source,
+        "destination": destination,
+        "filters": rules,
+        "payload": payload,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::collections::HashMap;
+
+    fn settings() -> Settings {
+        let data = HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/T0/B0/secret".to_string(),
+            ),
+            ("auth_token".to_string(), "s3cret".to_string()),
+            (
+                "filters".to_string(),
+                r#"{"include": [{"field": "env", "equals": "prod"}]}"#.to_string(),
+            ),
+        ]);
+        Settings::from_map(&data).unwrap()
+    }
+
+    #[test]
+    fn test_requested() {
+        let req = testing::RequestFixture::post()
+            .header("X-Debug", "true")
+            .header("Authorization", "Bearer s3cret")
+            .build();
+        assert!(requested(&req, &settings()).unwrap());
+        assert!(!requested(&testing::RequestFixture::post().build(), &settings()).unwrap());
+
+        let req = testing::RequestFixture::post()
+            .header("X-Debug", "true")
+            .build();
+        let err = requested(&req, &settings()).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ApiError>().unwrap().status,
+            http::StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[test]
+    fn test_details() {
+        let request = NotificationRequest::from_value(
+            json!({ "message": "Disk full", "env": "prod" }),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            details(&settings(), &request, None).unwrap(),
+            json!({
+                "source": null,
+                "destination": "https://hooks.slack.com/services/T0/B0/****",
+                "filters": ["include[0]"],
+                "payload": { "text": "Disk full" },
+            })
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use anyhow::Result;
+
+use crate::client::OutboundClient;
+use crate::store::{self, HttpStore, KeyValue};
+use crate::Settings;
+
+// Duplicate notifications
+//
+// Notifications carrying a dedup key, like the GUID of a feed item, are
+// delivered once: the key is stored under `dedup/<key>` after a delivery
+// and later notifications with it are dropped. Needs the key-value store,
+// every notification is delivered without it.
+
+fn dedup_key(key: &str) -> String {
+    format!("dedup/{}", store::segment(key))
+}
+
+/// Whether a notification with `key` was already delivered.
+pub fn seen(settings: &Settings, client: &dyn OutboundClient, key: Option<&str>) -> Result<bool> {
+    let (Some(key), Some(_)) = (key, &settings.token_store_endpoint) else {
+        return Ok(false);
+    };
+    let store = HttpStore::from_settings(settings, client)?;
+    Ok(store.get(&dedup_key(key))?.is_some())
+}
+
+/// Records the delivery of a notification with `key`.
+pub fn remember(settings: &Settings, client: &dyn OutboundClient, key: Option<&str>) -> Result<()> {
+    let (Some(key), Some(_)) = (key, &settings.token_store_endpoint) else {
+        return Ok(());
+    };
+    let store = HttpStore::from_settings(settings, client)?;
+    store.put(&dedup_key(key), "1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockClient;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_seen() {
+        let data = HashMap::from([
+            ("bot_token".to_string(), "xoxb-1".to_string()),
+            (
+                "token_store_endpoint".to_string(),
+                "https://kv.example.com/slack".to_string(),
+            ),
+        ]);
+        let settings = Settings::from_map(&data).unwrap();
+        let client = MockClient::new()
+            .respond_with_body(200, r#"{"value": "1"}"#)
+            .respond_with(404);
+
+        assert!(seen(&settings, &client, Some("feed/https://blog.example.com/1")).unwrap());
+        assert!(!seen(&settings, &client, Some("feed/2")).unwrap());
+        assert!(!seen(&settings, &client, None).unwrap());
+        let requests = client.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[0].url,
+            "https://kv.example.com/slack/dedup/feed%2Fhttps%3A%2F%2Fblog.example.com%2F1"
+        );
+    }
+}
@@ -0,0 +1,346 @@
+use anyhow::Result;
+use http::{Request, Response};
+use serde_json::{json, Value};
+
+use crate::blocklist::Blocklist;
+use crate::client::OutboundClient;
+use crate::helpers::body::Json;
+use crate::helpers::negotiate::Negotiated;
+use crate::helpers::ApiError;
+use crate::request::{MessageBody, NotificationRequest};
+use crate::store::{HttpStore, KeyValue};
+use crate::{auth, normalize, pii, ComponentResponse, Settings};
+
+// Digests
+//
+// Requests with a severity listed in `digest_severities` are not posted but
+// appended to the `digest` key of the store. `POST /flush`, meant to be
+// called by a scheduler, posts one summary per workspace and channels with
+// the messages buffered since the last flush, the way a request would be.
+// The buffer is read and written back without locking, a message buffered
+// while a flush runs can be lost. The store being outside the edge, only the
+// message, severity and destination are kept, the message masked like a
+// posted one and cut to `MAX_ENTRY_CHARS`. A full buffer answers new
+// messages with a 429 until the next flush.
+
+pub const FLUSH_PATH: &str = "/flush";
+
+const BUFFER_KEY: &str = "digest";
+
+/// Characters of a buffered message kept, longer ones being cut.
+const MAX_ENTRY_CHARS: usize = 300;
+
+/// Messages the buffer holds, their summary staying under Slack's 40,000
+/// characters of text.
+const MAX_ENTRIES: usize = 100;
+
+/// Whether `request` waits for the next flush instead of being posted.
+pub fn holds(settings: &Settings, request: &NotificationRequest) -> bool {
+    request
+        .severity
+        .as_ref()
+        .is_some_and(|severity| settings.digest_severities.contains(severity))
+}
+
+fn load(store: &HttpStore) -> Result<Vec<NotificationRequest>> {
+    match store.get(BUFFER_KEY)? {
+        Some(buffer) => Ok(serde_json::from_str(&buffer)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Appends `request` to the digest buffer.
+pub fn buffer(
+    settings: &Settings,
+    request: NotificationRequest,
+    client: &dyn OutboundClient,
+) -> Result<ComponentResponse> {
+    if request.message.is_none() {
+        return Err(ApiError::bad_request("Missing 'message' field in request body").into());
+    }
+
+    let store = HttpStore::from_settings(settings, client)?;
+    let mut buffered = load(&store)
+        .map_err(|err| ApiError::bad_gateway(format!("Failed to read digest: {err}")))?;
+    if buffered.len() >= MAX_ENTRIES {
+        return Err(ApiError::too_many_requests(format!(
+            "The digest holds {MAX_ENTRIES} messages already, until the next flush"
+        ))
+        .into());
+    }
+    buffered.push(entry(settings, request));
+    store
+        .put(BUFFER_KEY, &serde_json::to_string(&buffered)?)
+        .map_err(|err| ApiError::bad_gateway(format!("Failed to store digest: {err}")))?;
+
+    Ok(ComponentResponse {
+        status: http::StatusCode::ACCEPTED,
+        body: json!({ "ok": true, "buffered": true }),
+        slack_status: None,
+    })
+}
+
+// What the summary shows of `request`, masked like the message
+fn entry(settings: &Settings, request: NotificationRequest) -> NotificationRequest {
+    let message = request.message.as_deref().unwrap_or_default();
+    let message = normalize::text(message, settings.normalize_text);
+    let message = match settings.scrub_pii {
+        true => pii::scrub(&message),
+        false => message,
+    };
+    let mut message = match Blocklist::new(&settings.blocklist) {
+        Some(blocklist) => blocklist.mask(&message),
+        None => message,
+    };
+    if message.chars().count() > MAX_ENTRY_CHARS {
+        message = message
+            .chars()
+            .take(MAX_ENTRY_CHARS - 1)
+            .collect::<String>()
+            + "…";
+    }
+
+    NotificationRequest {
+        message: Some(message),
+        severity: request.severity,
+        channel: request.channel,
+        channels: request.channels,
+        team_id: request.team_id,
+        ..Default::default()
+    }
+}
+
+// Summary of requests sharing their destination
+fn summary(requests: &[NotificationRequest]) -> NotificationRequest {
+    let mut message = format!("Digest of {} messages:", requests.len());
+    for request in requests {
+        let text = request.message.as_deref().unwrap_or_default();
+        match &request.severity {
+            Some(severity) => message.push_str(&format!("\n• *{severity}* {text}")),
+            None => message.push_str(&format!("\n• {text}")),
+        }
+    }
+
+    let first = &requests[0];
+    NotificationRequest {
+        message: Some(message),
+        channel: first.channel.clone(),
+        channels: first.channels.clone(),
+        team_id: first.team_id.clone(),
+        ..Default::default()
+    }
+}
+
+/// Handles `POST /flush`, which needs the `auth_token` as a bearer token.
+pub fn handle_flush(
+    req: &Request<Option<MessageBody>>,
+    settings: &Settings,
+    client: &dyn OutboundClient,
+) -> Result<Response<Negotiated<Value>>> {
+    let Some(expected) = settings.auth_token.as_deref() else {
+        return Err(ApiError::not_found("Digests are not configured").into());
+    };
+    if !auth::is_authorized(req.headers(), None, expected) {
+        return Err(ApiError::unauthorized("Unauthorized").into());
+    }
+
+    let store = HttpStore::from_settings(settings, client)
+        .map_err(|err| ApiError::not_found(err.to_string()))?;
+    let buffered = load(&store)
+        .map_err(|err| ApiError::bad_gateway(format!("Failed to read digest: {err}")))?;
+
+    let mut groups: Vec<Vec<NotificationRequest>> = Vec::new();
+    for request in buffered {
+        let same_destination = |group: &&mut Vec<NotificationRequest>| {
+            let first = &group[0];
+            (&first.team_id, &first.channel, &first.channels)
+                == (&request.team_id, &request.channel, &request.channels)
+        };
+        match groups.iter_mut().find(same_destination) {
+            Some(group) => group.push(request),
+            None => groups.push(vec![request]),
+        }
+    }
+
+    // Groups Slack refused stay buffered for the next flush
+    let mut flushed = 0;
+    let mut pending = Vec::new();
+    let mut errors = Vec::new();
+    for group in groups {
+        match crate::handle_notification(settings, summary(&group), client) {
+            Ok(response) if response.status.is_success() => flushed += group.len(),
+            Ok(response) => {
+                errors.push(response.body);
+                pending.extend(group);
+            }
+            Err(err) => {
+                errors.push(json!({ "error": err.to_string() }));
+                pending.extend(group);
+            }
+        }
+    }
+    if flushed > 0 {
+        store
+            .put(BUFFER_KEY, &serde_json::to_string(&pending)?)
+            .map_err(|err| ApiError::bad_gateway(format!("Failed to store digest: {err}")))?;
+    }
+
+    let mut body = json!({ "ok": errors.is_empty(), "flushed": flushed, "pending": pending.len() });
+    if !errors.is_empty() {
+        body["errors"] = json!(errors);
+    }
+    let mut res = Response::new(Negotiated::from(Json(body)));
+    if !errors.is_empty() {
+        *res.status_mut() = http::StatusCode::BAD_GATEWAY;
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockClient;
+
+    fn settings() -> Settings {
+        let data = std::collections::HashMap::from([
+            ("bot_token".to_string(), "xoxb-1".to_string()),
+            ("default_channel".to_string(), "#general".to_string()),
+            (
+                "token_store_endpoint".to_string(),
+                "https://kv.example.com/slack".to_string(),
+            ),
+            ("auth_token".to_string(), "s3cret".to_string()),
+            ("digest_severities".to_string(), "info".to_string()),
+        ]);
+        Settings::from_map(&data).unwrap()
+    }
+
+    fn request(message: &str, channel: Option<&str>) -> NotificationRequest {
+        NotificationRequest {
+            message: Some(message.to_string()),
+            severity: Some("info".to_string()),
+            channel: channel.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn stored(value: impl serde::Serialize) -> String {
+        json!({ "value": serde_json::to_string(&value).unwrap() }).to_string()
+    }
+
+    fn flush_request(token: &str) -> Request<Option<MessageBody>> {
+        Request::builder()
+            .method(http::Method::POST)
+            .uri(FLUSH_PATH)
+            .header(http::header::AUTHORIZATION, format!("Bearer {token}"))
+            .body(None)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_holds() {
+        assert!(holds(&settings(), &request("Backup done", None)));
+        let request = NotificationRequest {
+            severity: Some("error".to_string()),
+            ..request("Disk full", None)
+        };
+        assert!(!holds(&settings(), &request));
+    }
+
+    #[test]
+    fn test_buffer() {
+        let client = MockClient::new()
+            .respond_with_body(200, &stored([request("Backup done", None)]))
+            .respond_with(204);
+        let response = buffer(&settings(), request("Cache warmed", None), &client).unwrap();
+        assert_eq!(response.status, http::StatusCode::ACCEPTED);
+
+        let requests = client.requests();
+        assert_eq!(requests[1].url, "https://kv.example.com/slack/digest");
+        let buffer: Value = serde_json::from_slice(&requests[1].body).unwrap();
+        let buffer: Vec<NotificationRequest> =
+            serde_json::from_str(buffer["value"].as_str().unwrap()).unwrap();
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(buffer[1].message.as_deref(), Some("Cache warmed"));
+    }
+
+    #[test]
+    fn test_buffer_keeps_masked_entry() {
+        let mut settings = settings();
+        settings.scrub_pii = true;
+        settings.blocklist = vec!["darn".to_string()];
+        let client = MockClient::new().respond_with(404).respond_with(204);
+        let request = NotificationRequest {
+            username: Some("backup-bot".to_string()),
+            ..request(
+                &format!("Darn, mail jane@example.com {}", "x".repeat(400)),
+                Some("#ops"),
+            )
+        };
+        buffer(&settings, request, &client).unwrap();
+
+        let requests = client.requests();
+        let buffer: Value = serde_json::from_slice(&requests[1].body).unwrap();
+        let buffer: Vec<NotificationRequest> =
+            serde_json::from_str(buffer["value"].as_str().unwrap()).unwrap();
+        let message = buffer[0].message.as_deref().unwrap();
+        assert!(message.starts_with("D***, mail [email] xxx"));
+        assert!(message.ends_with("x…"));
+        assert_eq!(message.chars().count(), MAX_ENTRY_CHARS);
+        assert_eq!(buffer[0].channel.as_deref(), Some("#ops"));
+        assert_eq!(buffer[0].username, None);
+    }
+
+    #[test]
+    fn test_buffer_full() {
+        let full = vec![request("Backup done", None); MAX_ENTRIES];
+        let client = MockClient::new().respond_with_body(200, &stored(full));
+        let err = buffer(&settings(), request("Cache warmed", None), &client).unwrap_err();
+        let err = err.downcast_ref::<ApiError>().unwrap();
+        assert_eq!(err.status, http::StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(client.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_flush() {
+        let client = MockClient::new()
+            .respond_with_body(
+                200,
+                &stored([
+                    request("Backup done", None),
+                    request("Deploy started", Some("#deploys")),
+                    request("Cache warmed", None),
+                ]),
+            )
+            .respond_with_body(200, r#"{"ok": true, "channel": "C1", "ts": "1.1"}"#)
+            .respond_with_body(200, r#"{"ok": false, "error": "channel_not_found"}"#)
+            .respond_with(204);
+
+        let res = handle_flush(&flush_request("s3cret"), &settings(), &client).unwrap();
+        assert_eq!(res.status(), http::StatusCode::BAD_GATEWAY);
+        let body = crate::testing::assert_status(&res, http::StatusCode::BAD_GATEWAY);
+        assert_eq!(body["flushed"], 2);
+        assert_eq!(body["pending"], 1);
+
+        let requests = client.requests();
+        let posted: Value = serde_json::from_slice(&requests[1].body).unwrap();
+        assert_eq!(
+            posted["text"],
+            "Digest of 2 messages:\n• *info* Backup done\n• *info* Cache warmed"
+        );
+        assert_eq!(posted["channel"], "#general");
+        let buffer: Value = serde_json::from_slice(&requests[3].body).unwrap();
+        let buffer: Vec<NotificationRequest> =
+            serde_json::from_str(buffer["value"].as_str().unwrap()).unwrap();
+        assert_eq!(buffer, vec![request("Deploy started", Some("#deploys"))]);
+    }
+
+    #[test]
+    fn test_flush_requires_auth_token() {
+        let client = MockClient::new();
+        let err = handle_flush(&flush_request("wrong"), &settings(), &client).unwrap_err();
+        let err = err.downcast_ref::<ApiError>().unwrap();
+        assert_eq!(err.status, http::StatusCode::UNAUTHORIZED);
+        assert!(client.requests().is_empty());
+    }
+}
@@ -0,0 +1,63 @@
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+// Keyword emojis
+//
+// Words of the message matching a key of the `emojify` setting, case
+// insensitively, get the emoji they map to put in front of them, e.g.
+// "error" becomes "🔴 error". `:shortcode:` sequences and Slack tokens like
+// `<https://example.com|deploy>` are left as written.
+
+static TOKEN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<[^>]*>|:[a-z0-9_+'-]+:|\w+").unwrap());
+
+/// Puts the emoji of each keyword of `text` in front of it.
+pub fn emojify(text: &str, keywords: &HashMap<String, String>) -> String {
+    if keywords.is_empty() {
+        return text.to_string();
+    }
+    let keywords: HashMap<String, &str> = keywords
+        .iter()
+        .map(|(keyword, emoji)| (keyword.to_lowercase(), emoji.as_str()))
+        .collect();
+
+    TOKEN
+        .replace_all(text, |caps: &Captures| {
+            let word = &caps[0];
+            match keywords.get(&word.to_lowercase()) {
+                Some(emoji) => format!("{emoji} {word}"),
+                None => word.to_string(),
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keywords() -> HashMap<String, String> {
+        HashMap::from([
+            ("error".to_string(), "🔴".to_string()),
+            ("Deploy".to_string(), ":rocket:".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_emojify() {
+        assert_eq!(
+            emojify("deploy failed with an Error", &keywords()),
+            ":rocket: deploy failed with an 🔴 Error"
+        );
+        assert_eq!(emojify("errors: 0", &keywords()), "errors: 0");
+    }
+
+    #[test]
+    fn test_emojify_keeps_shortcodes_and_tokens() {
+        assert_eq!(
+            emojify(":error: see <https://ci.example.com|deploy>", &keywords()),
+            ":error: see <https://ci.example.com|deploy>"
+        );
+    }
+}
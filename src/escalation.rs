@@ -0,0 +1,245 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serde_json::json;
+
+use crate::client::OutboundClient;
+use crate::events::{self, TrackedMessage};
+use crate::slack_api::{self, PostedMessage};
+use crate::store::{HttpStore, KeyValue};
+use crate::Settings;
+
+// Escalation of unacknowledged critical messages
+//
+// Components only run when called, so overdue messages are looked for on
+// the next message request. Critical messages are listed under
+// `escalations/<team>` when posted, the ones still unacknowledged after
+// `escalate_after_seconds` are posted again in their thread, broadcast to
+// the channel and mentioning `oncall_group`, once. The list is read and
+// written back without locking, two concurrent requests may escalate the
+// same message twice.
+
+pub const CRITICAL: &str = "critical";
+
+/// Critical message waiting for an acknowledgement.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct Pending {
+    pub channel_id: String,
+    pub ts: String,
+    /// Unix time the message was posted at, in seconds.
+    pub posted_at: u64,
+}
+
+fn pending_key(team_id: Option<&str>) -> String {
+    format!("escalations/{}", team_id.unwrap_or("default"))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Escalates the overdue messages of `team_id`, then lists the critical
+/// messages just posted.
+pub fn run(
+    settings: &Settings,
+    client: &dyn OutboundClient,
+    bot_token: &str,
+    team_id: Option<&str>,
+    posted: &[PostedMessage],
+    severity: Option<&str>,
+) -> Result<()> {
+    let (Some(after), Some(group)) = (
+        settings.escalate_after_seconds,
+        settings.oncall_group.as_deref(),
+    ) else {
+        return Ok(());
+    };
+
+    let store = HttpStore::from_settings(settings, client)?;
+    let key = pending_key(team_id);
+    let mut pending: Vec<Pending> = match store.get(&key)? {
+        Some(list) => serde_json::from_str(&list)?,
+        None => Vec::new(),
+    };
+    let listed = pending.len();
+
+    let now = unix_now();
+    let (overdue, waiting): (Vec<_>, Vec<_>) = pending
+        .into_iter()
+        .partition(|message| now.saturating_sub(message.posted_at) >= after);
+    pending = waiting;
+    // Escalations are not retried, a failed one is dropped like the others
+    for message in &overdue {
+        if let Err(err) = escalate(&store, client, bot_token, group, message) {
+            eprintln!("Failed to escalate message {}: {err}", message.ts);
+        }
+    }
+
+    if severity == Some(CRITICAL) {
+        pending.extend(posted.iter().filter_map(|message| {
+            Some(Pending {
+                channel_id: message.channel_id.clone()?,
+                ts: message.ts.clone()?,
+                posted_at: now,
+            })
+        }));
+    }
+
+    if !overdue.is_empty() || pending.len() != listed {
+        store.put(&key, &serde_json::to_string(&pending)?)?;
+    }
+    Ok(())
+}
+
+fn escalate(
+    store: &HttpStore,
+    client: &dyn OutboundClient,
+    bot_token: &str,
+    group: &str,
+    message: &Pending,
+) -> Result<()> {
+    let Some(tracked) = store.get(&events::message_key(&message.channel_id, &message.ts))? else {
+        return Ok(());
+    };
+    let tracked: TrackedMessage = serde_json::from_str(&tracked)?;
+    if tracked.acknowledged_by.is_some() {
+        return Ok(());
+    }
+
+    let payload = json!({
+        "text": format!("<!subteam^{group}> Not acknowledged yet: {}", tracked.text),
+        "thread_ts": message.ts,
+        "reply_broadcast": true,
+    });
+    let channels = [message.channel_id.clone()];
+    let posted = slack_api::post_messages(client, bot_token, &channels, &payload, 1)?;
+    if let Some(error) = posted.iter().find_map(|message| message.error.as_deref()) {
+        anyhow::bail!("Slack refused the escalation: {error}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockClient;
+    use serde_json::Value;
+
+    fn settings() -> Settings {
+        let data = std::collections::HashMap::from([
+            ("bot_token".to_string(), "xoxb-1".to_string()),
+            (
+                "token_store_endpoint".to_string(),
+                "https://kv.example.com/slack".to_string(),
+            ),
+            ("signing_secret".to_string(), "shh".to_string()),
+            ("escalate_after_seconds".to_string(), "600".to_string()),
+            ("oncall_group".to_string(), "S0123".to_string()),
+        ]);
+        Settings::from_map(&data).unwrap()
+    }
+
+    fn posted(ts: &str) -> PostedMessage {
+        PostedMessage {
+            channel: "#alerts".to_string(),
+            channel_id: Some("C1".to_string()),
+            ts: Some(ts.to_string()),
+            error: None,
+        }
+    }
+
+    fn stored(value: impl serde::Serialize) -> String {
+        json!({ "value": serde_json::to_string(&value).unwrap() }).to_string()
+    }
+
+    #[test]
+    fn test_run_lists_critical_messages() {
+        let client = MockClient::new().respond_with(404).respond_with(204);
+        run(
+            &settings(),
+            &client,
+            "xoxb-1",
+            Some("T1"),
+            &[posted("1.1")],
+            Some(CRITICAL),
+        )
+        .unwrap();
+
+        let requests = client.requests();
+        assert_eq!(
+            requests[0].url,
+            "https://kv.example.com/slack/escalations/T1"
+        );
+        let list: Value = serde_json::from_slice(&requests[1].body).unwrap();
+        let list: Vec<Pending> = serde_json::from_str(list["value"].as_str().unwrap()).unwrap();
+        assert_eq!(list[0].ts, "1.1");
+    }
+
+    #[test]
+    fn test_run_ignores_other_severities() {
+        let client = MockClient::new().respond_with(404);
+        run(
+            &settings(),
+            &client,
+            "xoxb-1",
+            None,
+            &[posted("1.1")],
+            Some("warning"),
+        )
+        .unwrap();
+        assert_eq!(client.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_run_escalates_overdue_messages() {
+        let overdue = Pending {
+            channel_id: "C1".to_string(),
+            ts: "1.1".to_string(),
+            posted_at: 0,
+        };
+        let acknowledged = Pending {
+            ts: "2.2".to_string(),
+            ..overdue.clone()
+        };
+        let waiting = Pending {
+            ts: "3.3".to_string(),
+            posted_at: unix_now(),
+            ..overdue.clone()
+        };
+        let client = MockClient::new()
+            .respond_with_body(200, &stored([&overdue, &acknowledged, &waiting]))
+            .respond_with_body(
+                200,
+                &stored(json!({ "channel": "#alerts", "text": "DB down" })),
+            )
+            .respond_with_body(200, r#"{"ok": true, "channel": "C1", "ts": "4.4"}"#)
+            .respond_with_body(
+                200,
+                &stored(
+                    json!({ "channel": "#alerts", "text": "Disk full", "acknowledged_by": "U1" }),
+                ),
+            )
+            .respond_with(204);
+
+        run(&settings(), &client, "xoxb-1", None, &[], Some("info")).unwrap();
+
+        let requests = client.requests();
+        assert_eq!(requests[2].url, "https://slack.com/api/chat.postMessage");
+        assert_eq!(
+            serde_json::from_slice::<Value>(&requests[2].body).unwrap(),
+            json!({
+                "channel": "C1",
+                "text": "<!subteam^S0123> Not acknowledged yet: DB down",
+                "thread_ts": "1.1",
+                "reply_broadcast": true,
+            })
+        );
+        assert_eq!(requests.len(), 5);
+        let list: Value = serde_json::from_slice(&requests[4].body).unwrap();
+        let list: Vec<Pending> = serde_json::from_str(list["value"].as_str().unwrap()).unwrap();
+        assert_eq!(list, vec![waiting]);
+    }
+}
@@ -0,0 +1,428 @@
+use anyhow::Result;
+use http::{Request, Response};
+use serde_json::{json, Value};
+
+use crate::client::OutboundClient;
+use crate::helpers::body::Json;
+use crate::helpers::negotiate::Negotiated;
+use crate::helpers::ApiError;
+use crate::request::MessageBody;
+use crate::slack_api::PostedMessage;
+use crate::store::{HttpStore, KeyValue};
+use crate::{secrets, signature, Settings};
+
+// Slack Events API
+//
+// `POST /slack/events` receives the events the Slack app is subscribed to.
+// Messages posted in bot-token mode are tracked by channel and ts when the
+// `ack_callback_url` or `escalate_after_seconds` setting is set, a ✅
+// reaction or a reply in the thread of one of them acknowledges it: the
+// callback is called once, the first acknowledgement wins.
+
+pub const EVENTS_PATH: &str = "/slack/events";
+
+/// Reaction acknowledging a tracked message.
+pub const ACK_REACTION: &str = "white_check_mark";
+
+#[derive(serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Envelope {
+    UrlVerification {
+        challenge: String,
+    },
+    EventCallback {
+        #[serde(default)]
+        team_id: Option<String>,
+        event: Box<Event>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(serde::Deserialize)]
+struct Event {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    reaction: Option<String>,
+    #[serde(default)]
+    user: Option<String>,
+    #[serde(default)]
+    item: Option<Item>,
+    #[serde(default)]
+    channel: Option<String>,
+    #[serde(default)]
+    thread_ts: Option<String>,
+    #[serde(default)]
+    subtype: Option<String>,
+    #[serde(default)]
+    bot_id: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct Item {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    channel: Option<String>,
+    #[serde(default)]
+    ts: Option<String>,
+}
+
+/// Message posted by the component, stored until it is acknowledged.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct TrackedMessage {
+    /// Channel as requested, e.g. `#alerts`.
+    pub channel: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<String>,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acknowledged_by: Option<String>,
+}
+
+pub fn message_key(channel_id: &str, ts: &str) -> String {
+    format!("messages/{channel_id}/{ts}")
+}
+
+/// Stores the messages Slack accepted so reactions on them can be matched.
+pub fn track(
+    settings: &Settings,
+    client: &dyn OutboundClient,
+    messages: &[PostedMessage],
+    severity: Option<&str>,
+    text: &str,
+) -> Result<()> {
+    if !settings.tracks_messages() {
+        return Ok(());
+    }
+
+    let store = HttpStore::from_settings(settings, client)?;
+    for message in messages {
+        let (Some(channel_id), Some(ts)) = (&message.channel_id, &message.ts) else {
+            continue;
+        };
+        let tracked = TrackedMessage {
+            channel: message.channel.clone(),
+            severity: severity.map(str::to_string),
+            text: text.to_string(),
+            acknowledged_by: None,
+        };
+        store.put(
+            &message_key(channel_id, ts),
+            &serde_json::to_string(&tracked)?,
+        )?;
+    }
+    Ok(())
+}
+
+/// Handles an Events API request, Slack's signature being checked first.
+pub fn handle(
+    req: &Request<Option<MessageBody>>,
+    settings: &Settings,
+    client: &dyn OutboundClient,
+) -> Result<Response<Negotiated<Value>>> {
+    let Some(signing_secret) = settings.signing_secret.as_deref() else {
+        return Err(ApiError::not_found("Slack events are not configured").into());
+    };
+    let Some(MessageBody(data, Some(raw), _)) = req.body() else {
+        return Err(ApiError::bad_request("Missing request body").into());
+    };
+
+    let signing_secret = secrets::resolve(
+        signing_secret,
+        settings.secrets_endpoint.as_deref(),
+        settings.secrets_token.as_deref(),
+        client,
+    )?;
+    if !signature::verify(req.headers(), raw, &signing_secret) {
+        return Err(ApiError::unauthorized("Invalid Slack signature").into());
+    }
+
+    let envelope: Envelope = serde_json::from_value(data.clone())
+        .map_err(|err| ApiError::bad_request(format!("Invalid Slack event: {err}")))?;
+    let body = match envelope {
+        Envelope::UrlVerification { challenge } => json!({ "challenge": challenge }),
+        Envelope::EventCallback { team_id, event } => {
+            acknowledge(settings, client, team_id, *event)?;
+            json!({ "ok": true })
+        }
+        Envelope::Other => json!({ "ok": true }),
+    };
+    Ok(Response::new(Negotiated::from(Json(body))))
+}
+
+// Channel and ts of the message an event acknowledges, through a ✅
+// reaction or a person replying in its thread
+fn acknowledged_message(event: &Event) -> Option<(&str, &str)> {
+    match event.kind.as_str() {
+        "reaction_added" if event.reaction.as_deref() == Some(ACK_REACTION) => {
+            let item = event.item.as_ref().filter(|item| item.kind == "message")?;
+            Some((item.channel.as_deref()?, item.ts.as_deref()?))
+        }
+        "message" if event.subtype.is_none() && event.bot_id.is_none() => {
+            Some((event.channel.as_deref()?, event.thread_ts.as_deref()?))
+        }
+        _ => None,
+    }
+}
+
+// Records the first acknowledgement of a tracked message, calling
+// ack_callback_url when set
+fn acknowledge(
+    settings: &Settings,
+    client: &dyn OutboundClient,
+    team_id: Option<String>,
+    event: Event,
+) -> Result<()> {
+    if !settings.tracks_messages() {
+        return Ok(());
+    }
+    let Some((channel_id, ts)) = acknowledged_message(&event) else {
+        return Ok(());
+    };
+
+    let store = HttpStore::from_settings(settings, client)?;
+    let key = message_key(channel_id, ts);
+    let stored = store
+        .get(&key)
+        .map_err(|err| ApiError::bad_gateway(format!("Failed to read tracked message: {err}")))?;
+    let Some(stored) = stored else {
+        return Ok(());
+    };
+    let mut tracked: TrackedMessage = serde_json::from_str(&stored)?;
+    if tracked.acknowledged_by.is_some() {
+        return Ok(());
+    }
+
+    if let Some(callback_url) = settings.ack_callback_url.as_deref() {
+        let body = json!({
+            "team_id": team_id,
+            "channel": tracked.channel,
+            "channel_id": channel_id,
+            "ts": ts,
+            "severity": tracked.severity,
+            "text": tracked.text,
+            "acknowledged_by": event.user,
+        });
+        send_callback(client, callback_url, &body)?;
+    }
+
+    tracked.acknowledged_by = Some(event.user.clone().unwrap_or_default());
+    store
+        .put(&key, &serde_json::to_string(&tracked)?)
+        .map_err(|err| ApiError::bad_gateway(format!("Failed to store acknowledgement: {err}")))?;
+    Ok(())
+}
+
+fn send_callback(client: &dyn OutboundClient, url: &str, body: &Value) -> Result<()> {
+    let mut headers = http::HeaderMap::new();
+    headers.insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static("application/json"),
+    );
+    let status = client
+        .post(url, &headers, serde_json::to_vec(body)?)
+        .map_err(|err| ApiError::bad_gateway(format!("Failed to send acknowledgement: {err}")))?
+        .status;
+    if !(200..300).contains(&status) {
+        return Err(
+            ApiError::bad_gateway(format!("Acknowledgement callback answered {status}")).into(),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockClient;
+
+    const SECRET: &str = "8f742231b10e8888abcd99yyyzzz85a5";
+
+    fn settings() -> Settings {
+        let data = std::collections::HashMap::from([
+            (
+                "token_store_endpoint".to_string(),
+                "https://kv.example.com/slack".to_string(),
+            ),
+            ("signing_secret".to_string(), SECRET.to_string()),
+            (
+                "ack_callback_url".to_string(),
+                "https://alerts.example.com/ack".to_string(),
+            ),
+        ]);
+        Settings::from_map(&data).unwrap()
+    }
+
+    fn signed_request(body: Value) -> Request<Option<MessageBody>> {
+        let raw = serde_json::to_vec(&body).unwrap();
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let signature = signature::sign(SECRET, timestamp, &raw);
+        Request::builder()
+            .method(http::Method::POST)
+            .uri(EVENTS_PATH)
+            .header("x-slack-request-timestamp", timestamp.to_string())
+            .header("x-slack-signature", signature)
+            .body(Some(MessageBody(body, Some(raw.into()), Vec::new())))
+            .unwrap()
+    }
+
+    fn reaction(reaction: &str) -> Value {
+        json!({
+            "type": "event_callback",
+            "team_id": "T1",
+            "event": {
+                "type": "reaction_added",
+                "user": "U1",
+                "reaction": reaction,
+                "item": { "type": "message", "channel": "C1", "ts": "1.1" },
+            },
+        })
+    }
+
+    #[test]
+    fn test_url_verification() {
+        let req = signed_request(json!({ "type": "url_verification", "challenge": "abc" }));
+        let res = handle(&req, &settings(), &MockClient::new()).unwrap();
+        crate::testing::assert_json(&res, http::StatusCode::OK, json!({ "challenge": "abc" }));
+    }
+
+    #[test]
+    fn test_rejects_bad_signature() {
+        let mut req = signed_request(json!({ "type": "url_verification", "challenge": "abc" }));
+        req.headers_mut()
+            .insert("x-slack-signature", "v0=00".parse().unwrap());
+        let err = handle(&req, &settings(), &MockClient::new()).unwrap_err();
+        let err = err.downcast_ref::<ApiError>().unwrap();
+        assert_eq!(err.status, http::StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_track() {
+        let client = MockClient::new().respond_with(204);
+        let messages = vec![
+            PostedMessage {
+                channel: "#alerts".to_string(),
+                channel_id: Some("C1".to_string()),
+                ts: Some("1.1".to_string()),
+                error: None,
+            },
+            PostedMessage {
+                channel: "#gone".to_string(),
+                channel_id: None,
+                ts: None,
+                error: Some("channel_not_found".to_string()),
+            },
+        ];
+
+        track(&settings(), &client, &messages, Some("error"), "Disk full").unwrap();
+        let requests = client.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].url,
+            "https://kv.example.com/slack/messages/C1/1.1"
+        );
+    }
+
+    #[test]
+    fn test_reaction_acknowledges_tracked_message() {
+        let tracked = json!({ "channel": "#alerts", "severity": "error", "text": "Disk full" });
+        let client = MockClient::new()
+            .respond_with_body(200, &json!({ "value": tracked.to_string() }).to_string())
+            .respond_with(200)
+            .respond_with(204);
+
+        let res = handle(
+            &signed_request(reaction(ACK_REACTION)),
+            &settings(),
+            &client,
+        )
+        .unwrap();
+        crate::testing::assert_json(&res, http::StatusCode::OK, json!({ "ok": true }));
+
+        let requests = client.requests();
+        assert_eq!(
+            requests[0].url,
+            "https://kv.example.com/slack/messages/C1/1.1"
+        );
+        assert_eq!(requests[1].url, "https://alerts.example.com/ack");
+        assert_eq!(
+            serde_json::from_slice::<Value>(&requests[1].body).unwrap(),
+            json!({
+                "team_id": "T1",
+                "channel": "#alerts",
+                "channel_id": "C1",
+                "ts": "1.1",
+                "severity": "error",
+                "text": "Disk full",
+                "acknowledged_by": "U1",
+            })
+        );
+        let stored: Value = serde_json::from_slice(&requests[2].body).unwrap();
+        let stored: TrackedMessage =
+            serde_json::from_str(stored["value"].as_str().unwrap()).unwrap();
+        assert_eq!(stored.acknowledged_by.as_deref(), Some("U1"));
+    }
+
+    #[test]
+    fn test_thread_reply_acknowledges_tracked_message() {
+        let tracked = json!({ "channel": "#alerts", "text": "Disk full" });
+        let client = MockClient::new()
+            .respond_with_body(200, &json!({ "value": tracked.to_string() }).to_string())
+            .respond_with(200)
+            .respond_with(204);
+        let reply = json!({
+            "type": "event_callback",
+            "event": { "type": "message", "user": "U2", "channel": "C1", "thread_ts": "1.1", "ts": "1.2" },
+        });
+
+        handle(&signed_request(reply), &settings(), &client).unwrap();
+        let requests = client.requests();
+        assert_eq!(
+            requests[0].url,
+            "https://kv.example.com/slack/messages/C1/1.1"
+        );
+        assert_eq!(requests[1].url, "https://alerts.example.com/ack");
+
+        // Replies from bots, the component's escalations included, are ignored
+        let client = MockClient::new();
+        let reply = json!({
+            "type": "event_callback",
+            "event": { "type": "message", "bot_id": "B1", "channel": "C1", "thread_ts": "1.1", "ts": "1.2" },
+        });
+        handle(&signed_request(reply), &settings(), &client).unwrap();
+        assert!(client.requests().is_empty());
+    }
+
+    #[test]
+    fn test_reaction_ignored() {
+        // Other reactions, untracked and already acknowledged messages
+        let client = MockClient::new();
+        handle(&signed_request(reaction("eyes")), &settings(), &client).unwrap();
+        assert!(client.requests().is_empty());
+
+        let client = MockClient::new().respond_with(404);
+        handle(
+            &signed_request(reaction(ACK_REACTION)),
+            &settings(),
+            &client,
+        )
+        .unwrap();
+        assert_eq!(client.requests().len(), 1);
+
+        let tracked = json!({ "channel": "#alerts", "text": "Disk full", "acknowledged_by": "U2" });
+        let client = MockClient::new()
+            .respond_with_body(200, &json!({ "value": tracked.to_string() }).to_string());
+        handle(
+            &signed_request(reaction(ACK_REACTION)),
+            &settings(),
+            &client,
+        )
+        .unwrap();
+        assert_eq!(client.requests().len(), 1);
+    }
+}
@@ -0,0 +1,162 @@
+use anyhow::Result;
+use regex::Regex;
+
+use crate::request::NotificationRequest;
+use crate::template;
+
+// Suppression filters
+//
+// The `filters` setting drops known-noisy notifications before they reach
+// Slack. A rule matches when the message matches its `message` regex and
+// its `field` equals `equals`, either being optional. Notifications
+// matching an `exclude` rule are dropped, as are those matching no
+// `include` rule when there are some. Fields are named like template
+// variables, `fields.host` reaching into objects.
+
+/// Include and exclude rules of the `filters` setting.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Filters {
+    #[serde(default)]
+    pub include: Vec<FilterRule>,
+    #[serde(default)]
+    pub exclude: Vec<FilterRule>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct FilterRule {
+    /// Regex the message text must match.
+    pub message: Option<String>,
+    /// Request field compared with `equals`.
+    pub field: Option<String>,
+    pub equals: Option<String>,
+}
+
+impl Filters {
+    /// Errors of rules with an invalid regex or a field without a value.
+    pub fn validate(&self, errors: &mut Vec<String>) {
+        for rule in self.include.iter().chain(&self.exclude) {
+            if let Some(Err(err)) = rule.message.as_deref().map(Regex::new) {
+                errors.push(format!("Invalid regex in filters setting: {err}"));
+            }
+            if rule.field.is_some() != rule.equals.is_some() {
+                errors.push("filters setting rules need both field and equals".to_string());
+            }
+        }
+    }
+
+    /// Rules matching `request`, as `exclude[0]`-style labels.
+    pub fn matched(&self, request: &NotificationRequest) -> Result<Vec<String>> {
+        let data = serde_json::to_value(request)?;
+        let message = request.message.as_deref().unwrap_or_default();
+        let matches = |rule: &FilterRule| -> Result<bool> {
+            if let Some(pattern) = &rule.message {
+                if !Regex::new(pattern)?.is_match(message) {
+                    return Ok(false);
+                }
+            }
+            Ok(match (&rule.field, &rule.equals) {
+                (Some(field), Some(equals)) => {
+                    template::field(&data, field).as_ref() == Some(equals)
+                }
+                _ => true,
+            })
+        };
+
+        let mut matched = Vec::new();
+        for (kind, rules) in [("include", &self.include), ("exclude", &self.exclude)] {
+            for (index, rule) in rules.iter().enumerate() {
+                if matches(rule)? {
+                    matched.push(format!("{kind}[{index}]"));
+                }
+            }
+        }
+        Ok(matched)
+    }
+
+    /// Whether `request` is dropped.
+    pub fn drops(&self, request: &NotificationRequest) -> Result<bool> {
+        let matched = self.matched(request)?;
+        let excluded = matched.iter().any(|label| label.starts_with("exclude"));
+        let included = matched.iter().any(|label| label.starts_with("include"));
+        Ok(excluded || (!self.include.is_empty() && !included))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(value: serde_json::Value) -> NotificationRequest {
+        NotificationRequest::from_value(value, false).unwrap()
+    }
+
+    #[test]
+    fn test_drops_excluded() {
+        let filters: Filters = serde_json::from_value(json!({
+            "exclude": [
+                { "message": "(?i)heartbeat" },
+                { "message": "^Disk", "field": "fields.host", "equals": "ci-1" },
+            ],
+        }))
+        .unwrap();
+        assert!(filters
+            .drops(&request(json!({ "message": "Heartbeat OK" })))
+            .unwrap());
+        assert!(filters
+            .drops(&request(
+                json!({ "message": "Disk full", "fields": { "host": "ci-1" } })
+            ))
+            .unwrap());
+        assert_eq!(
+            filters
+                .matched(&request(
+                    json!({ "message": "Disk full, heartbeat", "fields": { "host": "ci-1" } })
+                ))
+                .unwrap(),
+            ["exclude[0]", "exclude[1]"]
+        );
+        assert!(!filters
+            .drops(&request(
+                json!({ "message": "Disk full", "fields": { "host": "db-1" } })
+            ))
+            .unwrap());
+    }
+
+    #[test]
+    fn test_drops_not_included() {
+        let filters: Filters = serde_json::from_value(json!({
+            "include": [{ "field": "env", "equals": "prod" }, { "field": "severity", "equals": "critical" }],
+        }))
+        .unwrap();
+        assert!(!filters
+            .drops(&request(json!({ "message": "Deployed", "env": "prod" })))
+            .unwrap());
+        assert!(!filters
+            .drops(&request(
+                json!({ "message": "Down", "severity": "critical" })
+            ))
+            .unwrap());
+        assert!(filters
+            .drops(&request(json!({ "message": "Deployed", "env": "staging" })))
+            .unwrap());
+    }
+
+    #[test]
+    fn test_validate() {
+        let filters: Filters = serde_json::from_value(json!({
+            "exclude": [{ "message": "(unclosed" }, { "field": "env" }],
+        }))
+        .unwrap();
+        let mut errors = Vec::new();
+        filters.validate(&mut errors);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("Invalid regex in filters setting: "));
+        assert_eq!(
+            errors[1],
+            "filters setting rules need both field and equals"
+        );
+    }
+}
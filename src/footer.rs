@@ -0,0 +1,151 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::request::{NotificationRequest, RawField};
+use crate::{blocks, dates, Settings, SlackMessagePayload};
+
+// Message footer
+//
+// With the `footer` setting, messages tell where they come from: the source
+// of the request, the `environment` setting and the component version, e.g.
+// `datadog · production · slack-message-component 1.0.0`. The source is the
+// transformer of vendor payloads, or the `source` field, event source or
+// CloudEvent source of the request. Attachments get it as their `footer`
+// and `ts`, shown by Slack under the last one, other messages as a context
+// block with the time they were sent, after their blocks or a section
+// holding their text.
+
+const SEPARATOR: &str = " · ";
+
+/// Footer of `request`, without the time.
+pub fn text(settings: &Settings, request: &NotificationRequest) -> String {
+    let source = request
+        .extra
+        .get("source")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .or_else(|| request.event.as_ref()?.source.clone())
+        .or_else(|| Some(request.cloudevent.as_ref()?["source"].as_str()?.to_string()));
+    let version = format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    [source, settings.environment.clone(), Some(version)]
+        .into_iter()
+        .flatten()
+        .filter(|part| !part.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join(SEPARATOR)
+}
+
+/// Adds the footer `text` to `payload`, sent at the unix time `now`.
+pub fn add(payload: &mut SlackMessagePayload, text: &str, now: i64) -> Result<()> {
+    let mut attachments = match &payload.attachments {
+        Some(attachments) => attachments.to_value()?,
+        None => Value::Null,
+    };
+    if payload.blocks.is_none() {
+        if let Some(last) = attachments
+            .as_array_mut()
+            .and_then(|items| items.last_mut())
+        {
+            if last.is_object() && last.get("footer").is_none() {
+                last["footer"] = text.into();
+                last["ts"] = now.into();
+                payload.attachments = Some(RawField::from_value(&attachments)?);
+            }
+            return Ok(());
+        }
+    }
+
+    let mut all_blocks = match &payload.blocks {
+        Some(blocks) => blocks::to_vec(blocks)?,
+        None => vec![blocks::section(&payload.text)],
+    };
+    all_blocks.push(json!({
+        "type": "context",
+        "elements": [{
+            "type": "mrkdwn",
+            "text": format!("{text}{SEPARATOR}{}", dates::token(now)),
+        }],
+    }));
+    payload.blocks = Some(RawField::from_value(&all_blocks.into())?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn settings() -> Settings {
+        let data = HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            ("footer".to_string(), "true".to_string()),
+            ("environment".to_string(), "production".to_string()),
+        ]);
+        Settings::from_map(&data).unwrap()
+    }
+
+    fn version() -> String {
+        format!("slack-message-component {}", env!("CARGO_PKG_VERSION"))
+    }
+
+    #[test]
+    fn test_text() {
+        let request = NotificationRequest::from_value(
+            json!({ "message": "Disk full", "event": { "title": "Disk full", "source": "prometheus" } }),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            text(&settings(), &request),
+            format!("prometheus · production · {}", version())
+        );
+
+        let request = NotificationRequest::from_value(
+            json!({ "message": "Deployed", "cloudevent": { "source": "/ci" } }),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            text(&settings(), &request),
+            format!("/ci · production · {}", version())
+        );
+    }
+
+    #[test]
+    fn test_add_to_text() {
+        let mut payload = SlackMessagePayload::new("Disk full".to_string());
+        add(&mut payload, "datadog", 1706697000).unwrap();
+        assert_eq!(
+            payload.blocks.unwrap().to_value().unwrap(),
+            json!([
+                blocks::section("Disk full"),
+                {
+                    "type": "context",
+                    "elements": [{
+                        "type": "mrkdwn",
+                        "text": "datadog · <!date^1706697000^{date_short} {time}|2024-01-31T10:30:00Z>",
+                    }],
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_add_to_attachments() {
+        let mut payload = SlackMessagePayload::new("Disk full".to_string());
+        payload.attachments =
+            Some(RawField::from_value(&json!([{ "text": "db-1" }, { "text": "db-2" }])).unwrap());
+        add(&mut payload, "datadog", 1706697000).unwrap();
+        assert_eq!(payload.blocks, None);
+        assert_eq!(
+            payload.attachments.unwrap().to_value().unwrap(),
+            json!([
+                { "text": "db-1" },
+                { "text": "db-2", "footer": "datadog", "ts": 1706697000 },
+            ])
+        );
+    }
+}
@@ -1,146 +1,1665 @@
-mod helpers;
+mod auth;
+mod blocklist;
+mod blocks;
+mod canvas;
+mod client;
+mod cloudevents;
+mod dates;
+mod debug;
+mod dedup;
+mod digest;
+mod emoji;
+mod escalation;
+mod events;
+mod filters;
+mod footer;
+mod links;
+#[cfg(feature = "local-dev")]
+pub mod local_dev;
+mod normalize;
+mod oauth;
+mod openapi;
+mod pii;
+mod proto;
+pub mod request;
+mod rotation;
+mod schema;
+mod secrets;
+mod selftest;
+mod signature;
+mod size;
+mod slack_api;
+mod store;
+mod table;
+mod template;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+mod threads;
+mod timings;
+mod transformers;
+mod version;
 
 use bindings::wasi::http::types::{IncomingRequest, ResponseOutparam};
+pub use canvas::CanvasMode;
+pub use client::{ClientResponse, OutboundClient};
+use edgee_component_helpers::{self as helpers, bindings};
+pub use filters::{FilterRule, Filters};
 use helpers::body::Json;
+use helpers::negotiate::Negotiated;
+pub use normalize::TextNormalization;
+pub use request::NotificationRequest;
+use request::{MessageBody, MessageMetadata};
 use std::collections::HashMap;
+pub use transformers::SourceSettings;
+pub use version::ApiVersion;
 
-#[cfg(not(test))]
-use waki::Response;
-
-mod bindings {
-    wit_bindgen::generate!({
-        path: ".edgee/wit",
-        world: "edge-function",
-        generate_all,
-        pub_export_macro: true,
-        default_bindings_module: "$crate::bindings",
-    });
-}
-
-struct Component;
+/// The Slack component, exported to the host through the helpers bindings.
+pub struct Component;
+// Export names like `wasi:http/incoming-handler` only link on wasm, native
+// builds (tests, local-dev) use the handler directly
+#[cfg(target_arch = "wasm32")]
 bindings::export!(Component);
 
 impl bindings::exports::wasi::http::incoming_handler::Guest for Component {
     fn handle(req: IncomingRequest, resp: ResponseOutparam) {
-        helpers::run(req, resp, Self::handle_json_request);
+        let client = Self::client();
+        helpers::run_router(req, resp, &Self::router(&client), Settings::run_options);
+    }
+}
+
+type JsonResponse = http::Response<Json<serde_json::Value>>;
+
+/// Body of the component routes: a document in the representation the
+/// client accepts, or a redirect.
+#[derive(Debug)]
+pub enum Reply {
+    Document(Negotiated<serde_json::Value>),
+    Redirect(helpers::body::Redirect),
+}
+
+impl From<Negotiated<serde_json::Value>> for Reply {
+    fn from(document: Negotiated<serde_json::Value>) -> Self {
+        Self::Document(document)
+    }
+}
+
+impl From<helpers::body::Redirect> for Reply {
+    fn from(redirect: helpers::body::Redirect) -> Self {
+        Self::Redirect(redirect)
+    }
+}
+
+impl helpers::body::IntoBody for Reply {
+    fn into_body(self) -> anyhow::Result<bytes::Bytes> {
+        match self {
+            Self::Document(document) => document.into_body(),
+            Self::Redirect(redirect) => redirect.into_body(),
+        }
+    }
+
+    fn into_chunks(self) -> anyhow::Result<helpers::body::BodyChunks> {
+        match self {
+            Self::Document(document) => document.into_chunks(),
+            Self::Redirect(redirect) => redirect.into_chunks(),
+        }
+    }
+
+    fn extend_response_parts(&self, parts: &mut http::response::Parts) {
+        match self {
+            Self::Document(document) => document.extend_response_parts(parts),
+            Self::Redirect(redirect) => redirect.extend_response_parts(parts),
+        }
+    }
+}
+
+// Redirects have no document, `null` for the response assertions
+impl AsRef<serde_json::Value> for Reply {
+    fn as_ref(&self) -> &serde_json::Value {
+        static NULL: serde_json::Value = serde_json::Value::Null;
+        match self {
+            Self::Document(document) => document.as_ref(),
+            Self::Redirect(_) => &NULL,
+        }
     }
 }
 
+fn reply<O: Into<Reply>>(res: http::Response<O>) -> http::Response<Reply> {
+    res.map(Into::into)
+}
+
 impl Component {
-    fn handle_json_request(
-        req: http::Request<Json<serde_json::Value>>,
+    #[cfg(not(test))]
+    fn client() -> client::WasiClient {
+        client::WasiClient::new()
+    }
+
+    // Components are mounted on any path, so messages are accepted everywhere
+    // but on the routes registered before the catch-all ones
+    fn router(client: &dyn OutboundClient) -> helpers::Router<'_, Option<MessageBody>, Reply> {
+        // Message routes always answer JSON
+        let handle_message =
+            |req| Self::handle_with_client(req, client).map(|res| reply(res.map(Negotiated::from)));
+        helpers::Router::new()
+            .layer(helpers::middleware::Logger)
+            .layer(helpers::middleware::RequestId)
+            .route(http::Method::GET, "/health", |req| {
+                Self::handle_health(req).map(reply)
+            })
+            .route(http::Method::GET, "/openapi.json", |req| {
+                Self::handle_openapi(req).map(reply)
+            })
+            .route(http::Method::GET, oauth::INSTALL_PATH, |req| {
+                oauth::install(&Settings::from_req(&req)?).map(reply)
+            })
+            .route(http::Method::GET, oauth::CALLBACK_PATH, |req| {
+                let settings = Settings::from_req(&req)?;
+                oauth::callback(&req, &settings, &settings.client(client)).map(reply)
+            })
+            .route(http::Method::POST, events::EVENTS_PATH, |req| {
+                let settings = Settings::from_req(&req)?;
+                events::handle(&req, &settings, &settings.client(client)).map(reply)
+            })
+            .route(http::Method::POST, digest::FLUSH_PATH, |req| {
+                let settings = Settings::from_req(&req)?;
+                digest::handle_flush(&req, &settings, &settings.client(client)).map(reply)
+            })
+            .route(http::Method::POST, selftest::TEST_PATH, |req| {
+                let settings = Settings::from_req(&req)?;
+                selftest::handle(&req, &settings, &settings.client(client)).map(reply)
+            })
+            .route(
+                http::Method::POST,
+                transformers::TRANSFORM_PATH,
+                handle_message,
+            )
+            .route(http::Method::POST, "/*path", handle_message)
+            .route(http::Method::GET, "/*path", handle_message)
+    }
+
+    fn handle_health(
+        req: http::Request<Option<MessageBody>>,
+    ) -> anyhow::Result<http::Response<Negotiated<serde_json::Value>>> {
+        let body = Negotiated::new(req.headers(), serde_json::json!({ "ok": true }));
+        let mut res = http::Response::new(body);
+        res.extensions_mut().insert(helpers::etag::Cacheable);
+        Ok(res)
+    }
+
+    fn handle_openapi(
+        _req: http::Request<Option<MessageBody>>,
+    ) -> anyhow::Result<http::Response<Negotiated<serde_json::Value>>> {
+        let mut res = http::Response::new(Negotiated::from(Json(openapi::document())));
+        res.extensions_mut().insert(helpers::etag::Cacheable);
+        Ok(res)
+    }
+
+    fn handle_with_client(
+        req: http::Request<Option<MessageBody>>,
+        client: &dyn OutboundClient,
     ) -> Result<http::Response<Json<serde_json::Value>>, anyhow::Error> {
         let settings = Settings::from_req(&req)?;
+        let client = &settings.client(client);
+        let version = match ApiVersion::from_path(req.uri().path()) {
+            Some(version) => {
+                version.map_err(|err| helpers::ApiError::not_found(err.to_string()))?
+            }
+            None => settings.default_api_version,
+        };
+
+        match version {
+            ApiVersion::V1 => Self::handle_v1(req, &settings, client),
+        }
+    }
+
+    fn handle_v1(
+        req: http::Request<Option<MessageBody>>,
+        settings: &Settings,
+        client: &dyn OutboundClient,
+    ) -> Result<http::Response<Json<serde_json::Value>>, anyhow::Error> {
+        let started = helpers::monotonic_now();
+        // Extract message from query string or request body
+        let (data, raw, uploads) = match (req.body(), req.method()) {
+            (Some(MessageBody(data, raw, uploads)), _) => {
+                (data.clone(), raw.clone(), uploads.clone())
+            }
+            (None, &http::Method::GET) => {
+                if !settings.query_trigger {
+                    let mut res = helpers::ApiError::method_not_allowed("Method GET not allowed")
+                        .into_response();
+                    res.headers_mut()
+                        .insert(http::header::ALLOW, http::HeaderValue::from_static("POST"));
+                    return Ok(res);
+                }
+
+                let helpers::Query(params) = helpers::Query::<SendParams>::from_req(&req)?;
+                let authorized = settings.auth_token.as_deref().is_some_and(|expected| {
+                    auth::is_authorized(req.headers(), params.token.as_deref(), expected)
+                });
+                if !authorized {
+                    return Ok(helpers::ApiError::unauthorized("Unauthorized").into_response());
+                }
+                (serde_json::Value::Object(params.fields), None, Vec::new())
+            }
+            (None, _) => return Err(helpers::ApiError::bad_request("Missing request body").into()),
+        };
+
+        // CloudEvents sent in binary mode carry their attributes in headers
+        let (data, raw) = match cloudevents::CloudEvent::from_headers(req.headers(), data.clone()) {
+            Some(event) => (
+                event
+                    .map_err(|err| helpers::ApiError::bad_request(err.to_string()))?
+                    .into_context(),
+                None,
+            ),
+            None => (data, raw),
+        };
+
+        // Reject payloads not matching the operator provided schema
+        if let Some(input_schema) = &settings.input_schema {
+            let violations = schema::validate(input_schema, &data)?;
+            if !violations.is_empty() {
+                let mut res =
+                    helpers::ApiError::unprocessable("Request body does not match input_schema")
+                        .into_response();
+                res.body_mut().0["violations"] = serde_json::json!(violations);
+                return Ok(res);
+            }
+        }
+
+        let debug = debug::requested(&req, settings)?;
+        let mut debug_details = None;
+        let client = &client::TimedClient::new(client);
+        // Vendor payloads are turned into notifications by their transformer
+        let source = transformers::source(&req);
+        let parsed;
+        let response = match (settings.passthrough && source.is_none(), raw) {
+            (true, Some(raw)) => {
+                parsed = helpers::monotonic_now();
+                if debug {
+                    debug_details = Some(serde_json::json!({
+                        "destination": selftest::destination(settings, &NotificationRequest::default()),
+                        "payload": data,
+                    }));
+                }
+                match data.get("blocks").map(blocks::check_limits) {
+                    Some(Err(err)) if settings.degrade_to_text => {
+                        eprintln!("{err}, sending the text only");
+                        let mut data = data;
+                        data.as_object_mut().map(|fields| fields.remove("blocks"));
+                        forward(settings, serde_json::to_vec(&data)?.into(), client)?
+                    }
+                    Some(Err(err)) => return Err(err),
+                    _ => forward(settings, raw, client)?,
+                }
+            }
+            _ => {
+                let request = match &source {
+                    Some(source) => transformers::transform(source, &req, settings, &data)?,
+                    None => match NotificationRequest::from_value(data, settings.strict) {
+                        Ok(request) => NotificationRequest { uploads, ..request },
+                        Err(err) => {
+                            return Ok(
+                                helpers::ApiError::bad_request(err.to_string()).into_response()
+                            )
+                        }
+                    },
+                };
+                parsed = helpers::monotonic_now();
+                let source_settings = source
+                    .as_deref()
+                    .and_then(|source| transformers::settings_for(settings, source));
+                if debug {
+                    debug_details = Some(debug::details(
+                        source_settings.as_ref().unwrap_or(settings),
+                        &request,
+                        source.as_deref(),
+                    )?);
+                }
+                match &settings.filters {
+                    // Known noise, dropped before reaching Slack
+                    Some(filters) if filters.drops(&request)? => ComponentResponse {
+                        status: http::StatusCode::OK,
+                        body: serde_json::json!({ "ok": true, "filtered": true }),
+                        slack_status: None,
+                    },
+                    _ => handle_notification(
+                        source_settings.as_ref().unwrap_or(settings),
+                        request,
+                        client,
+                    )?,
+                }
+            }
+        };
+
+        let timings = timings::Timings::new(
+            parsed.saturating_sub(started),
+            helpers::monotonic_now().saturating_sub(started),
+            client.slack(),
+        );
+        eprintln!("Timings: {timings}");
+        let mut res = response.into_response(settings.response_format);
+        if settings.response_format == ResponseFormat::Structured {
+            if let Some(fields) = res.body_mut().0.as_object_mut() {
+                fields.insert("timings".to_string(), serde_json::json!(timings));
+            }
+        }
+        settings
+            .response_headers()
+            .apply(&client.slack_headers(), res.headers_mut());
+        if let (Some(mut details), Some(fields)) = (debug_details, res.body_mut().0.as_object_mut())
+        {
+            details["timings"] = serde_json::json!(timings);
+            fields.insert("debug".to_string(), details);
+        }
+        Ok(res)
+    }
+}
+
+/// Outcome of a notification, independent of the HTTP layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentResponse {
+    pub status: http::StatusCode,
+    pub body: serde_json::Value,
+    /// Status of the webhook call, mirrored as is by the legacy format.
+    pub slack_status: Option<u16>,
+}
+
+/// Payload fields applied to every message unless the request sets them.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PayloadDefaults {
+    /// Bot-token mode channel, `default_channel` taking precedence.
+    pub channel: Option<String>,
+    pub username: Option<String>,
+    pub icon_emoji: Option<String>,
+    pub icon_url: Option<String>,
+    pub unfurl_links: Option<bool>,
+    pub unfurl_media: Option<bool>,
+}
+
+/// Shape of the message responses.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseFormat {
+    /// `{"ok": ...}` with the status Slack answered the webhook call with.
+    #[default]
+    Legacy,
+    /// 200 on success, 502 when Slack refused the message, with the Slack
+    /// status in `slack_status` and `ok` always set.
+    Structured,
+}
+
+impl ComponentResponse {
+    fn into_response(self, format: ResponseFormat) -> JsonResponse {
+        let (status, body) = match format {
+            ResponseFormat::Legacy => (self.status, self.body),
+            ResponseFormat::Structured => self.structured(),
+        };
+        let mut res = http::Response::new(Json(body));
+        *res.status_mut() = status;
+        res
+    }
+
+    fn structured(self) -> (http::StatusCode, serde_json::Value) {
+        let status = match (self.status.is_success(), self.slack_status) {
+            (true, _) => http::StatusCode::OK,
+            (false, Some(_)) => http::StatusCode::BAD_GATEWAY,
+            (false, None) => self.status,
+        };
+
+        let mut body = self.body;
+        if let Some(fields) = body.as_object_mut() {
+            fields.insert("ok".to_string(), status.is_success().into());
+            if let Some(slack_status) = self.slack_status {
+                fields.insert("slack_status".to_string(), slack_status.into());
+            }
+        }
+        (status, body)
+    }
+}
+
+/// Request to Slack to response logic, with the outbound call injected so the
+/// whole flow can be exercised without WASI.
+pub fn handle_notification(
+    settings: &Settings,
+    mut request: NotificationRequest,
+    client: &dyn OutboundClient,
+) -> anyhow::Result<ComponentResponse> {
+    if !settings.interpolated_fields.is_empty() {
+        template::interpolate(&mut request, &settings.interpolated_fields)?;
+    }
+
+    // Duplicates are better than nothing when the store is down
+    let dedup_key = request.dedup_key.clone();
+    if dedup::seen(settings, client, dedup_key.as_deref()).unwrap_or_else(|err| {
+        eprintln!("Failed to check the dedup key: {err}");
+        false
+    }) {
+        return Ok(ComponentResponse {
+            status: http::StatusCode::OK,
+            body: serde_json::json!({ "ok": true, "duplicate": true }),
+            slack_status: None,
+        });
+    }
+
+    let response = deliver(settings, request, client)?;
+    if response.status.is_success() {
+        if let Err(err) = dedup::remember(settings, client, dedup_key.as_deref()) {
+            eprintln!("Failed to store the dedup key: {err}");
+        }
+    }
+    Ok(response)
+}
+
+fn deliver(
+    settings: &Settings,
+    request: NotificationRequest,
+    client: &dyn OutboundClient,
+) -> anyhow::Result<ComponentResponse> {
+    if digest::holds(settings, &request) {
+        return digest::buffer(settings, request, client);
+    }
+
+    if let Some(bot_token) = &settings.bot_token {
+        return post_to_channels(settings, bot_token, None, request, client);
+    }
+
+    // Workspaces installed through OAuth post with the token stored for them
+    if settings.uses_token_store() {
+        let team_id = request
+            .team_id
+            .clone()
+            .or_else(|| settings.default_team_id.clone())
+            .ok_or_else(|| {
+                helpers::ApiError::bad_request("Missing 'team_id' field in request body")
+            })?;
+        let bot_token = oauth::bot_token(settings, &team_id, client)?;
+        return post_to_channels(settings, &bot_token, Some(&team_id), request, client);
+    }
+
+    // Build Slack API payload for simple text message and send it
+    let mut slack_message_payload = render(settings, request)?;
+    let size_strategy = size::guard(&mut slack_message_payload, None)?;
+    let mut response = send_to_webhook(
+        settings,
+        serde_json::to_vec(&slack_message_payload)?,
+        client,
+    )?;
+    if settings.degrade_to_text
+        && slack_message_payload.blocks.is_some()
+        && response.status == 400
+        && response.body.starts_with(INVALID_BLOCKS.as_bytes())
+    {
+        eprintln!("Slack refused the blocks, sending the text only");
+        slack_message_payload.blocks = None;
+        response = send_to_webhook(
+            settings,
+            serde_json::to_vec(&slack_message_payload)?,
+            client,
+        )?;
+    }
+    let mut response = webhook_response(settings, &response)?;
+    if let Some(strategy) = size_strategy {
+        response.body["size_strategy"] = serde_json::json!(strategy);
+    }
+    Ok(response)
+}
+
+/// Error Slack answers blocks it can't show with.
+const INVALID_BLOCKS: &str = "invalid_blocks";
+
+/// Forwards a Slack-shaped JSON body to the webhook exactly as received,
+/// without parsing it into a request and serializing it back.
+pub fn forward(
+    settings: &Settings,
+    raw: bytes::Bytes,
+    client: &dyn OutboundClient,
+) -> anyhow::Result<ComponentResponse> {
+    post_to_webhook(settings, raw.into(), client)
+}
+
+fn post_to_webhook(
+    settings: &Settings,
+    body: Vec<u8>,
+    client: &dyn OutboundClient,
+) -> anyhow::Result<ComponentResponse> {
+    webhook_response(settings, &send_to_webhook(settings, body, client)?)
+}
+
+fn send_to_webhook(
+    settings: &Settings,
+    body: Vec<u8>,
+    client: &dyn OutboundClient,
+) -> anyhow::Result<ClientResponse> {
+    rotation::send(settings, client, body, &|webhook_url, body| {
+        send_to(settings, webhook_url, body, client)
+    })
+}
+
+// `webhook_url` being the setting, which may reference a secret
+fn send_to(
+    settings: &Settings,
+    webhook_url: &str,
+    body: Vec<u8>,
+    client: &dyn OutboundClient,
+) -> anyhow::Result<ClientResponse> {
+    let webhook_url = secrets::resolve(
+        webhook_url,
+        settings.secrets_endpoint.as_deref(),
+        settings.secrets_token.as_deref(),
+        client,
+    )?;
+    let mut headers = http::HeaderMap::new();
+    headers.insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static("application/json"),
+    );
+    let mut response = client
+        .post(&webhook_url, &headers, body)
+        .map_err(send_error)?;
+    response.body = redact_webhook_path(response.body, &webhook_url);
+    Ok(response)
+}
+
+// Calls the handler timeout left no time for keep their 504
+fn send_error(err: anyhow::Error) -> anyhow::Error {
+    match err.downcast::<helpers::ApiError>() {
+        Ok(err) => err.into(),
+        Err(err) => {
+            helpers::ApiError::bad_gateway(format!("Failed to send Slack message: {err}")).into()
+        }
+    }
+}
+
+// The webhook path is a secret, error pages may echo it
+fn redact_webhook_path(body: Vec<u8>, webhook_url: &str) -> Vec<u8> {
+    let Ok(uri) = webhook_url.parse::<http::Uri>() else {
+        return body;
+    };
+    let secret = uri.path().rsplit('/').next().unwrap_or_default();
+    let text = String::from_utf8_lossy(&body);
+    if secret.len() < 8 || !text.contains(secret) {
+        return body;
+    }
+    text.replace(uri.path(), "/****")
+        .replace(secret, "****")
+        .into_bytes()
+}
+
+fn webhook_response(
+    settings: &Settings,
+    response: &ClientResponse,
+) -> anyhow::Result<ComponentResponse> {
+    let response_status = response.status;
+    // create response body based on Slack response's status code
+    let component_response = SlackResponse::from_response(response, settings.verbose_errors);
+
+    Ok(ComponentResponse {
+        status: http::StatusCode::from_u16(response_status)?,
+        body: serde_json::json!(component_response),
+        slack_status: Some(response_status),
+    })
+}
+
+// Bot-token mode: the same message is posted to every requested channel
+fn post_to_channels(
+    settings: &Settings,
+    bot_token: &str,
+    team_id: Option<&str>,
+    mut request: NotificationRequest,
+    client: &dyn OutboundClient,
+) -> anyhow::Result<ComponentResponse> {
+    if settings.canvas_mode == CanvasMode::Instead {
+        return post_to_canvas(settings, bot_token, request, client);
+    }
+
+    let default_channel = settings.default_channel.as_ref().or(settings
+        .defaults
+        .as_ref()
+        .and_then(|defaults| defaults.channel.as_ref()));
+    let channels = match (&request.channels, &request.channel, default_channel) {
+        (Some(channels), _, _) if !channels.is_empty() => channels.clone(),
+        (_, Some(channel), _) | (_, None, Some(channel)) => vec![channel.clone()],
+        _ => {
+            return Ok(ComponentResponse {
+                status: http::StatusCode::BAD_REQUEST,
+                body: serde_json::json!({
+                    "error": "Missing 'channel' field in request body",
+                }),
+                slack_status: None,
+            })
+        }
+    };
+
+    let severity = request.severity.clone();
+    let correlation_id = request.correlation_id.clone();
+    let broadcast = request.broadcast.unwrap_or_else(|| {
+        severity
+            .as_ref()
+            .is_some_and(|severity| settings.broadcast_severities.contains(severity))
+    });
+    let thread_ts = request.thread_ts.clone();
+    let metadata = request.metadata.clone();
+    let bookmark = request.bookmark.clone();
+    let mut uploads = std::mem::take(&mut request.uploads);
+    let pin = request.pin.unwrap_or_else(|| {
+        severity
+            .as_ref()
+            .is_some_and(|severity| settings.pin_severities.contains(severity))
+    });
+    if bookmark
+        .as_ref()
+        .is_some_and(|bookmark| bookmark.title.is_empty() || !is_http_url(&bookmark.link))
+    {
+        return Err(helpers::ApiError::bad_request(
+            "Invalid 'bookmark' field in request body, a title and an http(s) link are needed",
+        )
+        .into());
+    }
+    let canvas_entry = canvas_entry(settings, &request)?;
+    let mut slack_message_payload = render(settings, request)?;
+    slack_message_payload.metadata = metadata;
+    let size_strategy = size::guard(&mut slack_message_payload, Some(&mut uploads))?;
+
+    // Unthreaded messages are better than none when the store is down
+    let threads = threads::find(settings, client, &channels, correlation_id.as_deref())
+        .unwrap_or_else(|err| {
+            eprintln!("Failed to find Slack threads: {err}");
+            HashMap::new()
+        });
+    if broadcast && (thread_ts.is_some() || !threads.is_empty()) {
+        slack_message_payload.reply_broadcast = Some(true);
+    }
+    slack_message_payload.thread_ts = thread_ts;
+
+    let bot_token = secrets::resolve(
+        bot_token,
+        settings.secrets_endpoint.as_deref(),
+        settings.secrets_token.as_deref(),
+        client,
+    )?;
+    let mut messages = slack_api::post_messages_in_threads(
+        client,
+        &bot_token,
+        &channels,
+        &slack_message_payload,
+        &threads,
+        settings.max_concurrency,
+    )
+    .map_err(send_error)?;
+
+    // Channels that refused the blocks get the text only
+    let refused: Vec<String> = messages
+        .iter()
+        .filter(|message| message.error.as_deref() == Some(INVALID_BLOCKS))
+        .map(|message| message.channel.clone())
+        .collect();
+    if settings.degrade_to_text && slack_message_payload.blocks.is_some() && !refused.is_empty() {
+        eprintln!("Slack refused the blocks, sending the text only");
+        slack_message_payload.blocks = None;
+        let retried = slack_api::post_messages_in_threads(
+            client,
+            &bot_token,
+            &refused,
+            &slack_message_payload,
+            &threads,
+            settings.max_concurrency,
+        )
+        .map_err(send_error)?;
+        for message in retried {
+            if let Some(posted) = messages
+                .iter_mut()
+                .find(|posted| posted.channel == message.channel)
+            {
+                *posted = message;
+            }
+        }
+    }
+
+    // Delivery already happened, a tracking failure only loses the acks
+    if let Err(err) = events::track(
+        settings,
+        client,
+        &messages,
+        severity.as_deref(),
+        &slack_message_payload.text,
+    ) {
+        eprintln!("Failed to track Slack messages: {err}");
+    }
+    if let Err(err) = threads::remember(
+        settings,
+        client,
+        &messages,
+        &threads,
+        correlation_id.as_deref(),
+    ) {
+        eprintln!("Failed to store Slack threads: {err}");
+    }
+    if let Err(err) = escalation::run(
+        settings,
+        client,
+        &bot_token,
+        team_id,
+        &messages,
+        severity.as_deref(),
+    ) {
+        eprintln!("Failed to escalate Slack messages: {err}");
+    }
+
+    // Follow-ups don't undo a delivery, their failures are only reported
+    let mut warnings = Vec::new();
+    if let Some(bookmark) = &bookmark {
+        for message in messages.iter().filter(|message| message.error.is_none()) {
+            let Some(channel_id) = &message.channel_id else {
+                continue;
+            };
+            if let Err(err) = slack_api::add_bookmark(client, &bot_token, channel_id, bookmark) {
+                warnings.push(format!("Failed to bookmark in {}: {err}", message.channel));
+            }
+        }
+    }
+    if pin {
+        for message in &messages {
+            let (Some(channel_id), Some(ts)) = (&message.channel_id, &message.ts) else {
+                continue;
+            };
+            if let Err(err) = slack_api::add_pin(client, &bot_token, channel_id, ts) {
+                warnings.push(format!("Failed to pin in {}: {err}", message.channel));
+            }
+        }
+    }
 
-        // Extract message from request body
-        let Json(data) = req.body();
-        let message = match data.get("message") {
-            Some(value) => value.as_str().unwrap_or_default().to_string(),
-            None => return Err(anyhow::anyhow!("Missing 'message' field in request body")),
+    for message in &messages {
+        let (Some(channel_id), Some(ts)) = (&message.channel_id, &message.ts) else {
+            continue;
         };
+        for upload in &uploads {
+            if let Err(err) = slack_api::upload_file(client, &bot_token, channel_id, ts, upload) {
+                warnings.push(format!(
+                    "Failed to upload {} in {}: {err}",
+                    upload.filename, message.channel
+                ));
+            }
+        }
+    }
+
+    let canvas_error = canvas_entry
+        .and_then(|entry| canvas::append(settings, client, &bot_token, &entry).err())
+        .map(|err| err.to_string());
+
+    let ok = messages.iter().all(|message| message.error.is_none()) && canvas_error.is_none();
+    let mut body = serde_json::json!({ "ok": ok, "messages": messages });
+    if let Some(canvas_error) = canvas_error {
+        body["canvas_error"] = canvas_error.into();
+    }
+    if !warnings.is_empty() {
+        body["warnings"] = warnings.into();
+    }
+    if let Some(strategy) = size_strategy {
+        body["size_strategy"] = serde_json::json!(strategy);
+    }
+    Ok(ComponentResponse {
+        status: match ok {
+            true => http::StatusCode::OK,
+            false => http::StatusCode::BAD_GATEWAY,
+        },
+        body,
+        slack_status: None,
+    })
+}
+
+// Canvas entry of a request, masked like the message
+fn canvas_entry(
+    settings: &Settings,
+    request: &NotificationRequest,
+) -> anyhow::Result<Option<String>> {
+    if settings.canvas_id.is_none() {
+        return Ok(None);
+    }
+    let entry =
+        canvas::entry(request).map_err(|err| helpers::ApiError::bad_request(err.to_string()))?;
+    let entry = match settings.scrub_pii {
+        true => pii::scrub(&entry),
+        false => entry,
+    };
+    Ok(Some(match blocklist::Blocklist::new(&settings.blocklist) {
+        Some(blocklist) => blocklist.mask(&entry),
+        None => entry,
+    }))
+}
+
+// Canvas-only mode: the entry is appended and no message is posted
+fn post_to_canvas(
+    settings: &Settings,
+    bot_token: &str,
+    request: NotificationRequest,
+    client: &dyn OutboundClient,
+) -> anyhow::Result<ComponentResponse> {
+    let entry = canvas_entry(settings, &request)?.unwrap_or_default();
+    let bot_token = secrets::resolve(
+        bot_token,
+        settings.secrets_endpoint.as_deref(),
+        settings.secrets_token.as_deref(),
+        client,
+    )?;
+    canvas::append(settings, client, &bot_token, &entry).map_err(|err| {
+        helpers::ApiError::bad_gateway(format!("Failed to update Slack canvas: {err}"))
+    })?;
+
+    Ok(ComponentResponse {
+        status: http::StatusCode::OK,
+        body: serde_json::json!({ "ok": true, "canvas_id": settings.canvas_id }),
+        slack_status: None,
+    })
+}
+
+/// Builds the Slack payload for a request without sending it.
+pub fn render(
+    settings: &Settings,
+    request: NotificationRequest,
+) -> anyhow::Result<SlackMessagePayload> {
+    // Normalized first, templates reading the request fields
+    let request = normalize::request(request, settings.normalize_text)?;
+    let footer = settings.footer.then(|| footer::text(settings, &request));
+    let (prefix, suffix) = match (&settings.prefix, &settings.suffix) {
+        (None, None) => (None, None),
+        (prefix, suffix) => {
+            let data = serde_json::to_value(&request)?;
+            let expand = |template: &String| template::expand(template, &data);
+            (prefix.as_ref().map(expand), suffix.as_ref().map(expand))
+        }
+    };
+
+    // Clients not showing blocks fall back to the text, made up from the
+    // blocks when no message is sent
+    let message = match (request.message, &request.blocks) {
+        (Some(message), _) => Some(message),
+        (None, Some(blocks)) => blocks::fallback_text(blocks)?,
+        (None, None) => None,
+    };
+    let Some(message) = message else {
+        return Err(
+            helpers::ApiError::bad_request("Missing 'message' field in request body").into(),
+        );
+    };
+
+    let message = match &settings.emojify {
+        Some(keywords) => emoji::emojify(&message, keywords),
+        None => message,
+    };
+    let message = [prefix, Some(message), suffix]
+        .into_iter()
+        .flatten()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut payload = SlackMessagePayload::new(message);
+    payload.blocks = request.blocks;
+    payload.attachments = request.attachments;
+
+    // Presentation set by the operator, unless the request has its own
+    let defaults = settings.defaults.clone().unwrap_or_default();
+    payload.username = request.username.or(defaults.username);
+    payload.icon_emoji = request.icon_emoji.or(defaults.icon_emoji);
+    payload.icon_url = request.icon_url.or(defaults.icon_url);
+    payload.unfurl_links = request.unfurl_links.or(defaults.unfurl_links);
+    payload.unfurl_media = request.unfurl_media.or(defaults.unfurl_media);
+
+    // Mask personal data before it leaves the edge
+    if settings.scrub_pii {
+        payload.text = pii::scrub(&payload.text);
+        for field in [&mut payload.blocks, &mut payload.attachments]
+            .into_iter()
+            .flatten()
+        {
+            let mut value = field.to_value()?;
+            pii::scrub_json(&mut value);
+            *field = request::RawField::from_value(&value)?;
+        }
+    }
+
+    // After scrubbing, the unix times of the tokens look like phone numbers
+    if settings.format_dates {
+        payload.text = dates::format(&payload.text);
+        for field in [&mut payload.blocks, &mut payload.attachments]
+            .into_iter()
+            .flatten()
+        {
+            let mut value = field.to_value()?;
+            dates::format_json(&mut value);
+            *field = request::RawField::from_value(&value)?;
+        }
+    }
 
-        // Build Slack API payload for simple text message and send it
-        let slack_message_payload = SlackMessagePayload::new(message);
-        let slack_response = slack_message_payload
-            .send(&settings.webhook_url)
-            .expect("Failed to send Slack message");
+    // Code blocks show date tokens as written, the table comes last,
+    // scrubbed before the columns are aligned
+    if let Some(mut table) = request.table {
+        if settings.scrub_pii {
+            table.headers = table
+                .headers
+                .iter()
+                .map(|header| pii::scrub(header))
+                .collect();
+            table.rows.iter_mut().flatten().for_each(pii::scrub_json);
+        }
+        if let Some(table) = table::render(&table) {
+            payload.text = format!("{}\n{table}", payload.text);
+        }
+    }
 
-        // create response body based on Slack response's status code
-        let response_status = slack_response.status_code();
-        let component_response = SlackResponse::from_status(response_status);
+    // Blocks replace the text in the message, which comes as a section
+    // first when the request sent no blocks or event of its own
+    let event_blocks = match request.event {
+        Some(event) => Some(blocks::event(event, settings.scrub_pii)?),
+        None => None,
+    };
+    let mut extra_blocks = Vec::new();
+    if let Some(images) = request.images {
+        extra_blocks.extend(blocks::images(images, settings.scrub_pii)?);
+    }
+    if let Some(author) = request.author {
+        extra_blocks.push(blocks::author(author, settings.scrub_pii)?);
+    }
+    if !extra_blocks.is_empty() || event_blocks.is_some() {
+        let mut all_blocks = match (&payload.blocks, event_blocks) {
+            (Some(blocks), _) => blocks::to_vec(blocks)?,
+            (None, Some(event_blocks)) => event_blocks,
+            (None, None) => vec![blocks::section(&payload.text)],
+        };
+        all_blocks.extend(extra_blocks);
+        payload.blocks = Some(request::RawField::from_value(&all_blocks.into())?);
+    }
+    if let Some(footer) = footer {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        footer::add(&mut payload, &footer, now as i64)?;
+    }
 
-        // note: Content-type is already set by helpers::run_json
-        Ok(http::Response::builder()
-            .status(response_status)
-            .body(Json(serde_json::json!(component_response)))?)
+    // Masked last, in the blocks built from request fields too
+    if let Some(blocklist) = blocklist::Blocklist::new(&settings.blocklist) {
+        payload.text = blocklist.mask(&payload.text);
+        for field in [&mut payload.blocks, &mut payload.attachments]
+            .into_iter()
+            .flatten()
+        {
+            let mut value = field.to_value()?;
+            blocklist.mask_json(&mut value);
+            *field = request::RawField::from_value(&value)?;
+        }
+    }
+    if let Some(base) = &settings.link_rewrite_base {
+        payload.text = links::rewrite_text(base, &payload.text);
+        for field in [&mut payload.blocks, &mut payload.attachments]
+            .into_iter()
+            .flatten()
+        {
+            let mut value = field.to_value()?;
+            links::rewrite_json(base, &mut value);
+            *field = request::RawField::from_value(&value)?;
+        }
+    }
+    if let Some(blocks) = &payload.blocks {
+        if let Err(err) = blocks::check_limits(&blocks.to_value()?) {
+            if !settings.degrade_to_text {
+                return Err(err);
+            }
+            eprintln!("{err}, sending the text only");
+            payload.blocks = None;
+        }
     }
+
+    Ok(payload)
+}
+
+/// Query string of a GET trigger, the token is kept out of the message fields.
+#[derive(serde::Deserialize)]
+struct SendParams {
+    token: Option<String>,
+    #[serde(flatten)]
+    fields: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
-struct SlackMessagePayload {
+pub struct SlackMessagePayload {
     text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    blocks: Option<request::RawField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    attachments: Option<request::RawField>,
+    /// Only understood by chat.postMessage, webhooks drop it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metadata: Option<MessageMetadata>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thread_ts: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reply_broadcast: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon_emoji: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    unfurl_links: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    unfurl_media: Option<bool>,
 }
 
 impl SlackMessagePayload {
     fn new(text: String) -> Self {
-        Self { text }
+        Self {
+            text,
+            blocks: None,
+            attachments: None,
+            metadata: None,
+            thread_ts: None,
+            reply_broadcast: None,
+            username: None,
+            icon_emoji: None,
+            icon_url: None,
+            unfurl_links: None,
+            unfurl_media: None,
+        }
     }
 
-    #[cfg(not(test))]
-    fn send(&self, webhook_url: &str) -> anyhow::Result<Response> {
-        let client = waki::Client::new();
-        let response = client
-            .post(webhook_url)
-            .header("Content-Type", "application/json")
-            .body(serde_json::to_vec(self)?)
-            .send()?;
-        Ok(response)
+    /// Pretty-printed JSON as posted to Slack, for snapshot tests.
+    pub fn to_json_pretty(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
     }
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
 struct SlackResponse {
     ok: bool,
+    /// Code Slack answered an error with, or the one of its status.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Error body as Slack sent it, with the `verbose_errors` setting.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    slack_body: Option<String>,
 }
 
 impl SlackResponse {
-    fn from_status(status: u16) -> Self {
-        Self { ok: status == 200 }
+    fn from_response(response: &ClientResponse, verbose_errors: bool) -> Self {
+        if response.status == 200 {
+            return Self {
+                ok: true,
+                error: None,
+                slack_body: None,
+            };
+        }
+
+        let body = String::from_utf8_lossy(&response.body);
+        let body = body.trim();
+        Self {
+            ok: false,
+            error: Some(webhook_error_code(response.status, body)),
+            slack_body: (verbose_errors && !body.is_empty()).then(|| body.to_string()),
+        }
+    }
+}
+
+// Webhooks answer errors with a bare code, e.g. `channel_not_found`, proxies
+// and outages with pages the status stands for
+fn webhook_error_code(status: u16, body: &str) -> String {
+    let is_code = (1..=64).contains(&body.len())
+        && body
+            .bytes()
+            .all(|byte| byte.is_ascii_lowercase() || byte == b'_');
+    if is_code {
+        return body.to_string();
     }
+    match status {
+        400 => "invalid_payload",
+        403 => "action_prohibited",
+        404 => "no_service",
+        410 => "channel_is_archived",
+        429 => "rate_limited",
+        _ => "slack_error",
+    }
+    .to_string()
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Debug)]
+const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
 pub struct Settings {
+    #[serde(default)]
     pub webhook_url: String,
+    /// Webhook messages go to while `webhook_url` fails.
+    #[serde(default)]
+    pub secondary_webhook_url: Option<String>,
+    /// Delay after which a failing webhook is tried again.
+    #[serde(default = "default_webhook_probe_seconds")]
+    pub webhook_probe_seconds: u64,
+    /// Posts through `chat.postMessage` instead of a webhook when set.
+    #[serde(default)]
+    pub bot_token: Option<String>,
+    #[serde(default)]
+    pub default_channel: Option<String>,
+    /// Presentation of every message, as a JSON object.
+    #[serde(default, deserialize_with = "json_setting")]
+    pub defaults: Option<PayloadDefaults>,
+    #[serde(default)]
+    pub scrub_pii: bool,
+    /// Words and phrases masked in outgoing messages.
+    #[serde(default)]
+    pub blocklist: Vec<String>,
+    /// Put before every message, `{{field}}` being replaced with the
+    /// request field.
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Put after every message, like `prefix`.
+    #[serde(default)]
+    pub suffix: Option<String>,
+    /// Fields `{{field}}` may name in request channels, usernames and
+    /// attachment titles, which are left as written without any.
+    #[serde(default)]
+    pub interpolated_fields: Vec<String>,
+    /// Ends messages with their source, environment and component version.
+    #[serde(default)]
+    pub footer: bool,
+    /// Environment shown in the footer, e.g. `production`.
+    #[serde(default)]
+    pub environment: Option<String>,
+    /// Redirect endpoint links go through, the original URL being passed in
+    /// its `url` query parameter.
+    #[serde(default)]
+    pub link_rewrite_base: Option<String>,
+    /// Emojis put in front of message keywords, as a JSON object.
+    #[serde(default, deserialize_with = "json_setting")]
+    pub emojify: Option<HashMap<String, String>>,
+    /// Sends the text alone when blocks exceed the Slack limits or are
+    /// refused.
+    #[serde(default)]
+    pub degrade_to_text: bool,
+    /// Shows ISO-8601 timestamps in each reader's timezone.
+    #[serde(default)]
+    pub format_dates: bool,
+    /// Composes request text to NFC, `strip` also removing control and
+    /// invisible characters.
+    #[serde(default)]
+    pub normalize_text: TextNormalization,
+    /// Adds the body of Slack webhook errors to responses.
+    #[serde(default)]
+    pub verbose_errors: bool,
+    /// Slack response headers copied onto the response, as a JSON object
+    /// of Slack header names to response header names.
+    #[serde(default, deserialize_with = "json_setting")]
+    pub response_headers: Option<HashMap<String, String>>,
+    /// Rules dropping notifications before they reach Slack, as a JSON
+    /// object.
+    #[serde(default, deserialize_with = "json_setting")]
+    pub filters: Option<Filters>,
+    /// Headers added to the requests sent to Slack, as a JSON object.
+    #[serde(default, deserialize_with = "json_setting")]
+    pub outbound_headers: Option<HashMap<String, String>>,
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+    #[serde(default)]
+    pub cors_allowed_origins: Vec<String>,
+    #[serde(default)]
+    pub cors_allowed_methods: Vec<String>,
+    #[serde(default = "default_cors_allowed_headers")]
+    pub cors_allowed_headers: Vec<String>,
+    #[serde(default)]
+    pub query_trigger: bool,
+    #[serde(default)]
+    pub auth_token: Option<String>,
+    #[serde(default, deserialize_with = "json_setting")]
+    pub input_schema: Option<serde_json::Value>,
+    #[serde(default)]
+    pub strict: bool,
+    #[serde(default)]
+    pub secrets_endpoint: Option<String>,
+    #[serde(default)]
+    pub secrets_token: Option<String>,
+    #[serde(default)]
+    pub passthrough: bool,
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: usize,
+    #[serde(default)]
+    pub handler_timeout_ms: Option<u64>,
+    /// Version serving requests without a `/v1`-style path prefix.
+    #[serde(default)]
+    pub default_api_version: ApiVersion,
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+    /// Slack app credentials enabling the `/slack/install` flow.
+    #[serde(default)]
+    pub oauth_client_id: Option<String>,
+    #[serde(default)]
+    pub oauth_client_secret: Option<String>,
+    #[serde(default)]
+    pub oauth_redirect_uri: Option<String>,
+    #[serde(default = "default_oauth_scopes")]
+    pub oauth_scopes: Vec<String>,
+    /// Key-value service storing the bot tokens of installed workspaces.
+    #[serde(default)]
+    pub token_store_endpoint: Option<String>,
+    #[serde(default)]
+    pub token_store_token: Option<String>,
+    /// Workspace posted to when the request has no `team_id`.
+    #[serde(default)]
+    pub default_team_id: Option<String>,
+    /// Slack app signing secret, enables the `/slack/events` route.
+    #[serde(default)]
+    pub signing_secret: Option<String>,
+    /// Key Shopify signs its webhooks with, enables the `shopify` source.
+    #[serde(default)]
+    pub shopify_secret: Option<String>,
+    /// Twilio account auth token, enables the `twilio` source.
+    #[serde(default)]
+    pub twilio_auth_token: Option<String>,
+    /// Columns whose values database change events show, as `column` or
+    /// `table.column`.
+    #[serde(default)]
+    pub change_columns: Vec<String>,
+    /// Template, severity mapping and webhook by source, as a JSON object.
+    #[serde(default, deserialize_with = "json_setting")]
+    pub sources: Option<HashMap<String, SourceSettings>>,
+    /// Called when a posted message gets a ✅ reaction.
+    #[serde(default)]
+    pub ack_callback_url: Option<String>,
+    /// Delay after which an unacknowledged critical message is escalated.
+    #[serde(default)]
+    pub escalate_after_seconds: Option<u64>,
+    /// Slack user group ID mentioned by escalations, e.g. `S0123ABCD`.
+    #[serde(default)]
+    pub oncall_group: Option<String>,
+    /// Severities whose thread replies are also shown in the channel.
+    #[serde(default)]
+    pub broadcast_severities: Vec<String>,
+    /// Severities whose messages are pinned to their channel.
+    #[serde(default)]
+    pub pin_severities: Vec<String>,
+    /// Severities buffered until the next `POST /flush`.
+    #[serde(default)]
+    pub digest_severities: Vec<String>,
+    /// Canvas messages are logged to, in bot-token mode.
+    #[serde(default)]
+    pub canvas_id: Option<String>,
+    /// Heading the entries go under, newest first.
+    #[serde(default)]
+    pub canvas_section: Option<String>,
+    #[serde(default)]
+    pub canvas_mode: CanvasMode,
+}
+
+fn default_max_body_bytes() -> usize {
+    DEFAULT_MAX_BODY_BYTES
+}
+
+fn default_webhook_probe_seconds() -> u64 {
+    60
+}
+
+fn default_max_concurrency() -> usize {
+    4
+}
+
+fn default_oauth_scopes() -> Vec<String> {
+    vec!["chat:write".to_string()]
+}
+
+fn default_cors_allowed_headers() -> Vec<String> {
+    vec!["Content-Type".to_string()]
+}
+
+// JSON documents are passed as strings in the settings
+fn json_setting<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::de::DeserializeOwned,
+{
+    let value: Option<String> = serde::Deserialize::deserialize(deserializer)?;
+    value
+        .map(|value| serde_json::from_str(&value).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+impl helpers::Validate for Settings {
+    fn validate(&self, errors: &mut Vec<String>) {
+        match (self.webhook_url.is_empty(), &self.bot_token) {
+            (true, None) if self.token_store_endpoint.is_none() => {
+                errors.push("Missing webhook_url setting".to_string())
+            }
+            (false, Some(_)) => {
+                errors.push("webhook_url and bot_token settings are mutually exclusive".to_string())
+            }
+            _ => {}
+        }
+        if !self.webhook_url.is_empty()
+            && !is_http_url(&self.webhook_url)
+            && !secrets::is_reference(&self.webhook_url)
+        {
+            errors.push("webhook_url setting must be an http(s) URL".to_string());
+        }
+        if secrets::is_reference(&self.webhook_url) && self.secrets_endpoint.is_none() {
+            errors.push(
+                "webhook_url setting references a secret but no secrets_endpoint is set"
+                    .to_string(),
+            );
+        }
+        if let Some(url) = &self.secondary_webhook_url {
+            if self.webhook_url.is_empty() {
+                errors.push("secondary_webhook_url setting requires webhook_url".to_string());
+            }
+            if !is_http_url(url) && !secrets::is_reference(url) {
+                errors.push("secondary_webhook_url setting must be an http(s) URL".to_string());
+            }
+            if secrets::is_reference(url) && self.secrets_endpoint.is_none() {
+                errors.push(
+                    "secondary_webhook_url setting references a secret but no secrets_endpoint is set"
+                        .to_string(),
+                );
+            }
+        }
+        if let Some(base) = &self.link_rewrite_base {
+            if !is_http_url(base) {
+                errors.push("link_rewrite_base setting must be an http(s) URL".to_string());
+            }
+        }
+        if let Some(endpoint) = &self.secrets_endpoint {
+            if !is_http_url(endpoint) {
+                errors.push("secrets_endpoint setting must be an http(s) URL".to_string());
+            }
+        }
+        for (name, value) in self.outbound_headers.iter().flatten() {
+            if http::HeaderName::try_from(name).is_err()
+                || http::HeaderValue::try_from(value).is_err()
+            {
+                errors.push(format!(
+                    "Invalid header '{name}' in outbound_headers setting"
+                ));
+            } else if helpers::is_forbidden_header(name) {
+                errors.push(format!(
+                    "Header '{name}' in outbound_headers setting can't be sent"
+                ));
+            }
+        }
+        for (name, source) in self.sources.iter().flatten() {
+            let Some(url) = &source.webhook_url else {
+                continue;
+            };
+            if !is_http_url(url) && !secrets::is_reference(url) {
+                errors.push(format!(
+                    "Invalid source '{name}' in sources setting: webhook_url must be an http(s) URL"
+                ));
+            }
+            if self.bot_token.is_some() || self.uses_token_store() {
+                errors.push(format!(
+                    "Invalid source '{name}' in sources setting: webhook_url is not supported with bot tokens"
+                ));
+            }
+        }
+        for (from, to) in self.response_headers.iter().flatten() {
+            if helpers::HeaderMapping::new([(from.as_str(), to.as_str())]).is_err() {
+                errors.push(format!(
+                    "Invalid header '{from}' in response_headers setting"
+                ));
+            } else if helpers::is_forbidden_header(to) {
+                errors.push(format!(
+                    "Header '{to}' in response_headers setting can't be sent"
+                ));
+            }
+        }
+        if let Some(filters) = &self.filters {
+            filters.validate(errors);
+        }
+        if self.max_body_bytes == 0 {
+            errors.push("max_body_bytes setting must be greater than 0".to_string());
+        }
+        if self.max_concurrency == 0 {
+            errors.push("max_concurrency setting must be greater than 0".to_string());
+        }
+        if self.handler_timeout_ms == Some(0) {
+            errors.push("handler_timeout_ms setting must be greater than 0".to_string());
+        }
+        for method in &self.cors_allowed_methods {
+            if http::Method::from_bytes(method.as_bytes()).is_err() {
+                errors.push(format!(
+                    "Invalid method '{method}' in cors_allowed_methods setting"
+                ));
+            }
+        }
+        if self.query_trigger && self.auth_token.is_none() {
+            errors.push("query_trigger setting requires an auth_token".to_string());
+        }
+        if self.oauth_client_id.is_some()
+            && (self.oauth_client_secret.is_none() || self.token_store_endpoint.is_none())
+        {
+            errors.push(
+                "oauth_client_id setting requires oauth_client_secret and token_store_endpoint"
+                    .to_string(),
+            );
+        }
+        if let Some(endpoint) = &self.token_store_endpoint {
+            if !is_http_url(endpoint) {
+                errors.push("token_store_endpoint setting must be an http(s) URL".to_string());
+            }
+        }
+        if let Some(url) = &self.ack_callback_url {
+            if !is_http_url(url) {
+                errors.push("ack_callback_url setting must be an http(s) URL".to_string());
+            }
+            if self.signing_secret.is_none() || self.token_store_endpoint.is_none() {
+                errors.push(
+                    "ack_callback_url setting requires signing_secret and token_store_endpoint"
+                        .to_string(),
+                );
+            }
+            // Webhooks don't answer with the ts reactions refer to
+            if !self.webhook_url.is_empty() {
+                errors
+                    .push("ack_callback_url setting is not supported with webhook_url".to_string());
+            }
+        }
+        if let Some(after) = self.escalate_after_seconds {
+            if after == 0 {
+                errors.push("escalate_after_seconds setting must be greater than 0".to_string());
+            }
+            if self.oncall_group.is_none()
+                || self.signing_secret.is_none()
+                || self.token_store_endpoint.is_none()
+            {
+                errors.push(
+                    "escalate_after_seconds setting requires oncall_group, signing_secret and token_store_endpoint"
+                        .to_string(),
+                );
+            }
+            if !self.webhook_url.is_empty() {
+                errors.push(
+                    "escalate_after_seconds setting is not supported with webhook_url".to_string(),
+                );
+            }
+        }
+        if !self.digest_severities.is_empty()
+            && (self.auth_token.is_none() || self.token_store_endpoint.is_none())
+        {
+            errors.push(
+                "digest_severities setting requires auth_token and token_store_endpoint"
+                    .to_string(),
+            );
+        }
+        if self.canvas_id.is_some() && !self.webhook_url.is_empty() {
+            errors.push("canvas_id setting requires a bot token".to_string());
+        }
+        if self.canvas_mode == CanvasMode::Instead && self.canvas_id.is_none() {
+            errors.push("canvas_mode setting instead requires canvas_id".to_string());
+        }
+        // chat.postMessage needs the channel added to the payload
+        if self.passthrough && (self.bot_token.is_some() || self.uses_token_store()) {
+            errors.push("passthrough setting is not supported with bot tokens".to_string());
+        }
+        // Passthrough bodies are forwarded untouched, these would be skipped
+        let rewriting = [
+            ("scrub_pii", self.scrub_pii),
+            ("blocklist", !self.blocklist.is_empty()),
+            ("filters", self.filters.is_some()),
+            ("link_rewrite_base", self.link_rewrite_base.is_some()),
+            ("footer", self.footer),
+        ];
+        let rewriting: Vec<&str> = rewriting
+            .into_iter()
+            .filter_map(|(name, set)| set.then_some(name))
+            .collect();
+        if self.passthrough && !rewriting.is_empty() {
+            errors.push(format!(
+                "passthrough setting forwards bodies untouched, it can't be combined with {}",
+                rewriting.join(", ")
+            ));
+        }
+    }
+}
+
+fn is_http_url(value: &str) -> bool {
+    value
+        .parse::<http::Uri>()
+        .is_ok_and(|uri| matches!(uri.scheme_str(), Some("http" | "https")) && uri.host().is_some())
 }
 
 impl Settings {
     pub fn new(headers: &http::header::HeaderMap) -> anyhow::Result<Self> {
-        let value = headers
-            .get("x-edgee-component-settings")
-            .ok_or_else(|| anyhow::anyhow!("Missing 'x-edgee-component-settings' header"))
-            .and_then(|value| value.to_str().map_err(Into::into))?;
-        let data: HashMap<String, String> = serde_json::from_str(value)?;
-
-        Ok(Self {
-            webhook_url: data
-                .get("webhook_url")
-                .ok_or_else(|| anyhow::anyhow!("Missing webhook_url setting"))?
-                .to_string(),
+        let helpers::EdgeeSettings(settings) = helpers::EdgeeSettings::from_headers(headers)?;
+        Ok(settings)
+    }
+
+    /// Parses settings from their raw string values, as sent by Edgee.
+    pub fn from_map(data: &HashMap<String, String>) -> anyhow::Result<Self> {
+        let helpers::EdgeeSettings(settings) = helpers::EdgeeSettings::from_map(data)?;
+        Ok(settings)
+    }
+
+    /// Mapping of the `response_headers`, checked with the settings.
+    pub fn response_headers(&self) -> helpers::HeaderMapping {
+        let names = self
+            .response_headers
+            .iter()
+            .flatten()
+            .map(|(from, to)| (from.as_str(), to.as_str()));
+        helpers::HeaderMapping::new(names).unwrap_or_default()
+    }
+
+    /// `client` adding the `outbound_headers` to the requests sent to Slack.
+    pub fn client<'a>(&self, client: &'a dyn OutboundClient) -> client::SlackHeadersClient<'a> {
+        let headers = self
+            .outbound_headers
+            .iter()
+            .flatten()
+            .filter_map(|(name, value)| {
+                Some((
+                    http::HeaderName::try_from(name).ok()?,
+                    http::HeaderValue::try_from(value).ok()?,
+                ))
+            })
+            .collect();
+        client::SlackHeadersClient::new(client, headers)
+    }
+
+    /// Whether messages are posted with the bot token stored for their team,
+    /// neither a webhook URL nor a bot token being set.
+    pub fn uses_token_store(&self) -> bool {
+        self.webhook_url.is_empty()
+            && self.bot_token.is_none()
+            && self.token_store_endpoint.is_some()
+    }
+
+    /// Whether posted messages are stored for acknowledgements.
+    pub fn tracks_messages(&self) -> bool {
+        self.ack_callback_url.is_some() || self.escalate_after_seconds.is_some()
+    }
+
+    fn cors(&self) -> Option<helpers::Cors> {
+        if self.cors_allowed_origins.is_empty() {
+            return None;
+        }
+
+        Some(helpers::Cors {
+            allowed_origins: self.cors_allowed_origins.clone(),
+            allowed_methods: self
+                .cors_allowed_methods
+                .iter()
+                .filter_map(|method| http::Method::from_bytes(method.as_bytes()).ok())
+                .collect(),
+            allowed_headers: self.cors_allowed_headers.clone(),
+            max_age: None,
         })
     }
 
+    /// Settings parsed by `run_options`, or parsed from the headers when the
+    /// request did not go through `run`.
     pub fn from_req<B>(req: &http::Request<B>) -> anyhow::Result<Self> {
-        Self::new(req.headers())
+        match req.extensions().get::<Self>() {
+            Some(settings) => Ok(settings.clone()),
+            None => Self::new(req.headers()),
+        }
+    }
+
+    // Options are needed before the handler runs, so fall back to the
+    // defaults when settings are invalid and let the handler report the error
+    fn run_options(parts: &mut http::request::Parts) -> helpers::RunOptions {
+        match Self::new(&parts.headers) {
+            Ok(settings) => {
+                let options = helpers::RunOptions {
+                    max_body_bytes: Some(settings.max_body_bytes),
+                    cors: settings.cors(),
+                    handler_timeout: settings
+                        .handler_timeout_ms
+                        .map(std::time::Duration::from_millis),
+                    error_handler: Some(helpers::negotiate::error_handler(&parts.headers)),
+                };
+                parts.extensions.insert(settings);
+                options
+            }
+            Err(_) => helpers::RunOptions {
+                max_body_bytes: Some(DEFAULT_MAX_BODY_BYTES),
+                error_handler: Some(helpers::negotiate::error_handler(&parts.headers)),
+                ..Default::default()
+            },
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use http::{HeaderValue, Request};
-    use lazy_static;
     use serde_json::json;
     use std::sync::Mutex;
 
     use super::*;
 
-    // Patch SlackMessagePayload::send for this test
+    // Patch Component::client for this test
     lazy_static::lazy_static! {
         static ref SEND_CALLED: Mutex<bool> = Mutex::new(false);
     }
 
-    // Mock SlackMessagePayload::send to avoid real HTTP call
-    pub struct MockResponse;
-    impl MockResponse {
-        pub fn status_code(&self) -> u16 {
-            200
+    // Mock outbound client to avoid real HTTP call
+    pub struct FlagClient;
+    impl OutboundClient for FlagClient {
+        fn send(
+            &self,
+            method: http::Method,
+            url: &str,
+            headers: &http::HeaderMap,
+            body: Vec<u8>,
+        ) -> anyhow::Result<ClientResponse> {
+            *SEND_CALLED.lock().unwrap() = true;
+            client::MockClient::new().send(method, url, headers, body)
         }
     }
 
-    impl SlackMessagePayload {
-        pub fn send(&self, _webhook_url: &str) -> anyhow::Result<MockResponse> {
-            *SEND_CALLED.lock().unwrap() = true;
-            Ok(MockResponse)
+    impl Component {
+        pub fn client() -> FlagClient {
+            FlagClient
+        }
+
+        fn handle_json_request(
+            req: http::Request<Option<MessageBody>>,
+        ) -> anyhow::Result<JsonResponse> {
+            Self::handle_with_client(req, &Self::client())
         }
     }
 
@@ -149,19 +1668,121 @@ mod tests {
         let mut headers = http::header::HeaderMap::new();
         headers.insert(
             "x-edgee-component-settings",
-            HeaderValue::from_static(r#"{"webhook_url": "test_value"}"#),
+            HeaderValue::from_static(r#"{"webhook_url": "https://hooks.slack.com/services/test"}"#),
         );
 
         let settings = Settings::new(&headers).unwrap();
-        assert_eq!(settings.webhook_url, "test_value");
+        assert_eq!(
+            settings.webhook_url,
+            "https://hooks.slack.com/services/test"
+        );
+        assert!(!settings.scrub_pii);
     }
 
     #[test]
-    fn test_settings_new_missing_header() {
-        let headers = http::header::HeaderMap::new();
-        let result = Settings::new(&headers);
-        assert!(result.is_err());
-        assert_eq!(
+    fn test_settings_new_scrub_pii() {
+        let mut headers = http::header::HeaderMap::new();
+        headers.insert(
+            "x-edgee-component-settings",
+            HeaderValue::from_static(
+                r#"{"webhook_url": "https://hooks.slack.com/services/test", "scrub_pii": "true"}"#,
+            ),
+        );
+
+        let settings = Settings::new(&headers).unwrap();
+        assert!(settings.scrub_pii);
+    }
+
+    #[test]
+    fn test_settings_new_max_body_bytes() {
+        let mut headers = http::header::HeaderMap::new();
+        headers.insert(
+            "x-edgee-component-settings",
+            HeaderValue::from_static(r#"{"webhook_url": "https://hooks.slack.com/services/test"}"#),
+        );
+        let settings = Settings::new(&headers).unwrap();
+        assert_eq!(settings.max_body_bytes, DEFAULT_MAX_BODY_BYTES);
+
+        headers.insert(
+            "x-edgee-component-settings",
+            HeaderValue::from_static(r#"{"webhook_url": "https://hooks.slack.com/services/test", "max_body_bytes": "2048"}"#),
+        );
+        let settings = Settings::new(&headers).unwrap();
+        assert_eq!(settings.max_body_bytes, 2048);
+    }
+
+    #[test]
+    fn test_settings_new_invalid_max_body_bytes() {
+        let mut headers = http::header::HeaderMap::new();
+        headers.insert(
+            "x-edgee-component-settings",
+            HeaderValue::from_static(r#"{"webhook_url": "https://hooks.slack.com/services/test", "max_body_bytes": "lots"}"#),
+        );
+        let result = Settings::new(&headers);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid max_body_bytes setting: expected usize, got \"lots\""
+        );
+    }
+
+    #[test]
+    fn test_settings_run_options_falls_back_to_default() {
+        let (mut parts, _) = Request::builder().body(()).unwrap().into_parts();
+        let options = Settings::run_options(&mut parts);
+        assert_eq!(options.max_body_bytes, Some(DEFAULT_MAX_BODY_BYTES));
+        assert!(options.cors.is_none());
+    }
+
+    #[test]
+    fn test_settings_cors_disabled_by_default() {
+        let mut headers = http::header::HeaderMap::new();
+        headers.insert(
+            "x-edgee-component-settings",
+            HeaderValue::from_static(r#"{"webhook_url": "https://hooks.slack.com/services/test"}"#),
+        );
+        let settings = Settings::new(&headers).unwrap();
+        assert!(settings.cors().is_none());
+    }
+
+    #[test]
+    fn test_settings_cors() {
+        let mut headers = http::header::HeaderMap::new();
+        headers.insert(
+            "x-edgee-component-settings",
+            HeaderValue::from_static(
+                r#"{"webhook_url": "https://hooks.slack.com/services/test", "cors_allowed_origins": "https://a.com, https://b.com", "cors_allowed_methods": "POST,OPTIONS"}"#,
+            ),
+        );
+        let settings = Settings::new(&headers).unwrap();
+        let cors = settings.cors().unwrap();
+        assert_eq!(cors.allowed_origins, vec!["https://a.com", "https://b.com"]);
+        assert_eq!(
+            cors.allowed_methods,
+            vec![http::Method::POST, http::Method::OPTIONS]
+        );
+        assert_eq!(cors.allowed_headers, vec!["Content-Type"]);
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let data = HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            ("cors_allowed_origins".to_string(), " a, ,b ,".to_string()),
+        ]);
+        let settings = Settings::from_map(&data).unwrap();
+        assert_eq!(settings.cors_allowed_origins, vec!["a", "b"]);
+        assert!(settings.cors_allowed_methods.is_empty());
+    }
+
+    #[test]
+    fn test_settings_new_missing_header() {
+        let headers = http::header::HeaderMap::new();
+        let result = Settings::new(&headers);
+        assert!(result.is_err());
+        assert_eq!(
             result.unwrap_err().to_string(),
             "Missing 'x-edgee-component-settings' header"
         );
@@ -211,7 +1832,7 @@ mod tests {
                 "x-edgee-component-settings",
                 r#"{"webhook_url": "http://example.com/webhook"}"#,
             )
-            .body(Json(body))
+            .body(Some(MessageBody(body, None, Vec::new())))
             .unwrap();
 
         // Call the handler
@@ -234,7 +1855,7 @@ mod tests {
                 "x-edgee-component-settings",
                 r#"{"webhook_url": "http://example.com/webhook"}"#,
             )
-            .body(Json(body))
+            .body(Some(MessageBody(body, None, Vec::new())))
             .unwrap();
 
         let result = Component::handle_json_request(req);
@@ -248,7 +1869,9 @@ mod tests {
     #[test]
     fn test_handle_json_request_invalid_settings() {
         let body = json!({ "message": "Test" });
-        let req = Request::builder().body(Json(body)).unwrap();
+        let req = Request::builder()
+            .body(Some(MessageBody(body, None, Vec::new())))
+            .unwrap();
 
         let result = Component::handle_json_request(req);
         assert!(result.is_err());
@@ -257,4 +1880,2011 @@ mod tests {
             "Missing 'x-edgee-component-settings' header"
         );
     }
+
+    #[test]
+    fn test_settings_query_trigger_requires_auth_token() {
+        let mut headers = http::header::HeaderMap::new();
+        headers.insert(
+            "x-edgee-component-settings",
+            HeaderValue::from_static(r#"{"webhook_url": "https://hooks.slack.com/services/test", "query_trigger": "true"}"#),
+        );
+        let result = Settings::new(&headers);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "query_trigger setting requires an auth_token"
+        );
+    }
+
+    fn query_request(uri: &str, settings: &'static str) -> Request<Option<MessageBody>> {
+        Request::builder()
+            .method(http::Method::GET)
+            .uri(uri)
+            .header("x-edgee-component-settings", settings)
+            .body(None)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_handle_json_request_query_trigger() {
+        let req = query_request(
+            "/send?message=disk%20full&severity=warning&token=s3cret",
+            r#"{"webhook_url": "http://example.com/webhook", "query_trigger": "true", "auth_token": "s3cret"}"#,
+        );
+
+        let resp = Component::handle_json_request(req).unwrap();
+        assert_eq!(resp.status(), 200);
+        let Json(data) = resp.body();
+        assert_eq!(data.to_string(), "{\"ok\":true}");
+    }
+
+    #[test]
+    fn test_handle_json_request_query_trigger_unauthorized() {
+        let req = query_request(
+            "/send?message=disk%20full&token=wrong",
+            r#"{"webhook_url": "http://example.com/webhook", "query_trigger": "true", "auth_token": "s3cret"}"#,
+        );
+
+        let resp = Component::handle_json_request(req).unwrap();
+        assert_eq!(resp.status(), 401);
+    }
+
+    #[test]
+    fn test_handle_json_request_query_trigger_disabled() {
+        let req = query_request(
+            "/send?message=disk%20full",
+            r#"{"webhook_url": "http://example.com/webhook"}"#,
+        );
+
+        let resp = Component::handle_json_request(req).unwrap();
+        assert_eq!(resp.status(), 405);
+        assert_eq!(resp.headers().get(http::header::ALLOW).unwrap(), "POST");
+    }
+
+    #[test]
+    fn test_handle_json_request_missing_body() {
+        let req = Request::builder()
+            .method(http::Method::POST)
+            .header(
+                "x-edgee-component-settings",
+                r#"{"webhook_url": "http://example.com/webhook"}"#,
+            )
+            .body(None)
+            .unwrap();
+
+        let result = Component::handle_json_request(req);
+        assert_eq!(result.unwrap_err().to_string(), "Missing request body");
+    }
+
+    #[test]
+    fn test_handle_json_request_binary_cloudevent() {
+        let body = json!({ "env": "prod" });
+        let req = Request::builder()
+            .method(http::Method::POST)
+            .header(
+                "x-edgee-component-settings",
+                r#"{"webhook_url": "http://example.com/webhook"}"#,
+            )
+            .header("ce-specversion", "1.0")
+            .header("ce-type", "com.example.deploy")
+            .header("ce-source", "/ci")
+            .body(Some(MessageBody(body, None, Vec::new())))
+            .unwrap();
+
+        let resp = Component::handle_json_request(req).unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+
+    #[test]
+    fn test_handle_json_request_invalid_binary_cloudevent() {
+        let req = Request::builder()
+            .method(http::Method::POST)
+            .header(
+                "x-edgee-component-settings",
+                r#"{"webhook_url": "http://example.com/webhook"}"#,
+            )
+            .header("ce-specversion", "1.0")
+            .body(Some(MessageBody(
+                json!({ "message": "hi" }),
+                None,
+                Vec::new(),
+            )))
+            .unwrap();
+
+        let result = Component::handle_json_request(req);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid CloudEvent: missing 'type' attribute"
+        );
+    }
+
+    #[test]
+    fn test_settings_new_invalid_input_schema() {
+        let mut headers = http::header::HeaderMap::new();
+        headers.insert(
+            "x-edgee-component-settings",
+            HeaderValue::from_static(
+                r#"{"webhook_url": "https://hooks.slack.com/services/test", "input_schema": "{not json"}"#,
+            ),
+        );
+        let result = Settings::new(&headers);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid input_schema setting: key must be a string at line 1 column 2"
+        );
+    }
+
+    fn schema_request(body: serde_json::Value) -> Request<Option<MessageBody>> {
+        Request::builder()
+            .method(http::Method::POST)
+            .header(
+                "x-edgee-component-settings",
+                r#"{"webhook_url": "http://example.com/webhook", "input_schema": "{\"type\": \"object\", \"required\": [\"message\", \"severity\"]}"}"#,
+            )
+            .body(Some(MessageBody(body, None, Vec::new())))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_handle_json_request_input_schema_valid() {
+        let req = schema_request(json!({ "message": "hi", "severity": "info" }));
+        let resp = Component::handle_json_request(req).unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+
+    #[test]
+    fn test_handle_json_request_input_schema_violations() {
+        let req = schema_request(json!({ "message": "hi" }));
+        let resp = Component::handle_json_request(req).unwrap();
+        assert_eq!(resp.status(), 422);
+
+        let Json(data) = resp.body();
+        assert_eq!(data["error"], "Request body does not match input_schema");
+        assert_eq!(data["violations"].as_array().unwrap().len(), 1);
+        assert_eq!(data["violations"][0]["path"], "");
+    }
+
+    fn strict_request(body: serde_json::Value) -> Request<Option<MessageBody>> {
+        Request::builder()
+            .method(http::Method::POST)
+            .header(
+                "x-edgee-component-settings",
+                r#"{"webhook_url": "http://example.com/webhook", "strict": "true"}"#,
+            )
+            .body(Some(MessageBody(body, None, Vec::new())))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_handle_json_request_strict_unknown_field() {
+        let req = strict_request(json!({ "mesage": "hi" }));
+        let resp = Component::handle_json_request(req).unwrap();
+        assert_eq!(resp.status(), 400);
+
+        let Json(data) = resp.body();
+        assert_eq!(data["error"], "Unknown field 'mesage' in request body");
+    }
+
+    #[test]
+    fn test_handle_json_request_strict_valid() {
+        let req = strict_request(json!({ "message": "hi", "severity": "info" }));
+        let resp = Component::handle_json_request(req).unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+
+    #[test]
+    fn test_handle_json_request_invalid_message_type() {
+        let req = Request::builder()
+            .method(http::Method::POST)
+            .header(
+                "x-edgee-component-settings",
+                r#"{"webhook_url": "http://example.com/webhook"}"#,
+            )
+            .body(Some(MessageBody(
+                json!({ "message": 42 }),
+                None,
+                Vec::new(),
+            )))
+            .unwrap();
+
+        let resp = Component::handle_json_request(req).unwrap();
+        assert_eq!(resp.status(), 400);
+    }
+
+    fn notification_settings() -> Settings {
+        let mut headers = http::header::HeaderMap::new();
+        headers.insert(
+            "x-edgee-component-settings",
+            HeaderValue::from_static(
+                r#"{"webhook_url": "http://example.com/webhook", "scrub_pii": "true"}"#,
+            ),
+        );
+        Settings::new(&headers).unwrap()
+    }
+
+    #[test]
+    fn test_handle_notification() {
+        let settings = notification_settings();
+        let request = NotificationRequest {
+            message: Some("Contact jane@example.com".to_string()),
+            ..Default::default()
+        };
+        let client = client::MockClient::new();
+
+        let response = handle_notification(&settings, request, &client).unwrap();
+
+        assert_eq!(response.status, http::StatusCode::OK);
+        assert_eq!(response.body, json!({ "ok": true }));
+
+        let requests = client.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].url, "http://example.com/webhook");
+        assert_eq!(
+            requests[0].headers.get(http::header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&requests[0].body).unwrap(),
+            json!({ "text": "Contact [email]" })
+        );
+    }
+
+    #[test]
+    fn test_handle_notification_dedup() {
+        let mut settings = notification_settings();
+        settings.token_store_endpoint = Some("https://kv.example.com/slack".to_string());
+        let request = NotificationRequest {
+            message: Some("New post".to_string()),
+            dedup_key: Some("feed/1".to_string()),
+            ..Default::default()
+        };
+
+        let client = client::MockClient::new()
+            .respond_with(404)
+            .respond_with_body(200, "ok")
+            .respond_with(204);
+        let response = handle_notification(&settings, request.clone(), &client).unwrap();
+        assert_eq!(response.body, json!({ "ok": true }));
+        let requests = client.requests();
+        assert_eq!(requests[1].url, "http://example.com/webhook");
+        assert_eq!(requests[2].method, http::Method::PUT);
+        assert_eq!(
+            requests[2].url,
+            "https://kv.example.com/slack/dedup/feed%2F1"
+        );
+
+        let client = client::MockClient::new().respond_with_body(200, r#"{"value": "1"}"#);
+        let response = handle_notification(&settings, request, &client).unwrap();
+        assert_eq!(response.body, json!({ "ok": true, "duplicate": true }));
+        assert_eq!(client.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_handle_notification_oversized_message() {
+        let request = NotificationRequest {
+            message: Some("x".repeat(50_000)),
+            ..Default::default()
+        };
+
+        let client = client::MockClient::new();
+        let response =
+            handle_notification(&notification_settings(), request.clone(), &client).unwrap();
+        assert_eq!(
+            response.body,
+            json!({ "ok": true, "size_strategy": "truncate" })
+        );
+        assert!(client.requests()[0].body.len() <= 40_000);
+
+        let client = client::MockClient::new()
+            .respond_with_body(200, r#"{"ok": true, "channel": "C1", "ts": "1.1"}"#)
+            .respond_with_body(200, r#"{"ok": false, "error": "missing_scope"}"#);
+        let response = handle_notification(&bot_settings(), request, &client).unwrap();
+        assert_eq!(response.body["size_strategy"], "upload");
+        assert_eq!(
+            client.requests()[1].url,
+            "https://slack.com/api/files.getUploadURLExternal"
+        );
+    }
+
+    #[test]
+    fn test_handle_notification_slack_error_status() {
+        let settings = notification_settings();
+        let request = NotificationRequest {
+            message: Some("hi".to_string()),
+            ..Default::default()
+        };
+
+        let client = client::MockClient::new().respond_with(404);
+        let response = handle_notification(&settings, request, &client).unwrap();
+        assert_eq!(response.status, http::StatusCode::NOT_FOUND);
+        assert_eq!(response.body, json!({ "ok": false, "error": "no_service" }));
+    }
+
+    #[test]
+    fn test_handle_notification_slack_error_body() {
+        let request = NotificationRequest {
+            message: Some("hi".to_string()),
+            ..Default::default()
+        };
+        let page = "<p>No hook at /services/T0/B0/Xy12Ab34Cd56</p>";
+
+        let mut settings = notification_settings();
+        settings.webhook_url = "https://hooks.slack.com/services/T0/B0/Xy12Ab34Cd56".to_string();
+        let client = client::MockClient::new()
+            .respond_with_body(404, page)
+            .respond_with_body(403, "invalid_token");
+        let response = handle_notification(&settings, request.clone(), &client).unwrap();
+        assert_eq!(response.body, json!({ "ok": false, "error": "no_service" }));
+        let response = handle_notification(&settings, request.clone(), &client).unwrap();
+        assert_eq!(
+            response.body,
+            json!({ "ok": false, "error": "invalid_token" })
+        );
+
+        settings.verbose_errors = true;
+        let client = client::MockClient::new().respond_with_body(404, page);
+        let response = handle_notification(&settings, request, &client).unwrap();
+        assert_eq!(
+            response.body,
+            json!({
+                "ok": false,
+                "error": "no_service",
+                "slack_body": "<p>No hook at /****</p>",
+            })
+        );
+    }
+
+    #[test]
+    fn test_component_response_formats() {
+        let response = ComponentResponse {
+            status: http::StatusCode::NOT_FOUND,
+            body: json!({ "ok": false }),
+            slack_status: Some(404),
+        };
+
+        let legacy = response.clone().into_response(ResponseFormat::Legacy);
+        testing::assert_json(&legacy, http::StatusCode::NOT_FOUND, json!({ "ok": false }));
+
+        let structured = response.into_response(ResponseFormat::Structured);
+        testing::assert_json(
+            &structured,
+            http::StatusCode::BAD_GATEWAY,
+            json!({ "ok": false, "slack_status": 404 }),
+        );
+
+        let response = ComponentResponse {
+            status: http::StatusCode::BAD_REQUEST,
+            body: json!({ "error": "Missing 'channel' field in request body" }),
+            slack_status: None,
+        };
+        let structured = response.into_response(ResponseFormat::Structured);
+        testing::assert_json(
+            &structured,
+            http::StatusCode::BAD_REQUEST,
+            json!({ "ok": false, "error": "Missing 'channel' field in request body" }),
+        );
+    }
+
+    #[test]
+    fn test_handle_with_client_structured_timings() {
+        let req = testing::RequestFixture::post()
+            .settings(json!({
+                "webhook_url": "https://hooks.slack.com/services/test",
+                "response_format": "structured",
+            }))
+            .json(json!({ "message": "Disk full" }))
+            .build();
+        let client = client::MockClient::new();
+
+        let res = Component::handle_with_client(req, &client).unwrap();
+        let timings = res.body().0["timings"].as_object().unwrap();
+        let mut keys: Vec<_> = timings.keys().collect();
+        keys.sort();
+        assert_eq!(keys, ["parse_ms", "render_ms", "slack_ms"]);
+        assert!(timings.values().all(|value| value.as_f64().is_some()));
+    }
+
+    #[test]
+    fn test_handle_notification_send_failure() {
+        let settings = notification_settings();
+        let request = NotificationRequest {
+            message: Some("hi".to_string()),
+            ..Default::default()
+        };
+
+        let client = client::MockClient::new().fail_with(anyhow::anyhow!("connection refused"));
+        let result = handle_notification(&settings, request, &client);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Failed to send Slack message: connection refused"
+        );
+    }
+
+    #[test]
+    fn test_handle_notification_missing_message() {
+        let settings = notification_settings();
+        let client = client::MockClient::new();
+        let result = handle_notification(&settings, NotificationRequest::default(), &client);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Missing 'message' field in request body"
+        );
+        assert!(client.requests().is_empty());
+    }
+
+    #[test]
+    fn test_handle_notification_errors_carry_status() {
+        let settings = notification_settings();
+        let client = client::MockClient::new();
+        let err = handle_notification(&settings, NotificationRequest::default(), &client)
+            .unwrap_err()
+            .downcast::<helpers::ApiError>()
+            .unwrap();
+        assert_eq!(err.status, http::StatusCode::BAD_REQUEST);
+
+        let request = NotificationRequest {
+            message: Some("hi".to_string()),
+            ..Default::default()
+        };
+        let client = client::MockClient::new().fail_with(anyhow::anyhow!("connection refused"));
+        let err = handle_notification(&settings, request, &client)
+            .unwrap_err()
+            .downcast::<helpers::ApiError>()
+            .unwrap();
+        assert_eq!(err.status, http::StatusCode::BAD_GATEWAY);
+    }
+
+    #[test]
+    fn test_handle_json_request_plain_text_fixture() {
+        let req = testing::RequestFixture::post()
+            .settings(json!({ "webhook_url": "http://example.com/webhook" }))
+            .body("text/plain", "Deploy finished")
+            .unwrap()
+            .build();
+
+        let resp = Component::handle_json_request(req).unwrap();
+        testing::assert_json(&resp, http::StatusCode::OK, json!({ "ok": true }));
+    }
+
+    #[test]
+    fn test_handle_json_request_passthrough_forwards_raw_body() {
+        let raw = r#"{"text": "Deploy done",  "blocks": [{"type": "divider"}]}"#;
+        let req = testing::RequestFixture::post()
+            .settings(json!({ "webhook_url": "http://example.com/webhook", "passthrough": "true" }))
+            .body("application/json", raw)
+            .unwrap()
+            .build();
+        let client = client::MockClient::new();
+
+        let resp = Component::handle_with_client(req, &client).unwrap();
+        testing::assert_json(&resp, http::StatusCode::OK, json!({ "ok": true }));
+
+        let requests = client.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].url, "http://example.com/webhook");
+        assert_eq!(requests[0].body, raw.as_bytes());
+    }
+
+    #[test]
+    fn test_handle_json_request_passthrough_checks_block_limits() {
+        let blocks = vec![json!({ "type": "divider" }); 51];
+        let req = testing::RequestFixture::post()
+            .settings(json!({ "webhook_url": "http://example.com/webhook", "passthrough": "true" }))
+            .body(
+                "application/json",
+                json!({ "text": "Deploy done", "blocks": blocks }).to_string(),
+            )
+            .unwrap()
+            .build();
+        let client = client::MockClient::new();
+
+        let err = Component::handle_with_client(req, &client).unwrap_err();
+        let err = err.downcast_ref::<helpers::ApiError>().unwrap();
+        assert_eq!(err.status, http::StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(client.requests().is_empty());
+    }
+
+    #[test]
+    fn test_handle_notification_webhook_degrades_refused_blocks() {
+        let data = HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            ("degrade_to_text".to_string(), "true".to_string()),
+        ]);
+        let settings = Settings::from_map(&data).unwrap();
+        let request = NotificationRequest::from_value(
+            json!({ "message": "Disk full", "blocks": [{ "type": "bogus" }] }),
+            true,
+        )
+        .unwrap();
+        let client = client::MockClient::new()
+            .respond_with_body(400, "invalid_blocks")
+            .respond_with(200);
+
+        let response = handle_notification(&settings, request, &client).unwrap();
+        assert_eq!(response.status, http::StatusCode::OK);
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&client.requests()[1].body).unwrap(),
+            json!({ "text": "Disk full" })
+        );
+    }
+
+    #[test]
+    fn test_render_degrades_blocks_over_limits() {
+        let settings = Settings {
+            degrade_to_text: true,
+            ..notification_settings()
+        };
+        let request = NotificationRequest::from_value(
+            json!({ "message": "Disk full", "blocks": vec![json!({ "type": "divider" }); 51] }),
+            true,
+        )
+        .unwrap();
+        let payload = render(&settings, request).unwrap();
+        assert_eq!(
+            serde_json::to_value(&payload).unwrap(),
+            json!({ "text": "Disk full" })
+        );
+    }
+
+    #[test]
+    fn test_handle_json_request_passthrough_renders_non_json_bodies() {
+        let req = testing::RequestFixture::post()
+            .settings(json!({ "webhook_url": "http://example.com/webhook", "passthrough": "true" }))
+            .body("text/plain", "Deploy done")
+            .unwrap()
+            .build();
+        let client = client::MockClient::new();
+
+        Component::handle_with_client(req, &client).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&client.requests()[0].body).unwrap(),
+            json!({ "text": "Deploy done" })
+        );
+    }
+
+    #[test]
+    fn test_handle_with_client_outbound_headers() {
+        let req = testing::RequestFixture::post()
+            .settings(json!({
+                "webhook_url": "https://hooks.slack.com/services/test",
+                "outbound_headers": r#"{"x-gateway-key": "Basic Z3c6czNjcmV0"}"#,
+            }))
+            .json(json!({ "message": "Disk full" }))
+            .build();
+        let client = client::MockClient::new();
+
+        Component::handle_with_client(req, &client).unwrap();
+        let headers = &client.requests()[0].headers;
+        assert_eq!(headers["x-gateway-key"], "Basic Z3c6czNjcmV0");
+        assert_eq!(headers[http::header::CONTENT_TYPE], "application/json");
+    }
+
+    #[test]
+    fn test_settings_invalid_outbound_headers() {
+        let data = HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            (
+                "outbound_headers".to_string(),
+                r#"{"bad header": "x"}"#.to_string(),
+            ),
+        ]);
+        assert_eq!(
+            Settings::from_map(&data).unwrap_err().to_string(),
+            "Invalid header 'bad header' in outbound_headers setting"
+        );
+    }
+
+    #[test]
+    fn test_settings_forbidden_response_headers() {
+        let data = HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            (
+                "response_headers".to_string(),
+                r#"{"x-slack-req-id": "Connection"}"#.to_string(),
+            ),
+        ]);
+        assert_eq!(
+            Settings::from_map(&data).unwrap_err().to_string(),
+            "Header 'Connection' in response_headers setting can't be sent"
+        );
+    }
+
+    #[test]
+    fn test_settings_forbidden_outbound_headers() {
+        let data = HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            (
+                "outbound_headers".to_string(),
+                r#"{"Host": "hooks.slack.com"}"#.to_string(),
+            ),
+        ]);
+        assert_eq!(
+            Settings::from_map(&data).unwrap_err().to_string(),
+            "Header 'Host' in outbound_headers setting can't be sent"
+        );
+    }
+
+    #[test]
+    fn test_settings_source_webhook_requires_webhook_mode() {
+        let data = HashMap::from([
+            ("bot_token".to_string(), "xoxb-1".to_string()),
+            (
+                "sources".to_string(),
+                r#"{"sentry": {"webhook_url": "https://hooks.slack.com/services/sentry"}}"#
+                    .to_string(),
+            ),
+        ]);
+        assert_eq!(
+            Settings::from_map(&data).unwrap_err().to_string(),
+            "Invalid source 'sentry' in sources setting: webhook_url is not supported with bot tokens"
+        );
+    }
+
+    #[test]
+    fn test_run_options_shares_settings_with_handler() {
+        let req = testing::RequestFixture::post()
+            .settings(
+                json!({ "webhook_url": "http://example.com/webhook", "max_body_bytes": "64" }),
+            )
+            .build();
+        let (mut parts, body) = req.into_parts();
+
+        let options = Settings::run_options(&mut parts);
+        assert_eq!(options.max_body_bytes, Some(64));
+
+        // Later header changes are not parsed again
+        parts.headers.remove("x-edgee-component-settings");
+        let req = Request::from_parts(parts, body);
+        assert_eq!(Settings::from_req(&req).unwrap().max_body_bytes, 64);
+    }
+
+    #[test]
+    fn test_settings_max_concurrency() {
+        let data = HashMap::from([(
+            "webhook_url".to_string(),
+            "https://hooks.slack.com/services/test".to_string(),
+        )]);
+        assert_eq!(Settings::from_map(&data).unwrap().max_concurrency, 4);
+
+        let data = HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            ("max_concurrency".to_string(), "0".to_string()),
+        ]);
+        assert_eq!(
+            Settings::from_map(&data).unwrap_err().to_string(),
+            "max_concurrency setting must be greater than 0"
+        );
+    }
+
+    #[test]
+    fn test_settings_handler_timeout() {
+        let data = HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            ("handler_timeout_ms".to_string(), "2500".to_string()),
+        ]);
+        assert_eq!(
+            Settings::from_map(&data).unwrap().handler_timeout_ms,
+            Some(2500)
+        );
+
+        let data = HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            ("handler_timeout_ms".to_string(), "0".to_string()),
+        ]);
+        assert_eq!(
+            Settings::from_map(&data).unwrap_err().to_string(),
+            "handler_timeout_ms setting must be greater than 0"
+        );
+    }
+
+    #[test]
+    fn test_settings_oauth_requires_store() {
+        let data = HashMap::from([
+            ("bot_token".to_string(), "xoxb-test".to_string()),
+            ("oauth_client_id".to_string(), "123.456".to_string()),
+            (
+                "token_store_endpoint".to_string(),
+                "kv.example.com".to_string(),
+            ),
+        ]);
+        assert_eq!(
+            Settings::from_map(&data)
+                .unwrap_err()
+                .downcast_ref::<helpers::SettingsErrors>()
+                .unwrap()
+                .0,
+            vec![
+                "oauth_client_id setting requires oauth_client_secret and token_store_endpoint",
+                "token_store_endpoint setting must be an http(s) URL",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_settings_ack_callback_url() {
+        let data = HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            (
+                "ack_callback_url".to_string(),
+                "alerts.example.com".to_string(),
+            ),
+        ]);
+        assert_eq!(
+            Settings::from_map(&data)
+                .unwrap_err()
+                .downcast_ref::<helpers::SettingsErrors>()
+                .unwrap()
+                .0,
+            vec![
+                "ack_callback_url setting must be an http(s) URL",
+                "ack_callback_url setting requires signing_secret and token_store_endpoint",
+                "ack_callback_url setting is not supported with webhook_url",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_settings_escalation() {
+        let data = HashMap::from([
+            ("bot_token".to_string(), "xoxb-test".to_string()),
+            ("escalate_after_seconds".to_string(), "0".to_string()),
+        ]);
+        assert_eq!(
+            Settings::from_map(&data)
+                .unwrap_err()
+                .downcast_ref::<helpers::SettingsErrors>()
+                .unwrap()
+                .0,
+            vec![
+                "escalate_after_seconds setting must be greater than 0",
+                "escalate_after_seconds setting requires oncall_group, signing_secret and token_store_endpoint",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_settings_passthrough_with_bot_token() {
+        let data = HashMap::from([
+            ("bot_token".to_string(), "xoxb-test".to_string()),
+            ("passthrough".to_string(), "true".to_string()),
+        ]);
+        assert_eq!(
+            Settings::from_map(&data).unwrap_err().to_string(),
+            "passthrough setting is not supported with bot tokens"
+        );
+    }
+
+    #[test]
+    fn test_settings_passthrough_with_rewriting() {
+        let data = HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            ("passthrough".to_string(), "true".to_string()),
+            ("scrub_pii".to_string(), "true".to_string()),
+            ("footer".to_string(), "true".to_string()),
+        ]);
+        assert_eq!(
+            Settings::from_map(&data).unwrap_err().to_string(),
+            "passthrough setting forwards bodies untouched, it can't be combined with scrub_pii, footer"
+        );
+    }
+
+    #[test]
+    fn test_handle_json_request_strict_fixture() {
+        let req = testing::RequestFixture::post()
+            .settings(json!({ "webhook_url": "http://example.com/webhook", "strict": "true" }))
+            .json(json!({ "message": "Hello", "mesage": "typo" }))
+            .build();
+
+        let resp = Component::handle_json_request(req).unwrap();
+        testing::assert_error(
+            &resp,
+            http::StatusCode::BAD_REQUEST,
+            "Unknown field 'mesage' in request body",
+        );
+    }
+
+    // Compares against `src/snapshots/<name>.json`, rewriting it instead when
+    // UPDATE_SNAPSHOTS is set.
+    fn assert_snapshot(name: &str, actual: &str) {
+        let path = format!("{}/src/snapshots/{name}.json", env!("CARGO_MANIFEST_DIR"));
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::write(&path, format!("{actual}\n")).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path)
+            .unwrap_or_else(|_| panic!("Missing snapshot {path}, run with UPDATE_SNAPSHOTS=1"));
+        pretty_assertions::assert_eq!(expected.trim_end(), actual);
+    }
+
+    fn render_body(content_type: &str, data: impl Into<bytes::Bytes>) -> String {
+        let settings = notification_settings();
+        let req = testing::RequestFixture::post()
+            .body(content_type, data)
+            .unwrap()
+            .build();
+        let MessageBody(value, ..) = req.into_body().unwrap();
+        let request = NotificationRequest::from_value(value, false).unwrap();
+        render(&settings, request)
+            .unwrap()
+            .to_json_pretty()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_render_json() {
+        let rendered = render_body(
+            "application/json",
+            r#"{"message": "Deploy finished for jane@example.com"}"#,
+        );
+        assert_snapshot("render_json", &rendered);
+    }
+
+    #[test]
+    fn test_render_json_blocks() {
+        let rendered = render_body(
+            "application/json",
+            r#"{"message": "Deploy finished", "blocks": [{"type": "section", "text": {"type": "mrkdwn", "text": "Ping jane@example.com"}}]}"#,
+        );
+        assert_snapshot("render_json_blocks", &rendered);
+    }
+
+    #[test]
+    fn test_render_keeps_raw_blocks() {
+        let settings = Settings {
+            scrub_pii: false,
+            ..notification_settings()
+        };
+        let request: NotificationRequest = serde_json::from_str(
+            r#"{"message": "hi", "blocks": [ {"type":"divider"} ], "attachments": [{"color": "good"}]}"#,
+        )
+        .unwrap();
+        let payload = render(&settings, request).unwrap();
+        assert_eq!(
+            serde_json::to_string(&payload).unwrap(),
+            r#"{"text":"hi","blocks":[ {"type":"divider"} ],"attachments":[{"color": "good"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_render_format_dates() {
+        let settings = Settings {
+            format_dates: true,
+            ..notification_settings()
+        };
+        let request: NotificationRequest = serde_json::from_str(
+            r#"{"message": "Down since 2024-01-31T10:30:00Z", "attachments": [{"text": "Back at 2024-01-31T11:00:00Z", "title_link": "https://status.example.com/2024-01-31T11:00:00Z"}]}"#,
+        )
+        .unwrap();
+        let payload = render(&settings, request).unwrap();
+        // Scrubbing first, or the unix time would be masked as a phone number
+        assert_eq!(
+            payload.text,
+            "Down since <!date^1706697000^{date_short} {time}|2024-01-31T10:30:00Z>"
+        );
+        let attachments = payload.attachments.unwrap().to_value().unwrap();
+        assert_eq!(
+            attachments[0]["text"],
+            "Back at <!date^1706698800^{date_short} {time}|2024-01-31T11:00:00Z>"
+        );
+        assert_eq!(
+            attachments[0]["title_link"],
+            "https://status.example.com/2024-01-31T11:00:00Z"
+        );
+    }
+
+    #[test]
+    fn test_render_blocklist() {
+        let settings = Settings {
+            blocklist: vec!["darn".to_string()],
+            ..notification_settings()
+        };
+        let request: NotificationRequest = serde_json::from_str(
+            r#"{"message": "Darn printer", "author": {"name": "darn user"}, "attachments": [{"title": "darn", "title_link": "https://example.com/darn"}]}"#,
+        )
+        .unwrap();
+        let payload = serde_json::to_value(render(&settings, request).unwrap()).unwrap();
+        assert_eq!(payload["text"], "D*** printer");
+        assert_eq!(payload["blocks"][1]["elements"][0]["text"], "d*** user");
+        assert_eq!(
+            payload["attachments"],
+            serde_json::json!([{"title": "d***", "title_link": "https://example.com/darn"}])
+        );
+    }
+
+    #[test]
+    fn test_render_link_rewrite_base() {
+        let settings = Settings {
+            link_rewrite_base: Some("https://r.example.com/click".to_string()),
+            ..notification_settings()
+        };
+        let request: NotificationRequest = serde_json::from_str(
+            r#"{"message": "Build failed: https://ci.example.com/1", "event": {"title": "Build failed", "url": "https://ci.example.com/1"}}"#,
+        )
+        .unwrap();
+        let payload = serde_json::to_value(render(&settings, request).unwrap()).unwrap();
+        let redirect = "https://r.example.com/click?url=https%3A%2F%2Fci.example.com%2F1";
+        assert_eq!(
+            payload["text"],
+            format!("Build failed: <{redirect}|https://ci.example.com/1>")
+        );
+        assert_eq!(payload["blocks"][1]["elements"][0]["url"], redirect);
+    }
+
+    #[test]
+    fn test_render_normalize_text() {
+        let data = HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            ("normalize_text".to_string(), "strip".to_string()),
+            ("prefix".to_string(), "[{{env}}]".to_string()),
+        ]);
+        let settings = Settings::from_map(&data).unwrap();
+        let request: NotificationRequest = serde_json::from_str(
+            r#"{"message": "*Dis\u200bk full* on cafe\u0301", "env": "pro\u202ed"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            render(&settings, request).unwrap().text,
+            "[prod] *Disk full* on café"
+        );
+    }
+
+    #[test]
+    fn test_render_emojify() {
+        let data = HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            (
+                "emojify".to_string(),
+                r#"{"error": "🔴", "deploy": ":rocket:"}"#.to_string(),
+            ),
+        ]);
+        let settings = Settings::from_map(&data).unwrap();
+        let request = NotificationRequest {
+            message: Some("Deploy error :warning:".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            render(&settings, request).unwrap().text,
+            ":rocket: Deploy 🔴 error :warning:"
+        );
+    }
+
+    #[test]
+    fn test_render_table() {
+        let rendered = render_body(
+            "application/json",
+            r#"{"message": "Disk usage", "table": {"headers": ["host", "owner", "usage"], "rows": [["db-1", "jane@example.com", 97], ["web-1", "ops", 40]]}}"#,
+        );
+        assert_snapshot("render_table", &rendered);
+    }
+
+    #[test]
+    fn test_render_images() {
+        let rendered = render_body(
+            "application/json",
+            r#"{"message": "CPU spike", "images": [{"url": "https://grafana.example.com/render/cpu.png", "alt": "CPU graph"}, {"url": "https://grafana.example.com/render/mem.png"}]}"#,
+        );
+        assert_snapshot("render_images", &rendered);
+    }
+
+    #[test]
+    fn test_render_invalid_image() {
+        let request = NotificationRequest::from_value(
+            json!({ "message": "CPU spike", "images": [{ "url": "javascript:alert(1)" }] }),
+            true,
+        )
+        .unwrap();
+        let Err(err) = render(&notification_settings(), request) else {
+            panic!("image accepted");
+        };
+        let err = err.downcast_ref::<helpers::ApiError>().unwrap();
+        assert_eq!(err.status, http::StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_render_author() {
+        let rendered = render_body(
+            "application/json",
+            r#"{"message": "Deployed 1.2.0", "blocks": [{"type": "divider"}], "author": {"name": "Jane Doe", "icon_url": "https://avatars.example.com/jane.png"}}"#,
+        );
+        assert_snapshot("render_author", &rendered);
+    }
+
+    #[test]
+    fn test_render_blocks_without_message() {
+        let rendered = render_body(
+            "application/json",
+            r#"{"blocks": [{"type": "header", "text": {"type": "plain_text", "text": "Deploy finished"}}, {"type": "section", "text": {"type": "mrkdwn", "text": "Approved by jane@example.com"}}]}"#,
+        );
+        assert_snapshot("render_blocks_fallback", &rendered);
+    }
+
+    #[test]
+    fn test_render_rejects_blocks_over_limits() {
+        let request = NotificationRequest {
+            message: Some("x".repeat(3001)),
+            images: Some(vec![request::Image {
+                url: "https://grafana.example.com/cpu.png".to_string(),
+                alt: None,
+            }]),
+            ..Default::default()
+        };
+        let Err(err) = render(&notification_settings(), request) else {
+            panic!("blocks accepted");
+        };
+        let err = err.downcast_ref::<helpers::ApiError>().unwrap();
+        assert_eq!(err.status, http::StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[test]
+    fn test_render_event() {
+        let rendered = render_body(
+            "application/json",
+            r#"{"event": {"title": "Disk almost full", "description": "db-1 is at *97%*", "severity": "error", "source": "prometheus", "url": "https://grafana.example.com/d/disk", "fields": {"host": "db-1", "usage": 97}, "timestamp": "2024-01-31T10:30:00Z"}}"#,
+        );
+        assert_snapshot("render_event", &rendered);
+    }
+
+    #[test]
+    fn test_render_prefix_and_suffix() {
+        let settings = Settings {
+            prefix: Some("[{{env}}]".to_string()),
+            suffix: Some("({{fields.host}})".to_string()),
+            ..notification_settings()
+        };
+        let request = NotificationRequest::from_value(
+            json!({ "message": "Disk full", "env": "staging", "fields": { "host": "db-1" } }),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            render(&settings, request).unwrap().text,
+            "[staging] Disk full (db-1)"
+        );
+
+        let settings = Settings {
+            suffix: None,
+            ..settings
+        };
+        let request = NotificationRequest {
+            message: Some("Disk full".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(render(&settings, request).unwrap().text, "[] Disk full");
+    }
+
+    #[test]
+    fn test_render_defaults() {
+        let data = HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            (
+                "defaults".to_string(),
+                r#"{"username": "Deploy bot", "icon_emoji": ":rocket:", "unfurl_links": false}"#
+                    .to_string(),
+            ),
+        ]);
+        let settings = Settings::from_map(&data).unwrap();
+        let request = NotificationRequest::from_value(
+            json!({ "message": "Deployed", "icon_emoji": ":tada:" }),
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::to_value(render(&settings, request).unwrap()).unwrap(),
+            json!({
+                "text": "Deployed",
+                "username": "Deploy bot",
+                "icon_emoji": ":tada:",
+                "unfurl_links": false,
+            })
+        );
+
+        let data = HashMap::from([
+            ("bot_token".to_string(), "xoxb-1".to_string()),
+            ("defaults".to_string(), r#"{"chanel": "ops"}"#.to_string()),
+        ]);
+        assert!(Settings::from_map(&data).is_err());
+    }
+
+    #[test]
+    fn test_render_form() {
+        let rendered = render_body(
+            "application/x-www-form-urlencoded",
+            "message=Disk+full&severity=warning",
+        );
+        assert_snapshot("render_form", &rendered);
+    }
+
+    #[test]
+    fn test_render_yaml() {
+        let rendered = render_body("application/yaml", "message: Backup completed\n");
+        assert_snapshot("render_yaml", &rendered);
+    }
+
+    #[test]
+    fn test_render_text() {
+        let rendered = render_body("text/plain", "Build #42 failed\n");
+        assert_snapshot("render_text", &rendered);
+    }
+
+    #[test]
+    fn test_render_msgpack() {
+        let data = rmp_serde::to_vec_named(&json!({ "message": "Queue drained" })).unwrap();
+        let rendered = render_body("application/msgpack", data);
+        assert_snapshot("render_msgpack", &rendered);
+    }
+
+    #[test]
+    fn test_render_protobuf() {
+        let notification = proto::Notification {
+            message: "Certificate expires soon".to_string(),
+            ..Default::default()
+        };
+        let rendered = render_body(
+            "application/x-protobuf",
+            prost::Message::encode_to_vec(&notification),
+        );
+        assert_snapshot("render_protobuf", &rendered);
+    }
+
+    #[test]
+    fn test_render_multipart() {
+        let data = "--b\r\nContent-Disposition: form-data; name=\"message\"\r\n\r\nReport attached\r\n--b--\r\n";
+        let rendered = render_body("multipart/form-data; boundary=b", data);
+        assert_snapshot("render_multipart", &rendered);
+    }
+
+    #[test]
+    fn test_render_cloudevent() {
+        let rendered = render_body(
+            "application/cloudevents+json",
+            r#"{"specversion": "1.0", "id": "1", "type": "com.example.deploy", "source": "/ci"}"#,
+        );
+        assert_snapshot("render_cloudevent", &rendered);
+    }
+
+    #[test]
+    fn test_router_health() {
+        let client = client::MockClient::new();
+        let router = Component::router(&client);
+        let req = testing::RequestFixture::get("/health").build();
+
+        let resp = router.dispatch(req).unwrap();
+        testing::assert_json(&resp, http::StatusCode::OK, json!({ "ok": true }));
+        assert!(resp
+            .extensions()
+            .get::<helpers::etag::Cacheable>()
+            .is_some());
+        assert!(client.requests().is_empty());
+    }
+
+    #[test]
+    fn test_router_health_negotiates() {
+        use helpers::body::IntoBody;
+
+        let client = client::MockClient::new();
+        let req = testing::RequestFixture::get("/health")
+            .header("accept", "text/plain")
+            .build();
+
+        let resp = Component::router(&client).dispatch(req).unwrap();
+        assert_eq!(resp.into_body().into_body().unwrap(), "ok: true\n");
+    }
+
+    #[test]
+    fn test_run_options_negotiates_errors() {
+        let (mut parts, ()) = http::Request::builder()
+            .header("accept", "text/html")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let options = Settings::run_options(&mut parts);
+
+        let on_error = options.error_handler.unwrap();
+        let resp = on_error(
+            http::StatusCode::BAD_REQUEST,
+            &anyhow::anyhow!("Missing request body"),
+        );
+        assert_eq!(
+            resp.headers().get(http::header::CONTENT_TYPE).unwrap(),
+            "text/html; charset=utf-8"
+        );
+    }
+
+    #[test]
+    fn test_router_openapi() {
+        let client = client::MockClient::new();
+        let req = testing::RequestFixture::get("/openapi.json").build();
+
+        let resp = Component::router(&client).dispatch(req).unwrap();
+        let document = testing::assert_status(&resp, http::StatusCode::OK);
+        assert_eq!(document["openapi"], "3.1.0");
+        assert!(client.requests().is_empty());
+    }
+
+    #[test]
+    fn test_router_slack_install() {
+        let client = client::MockClient::new();
+        let req = testing::RequestFixture::get("/slack/install")
+            .settings(json!({
+                "bot_token": "xoxb-test",
+                "oauth_client_id": "123.456",
+                "oauth_client_secret": "shh",
+                "token_store_endpoint": "https://kv.example.com/slack",
+            }))
+            .build();
+
+        let resp = Component::router(&client).dispatch(req).unwrap();
+        let Reply::Redirect(redirect) = resp.body() else {
+            panic!("Expected a redirect");
+        };
+        assert_eq!(redirect.status, http::StatusCode::FOUND);
+        assert!(redirect
+            .location
+            .to_str()
+            .unwrap()
+            .starts_with("https://slack.com/oauth/v2/authorize?client_id=123.456"));
+        assert!(client.requests().is_empty());
+    }
+
+    #[test]
+    fn test_router_versioned_path() {
+        let client = client::MockClient::new();
+        let router = Component::router(&client);
+
+        let req = testing::RequestFixture::new(http::Method::POST, "/v1/edgee/slack")
+            .settings(json!({ "webhook_url": "http://example.com/webhook" }))
+            .json(json!({ "message": "Hello" }))
+            .build();
+        let resp = router.dispatch(req).unwrap();
+        testing::assert_json(&resp, http::StatusCode::OK, json!({ "ok": true }));
+
+        let req = testing::RequestFixture::new(http::Method::POST, "/v2/edgee/slack")
+            .settings(json!({ "webhook_url": "http://example.com/webhook" }))
+            .json(json!({ "message": "Hello" }))
+            .build();
+        let err = router.dispatch(req).unwrap_err();
+        let err = err.downcast_ref::<helpers::ApiError>().unwrap();
+        assert_eq!(err.status, http::StatusCode::NOT_FOUND);
+        assert_eq!(err.message, "Unsupported API version v2");
+        assert_eq!(client.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_settings_default_api_version() {
+        let data = HashMap::from([(
+            "webhook_url".to_string(),
+            "https://hooks.slack.com/services/test".to_string(),
+        )]);
+        assert_eq!(
+            Settings::from_map(&data).unwrap().default_api_version,
+            ApiVersion::V1
+        );
+
+        let data = HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            ("default_api_version".to_string(), "v3".to_string()),
+        ]);
+        assert_eq!(
+            Settings::from_map(&data).unwrap_err().to_string(),
+            "Invalid default_api_version setting: unknown variant `v3`, expected `v1`"
+        );
+    }
+
+    #[test]
+    fn test_router_message_on_any_path() {
+        let client = client::MockClient::new();
+        let router = Component::router(&client);
+        assert_eq!(
+            router.allowed_methods("/edgee/slack"),
+            vec![http::Method::POST, http::Method::GET, http::Method::HEAD]
+        );
+
+        let req = testing::RequestFixture::new(http::Method::POST, "/edgee/slack")
+            .settings(json!({ "webhook_url": "http://example.com/webhook" }))
+            .json(json!({ "message": "Hello" }))
+            .build();
+        let resp = router.dispatch(req).unwrap();
+        testing::assert_json(&resp, http::StatusCode::OK, json!({ "ok": true }));
+        assert_eq!(client.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_router_test() {
+        let client = client::MockClient::new();
+        let req = testing::RequestFixture::new(http::Method::POST, "/test")
+            .header("Authorization", "Bearer s3cret")
+            .settings(json!({
+                "webhook_url": "https://hooks.slack.com/services/T0/B0/xyz",
+                "auth_token": "s3cret",
+            }))
+            .build();
+
+        let resp = Component::router(&client).dispatch(req).unwrap();
+        testing::assert_json(
+            &resp,
+            http::StatusCode::OK,
+            json!({ "ok": true, "destination": "https://hooks.slack.com/services/T0/B0/****" }),
+        );
+        assert_eq!(client.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_handle_with_client_source_header() {
+        let req = testing::RequestFixture::post()
+            .header("X-Source", "datadog")
+            .settings(json!({ "webhook_url": "https://hooks.slack.com/services/test" }))
+            .json(
+                json!({ "title": "[Recovered] CPU high on db-1", "alert_transition": "Recovered" }),
+            )
+            .build();
+        let client = client::MockClient::new();
+
+        Component::handle_with_client(req, &client).unwrap();
+        let posted: serde_json::Value = serde_json::from_slice(&client.requests()[0].body).unwrap();
+        assert_eq!(posted["text"], "[Recovered] CPU high on db-1");
+        assert_eq!(posted["attachments"][0]["color"], "good");
+    }
+
+    #[test]
+    fn test_router_transform_unknown_source() {
+        let client = client::MockClient::new();
+        let req = testing::RequestFixture::new(http::Method::POST, "/transform/nagios")
+            .settings(json!({ "webhook_url": "https://hooks.slack.com/services/test" }))
+            .json(json!({ "message": "Disk full" }))
+            .build();
+
+        let err = Component::router(&client).dispatch(req).unwrap_err();
+        let err = err.downcast_ref::<helpers::ApiError>().unwrap();
+        assert_eq!(err.status, http::StatusCode::NOT_FOUND);
+        assert_eq!(err.message, "Unknown source 'nagios'");
+        assert!(client.requests().is_empty());
+    }
+
+    #[test]
+    fn test_router_transform_configured_source() {
+        let client = client::MockClient::new();
+        let sources = json!({
+            "sentry": {
+                "template": "{{project}}: {{title}}",
+                "webhook_url": "https://hooks.slack.com/services/sentry",
+            },
+        });
+        let req = testing::RequestFixture::new(http::Method::POST, "/transform/sentry")
+            .settings(json!({
+                "webhook_url": "https://hooks.slack.com/services/test",
+                "sources": sources.to_string(),
+            }))
+            .json(json!({ "project": "api", "title": "Panic in handler" }))
+            .build();
+
+        let res = Component::router(&client).dispatch(req).unwrap();
+        testing::assert_json(&res, http::StatusCode::OK, json!({ "ok": true }));
+        let requests = client.requests();
+        assert_eq!(requests[0].url, "https://hooks.slack.com/services/sentry");
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&requests[0].body).unwrap(),
+            json!({ "text": "api: Panic in handler" })
+        );
+    }
+
+    #[test]
+    fn test_router_filtered() {
+        let client = client::MockClient::new();
+        let filters = json!({ "exclude": [{ "message": "(?i)heartbeat" }] });
+        let req = testing::RequestFixture::post()
+            .settings(json!({
+                "webhook_url": "https://hooks.slack.com/services/test",
+                "filters": filters.to_string(),
+            }))
+            .json(json!({ "message": "Heartbeat OK" }))
+            .build();
+
+        let res = Component::router(&client).dispatch(req).unwrap();
+        testing::assert_json(
+            &res,
+            http::StatusCode::OK,
+            json!({ "ok": true, "filtered": true }),
+        );
+        assert!(client.requests().is_empty());
+    }
+
+    #[test]
+    fn test_router_debug() {
+        let client = client::MockClient::new();
+        let req = testing::RequestFixture::post()
+            .header("X-Debug", "true")
+            .header("Authorization", "Bearer s3cret")
+            .settings(json!({
+                "webhook_url": "https://hooks.slack.com/services/T0/B0/secret",
+                "auth_token": "s3cret",
+            }))
+            .json(json!({ "message": "Disk full" }))
+            .build();
+
+        let res = Component::router(&client).dispatch(req).unwrap();
+        let body = testing::assert_status(&res, http::StatusCode::OK);
+        assert_eq!(body["ok"], true);
+        assert_eq!(
+            body["debug"]["destination"],
+            "https://hooks.slack.com/services/T0/B0/****"
+        );
+        assert_eq!(body["debug"]["payload"], json!({ "text": "Disk full" }));
+        assert!(body["debug"]["timings"]["slack_ms"].is_number());
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&client.requests()[0].body).unwrap(),
+            json!({ "text": "Disk full" })
+        );
+    }
+
+    #[test]
+    fn test_router_response_headers() {
+        let client = client::MockClient::new()
+            .respond_with_headers(200, &[("x-slack-req-id", "r-123"), ("x-other", "x")]);
+        let req = testing::RequestFixture::post()
+            .settings(json!({
+                "webhook_url": "https://hooks.slack.com/services/test",
+                "response_headers": r#"{"x-slack-req-id": "x-slack-request-id"}"#,
+            }))
+            .json(json!({ "message": "Disk full" }))
+            .build();
+
+        let res = Component::router(&client).dispatch(req).unwrap();
+        assert_eq!(res.headers()["x-slack-request-id"], "r-123");
+        assert!(!res.headers().contains_key("x-other"));
+        assert!(!res.headers().contains_key("x-slack-req-id"));
+    }
+
+    #[test]
+    fn test_router_flush() {
+        let client = client::MockClient::new().respond_with(404);
+        let req = testing::RequestFixture::new(http::Method::POST, "/flush")
+            .header("Authorization", "Bearer s3cret")
+            .settings(json!({
+                "bot_token": "xoxb-test",
+                "token_store_endpoint": "https://kv.example.com/slack",
+                "auth_token": "s3cret",
+                "digest_severities": "info",
+            }))
+            .build();
+
+        let resp = Component::router(&client).dispatch(req).unwrap();
+        testing::assert_json(
+            &resp,
+            http::StatusCode::OK,
+            json!({ "ok": true, "flushed": 0, "pending": 0 }),
+        );
+        assert_eq!(client.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_router_echoes_request_id() {
+        let client = client::MockClient::new();
+        let req = testing::RequestFixture::get("/health")
+            .header("x-request-id", "req-42")
+            .build();
+
+        let resp = Component::router(&client).dispatch(req).unwrap();
+        assert_eq!(resp.headers().get("x-request-id").unwrap(), "req-42");
+    }
+
+    #[test]
+    fn test_settings_reports_every_problem() {
+        let mut headers = http::header::HeaderMap::new();
+        headers.insert(
+            "x-edgee-component-settings",
+            HeaderValue::from_static(
+                r#"{"webhook_url": "hooks.slack.com", "max_body_bytes": "0", "cors_allowed_methods": "POST,BAD METHOD", "query_trigger": "true"}"#,
+            ),
+        );
+
+        let err = Settings::new(&headers).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<helpers::SettingsErrors>().unwrap().0,
+            vec![
+                "webhook_url setting must be an http(s) URL",
+                "max_body_bytes setting must be greater than 0",
+                "Invalid method 'BAD METHOD' in cors_allowed_methods setting",
+                "query_trigger setting requires an auth_token",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_handle_json_request_settings_errors() {
+        let req = testing::RequestFixture::post()
+            .settings(json!({ "max_body_bytes": "lots" }))
+            .json(json!({ "message": "Hello" }))
+            .build();
+
+        let err = Component::handle_json_request(req).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid max_body_bytes setting: expected usize, got \"lots\"; Missing webhook_url setting"
+        );
+    }
+
+    #[test]
+    fn test_handle_notification_secret_webhook_url() {
+        let data = HashMap::from([
+            ("webhook_url".to_string(), "secret://slack-prod".to_string()),
+            (
+                "secrets_endpoint".to_string(),
+                "https://secrets.example.com".to_string(),
+            ),
+        ]);
+        let settings = Settings::from_map(&data).unwrap();
+        let client = client::MockClient::new()
+            .respond_with_body(200, r#"{"value": "https://hooks.slack.com/services/prod"}"#);
+        let request = NotificationRequest {
+            message: Some("hi".to_string()),
+            ..Default::default()
+        };
+
+        let response = handle_notification(&settings, request, &client).unwrap();
+        assert_eq!(response.status, http::StatusCode::OK);
+
+        let requests = client.requests();
+        assert_eq!(requests[0].url, "https://secrets.example.com/slack-prod");
+        assert_eq!(requests[1].url, "https://hooks.slack.com/services/prod");
+    }
+
+    #[test]
+    fn test_settings_secret_requires_endpoint() {
+        let data = HashMap::from([("webhook_url".to_string(), "secret://slack-prod".to_string())]);
+        assert_eq!(
+            Settings::from_map(&data).unwrap_err().to_string(),
+            "webhook_url setting references a secret but no secrets_endpoint is set"
+        );
+    }
+
+    fn bot_settings() -> Settings {
+        let data = HashMap::from([
+            ("bot_token".to_string(), "xoxb-1".to_string()),
+            ("default_channel".to_string(), "#general".to_string()),
+        ]);
+        Settings::from_map(&data).unwrap()
+    }
+
+    #[test]
+    fn test_handle_notification_multiple_channels() {
+        let client = client::MockClient::new()
+            .respond_with_body(200, r#"{"ok": true, "channel": "C1", "ts": "1.1"}"#)
+            .respond_with_body(200, r#"{"ok": true, "channel": "C2", "ts": "2.2"}"#);
+        let request = NotificationRequest {
+            message: Some("Disk full".to_string()),
+            channels: Some(vec!["#alerts".to_string(), "#oncall".to_string()]),
+            ..Default::default()
+        };
+
+        let response = handle_notification(&bot_settings(), request, &client).unwrap();
+        assert_eq!(response.status, http::StatusCode::OK);
+        assert_eq!(
+            response.body,
+            json!({
+                "ok": true,
+                "messages": [
+                    { "channel": "#alerts", "channel_id": "C1", "ts": "1.1" },
+                    { "channel": "#oncall", "channel_id": "C2", "ts": "2.2" },
+                ],
+            })
+        );
+
+        let channels: Vec<_> = client
+            .requests()
+            .iter()
+            .map(|request| {
+                serde_json::from_slice::<serde_json::Value>(&request.body).unwrap()["channel"]
+                    .clone()
+            })
+            .collect();
+        assert_eq!(channels, vec![json!("#alerts"), json!("#oncall")]);
+    }
+
+    #[test]
+    fn test_handle_notification_channel_failure() {
+        let client = client::MockClient::new()
+            .respond_with_body(200, r#"{"ok": false, "error": "channel_not_found"}"#);
+        let request = NotificationRequest {
+            message: Some("Disk full".to_string()),
+            ..Default::default()
+        };
+
+        let response = handle_notification(&bot_settings(), request, &client).unwrap();
+        assert_eq!(response.status, http::StatusCode::BAD_GATEWAY);
+        assert_eq!(
+            response.body,
+            json!({
+                "ok": false,
+                "messages": [{ "channel": "#general", "error": "channel_not_found" }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_handle_notification_degrades_refused_blocks() {
+        let settings = Settings {
+            degrade_to_text: true,
+            ..bot_settings()
+        };
+        let request = NotificationRequest::from_value(
+            json!({ "message": "Disk full", "channels": ["#ops", "#alerts"], "blocks": [{ "type": "bogus" }] }),
+            true,
+        )
+        .unwrap();
+        let client = client::MockClient::new()
+            .respond_with_body(200, r#"{"ok": true, "channel": "C1", "ts": "1.1"}"#)
+            .respond_with_body(200, r#"{"ok": false, "error": "invalid_blocks"}"#)
+            .respond_with_body(200, r#"{"ok": true, "channel": "C2", "ts": "2.2"}"#);
+
+        let response = handle_notification(&settings, request, &client).unwrap();
+        assert_eq!(response.status, http::StatusCode::OK);
+        assert_eq!(response.body["messages"][1]["ts"], "2.2");
+        let retry: serde_json::Value = serde_json::from_slice(&client.requests()[2].body).unwrap();
+        assert_eq!(retry, json!({ "channel": "#alerts", "text": "Disk full" }));
+    }
+
+    #[test]
+    fn test_handle_notification_metadata() {
+        let client = client::MockClient::new()
+            .respond_with_body(200, r#"{"ok": true, "channel": "C1", "ts": "1.1"}"#);
+        let request = NotificationRequest::from_value(
+            json!({
+                "message": "Deployed",
+                "metadata": { "event_type": "deploy_finished", "event_payload": { "version": "1.2.0" } },
+            }),
+            true,
+        )
+        .unwrap();
+
+        handle_notification(&bot_settings(), request, &client).unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&client.requests()[0].body).unwrap();
+        assert_eq!(
+            body["metadata"],
+            json!({ "event_type": "deploy_finished", "event_payload": { "version": "1.2.0" } })
+        );
+    }
+
+    #[test]
+    fn test_handle_notification_correlated_reply() {
+        let data = HashMap::from([
+            ("bot_token".to_string(), "xoxb-1".to_string()),
+            (
+                "token_store_endpoint".to_string(),
+                "https://kv.example.com/slack".to_string(),
+            ),
+            ("broadcast_severities".to_string(), "error".to_string()),
+        ]);
+        let settings = Settings::from_map(&data).unwrap();
+        let client = client::MockClient::new()
+            .respond_with_body(200, r#"{"value": "1.1"}"#)
+            .respond_with_body(200, r#"{"ok": true, "channel": "C1", "ts": "2.2"}"#);
+        let request = NotificationRequest {
+            message: Some("Still failing".to_string()),
+            severity: Some("error".to_string()),
+            channel: Some("#alerts".to_string()),
+            correlation_id: Some("deploy-42".to_string()),
+            ..Default::default()
+        };
+
+        handle_notification(&settings, request, &client).unwrap();
+        let requests = client.requests();
+        assert_eq!(requests.len(), 2);
+        let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+        assert_eq!(body["thread_ts"], "1.1");
+        assert_eq!(body["reply_broadcast"], true);
+    }
+
+    #[test]
+    fn test_handle_notification_canvas() {
+        let data = HashMap::from([
+            ("bot_token".to_string(), "xoxb-1".to_string()),
+            ("default_channel".to_string(), "#general".to_string()),
+            ("canvas_id".to_string(), "F1".to_string()),
+        ]);
+        let settings = Settings::from_map(&data).unwrap();
+        let client = client::MockClient::new()
+            .respond_with_body(200, r#"{"ok": true, "channel": "C1", "ts": "1.1"}"#)
+            .respond_with_body(200, r#"{"ok": false, "error": "canvas_not_found"}"#);
+        let request = NotificationRequest {
+            message: Some("Disk full".to_string()),
+            ..Default::default()
+        };
+
+        let response = handle_notification(&settings, request.clone(), &client).unwrap();
+        assert_eq!(response.status, http::StatusCode::BAD_GATEWAY);
+        assert_eq!(
+            response.body["canvas_error"],
+            "Slack API canvases.edit failed: canvas_not_found"
+        );
+
+        let mut settings = settings;
+        settings.canvas_mode = CanvasMode::Instead;
+        let client = client::MockClient::new().respond_with_body(200, r#"{"ok": true}"#);
+        let response = handle_notification(&settings, request, &client).unwrap();
+        assert_eq!(response.body, json!({ "ok": true, "canvas_id": "F1" }));
+        assert_eq!(
+            client.requests()[0].url,
+            "https://slack.com/api/canvases.edit"
+        );
+    }
+
+    #[test]
+    fn test_handle_notification_bookmark() {
+        let client = client::MockClient::new()
+            .respond_with_body(200, r#"{"ok": true, "channel": "C1", "ts": "1.1"}"#)
+            .respond_with_body(200, r#"{"ok": false, "error": "not_allowed"}"#);
+        let request = NotificationRequest::from_value(
+            json!({
+                "message": "DB down",
+                "bookmark": { "title": "Runbook", "link": "https://wiki.example.com/db" },
+            }),
+            true,
+        )
+        .unwrap();
+
+        let response = handle_notification(&bot_settings(), request, &client).unwrap();
+        assert_eq!(response.status, http::StatusCode::OK);
+        assert_eq!(
+            response.body["warnings"],
+            json!(["Failed to bookmark in #general: Slack API bookmarks.add failed: not_allowed"])
+        );
+        let requests = client.requests();
+        assert_eq!(requests[1].url, "https://slack.com/api/bookmarks.add");
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&requests[1].body).unwrap(),
+            json!({ "channel_id": "C1", "title": "Runbook", "type": "link", "link": "https://wiki.example.com/db" })
+        );
+    }
+
+    #[test]
+    fn test_handle_notification_pin() {
+        let data = HashMap::from([
+            ("bot_token".to_string(), "xoxb-1".to_string()),
+            ("default_channel".to_string(), "#general".to_string()),
+            ("pin_severities".to_string(), "critical".to_string()),
+        ]);
+        let settings = Settings::from_map(&data).unwrap();
+        let request = NotificationRequest {
+            message: Some("DB down".to_string()),
+            severity: Some("critical".to_string()),
+            ..Default::default()
+        };
+        let client = client::MockClient::new()
+            .respond_with_body(200, r#"{"ok": true, "channel": "C1", "ts": "1.1"}"#)
+            .respond_with_body(200, r#"{"ok": true}"#);
+
+        let response = handle_notification(&settings, request.clone(), &client).unwrap();
+        assert_eq!(response.body.get("warnings"), None);
+        let requests = client.requests();
+        assert_eq!(requests[1].url, "https://slack.com/api/pins.add");
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&requests[1].body).unwrap(),
+            json!({ "channel": "C1", "timestamp": "1.1" })
+        );
+
+        // The request has the last word
+        let request = NotificationRequest {
+            pin: Some(false),
+            ..request
+        };
+        let client = client::MockClient::new()
+            .respond_with_body(200, r#"{"ok": true, "channel": "C1", "ts": "1.1"}"#);
+        handle_notification(&settings, request, &client).unwrap();
+        assert_eq!(client.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_handle_notification_uploads() {
+        let client = client::MockClient::new()
+            .respond_with_body(200, r#"{"ok": true, "channel": "C1", "ts": "1.1"}"#)
+            .respond_with_body(200, r#"{"ok": false, "error": "missing_scope"}"#);
+        let request = NotificationRequest {
+            message: Some("Invoice 42".to_string()),
+            uploads: vec![request::Upload {
+                filename: "invoice.pdf".to_string(),
+                data: b"%PDF".to_vec(),
+            }],
+            ..Default::default()
+        };
+
+        let response = handle_notification(&bot_settings(), request, &client).unwrap();
+        assert_eq!(response.status, http::StatusCode::OK);
+        assert_eq!(
+            response.body["warnings"],
+            json!(["Failed to upload invoice.pdf in #general: Slack API files.getUploadURLExternal failed: missing_scope"])
+        );
+        assert_eq!(
+            client.requests()[1].url,
+            "https://slack.com/api/files.getUploadURLExternal"
+        );
+    }
+
+    #[test]
+    fn test_handle_multipart_uploads_files() {
+        let req = testing::RequestFixture::post()
+            .settings(json!({ "bot_token": "xoxb-1", "default_channel": "#general" }))
+            .body(
+                "multipart/form-data; boundary=b",
+                "--b\r\nContent-Disposition: form-data; name=\"message\"\r\n\r\nbuild failed\r\n\
+                 --b\r\nContent-Disposition: form-data; name=\"log\"; filename=\"build.log\"\r\n\
+                 Content-Type: text/plain\r\n\r\nerror\r\n--b--\r\n",
+            )
+            .unwrap()
+            .build();
+        let client = client::MockClient::new()
+            .respond_with_body(200, r#"{"ok": true, "channel": "C1", "ts": "1.1"}"#)
+            .respond_with_body(200, r#"{"ok": false, "error": "missing_scope"}"#);
+
+        let response = Component::handle_with_client(req, &client).unwrap();
+        let body = testing::assert_status(&response, http::StatusCode::OK);
+        assert_eq!(
+            body["warnings"],
+            json!(["Failed to upload build.log in #general: Slack API files.getUploadURLExternal failed: missing_scope"])
+        );
+    }
+
+    #[test]
+    fn test_handle_notification_invalid_bookmark() {
+        let client = client::MockClient::new();
+        let request = NotificationRequest::from_value(
+            json!({ "message": "DB down", "bookmark": { "title": "Runbook", "link": "wiki" } }),
+            true,
+        )
+        .unwrap();
+
+        let err = handle_notification(&bot_settings(), request, &client).unwrap_err();
+        let err = err.downcast_ref::<helpers::ApiError>().unwrap();
+        assert_eq!(err.status, http::StatusCode::BAD_REQUEST);
+        assert!(client.requests().is_empty());
+    }
+
+    #[test]
+    fn test_handle_notification_missing_channel() {
+        let data = HashMap::from([("bot_token".to_string(), "xoxb-1".to_string())]);
+        let settings = Settings::from_map(&data).unwrap();
+        let request = NotificationRequest {
+            message: Some("Disk full".to_string()),
+            ..Default::default()
+        };
+
+        let response = handle_notification(&settings, request, &client::MockClient::new()).unwrap();
+        assert_eq!(response.status, http::StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_handle_notification_defaults_channel() {
+        let data = HashMap::from([
+            ("bot_token".to_string(), "xoxb-1".to_string()),
+            (
+                "defaults".to_string(),
+                r#"{"channel": "C0123"}"#.to_string(),
+            ),
+        ]);
+        let settings = Settings::from_map(&data).unwrap();
+        let request = NotificationRequest {
+            message: Some("Disk full".to_string()),
+            ..Default::default()
+        };
+        let client = client::MockClient::new()
+            .respond_with_body(200, r#"{"ok": true, "channel": "C0123", "ts": "1.1"}"#);
+
+        handle_notification(&settings, request, &client).unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&client.requests()[0].body).unwrap();
+        assert_eq!(body["channel"], "C0123");
+    }
+
+    #[test]
+    fn test_handle_notification_token_store() {
+        let data = HashMap::from([
+            (
+                "token_store_endpoint".to_string(),
+                "https://kv.example.com/slack".to_string(),
+            ),
+            ("default_team_id".to_string(), "T1".to_string()),
+            ("default_channel".to_string(), "#general".to_string()),
+        ]);
+        let settings = Settings::from_map(&data).unwrap();
+        let client = client::MockClient::new()
+            .respond_with_body(200, r#"{"value": "xoxb-t2"}"#)
+            .respond_with_body(200, r#"{"ok": true, "channel": "C1", "ts": "1.1"}"#);
+        let request = NotificationRequest {
+            message: Some("Disk full".to_string()),
+            team_id: Some("T2".to_string()),
+            ..Default::default()
+        };
+
+        let response = handle_notification(&settings, request, &client).unwrap();
+        assert_eq!(response.status, http::StatusCode::OK);
+
+        let requests = client.requests();
+        assert_eq!(requests[0].url, "https://kv.example.com/slack/T2");
+        assert_eq!(
+            requests[1]
+                .headers
+                .get(http::header::AUTHORIZATION)
+                .unwrap(),
+            "Bearer xoxb-t2"
+        );
+    }
+
+    #[test]
+    fn test_handle_notification_token_store_missing_team() {
+        let data = HashMap::from([(
+            "token_store_endpoint".to_string(),
+            "https://kv.example.com/slack".to_string(),
+        )]);
+        let settings = Settings::from_map(&data).unwrap();
+        let request = NotificationRequest {
+            message: Some("Disk full".to_string()),
+            ..Default::default()
+        };
+
+        let err = handle_notification(&settings, request, &client::MockClient::new()).unwrap_err();
+        assert_eq!(err.to_string(), "Missing 'team_id' field in request body");
+    }
+
+    #[test]
+    fn test_settings_webhook_url_and_bot_token_exclusive() {
+        let data = HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            ("bot_token".to_string(), "xoxb-1".to_string()),
+        ]);
+        assert_eq!(
+            Settings::from_map(&data).unwrap_err().to_string(),
+            "webhook_url and bot_token settings are mutually exclusive"
+        );
+    }
+
+    #[test]
+    fn test_settings_digest_requires_auth_token_and_store() {
+        let data = HashMap::from([
+            ("bot_token".to_string(), "xoxb-1".to_string()),
+            ("digest_severities".to_string(), "info".to_string()),
+        ]);
+        assert_eq!(
+            Settings::from_map(&data).unwrap_err().to_string(),
+            "digest_severities setting requires auth_token and token_store_endpoint"
+        );
+    }
 }
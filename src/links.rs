@@ -0,0 +1,139 @@
+use regex::{Captures, Regex};
+use serde_json::Value;
+use std::sync::LazyLock;
+
+// Link rewriting
+//
+// With `link_rewrite_base` set, links go through that redirect endpoint,
+// the original URL being passed in its `url` query parameter, so teams can
+// count the clicks on alert links. In the message, Slack links like
+// `<https://example.com|label>` and bare URLs are rewritten, bare URLs
+// being shown as written. In blocks and attachments, Slack links and the
+// `url` and `*_link` fields like `title_link` are, images being fetched by
+// Slack itself.
+
+static LINK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"<(https?://[^|>\s]+)(?:\|([^>]*))?>|https?://[^\s<>|]+").unwrap()
+});
+
+// Punctuation ending a sentence rather than the URL
+const TRAILING: &[char] = &['.', ',', ';', ':', '!', '?', ')', '\'', '"'];
+
+/// `url` through the redirect endpoint at `base`, unless it already goes
+/// through it.
+pub fn rewrite(base: &str, url: &str) -> String {
+    if url.starts_with(base) {
+        return url.to_string();
+    }
+    let separator = match base.contains('?') {
+        true if base.ends_with(['?', '&']) => "",
+        true => "&",
+        false => "?",
+    };
+    let query = serde_urlencoded::to_string([("url", url)]).unwrap_or_default();
+    format!("{base}{separator}{query}")
+}
+
+/// Rewrites the Slack links of `text`, and its bare URLs when `bare` is set.
+fn rewrite_links(base: &str, text: &str, bare: bool) -> String {
+    LINK.replace_all(text, |caps: &Captures| match caps.get(1) {
+        Some(url) => {
+            let label = caps.get(2).map_or(url.as_str(), |label| label.as_str());
+            format!("<{}|{label}>", rewrite(base, url.as_str()))
+        }
+        None if bare => {
+            let url = caps[0].trim_end_matches(TRAILING);
+            let trailing = &caps[0][url.len()..];
+            format!("<{}|{url}>{trailing}", rewrite(base, url))
+        }
+        None => caps[0].to_string(),
+    })
+    .into_owned()
+}
+
+/// Rewrites the links and bare URLs of a mrkdwn `text`.
+pub fn rewrite_text(base: &str, text: &str) -> String {
+    rewrite_links(base, text, true)
+}
+
+/// Rewrites the Slack links and the link fields of blocks or attachments.
+pub fn rewrite_json(base: &str, value: &mut Value) {
+    match value {
+        Value::String(text) => *text = rewrite_links(base, text, false),
+        Value::Array(items) => items.iter_mut().for_each(|item| rewrite_json(base, item)),
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match (key.as_str(), &*value) {
+                    ("url", Value::String(url)) => *value = rewrite(base, url).into(),
+                    (key, Value::String(url)) if key.ends_with("_link") => {
+                        *value = rewrite(base, url).into()
+                    }
+                    // Images and icons are fetched by Slack
+                    (key, _) if key.ends_with("url") || key.ends_with("icon") => {}
+                    _ => rewrite_json(base, value),
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const BASE: &str = "https://r.example.com/click";
+
+    #[test]
+    fn test_rewrite() {
+        assert_eq!(
+            rewrite(BASE, "https://ci.example.com/runs?id=1&log=true"),
+            "https://r.example.com/click?url=https%3A%2F%2Fci.example.com%2Fruns%3Fid%3D1%26log%3Dtrue"
+        );
+        assert_eq!(
+            rewrite("https://r.example.com/?src=slack", "https://a.example.com"),
+            "https://r.example.com/?src=slack&url=https%3A%2F%2Fa.example.com"
+        );
+        let rewritten = rewrite(BASE, "https://a.example.com");
+        assert_eq!(rewrite(BASE, &rewritten), rewritten);
+    }
+
+    #[test]
+    fn test_rewrite_text() {
+        assert_eq!(
+            rewrite_text(
+                BASE,
+                "Failed (https://ci.example.com/1). See <https://ci.example.com/2|logs>, <@U123>"
+            ),
+            "Failed (<https://r.example.com/click?url=https%3A%2F%2Fci.example.com%2F1|https://ci.example.com/1>). \
+             See <https://r.example.com/click?url=https%3A%2F%2Fci.example.com%2F2|logs>, <@U123>"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_json() {
+        let mut value = json!([
+            {
+                "title": "Build",
+                "title_link": "https://ci.example.com/1",
+                "text": "See <https://ci.example.com/2> or https://ci.example.com/3",
+                "image_url": "https://ci.example.com/graph.png",
+            },
+            { "type": "actions", "elements": [{ "type": "button", "url": "https://ci.example.com/4" }] },
+        ]);
+        rewrite_json(BASE, &mut value);
+        assert_eq!(
+            value,
+            json!([
+                {
+                    "title": "Build",
+                    "title_link": "https://r.example.com/click?url=https%3A%2F%2Fci.example.com%2F1",
+                    "text": "See <https://r.example.com/click?url=https%3A%2F%2Fci.example.com%2F2|https://ci.example.com/2> or https://ci.example.com/3",
+                    "image_url": "https://ci.example.com/graph.png",
+                },
+                { "type": "actions", "elements": [{ "type": "button", "url": "https://r.example.com/click?url=https%3A%2F%2Fci.example.com%2F4" }] },
+            ])
+        );
+    }
+}
@@ -0,0 +1,46 @@
+//! Native entry point used by the `local-dev` binary, so settings, parsing
+//! and rendering can be iterated on without building the wasm component.
+
+use bytes::Bytes;
+
+use crate::client::{ClientResponse, OutboundClient};
+use crate::{helpers, Component, Settings};
+
+/// Runs a request through the same pipeline as the component.
+pub fn handle(req: http::Request<Bytes>, client: &dyn OutboundClient) -> http::Response<Bytes> {
+    let (mut parts, data) = req.into_parts();
+    let options = Settings::run_options(&mut parts);
+    let router = Component::router(client);
+    let allowed_methods = router.allowed_methods(parts.uri.path());
+
+    helpers::handle_bytes(
+        http::Request::from_parts(parts, data),
+        &allowed_methods,
+        &options,
+        |req| router.dispatch(req),
+    )
+}
+
+/// Prints outgoing requests instead of sending them.
+pub struct StdoutClient;
+
+impl OutboundClient for StdoutClient {
+    fn send(
+        &self,
+        method: http::Method,
+        url: &str,
+        _headers: &http::HeaderMap,
+        body: Vec<u8>,
+    ) -> anyhow::Result<ClientResponse> {
+        println!("{method} {url}");
+        if !body.is_empty() {
+            let payload: serde_json::Value = serde_json::from_slice(&body)?;
+            println!("{}", serde_json::to_string_pretty(&payload)?);
+        }
+
+        Ok(ClientResponse {
+            status: 200,
+            ..Default::default()
+        })
+    }
+}
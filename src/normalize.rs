@@ -0,0 +1,163 @@
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::request::NotificationRequest;
+
+// Text normalization
+//
+// Text pasted from documents and chats often carries decomposed accents,
+// zero-width spaces and bidirectional overrides: two alerts showing the
+// same words may then differ, filters miss them and mrkdwn like `*bold*`
+// stops applying. With `normalize_text` set to `nfc`, request text is
+// composed to NFC before the message is built, `strip` also removing
+// control characters other than newlines and tabs, and invisible format
+// characters. Zero-width joiners are kept inside emoji sequences like 👩‍💻.
+
+/// How request text is normalized.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TextNormalization {
+    #[default]
+    Off,
+    Nfc,
+    Strip,
+}
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+// Zero-width spaces and non-joiners, direction marks, embeddings, overrides
+// and isolates, invisible operators and byte order marks
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200B}'
+            | '\u{200C}'
+            | '\u{200E}'
+            | '\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{2066}'..='\u{2069}'
+            | '\u{FEFF}'
+    )
+}
+
+// Pictographs, dingbats and the variation selectors ending emojis
+fn is_emoji(c: Option<char>) -> bool {
+    c.is_some_and(|c| matches!(c, '\u{2600}'..='\u{27BF}' | '\u{FE0F}' | '\u{1F000}'..))
+}
+
+/// `text` composed to NFC, stripped of invisible characters with `Strip`.
+pub fn text(text: &str, mode: TextNormalization) -> String {
+    let text = match mode {
+        TextNormalization::Off => return text.to_string(),
+        TextNormalization::Nfc | TextNormalization::Strip => {
+            icu_normalizer::ComposingNormalizerBorrowed::new_nfc().normalize(text)
+        }
+    };
+    if mode != TextNormalization::Strip {
+        return text.into_owned();
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .iter()
+        .enumerate()
+        .filter(|&(index, &c)| match c {
+            '\n' | '\t' => true,
+            ZERO_WIDTH_JOINER => {
+                is_emoji(index.checked_sub(1).map(|index| chars[index]))
+                    && is_emoji(chars.get(index + 1).copied())
+            }
+            c => !c.is_control() && !is_invisible(c),
+        })
+        .map(|(_, &c)| c)
+        .collect()
+}
+
+/// Normalizes every string of a JSON document, keys included.
+pub fn json(value: &mut Value, mode: TextNormalization) {
+    match value {
+        Value::String(string) => *string = text(string, mode),
+        Value::Array(items) => items.iter_mut().for_each(|item| json(item, mode)),
+        Value::Object(map) => {
+            *map = std::mem::take(map)
+                .into_iter()
+                .map(|(key, mut value)| {
+                    json(&mut value, mode);
+                    (text(&key, mode), value)
+                })
+                .collect();
+        }
+        _ => {}
+    }
+}
+
+/// Normalizes the text of every field of `request`.
+pub fn request(
+    request: NotificationRequest,
+    mode: TextNormalization,
+) -> Result<NotificationRequest> {
+    if mode == TextNormalization::Off {
+        return Ok(request);
+    }
+    let (uploads, dedup_key) = (request.uploads.clone(), request.dedup_key.clone());
+    let mut value = serde_json::to_value(&request)?;
+    json(&mut value, mode);
+    Ok(NotificationRequest {
+        uploads,
+        dedup_key,
+        ..NotificationRequest::from_value(value, false)?
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_text() {
+        let decomposed = "Cafe\u{301} *d\u{200B}own*\u{202E}";
+        assert_eq!(text(decomposed, TextNormalization::Off), decomposed);
+        assert_eq!(
+            text(decomposed, TextNormalization::Nfc),
+            "Café *d\u{200B}own*\u{202E}"
+        );
+        assert_eq!(text(decomposed, TextNormalization::Strip), "Café *down*");
+        assert_eq!(
+            text("a\u{0}b\r\n\tc\u{7F}\u{FEFF}", TextNormalization::Strip),
+            "ab\n\tc"
+        );
+    }
+
+    #[test]
+    fn test_text_keeps_emoji_sequences() {
+        assert_eq!(
+            text("👩\u{200D}💻 on\u{200D}call", TextNormalization::Strip),
+            "👩\u{200D}💻 oncall"
+        );
+    }
+
+    #[test]
+    fn test_request() {
+        let mut request = NotificationRequest::from_value(
+            json!({
+                "message": "Disk\u{200B} full",
+                "attachments": [{ "title": "ho\u{200C}st", "text": "Re\u{301}sume\u{301}" }],
+                "env": "pro\u{2060}d",
+            }),
+            false,
+        )
+        .unwrap();
+        request.dedup_key = Some("feed/1".to_string());
+
+        let request = super::request(request, TextNormalization::Strip).unwrap();
+        assert_eq!(request.message.as_deref(), Some("Disk full"));
+        assert_eq!(
+            request.attachments.unwrap().to_value().unwrap(),
+            json!([{ "title": "host", "text": "Résumé" }])
+        );
+        assert_eq!(request.extra["env"], "prod");
+        assert_eq!(request.dedup_key.as_deref(), Some("feed/1"));
+    }
+}
@@ -0,0 +1,363 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use http::{Request, Response};
+use serde_json::{json, Value};
+
+use crate::client::OutboundClient;
+use crate::helpers::body::Redirect;
+use crate::helpers::cookies::{ResponseCookies, SetCookie};
+use crate::helpers::negotiate::Negotiated;
+use crate::helpers::{ApiError, Cookies, Query};
+use crate::store::{HttpStore, KeyValue};
+use crate::{auth, secrets, slack_api, Settings};
+
+// Slack OAuth v2 install flow
+//
+// `GET /slack/install` redirects to Slack's consent screen with a random
+// `state` also stored in a cookie, `GET /slack/oauth/callback` checks it
+// back, exchanges the code with `oauth.v2.access` and stores the bot token
+// by team ID in the key-value store, the `token_store_endpoint` HTTP service
+// standing in for `wasi:keyvalue` (see `store`).
+
+pub const INSTALL_PATH: &str = "/slack/install";
+pub const CALLBACK_PATH: &str = "/slack/oauth/callback";
+
+const AUTHORIZE_URL: &str = "https://slack.com/oauth/v2/authorize";
+const STATE_COOKIE: &str = "slack_oauth_state";
+const STATE_TTL: Duration = Duration::from_secs(600);
+
+/// Workspace the component was installed in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Installation {
+    pub team_id: String,
+    pub team_name: Option<String>,
+    pub bot_token: String,
+}
+
+#[derive(serde::Deserialize)]
+struct AccessResponse {
+    ok: bool,
+    #[serde(default)]
+    access_token: Option<String>,
+    #[serde(default)]
+    team: Option<Team>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct Team {
+    id: String,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct CallbackParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+pub fn authorize_url(settings: &Settings, client_id: &str, state: &str) -> Result<String> {
+    let scope = settings.oauth_scopes.join(",");
+    let mut params = vec![
+        ("client_id", client_id),
+        ("scope", &scope),
+        ("state", state),
+    ];
+    if let Some(redirect_uri) = &settings.oauth_redirect_uri {
+        params.push(("redirect_uri", redirect_uri));
+    }
+    Ok(format!(
+        "{AUTHORIZE_URL}?{}",
+        serde_urlencoded::to_string(params)?
+    ))
+}
+
+/// Trades the code Slack redirected with for a bot token.
+pub fn exchange(
+    client: &dyn OutboundClient,
+    client_id: &str,
+    client_secret: &str,
+    code: &str,
+    redirect_uri: Option<&str>,
+) -> Result<Installation> {
+    let mut params = vec![
+        ("client_id", client_id),
+        ("client_secret", client_secret),
+        ("code", code),
+    ];
+    if let Some(redirect_uri) = redirect_uri {
+        params.push(("redirect_uri", redirect_uri));
+    }
+
+    let mut headers = http::HeaderMap::new();
+    headers.insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static("application/x-www-form-urlencoded"),
+    );
+    let url = format!("{}/oauth.v2.access", slack_api::API_URL);
+    let response = client.post(&url, &headers, serde_urlencoded::to_string(params)?.into())?;
+    if response.status != 200 {
+        anyhow::bail!("Slack API oauth.v2.access answered {}", response.status);
+    }
+
+    let response: AccessResponse = serde_json::from_slice(&response.body)
+        .map_err(|err| anyhow::anyhow!("Invalid Slack API oauth.v2.access response: {err}"))?;
+    match (response.ok, response.access_token, response.team) {
+        (true, Some(bot_token), Some(team)) => Ok(Installation {
+            team_id: team.id,
+            team_name: team.name,
+            bot_token,
+        }),
+        _ => anyhow::bail!(
+            "Slack refused the installation: {}",
+            response.error.as_deref().unwrap_or("unknown_error")
+        ),
+    }
+}
+
+fn client_id(settings: &Settings) -> Result<&str> {
+    settings
+        .oauth_client_id
+        .as_deref()
+        .ok_or_else(|| ApiError::not_found("Slack install is not configured").into())
+}
+
+/// Redirects to Slack's consent screen.
+pub fn install(settings: &Settings) -> Result<Response<Redirect>> {
+    let client_id = client_id(settings)?;
+    let state = new_state();
+    let url = authorize_url(settings, client_id, &state)?;
+
+    let mut res = Response::new(Redirect::to(&url)?);
+    res.set_cookie(
+        &SetCookie::new(STATE_COOKIE, state)
+            .path(CALLBACK_PATH)
+            .max_age(STATE_TTL),
+    )?;
+    Ok(res)
+}
+
+/// Completes the install Slack redirected back from.
+pub fn callback<B>(
+    req: &Request<B>,
+    settings: &Settings,
+    client: &dyn OutboundClient,
+) -> Result<Response<Negotiated<Value>>> {
+    let client_id = client_id(settings)?;
+    let Query(params) = Query::<CallbackParams>::from_req(req)?;
+    if let Some(error) = params.error {
+        return Err(ApiError::bad_request(format!("Slack install was cancelled: {error}")).into());
+    }
+
+    // The state must come back from the browser that started the install
+    let cookies = Cookies::from_req(req);
+    let state_matches = match (params.state.as_deref(), cookies.get(STATE_COOKIE)) {
+        (Some(state), Some(expected)) => {
+            auth::constant_time_eq(state.as_bytes(), expected.as_bytes())
+        }
+        _ => false,
+    };
+    if !state_matches {
+        return Err(ApiError::bad_request("Invalid OAuth state").into());
+    }
+    let code = params
+        .code
+        .ok_or_else(|| ApiError::bad_request("Missing code"))?;
+
+    let client_secret = secrets::resolve(
+        settings.oauth_client_secret.as_deref().unwrap_or_default(),
+        settings.secrets_endpoint.as_deref(),
+        settings.secrets_token.as_deref(),
+        client,
+    )?;
+    let installation = exchange(
+        client,
+        client_id,
+        &client_secret,
+        &code,
+        settings.oauth_redirect_uri.as_deref(),
+    )
+    .map_err(|err| ApiError::bad_gateway(err.to_string()))?;
+    HttpStore::from_settings(settings, client)?
+        .put(&installation.team_id, &installation.bot_token)
+        .map_err(|err| ApiError::bad_gateway(format!("Failed to store bot token: {err}")))?;
+
+    let mut res = Response::new(Negotiated::new(
+        req.headers(),
+        json!({
+            "ok": true,
+            "team_id": installation.team_id,
+            "team": installation.team_name,
+        }),
+    ));
+    res.set_cookie(&SetCookie::removal(STATE_COOKIE).path(CALLBACK_PATH))?;
+    Ok(res)
+}
+
+/// Bot token stored for `team_id` by a previous install.
+pub fn bot_token(
+    settings: &Settings,
+    team_id: &str,
+    client: &dyn OutboundClient,
+) -> Result<String> {
+    HttpStore::from_settings(settings, client)?
+        .get(team_id)
+        .map_err(|err| ApiError::bad_gateway(format!("Failed to read bot token: {err}")))?
+        .ok_or_else(|| {
+            ApiError::not_found(format!("Slack app is not installed in team {team_id}")).into()
+        })
+}
+
+// Unguessable value tying the callback to the browser that started the flow
+#[cfg(target_arch = "wasm32")]
+fn new_state() -> String {
+    let bytes = crate::bindings::wasi::random::random::get_random_bytes(16);
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn new_state() -> String {
+    use std::hash::{BuildHasher, RandomState};
+
+    let state = RandomState::new();
+    format!("{:016x}{:016x}", state.hash_one(1), state.hash_one(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockClient;
+
+    fn settings() -> Settings {
+        let data = std::collections::HashMap::from([
+            ("bot_token".to_string(), "xoxb-test".to_string()),
+            ("oauth_client_id".to_string(), "123.456".to_string()),
+            ("oauth_client_secret".to_string(), "shh".to_string()),
+            (
+                "oauth_redirect_uri".to_string(),
+                "https://example.com/slack/oauth/callback".to_string(),
+            ),
+            (
+                "token_store_endpoint".to_string(),
+                "https://kv.example.com/slack".to_string(),
+            ),
+        ]);
+        Settings::from_map(&data).unwrap()
+    }
+
+    fn callback_request(state: &str, cookie: &str) -> Request<()> {
+        Request::builder()
+            .uri(format!("{CALLBACK_PATH}?code=c0de&state={state}"))
+            .header(http::header::COOKIE, format!("{STATE_COOKIE}={cookie}"))
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_authorize_url() {
+        assert_eq!(
+            authorize_url(&settings(), "123.456", "abc").unwrap(),
+            "https://slack.com/oauth/v2/authorize?client_id=123.456&scope=chat%3Awrite&state=abc&redirect_uri=https%3A%2F%2Fexample.com%2Fslack%2Foauth%2Fcallback"
+        );
+    }
+
+    #[test]
+    fn test_install_redirects_with_state_cookie() {
+        let res = install(&settings()).unwrap();
+
+        assert_eq!(res.body().status, http::StatusCode::FOUND);
+        let location = &res.body().location;
+        let cookie = res.headers().get(http::header::SET_COOKIE).unwrap();
+        let state = cookie
+            .to_str()
+            .unwrap()
+            .strip_prefix("slack_oauth_state=")
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap();
+        assert_eq!(state.len(), 32);
+        assert!(location
+            .to_str()
+            .unwrap()
+            .contains(&format!("state={state}")));
+    }
+
+    #[test]
+    fn test_install_not_configured() {
+        let mut settings = settings();
+        settings.oauth_client_id = None;
+
+        let err = install(&settings).unwrap_err();
+        let err = err.downcast_ref::<ApiError>().unwrap();
+        assert_eq!(err.status, http::StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_callback_stores_token() {
+        let client = MockClient::new()
+            .respond_with_body(
+                200,
+                r#"{"ok": true, "access_token": "xoxb-new", "team": {"id": "T1", "name": "Acme"}}"#,
+            )
+            .respond_with(201);
+
+        let res = callback(&callback_request("abc", "abc"), &settings(), &client).unwrap();
+        crate::testing::assert_json(
+            &res,
+            http::StatusCode::OK,
+            json!({ "ok": true, "team_id": "T1", "team": "Acme" }),
+        );
+        assert!(res
+            .headers()
+            .get(http::header::SET_COOKIE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("slack_oauth_state=; Path=/slack/oauth/callback; Max-Age=0"));
+
+        let requests = client.requests();
+        assert_eq!(requests[0].url, "https://slack.com/api/oauth.v2.access");
+        assert_eq!(
+            String::from_utf8(requests[0].body.clone()).unwrap(),
+            "client_id=123.456&client_secret=shh&code=c0de&redirect_uri=https%3A%2F%2Fexample.com%2Fslack%2Foauth%2Fcallback"
+        );
+        assert_eq!(requests[1].method, http::Method::PUT);
+        assert_eq!(requests[1].url, "https://kv.example.com/slack/T1");
+        assert_eq!(
+            serde_json::from_slice::<Value>(&requests[1].body).unwrap(),
+            json!({ "value": "xoxb-new" })
+        );
+    }
+
+    #[test]
+    fn test_callback_rejects_state_mismatch() {
+        let client = MockClient::new();
+        let err = callback(&callback_request("abc", "xyz"), &settings(), &client).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid OAuth state");
+        assert!(client.requests().is_empty());
+    }
+
+    #[test]
+    fn test_callback_slack_refusal() {
+        let client =
+            MockClient::new().respond_with_body(200, r#"{"ok": false, "error": "invalid_code"}"#);
+        let err = callback(&callback_request("abc", "abc"), &settings(), &client).unwrap_err();
+        let err = err.downcast_ref::<ApiError>().unwrap();
+        assert_eq!(err.status, http::StatusCode::BAD_GATEWAY);
+        assert_eq!(err.message, "Slack refused the installation: invalid_code");
+    }
+
+    #[test]
+    fn test_bot_token_not_installed() {
+        let client = MockClient::new().respond_with(404);
+        let err = bot_token(&settings(), "T9", &client).unwrap_err();
+        let err = err.downcast_ref::<ApiError>().unwrap();
+        assert_eq!(err.status, http::StatusCode::NOT_FOUND);
+        assert_eq!(err.message, "Slack app is not installed in team T9");
+    }
+}
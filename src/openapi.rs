@@ -0,0 +1,468 @@
+use serde_json::{json, Value};
+
+// OpenAPI description of the component's HTTP surface
+//
+// Hand-built next to the request and response models, update it with them.
+
+/// OpenAPI 3.1 document served at `GET /openapi.json`.
+pub fn document() -> Value {
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "Slack Message component",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Posts messages to Slack from HTTP requests, through an incoming webhook or a bot token.",
+        },
+        "paths": {
+            "/flush": {
+                "post": {
+                    "summary": "Post the messages buffered by digest_severities, one summary per destination",
+                    "parameters": [header_parameter("Authorization", "`Bearer <auth_token>`")],
+                    "responses": {
+                        "200": json_response("Buffered messages posted", json!({
+                            "type": "object",
+                            "properties": {
+                                "ok": { "type": "boolean" },
+                                "flushed": { "type": "integer" },
+                                "pending": { "type": "integer" },
+                            },
+                        })),
+                        "401": error_response("Missing or wrong auth_token"),
+                        "404": error_response("Digests are not configured"),
+                        "502": error_response("Store failed or Slack refused a summary, refused messages stay buffered"),
+                    },
+                },
+            },
+            "/health": {
+                "get": {
+                    "summary": "Liveness check, Slack is not called",
+                    "responses": {
+                        "200": json_response("Component is up", json!({
+                            "type": "object",
+                            "properties": { "ok": { "const": true } },
+                        })),
+                        "304": { "description": "Unchanged since the `If-None-Match` ETag" },
+                    },
+                },
+            },
+            "/openapi.json": {
+                "get": {
+                    "summary": "This document",
+                    "responses": {
+                        "200": json_response("OpenAPI document", json!({ "type": "object" })),
+                    },
+                },
+            },
+            "/slack/events": {
+                "post": {
+                    "summary": "Slack Events API request URL, with the signing_secret setting",
+                    "parameters": [
+                        header_parameter("X-Slack-Signature", "HMAC-SHA256 of the request, `v0=<hex>`"),
+                        header_parameter("X-Slack-Request-Timestamp", "Time the request was signed at"),
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": { "type": "object" } } },
+                    },
+                    "responses": {
+                        "200": json_response("Event handled, or `challenge` echoed back", json!({ "type": "object" })),
+                        "401": error_response("Missing or wrong Slack signature"),
+                        "404": error_response("Events are not configured"),
+                        "502": error_response("Acknowledgement callback or store failed"),
+                    },
+                },
+            },
+            "/slack/install": {
+                "get": {
+                    "summary": "Start installing the Slack app in a workspace, with the oauth_client_id setting",
+                    "responses": {
+                        "302": { "description": "Redirect to Slack's consent screen, with a state cookie" },
+                        "404": error_response("Install is not configured"),
+                    },
+                },
+            },
+            "/slack/oauth/callback": {
+                "get": {
+                    "summary": "Exchange the code Slack redirected with and store the bot token",
+                    "parameters": [
+                        query_parameter("code", "Temporary authorization code"),
+                        query_parameter("state", "State set by /slack/install"),
+                        query_parameter("error", "Set by Slack when the install was cancelled"),
+                    ],
+                    "responses": {
+                        "200": json_response("Workspace installed", json!({
+                            "type": "object",
+                            "properties": {
+                                "ok": { "const": true },
+                                "username": { "type": "string", "description": "Overrides the `defaults` setting" },
+            "icon_emoji": { "type": "string", "description": "Overrides the `defaults` setting" },
+            "icon_url": { "type": "string", "format": "uri", "description": "Overrides the `defaults` setting" },
+            "unfurl_links": { "type": "boolean", "description": "Overrides the `defaults` setting" },
+            "unfurl_media": { "type": "boolean", "description": "Overrides the `defaults` setting" },
+            "team_id": { "type": "string" },
+                                "team": { "type": "string" },
+                            },
+                        })),
+                        "400": error_response("Cancelled install, missing code or state mismatch"),
+                        "502": error_response("Slack refused the code or the token store failed"),
+                    },
+                },
+            },
+            "/test": {
+                "post": {
+                    "summary": "Post a configuration test message naming the component version and its destination",
+                    "parameters": [header_parameter("Authorization", "`Bearer <auth_token>`")],
+                    "requestBody": {
+                        "required": false,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "object",
+                                    "properties": {
+                                        "channel": { "type": "string" },
+                                        "channels": { "type": "array", "items": { "type": "string" } },
+                                        "team_id": { "type": "string" },
+                                    },
+                                },
+                            },
+                        },
+                    },
+                    "responses": {
+                        "200": json_response("Test message sent", json!({ "$ref": "#/components/schemas/MessageResponse" })),
+                        "401": error_response("Missing or wrong auth_token"),
+                        "404": error_response("No auth_token is set"),
+                        "502": error_response("Slack refused or could not be reached"),
+                    },
+                },
+            },
+            "/transform/{source}": {
+                "parameters": [{
+                    "name": "source",
+                    "in": "path",
+                    "required": true,
+                    "description": "Vendor the payload comes from, also accepted in an `X-Source` header on any path",
+                    "schema": { "type": "string" },
+                }],
+                "post": {
+                    "summary": "Send a message from a vendor webhook payload",
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": { "schema": { "type": "object" } },
+                            "application/x-www-form-urlencoded": { "schema": { "type": "object" } },
+                        },
+                    },
+                    "responses": message_responses(),
+                },
+            },
+            "/{path}": {
+                "parameters": [{
+                    "name": "path",
+                    "in": "path",
+                    "required": true,
+                    "description": "Any path the component is mounted on, a leading `v1/` pins the API version",
+                    "schema": { "type": "string" },
+                }],
+                "post": {
+                    "summary": "Send a message",
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": { "schema": { "$ref": "#/components/schemas/NotificationRequest" } },
+                            "application/x-www-form-urlencoded": { "schema": { "$ref": "#/components/schemas/NotificationRequest" } },
+                            "multipart/form-data": { "schema": { "$ref": "#/components/schemas/NotificationRequest" } },
+                            "application/cloudevents+json": { "schema": { "type": "object" } },
+                        },
+                    },
+                    "responses": message_responses(),
+                },
+                "get": {
+                    "summary": "Send a message from the query string, with the query_trigger setting",
+                    "parameters": [
+                        query_parameter("token", "Shared secret, when not sent as a bearer token"),
+                        query_parameter("message", "Message text"),
+                        query_parameter("severity", "Message severity"),
+                    ],
+                    "responses": message_responses(),
+                },
+            },
+        },
+        "components": {
+            "schemas": {
+                "NotificationRequest": notification_request(),
+                "MessageResponse": {
+                    "type": "object",
+                    "required": ["ok"],
+                    "properties": {
+                        "ok": { "type": "boolean" },
+                        "slack_status": {
+                            "type": "integer",
+                            "description": "Status of the webhook call, with the structured response_format",
+                        },
+                        "error": {
+                            "type": "string",
+                            "description": "Code of a webhook error, e.g. channel_not_found",
+                        },
+                        "slack_body": {
+                            "type": "string",
+                            "description": "Webhook error body, webhook path redacted, with the verbose_errors setting",
+                        },
+                        "timings": {
+                            "type": "object",
+                            "description": "Time spent parsing, rendering and waiting on Slack, with the structured response_format",
+                            "properties": {
+                                "parse_ms": { "type": "number" },
+                                "render_ms": { "type": "number" },
+                                "slack_ms": { "type": "number" },
+                            },
+                        },
+                        "canvas_id": {
+                            "type": "string",
+                            "description": "Canvas updated in place of posting, with the instead canvas_mode",
+                        },
+                        "canvas_error": {
+                            "type": "string",
+                            "description": "Why the canvas could not be updated",
+                        },
+                        "size_strategy": {
+                            "type": "string",
+                            "enum": ["upload", "truncate"],
+                            "description": "How a payload over the Slack size limit was shortened",
+                        },
+                        "debug": {
+                            "type": "object",
+                            "description": "Rendered payload, destination, matched filter rules, source and timings, with an `X-Debug: true` header and the auth_token",
+                            "properties": {
+                                "source": { "type": "string" },
+                                "destination": { "type": "string" },
+                                "filters": { "type": "array", "items": { "type": "string" } },
+                                "payload": { "type": "object" },
+                                "timings": { "type": "object" },
+                            },
+                        },
+                        "filtered": {
+                            "type": "boolean",
+                            "description": "Set when the filters setting dropped the notification, nothing being sent",
+                        },
+                        "duplicate": {
+                            "type": "boolean",
+                            "description": "Set when a notification with the same dedup key was already delivered, nothing being sent",
+                        },
+                        "warnings": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Follow-up calls that failed after the messages were posted",
+                        },
+                        "messages": {
+                            "type": "array",
+                            "description": "One entry per channel, in bot-token mode",
+                            "items": { "$ref": "#/components/schemas/PostedMessage" },
+                        },
+                    },
+                },
+                "PostedMessage": {
+                    "type": "object",
+                    "required": ["channel"],
+                    "properties": {
+                        "channel": { "type": "string" },
+                        "channel_id": { "type": "string" },
+                        "ts": { "type": "string" },
+                        "error": { "type": "string" },
+                    },
+                },
+                "Error": {
+                    "type": "object",
+                    "required": ["error"],
+                    "properties": {
+                        "error": { "type": "string" },
+                        "code": { "type": "string" },
+                        "errors": { "type": "array", "items": { "type": "string" } },
+                        "violations": { "type": "array", "items": { "type": "object" } },
+                    },
+                },
+            },
+        },
+    })
+}
+
+/// Schema of `NotificationRequest`, also used to check the examples in tests.
+pub fn notification_request() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "message": { "type": "string" },
+            "severity": { "type": "string", "examples": ["info", "warning", "error"] },
+            "channel": { "type": "string", "description": "Bot-token mode only" },
+            "channels": {
+                "type": "array",
+                "items": { "type": "string" },
+                "description": "Channels to post to in bot-token mode, in place of `channel`",
+            },
+            "fields": { "type": "object", "description": "Shown as a list of key/value pairs" },
+            "blocks": { "type": "array", "description": "Slack Block Kit blocks, passed through" },
+            "attachments": { "type": "array", "description": "Slack attachments, passed through" },
+            "thread_ts": { "type": "string", "description": "Replies in the thread of this message" },
+            "team_id": { "type": "string", "description": "Workspace to post to, with the token store" },
+            "correlation_id": {
+                "type": "string",
+                "description": "Posts messages sharing it in one thread per channel, with a bot token and the token store",
+            },
+            "broadcast": {
+                "type": "boolean",
+                "description": "Also shows a thread reply in the channel, defaults to the broadcast_severities setting",
+            },
+            "pin": {
+                "type": "boolean",
+                "description": "Pins the posted messages, defaults to the pin_severities setting",
+            },
+            "bookmark": {
+                "type": "object",
+                "required": ["title", "link"],
+                "properties": {
+                    "title": { "type": "string" },
+                    "link": { "type": "string", "format": "uri" },
+                },
+                "description": "Link bookmarked in the target channels, bot-token mode only",
+            },
+            "event": {
+                "type": "object",
+                "required": ["title"],
+                "properties": {
+                    "title": { "type": "string" },
+                    "description": { "type": "string" },
+                    "severity": { "type": "string" },
+                    "source": { "type": "string" },
+                    "url": { "type": "string", "format": "uri" },
+                    "fields": { "type": "object" },
+                    "timestamp": { "type": "string", "format": "date-time" },
+                },
+                "description": "Alert rendered with a consistent Block Kit layout, standing in for `message` and `severity`",
+            },
+            "author": {
+                "type": "object",
+                "required": ["name"],
+                "properties": {
+                    "name": { "type": "string" },
+                    "icon_url": { "type": "string", "format": "uri" },
+                },
+                "description": "Shown with its avatar in a context block at the bottom of the message",
+            },
+            "images": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["url"],
+                    "properties": {
+                        "url": { "type": "string", "format": "uri" },
+                        "alt": { "type": "string" },
+                    },
+                },
+                "description": "Shown as image blocks after the message",
+            },
+            "table": {
+                "type": "object",
+                "properties": {
+                    "headers": { "type": "array", "items": { "type": "string" } },
+                    "rows": { "type": "array", "items": { "type": "array" } },
+                },
+                "description": "Appended to the message as a column-aligned code block",
+            },
+            "metadata": {
+                "type": "object",
+                "required": ["event_type"],
+                "properties": {
+                    "event_type": { "type": "string" },
+                    "event_payload": { "type": "object" },
+                },
+                "description": "Slack message metadata, bot-token mode only",
+            },
+        },
+    })
+}
+
+fn json_response(description: &str, schema: Value) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": schema } },
+    })
+}
+
+fn error_response(description: &str) -> Value {
+    json_response(description, json!({ "$ref": "#/components/schemas/Error" }))
+}
+
+fn query_parameter(name: &str, description: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "query",
+        "description": description,
+        "schema": { "type": "string" },
+    })
+}
+
+fn header_parameter(name: &str, description: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "header",
+        "required": true,
+        "description": description,
+        "schema": { "type": "string" },
+    })
+}
+
+fn message_responses() -> Value {
+    json!({
+        "200": json_response("Message sent", json!({ "$ref": "#/components/schemas/MessageResponse" })),
+        "400": error_response("Invalid request or settings"),
+        "401": error_response("Missing or wrong token"),
+        "404": error_response("Unknown API version or source, or team_id without an installation"),
+        "413": error_response("Request body over max_body_bytes"),
+        "422": error_response("Request body not matching input_schema"),
+        "502": error_response("Slack refused or could not be reached"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_lists_routes() {
+        let document = document();
+        assert_eq!(document["openapi"], "3.1.0");
+        assert_eq!(document["info"]["version"], env!("CARGO_PKG_VERSION"));
+
+        let paths = document["paths"].as_object().unwrap();
+        assert_eq!(
+            paths.keys().collect::<Vec<_>>(),
+            vec![
+                "/flush",
+                "/health",
+                "/openapi.json",
+                "/slack/events",
+                "/slack/install",
+                "/slack/oauth/callback",
+                "/test",
+                "/transform/{source}",
+                "/{path}"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_notification_request_schema_accepts_requests() {
+        let request = json!({
+            "message": "Disk full",
+            "severity": "error",
+            "channels": ["#ops", "#alerts"],
+            "fields": { "host": "db-1" },
+            "blocks": [{ "type": "divider" }],
+        });
+        let violations = crate::schema::validate(&notification_request(), &request).unwrap();
+        assert!(violations.is_empty(), "{violations:?}");
+
+        let request = json!({ "message": 42 });
+        let violations = crate::schema::validate(&notification_request(), &request).unwrap();
+        assert_eq!(violations[0].path, "/message");
+    }
+}
@@ -0,0 +1,127 @@
+use regex::{Captures, Regex};
+use std::sync::LazyLock;
+
+// PII scrubbing helpers
+//
+// Patterns are intentionally loose: when in doubt we'd rather mask a value
+// than let it leave the edge.
+
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}").unwrap()
+});
+
+static IPV4: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(?:(?:25[0-5]|2[0-4]\d|1?\d?\d)\.){3}(?:25[0-5]|2[0-4]\d|1?\d?\d)\b").unwrap()
+});
+
+static IPV6: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(?:[0-9a-f]{1,4}:){7}[0-9a-f]{1,4}\b|\b(?:[0-9a-f]{1,4}:){1,6}:(?:[0-9a-f]{1,4}(?::[0-9a-f]{1,4})*)?\b").unwrap()
+});
+
+static PHONE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\+?\(?\d{1,4}\)?(?:[ .-]?\(?\d{1,4}\)?){2,7}").unwrap());
+
+const EMAIL_MASK: &str = "[email]";
+const IP_MASK: &str = "[ip]";
+const PHONE_MASK: &str = "[phone]";
+
+/// Masks emails, IP addresses and phone numbers found in `text`.
+pub fn scrub(text: &str) -> String {
+    let text = EMAIL.replace_all(text, EMAIL_MASK);
+    let text = IPV4.replace_all(&text, IP_MASK);
+    let text = IPV6.replace_all(&text, IP_MASK);
+    PHONE
+        .replace_all(&text, |caps: &Captures| {
+            // E.164 numbers are at most 15 digits, anything below 9 is more
+            // likely a date, an amount or an identifier than a phone number.
+            let matched = &caps[0];
+            let digits = matched.chars().filter(char::is_ascii_digit).count();
+            if (9..=15).contains(&digits) {
+                PHONE_MASK.to_string()
+            } else {
+                matched.to_string()
+            }
+        })
+        .into_owned()
+}
+
+/// Masks personal data in every string of a JSON document, keys included.
+pub fn scrub_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(text) => *text = scrub(text),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(scrub_json),
+        serde_json::Value::Object(map) => {
+            *map = std::mem::take(map)
+                .into_iter()
+                .map(|(key, mut value)| {
+                    scrub_json(&mut value);
+                    (scrub(&key), value)
+                })
+                .collect();
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrub_email() {
+        assert_eq!(
+            scrub("Contact john.doe+test@example.co.uk now"),
+            "Contact [email] now"
+        );
+    }
+
+    #[test]
+    fn test_scrub_ipv4() {
+        assert_eq!(scrub("Request from 192.168.1.42"), "Request from [ip]");
+    }
+
+    #[test]
+    fn test_scrub_ipv6() {
+        assert_eq!(
+            scrub("Request from 2001:0db8:85a3:0000:0000:8a2e:0370:7334"),
+            "Request from [ip]"
+        );
+        assert_eq!(scrub("Request from fe80::1"), "Request from [ip]");
+    }
+
+    #[test]
+    fn test_scrub_phone() {
+        assert_eq!(scrub("Call +33 6 12 34 56 78"), "Call [phone]");
+        assert_eq!(scrub("Call (555) 123-4567"), "Call [phone]");
+    }
+
+    #[test]
+    fn test_scrub_keeps_short_numbers() {
+        assert_eq!(scrub("Order 1234 shipped"), "Order 1234 shipped");
+        assert_eq!(scrub("Deployed on 2024-01-31"), "Deployed on 2024-01-31");
+    }
+
+    #[test]
+    fn test_scrub_mixed() {
+        assert_eq!(
+            scrub("jane@example.com signed up from 10.0.0.1, phone 0612345678"),
+            "[email] signed up from [ip], phone [phone]"
+        );
+    }
+
+    #[test]
+    fn test_scrub_json() {
+        let mut value = serde_json::json!([
+            { "type": "section", "text": { "type": "mrkdwn", "text": "Ping jane@example.com" } },
+            { "ts": 1700000000, "ip": "10.0.0.1" },
+        ]);
+        scrub_json(&mut value);
+        assert_eq!(
+            value,
+            serde_json::json!([
+                { "type": "section", "text": { "type": "mrkdwn", "text": "Ping [email]" } },
+                { "ts": 1700000000, "ip": "[ip]" },
+            ])
+        );
+    }
+}
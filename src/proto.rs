@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+/// Protobuf notification schema, see `proto/notification.proto`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Notification {
+    #[prost(string, tag = "1")]
+    pub message: String,
+    #[prost(string, optional, tag = "2")]
+    pub severity: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub channel: Option<String>,
+    #[prost(map = "string, string", tag = "4")]
+    pub fields: HashMap<String, String>,
+}
+
+impl From<Notification> for Value {
+    fn from(notification: Notification) -> Self {
+        let mut value = Map::new();
+        value.insert("message".to_string(), notification.message.into());
+        if let Some(severity) = notification.severity {
+            value.insert("severity".to_string(), severity.into());
+        }
+        if let Some(channel) = notification.channel {
+            value.insert("channel".to_string(), channel.into());
+        }
+        if !notification.fields.is_empty() {
+            value.insert(
+                "fields".to_string(),
+                notification
+                    .fields
+                    .into_iter()
+                    .map(|(key, value)| (key, Value::String(value)))
+                    .collect::<Map<_, _>>()
+                    .into(),
+            );
+        }
+        Value::Object(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+    use serde_json::json;
+
+    #[test]
+    fn test_notification_roundtrip() {
+        let notification = Notification {
+            message: "disk full".to_string(),
+            severity: Some("error".to_string()),
+            channel: None,
+            fields: HashMap::from([("host".to_string(), "db-1".to_string())]),
+        };
+        let bytes = notification.encode_to_vec();
+        assert_eq!(
+            Notification::decode(bytes.as_slice()).unwrap(),
+            notification
+        );
+    }
+
+    #[test]
+    fn test_notification_into_value() {
+        let notification = Notification {
+            message: "disk full".to_string(),
+            severity: Some("error".to_string()),
+            channel: None,
+            fields: HashMap::from([("host".to_string(), "db-1".to_string())]),
+        };
+        assert_eq!(
+            Value::from(notification),
+            json!({
+                "message": "disk full",
+                "severity": "error",
+                "fields": { "host": "db-1" },
+            })
+        );
+    }
+
+    #[test]
+    fn test_notification_into_value_minimal() {
+        let notification = Notification {
+            message: "hello".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(Value::from(notification), json!({ "message": "hello" }));
+    }
+}
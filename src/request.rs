@@ -0,0 +1,564 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::cloudevents::{self, CloudEvent};
+use crate::helpers::body::{self, Form, FromBody, Json, MsgPack, Multipart, Proto, Yaml};
+use crate::proto::Notification;
+
+const JSON: &str = "application/json";
+const FORM: &str = "application/x-www-form-urlencoded";
+const TEXT: &str = "text/plain";
+
+/// Notification request fields understood by the component. Anything else
+/// is collected into `extra`.
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct NotificationRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// Channels to post to in bot-token mode, in place of `channel`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channels: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fields: Option<serde_json::Map<String, serde_json::Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocks: Option<RawField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachments: Option<RawField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_ts: Option<String>,
+    /// Presentation overriding the `defaults` setting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon_emoji: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unfurl_links: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unfurl_media: Option<bool>,
+    /// Groups related messages in one thread per channel, bot-token mode
+    /// only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    /// Also shows a thread reply in the channel.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub broadcast: Option<bool>,
+    /// Pins the posted messages, defaults to the `pin_severities` setting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin: Option<bool>,
+    /// Link bookmarked in the target channels, bot-token mode only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bookmark: Option<Bookmark>,
+    /// Alert rendered with a consistent Block Kit layout, its title and
+    /// severity standing in for missing `message` and `severity` fields.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<Event>,
+    /// Shown in a context block at the bottom of the message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<Author>,
+    /// Shown as image blocks after the message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub images: Option<Vec<Image>>,
+    /// Appended to the message as a column-aligned code block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table: Option<Table>,
+    /// Slack message metadata, posted in bot-token mode only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<MessageMetadata>,
+    /// Workspace to post to when bot tokens come from the token store.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team_id: Option<String>,
+    /// Attributes of the CloudEvent the request was built from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cloudevent: Option<serde_json::Value>,
+    /// Files received in a multipart request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files: Option<Vec<serde_json::Value>>,
+    /// Files uploaded in the thread of the message, bot-token mode only. Set
+    /// by transformers or from the file parts of multipart requests.
+    #[serde(skip)]
+    pub uploads: Vec<Upload>,
+    /// Key the notification is delivered once for, with the key-value store.
+    /// Set by transformers, e.g. to the GUID of a feed item.
+    #[serde(skip)]
+    pub dedup_key: Option<String>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl NotificationRequest {
+    /// Deserializes a request, rejecting unknown fields when `strict` is set
+    /// so producer typos like `mesage` don't go unnoticed.
+    pub fn from_value(value: serde_json::Value, strict: bool) -> Result<Self> {
+        let mut request: Self = serde_json::from_value(value)
+            .map_err(|err| anyhow::anyhow!("Invalid request body: {err}"))?;
+        if strict {
+            if let Some(key) = request.extra.keys().next() {
+                anyhow::bail!("Unknown field '{key}' in request body");
+            }
+        }
+        if let Some(event) = &request.event {
+            request.message.get_or_insert_with(|| event.title.clone());
+            if let Some(severity) = &event.severity {
+                request.severity.get_or_insert_with(|| severity.clone());
+            }
+        }
+        Ok(request)
+    }
+}
+
+/// Channel bookmark, e.g. the runbook of an alert.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Bookmark {
+    pub title: String,
+    pub link: String,
+}
+
+/// Alert described by its parts rather than by Block Kit blocks.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Event {
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+    /// ISO-8601 time the event happened at.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+}
+
+/// Person or system behind a message, like the author of a commit.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Author {
+    pub name: String,
+    #[serde(default)]
+    pub icon_url: Option<String>,
+}
+
+/// Image shown with the message, like a monitoring graph.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Image {
+    pub url: String,
+    #[serde(default)]
+    pub alt: Option<String>,
+}
+
+/// File uploaded with a message, like an email attachment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Upload {
+    pub filename: String,
+    pub data: Vec<u8>,
+}
+
+/// Rows of cells under optional column headers.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Table {
+    #[serde(default)]
+    pub headers: Vec<String>,
+    #[serde(default)]
+    pub rows: Vec<Vec<serde_json::Value>>,
+}
+
+/// Machine-readable event attached to a message, for Slack workflows and
+/// apps listening to the channel.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct MessageMetadata {
+    pub event_type: String,
+    #[serde(default)]
+    pub event_payload: serde_json::Map<String, serde_json::Value>,
+}
+
+/// JSON passed on to Slack as written by the caller, only checked to be well
+/// formed, so large `blocks` or `attachments` arrays are not rebuilt.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(transparent)]
+pub struct RawField(pub Box<serde_json::value::RawValue>);
+
+impl RawField {
+    pub fn to_value(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::from_str(self.0.get())?)
+    }
+
+    pub fn from_value(value: &serde_json::Value) -> Result<Self> {
+        Ok(Self(serde_json::value::to_raw_value(value)?))
+    }
+}
+
+impl PartialEq for RawField {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.get() == other.0.get()
+    }
+}
+
+/// Body of a message request, sent as JSON, YAML, MessagePack, Protobuf, a
+/// structured CloudEvent, an url-encoded or multipart form or as plain text
+/// holding the message itself.
+///
+/// JSON bodies also keep the bytes as received, forwarded as is in
+/// passthrough mode, and multipart ones their file parts as uploads.
+#[derive(Debug, Clone)]
+pub struct MessageBody(pub serde_json::Value, pub Option<Bytes>, pub Vec<Upload>);
+
+impl FromBody for MessageBody {
+    const MEDIA_TYPES: &'static [&'static str] = &[
+        JSON,
+        FORM,
+        TEXT,
+        "application/yaml",
+        "application/x-yaml",
+        "text/yaml",
+        "application/msgpack",
+        "application/x-msgpack",
+        "multipart/form-data",
+        "application/x-protobuf",
+        "application/protobuf",
+    ];
+
+    fn from_data(data: Bytes) -> Result<Self> {
+        let Json(value) = Json::from_data(data.clone())?;
+        Ok(Self(value, Some(data), Vec::new()))
+    }
+
+    // JSON stays the default for callers not sending a Content-Type
+    fn from_content(data: Bytes, content_type: Option<&str>) -> Result<Self> {
+        match content_type {
+            Some(content_type)
+                if body::matches_media_type(content_type, cloudevents::MEDIA_TYPE) =>
+            {
+                let Json(value) = Json::from_data(data)?;
+                Ok(Self(
+                    CloudEvent::from_structured(value)?.into_context(),
+                    None,
+                    Vec::new(),
+                ))
+            }
+            Some(content_type) if body::matches_media_type(content_type, FORM) => {
+                let Form(value) = Form::from_data(data)?;
+                Ok(Self(value, None, Vec::new()))
+            }
+            Some(content_type)
+                if body::matches_any_media_type(content_type, Yaml::<()>::MEDIA_TYPES) =>
+            {
+                let Yaml(value) = Yaml::from_data(data)?;
+                Ok(Self(value, None, Vec::new()))
+            }
+            Some(content_type)
+                if body::matches_any_media_type(content_type, MsgPack::<()>::MEDIA_TYPES) =>
+            {
+                let MsgPack(value) = MsgPack::from_data(data)?;
+                Ok(Self(value, None, Vec::new()))
+            }
+            Some(content_type)
+                if body::matches_any_media_type(content_type, Multipart::MEDIA_TYPES) =>
+            {
+                let multipart = Multipart::from_content(data, Some(content_type))?;
+                let uploads = multipart
+                    .files()
+                    .map(|file| Upload {
+                        filename: file
+                            .filename
+                            .clone()
+                            .or_else(|| file.name.clone())
+                            .unwrap_or_else(|| "file".to_string()),
+                        data: file.data.to_vec(),
+                    })
+                    .collect();
+                Ok(Self(multipart_context(&multipart)?, None, uploads))
+            }
+            Some(content_type)
+                if body::matches_any_media_type(
+                    content_type,
+                    Proto::<Notification>::MEDIA_TYPES,
+                ) =>
+            {
+                let Proto(notification) = Proto::<Notification>::from_data(data)?;
+                Ok(Self(notification.into(), None, Vec::new()))
+            }
+            Some(content_type) if body::matches_media_type(content_type, TEXT) => {
+                let text = String::from_data(data)?;
+                let message = text.trim_end_matches(['\r', '\n']);
+                Ok(Self(
+                    serde_json::json!({ "message": message }),
+                    None,
+                    Vec::new(),
+                ))
+            }
+            _ => Self::from_data(data),
+        }
+    }
+}
+
+// Text parts become fields, file parts are described under `files`, their
+// content being uploaded in bot-token mode only
+fn multipart_context(multipart: &Multipart) -> Result<serde_json::Value> {
+    let mut context = serde_json::Map::new();
+    for (name, data) in multipart.fields() {
+        let value = std::str::from_utf8(data)?;
+        context.insert(name.to_string(), value.into());
+    }
+
+    let files: Vec<_> = multipart
+        .files()
+        .map(|file| {
+            serde_json::json!({
+                "name": file.name,
+                "filename": file.filename,
+                "content_type": file.content_type,
+                "size": file.data.len(),
+            })
+        })
+        .collect();
+    if !files.is_empty() {
+        context.insert("files".to_string(), files.into());
+    }
+
+    Ok(serde_json::Value::Object(context))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_notification_request_from_value() {
+        let request = NotificationRequest::from_value(
+            json!({ "message": "hi", "severity": "error", "env": "prod" }),
+            false,
+        )
+        .unwrap();
+        assert_eq!(request.message.as_deref(), Some("hi"));
+        assert_eq!(request.severity.as_deref(), Some("error"));
+        assert_eq!(request.extra.get("env"), Some(&json!("prod")));
+    }
+
+    #[test]
+    fn test_notification_request_event_defaults() {
+        let request = NotificationRequest::from_value(
+            json!({ "event": { "title": "Disk full", "severity": "error" } }),
+            true,
+        )
+        .unwrap();
+        assert_eq!(request.message.as_deref(), Some("Disk full"));
+        assert_eq!(request.severity.as_deref(), Some("error"));
+
+        let request = NotificationRequest::from_value(
+            json!({ "message": "db-1 disk full", "severity": "warning", "event": { "title": "Disk full", "severity": "error" } }),
+            true,
+        )
+        .unwrap();
+        assert_eq!(request.message.as_deref(), Some("db-1 disk full"));
+        assert_eq!(request.severity.as_deref(), Some("warning"));
+    }
+
+    #[test]
+    fn test_notification_request_serialize() {
+        let request = NotificationRequest {
+            message: Some("deploy done".to_string()),
+            severity: Some("info".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({ "message": "deploy done", "severity": "info" })
+        );
+    }
+
+    #[test]
+    fn test_notification_request_strict_unknown_field() {
+        let result = NotificationRequest::from_value(json!({ "mesage": "hi" }), true);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Unknown field 'mesage' in request body"
+        );
+    }
+
+    #[test]
+    fn test_notification_request_strict_known_fields() {
+        let request = NotificationRequest::from_value(
+            json!({ "message": "hi", "channel": "#ops", "thread_ts": "1.2", "blocks": [] }),
+            true,
+        )
+        .unwrap();
+        assert_eq!(request.channel.as_deref(), Some("#ops"));
+        assert!(request.extra.is_empty());
+    }
+
+    #[test]
+    fn test_notification_request_raw_blocks() {
+        let request = NotificationRequest::from_value(
+            json!({
+                "message": "hi",
+                "blocks": [{ "type": "section", "text": { "type": "mrkdwn", "text": "*hi*" } }],
+                "attachments": [{ "color": "#ff0000" }],
+            }),
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            request.blocks.unwrap().0.get(),
+            r##"[{"text":{"text":"*hi*","type":"mrkdwn"},"type":"section"}]"##
+        );
+        assert_eq!(
+            request.attachments.unwrap().to_value().unwrap(),
+            json!([{ "color": "#ff0000" }])
+        );
+    }
+
+    #[test]
+    fn test_notification_request_invalid_type() {
+        let result = NotificationRequest::from_value(json!({ "message": 42 }), true);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("Invalid request body: invalid type"));
+    }
+
+    #[test]
+    fn test_message_body_from_json() {
+        let data = Bytes::from(r#"{"message":"hello"}"#);
+        let body = MessageBody::from_content(data, Some("application/json")).unwrap();
+        assert_eq!(body.0, json!({ "message": "hello" }));
+    }
+
+    #[test]
+    fn test_message_body_without_content_type_is_json() {
+        let data = Bytes::from(r#"{"message":"hello"}"#);
+        let body = MessageBody::from_content(data, None).unwrap();
+        assert_eq!(body.0, json!({ "message": "hello" }));
+    }
+
+    #[test]
+    fn test_message_body_from_form() {
+        let data = Bytes::from("message=deploy+done&channel=%23ops");
+        let body = MessageBody::from_content(
+            data,
+            Some("application/x-www-form-urlencoded; charset=utf-8"),
+        )
+        .unwrap();
+        assert_eq!(
+            body.0,
+            json!({ "message": "deploy done", "channel": "#ops" })
+        );
+    }
+
+    #[test]
+    fn test_message_body_from_text() {
+        let data = Bytes::from("deploy done\n");
+        let body = MessageBody::from_content(data, Some("text/plain; charset=utf-8")).unwrap();
+        assert_eq!(body.0, json!({ "message": "deploy done" }));
+    }
+
+    #[test]
+    fn test_message_body_from_text_invalid_utf8() {
+        let data = Bytes::from_static(&[0xff, 0xfe]);
+        assert!(MessageBody::from_content(data, Some("text/plain")).is_err());
+    }
+
+    #[test]
+    fn test_message_body_from_yaml() {
+        let data = Bytes::from("message: build passed\nseverity: info\n");
+        let body = MessageBody::from_content(data, Some("application/yaml")).unwrap();
+        assert_eq!(
+            body.0,
+            json!({ "message": "build passed", "severity": "info" })
+        );
+    }
+
+    #[test]
+    fn test_message_body_accepts_yaml_media_types() {
+        for media_type in Yaml::<()>::MEDIA_TYPES {
+            assert!(MessageBody::MEDIA_TYPES.contains(media_type));
+        }
+    }
+
+    #[test]
+    fn test_message_body_from_msgpack() {
+        let data = rmp_serde::to_vec_named(&json!({ "message": "hello" })).unwrap();
+        let body = MessageBody::from_content(data.into(), Some("application/msgpack")).unwrap();
+        assert_eq!(body.0, json!({ "message": "hello" }));
+    }
+
+    #[test]
+    fn test_message_body_accepts_msgpack_media_types() {
+        for media_type in MsgPack::<()>::MEDIA_TYPES {
+            assert!(MessageBody::MEDIA_TYPES.contains(media_type));
+        }
+    }
+
+    #[test]
+    fn test_message_body_from_structured_cloudevent() {
+        let data = Bytes::from(
+            r#"{"specversion":"1.0","type":"deploy","source":"/ci","data":{"message":"done"}}"#,
+        );
+        let body = MessageBody::from_content(data, Some("application/cloudevents+json")).unwrap();
+        assert_eq!(body.0["message"], "done");
+        assert_eq!(body.0["cloudevent"]["type"], "deploy");
+    }
+
+    #[test]
+    fn test_message_body_from_invalid_cloudevent() {
+        let data = Bytes::from(r#"{"message":"done"}"#);
+        assert!(MessageBody::from_content(data, Some("application/cloudevents+json")).is_err());
+    }
+
+    #[test]
+    fn test_message_body_from_multipart() {
+        let data = Bytes::from(
+            "--b\r\nContent-Disposition: form-data; name=\"message\"\r\n\r\nbuild failed\r\n\
+             --b\r\nContent-Disposition: form-data; name=\"log\"; filename=\"build.log\"\r\n\
+             Content-Type: text/plain\r\n\r\nerror\r\n--b--\r\n",
+        );
+        let body =
+            MessageBody::from_content(data, Some("multipart/form-data; boundary=b")).unwrap();
+        assert_eq!(
+            body.0,
+            json!({
+                "message": "build failed",
+                "files": [{
+                    "name": "log",
+                    "filename": "build.log",
+                    "content_type": "text/plain",
+                    "size": 5,
+                }],
+            })
+        );
+        assert_eq!(
+            body.2,
+            vec![Upload {
+                filename: "build.log".to_string(),
+                data: b"error".to_vec(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_message_body_from_proto() {
+        use prost::Message;
+
+        let notification = Notification {
+            message: "disk full".to_string(),
+            ..Default::default()
+        };
+        let data = Bytes::from(notification.encode_to_vec());
+        let body = MessageBody::from_content(data, Some("application/x-protobuf")).unwrap();
+        assert_eq!(body.0, json!({ "message": "disk full" }));
+    }
+
+    #[test]
+    fn test_message_body_invalid_json() {
+        let data = Bytes::from("message=hello");
+        assert!(MessageBody::from_content(data, Some("application/json")).is_err());
+    }
+}
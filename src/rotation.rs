@@ -0,0 +1,268 @@
+use anyhow::Result;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::client::{ClientResponse, OutboundClient};
+use crate::store::{HttpStore, KeyValue};
+use crate::Settings;
+
+// Webhook rotation
+//
+// With `secondary_webhook_url` set, messages the `webhook_url` webhook
+// fails to deliver are sent to the secondary one, instead of every request
+// failing during a webhook outage. When the key-value store is configured,
+// the outcomes of the last deliveries to each webhook are kept under
+// `webhooks/primary` and `webhooks/secondary`: a webhook failing half of
+// them, three times at least, is skipped for `webhook_probe_seconds`, after
+// which the next message probes it again, closing the circuit on success.
+// Store errors are logged and the primary webhook is tried first.
+
+/// Deliveries the failure rate of a webhook is computed over.
+const WINDOW: usize = 10;
+
+/// Failures needed before a webhook is skipped.
+const MIN_FAILURES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Webhook {
+    Primary,
+    Secondary,
+}
+
+impl Webhook {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Primary => "primary",
+            Self::Secondary => "secondary",
+        }
+    }
+}
+
+/// Recent deliveries to a webhook, failures being `true`, oldest first.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default, PartialEq)]
+struct Health {
+    #[serde(default)]
+    recent: Vec<bool>,
+    /// Unix time until which the webhook is skipped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retry_at: Option<u64>,
+}
+
+impl Health {
+    fn is_down(&self, now: u64) -> bool {
+        self.retry_at.is_some_and(|retry_at| retry_at > now)
+    }
+
+    fn record(&mut self, failed: bool, now: u64, probe_seconds: u64) {
+        // A successful probe starts over
+        if !failed && self.retry_at.take().is_some() {
+            self.recent.clear();
+        }
+        self.recent.push(failed);
+        if self.recent.len() > WINDOW {
+            self.recent.drain(..self.recent.len() - WINDOW);
+        }
+        let failures = self.recent.iter().filter(|failed| **failed).count();
+        if failed && failures >= MIN_FAILURES && failures * 2 >= self.recent.len() {
+            self.retry_at = Some(now + probe_seconds);
+        }
+    }
+}
+
+/// Whether Slack answered that the webhook, rather than the message, is
+/// broken: server errors, rate limits, and removed or archived channels.
+fn is_failure(status: u16) -> bool {
+    status >= 500 || matches!(status, 403 | 404 | 410 | 429)
+}
+
+fn health_key(webhook: Webhook) -> String {
+    format!("webhooks/{}", webhook.name())
+}
+
+fn load(store: Option<&HttpStore>, webhook: Webhook) -> Health {
+    let Some(store) = store else {
+        return Health::default();
+    };
+    match store.get(&health_key(webhook)) {
+        Ok(value) => value
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default(),
+        Err(err) => {
+            eprintln!(
+                "Failed to read the {} webhook health: {err}",
+                webhook.name()
+            );
+            Health::default()
+        }
+    }
+}
+
+fn save(store: Option<&HttpStore>, webhook: Webhook, health: &Health) {
+    let Some(store) = store else {
+        return;
+    };
+    let saved = serde_json::to_string(health)
+        .map_err(anyhow::Error::from)
+        .and_then(|value| store.put(&health_key(webhook), &value));
+    if let Err(err) = saved {
+        eprintln!(
+            "Failed to store the {} webhook health: {err}",
+            webhook.name()
+        );
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Sends `body` with `post`, given the webhook URL setting to send to, to
+/// the healthy webhook first and to the other one when it fails.
+pub fn send(
+    settings: &Settings,
+    client: &dyn OutboundClient,
+    body: Vec<u8>,
+    post: &dyn Fn(&str, Vec<u8>) -> Result<ClientResponse>,
+) -> Result<ClientResponse> {
+    let Some(secondary_url) = &settings.secondary_webhook_url else {
+        return post(&settings.webhook_url, body);
+    };
+    let store = match &settings.token_store_endpoint {
+        Some(_) => Some(HttpStore::from_settings(settings, client)?),
+        None => None,
+    };
+    let store = store.as_ref();
+    let now = unix_now();
+
+    let mut primary = load(store, Webhook::Primary);
+    let mut secondary = load(store, Webhook::Secondary);
+    let order = match primary.is_down(now) && !secondary.is_down(now) {
+        true => [Webhook::Secondary, Webhook::Primary],
+        false => [Webhook::Primary, Webhook::Secondary],
+    };
+    let mut attempt = |webhook: Webhook, body: Vec<u8>| {
+        let (url, health) = match webhook {
+            Webhook::Primary => (&settings.webhook_url, &mut primary),
+            Webhook::Secondary => (secondary_url, &mut secondary),
+        };
+        let response = post(url, body);
+        let failed = response
+            .as_ref()
+            .map_or(true, |response| is_failure(response.status));
+        health.record(failed, now, settings.webhook_probe_seconds);
+        save(store, webhook, health);
+        (response, failed)
+    };
+
+    let (response, failed) = attempt(order[0], body.clone());
+    if !failed {
+        return response;
+    }
+    eprintln!(
+        "The {} webhook failed, sending to the {} one",
+        order[0].name(),
+        order[1].name()
+    );
+    attempt(order[1], body).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockClient;
+    use std::collections::HashMap;
+
+    const PRIMARY: &str = "https://hooks.slack.com/services/primary";
+    const SECONDARY: &str = "https://hooks.slack.com/services/secondary";
+
+    fn settings(store: bool) -> Settings {
+        let mut data = HashMap::from([
+            ("webhook_url".to_string(), PRIMARY.to_string()),
+            ("secondary_webhook_url".to_string(), SECONDARY.to_string()),
+        ]);
+        if store {
+            data.insert(
+                "token_store_endpoint".to_string(),
+                "https://kv.example.com/slack".to_string(),
+            );
+        }
+        Settings::from_map(&data).unwrap()
+    }
+
+    fn send_with(settings: &Settings, client: &MockClient) -> ClientResponse {
+        let post = |url: &str, body: Vec<u8>| client.post(url, &http::HeaderMap::new(), body);
+        send(settings, client, b"{}".to_vec(), &post).unwrap()
+    }
+
+    #[test]
+    fn test_health_record() {
+        let mut health = Health::default();
+        for failed in [false, true, false, true] {
+            health.record(failed, 1000, 60);
+        }
+        assert!(!health.is_down(1000));
+
+        health.record(true, 1000, 60);
+        assert_eq!(health.retry_at, Some(1060));
+        assert!(health.is_down(1059));
+        assert!(!health.is_down(1060));
+
+        health.record(false, 1060, 60);
+        assert_eq!(
+            health,
+            Health {
+                recent: vec![false],
+                retry_at: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_send_falls_back_to_secondary() {
+        let client = MockClient::new().respond_with(503).respond_with(200);
+        assert_eq!(send_with(&settings(false), &client).status, 200);
+
+        let urls: Vec<String> = client.requests().into_iter().map(|req| req.url).collect();
+        assert_eq!(urls, [PRIMARY, SECONDARY]);
+    }
+
+    #[test]
+    fn test_send_keeps_message_errors() {
+        let client = MockClient::new().respond_with_body(400, "invalid_payload");
+        assert_eq!(send_with(&settings(false), &client).status, 400);
+        assert_eq!(client.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_send_skips_down_primary() {
+        let down = serde_json::json!({ "recent": [true, true, true], "retry_at": u64::MAX });
+        let client = MockClient::new()
+            .respond_with_body(
+                200,
+                &serde_json::json!({ "value": down.to_string() }).to_string(),
+            )
+            .respond_with(404)
+            .respond_with(200)
+            .respond_with(204);
+        assert_eq!(send_with(&settings(true), &client).status, 200);
+
+        let requests = client.requests();
+        assert_eq!(
+            requests[0].url,
+            "https://kv.example.com/slack/webhooks/primary"
+        );
+        assert_eq!(requests[2].url, SECONDARY);
+        assert_eq!(requests[3].method, http::Method::PUT);
+        assert_eq!(
+            requests[3].url,
+            "https://kv.example.com/slack/webhooks/secondary"
+        );
+        assert_eq!(
+            requests[3].body,
+            serde_json::to_vec(&serde_json::json!({ "value": r#"{"recent":[false]}"# })).unwrap()
+        );
+        assert_eq!(requests.len(), 4);
+    }
+}
@@ -0,0 +1,78 @@
+use anyhow::Result;
+use serde_json::Value;
+
+// JSON Schema validation of inbound payloads
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Violation {
+    /// JSON pointer to the offending value, empty for the document root.
+    pub path: String,
+    pub message: String,
+}
+
+/// Validates `instance` against `schema`, returning every violation found.
+pub fn validate(schema: &Value, instance: &Value) -> Result<Vec<Violation>> {
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|err| anyhow::anyhow!("Invalid input_schema setting: {err}"))?;
+    Ok(validator
+        .iter_errors(instance)
+        .map(|err| Violation {
+            path: err.instance_path.to_string(),
+            message: err.to_string(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "required": ["message"],
+            "properties": {
+                "message": { "type": "string", "maxLength": 10 },
+                "severity": { "enum": ["info", "warning", "error"] },
+            },
+        })
+    }
+
+    #[test]
+    fn test_validate_valid_instance() {
+        let violations = validate(&schema(), &json!({ "message": "hello" })).unwrap();
+        assert!(violations.is_empty());
+    }
+
+    #[test]
+    fn test_validate_reports_every_violation() {
+        let violations = validate(
+            &schema(),
+            &json!({ "message": "way too long message", "severity": "fatal" }),
+        )
+        .unwrap();
+
+        let paths: Vec<_> = violations.iter().map(|v| v.path.as_str()).collect();
+        assert_eq!(violations.len(), 2);
+        assert!(paths.contains(&"/message"));
+        assert!(paths.contains(&"/severity"));
+    }
+
+    #[test]
+    fn test_validate_missing_required() {
+        let violations = validate(&schema(), &json!({})).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].path, "");
+        assert!(violations[0].message.contains("message"));
+    }
+
+    #[test]
+    fn test_validate_invalid_schema() {
+        let result = validate(&json!({ "type": "not-a-type" }), &json!({}));
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("Invalid input_schema setting"));
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::client::OutboundClient;
+
+// Secret references in settings
+//
+// A value like `secret://slack-prod` is fetched from the `secrets_endpoint`
+// setting as `GET <secrets_endpoint>/slack-prod`, which must answer
+// `{"value": "..."}`. Resolved values are cached since component instances
+// can serve several requests.
+
+pub const SCHEME: &str = "secret://";
+
+const CACHE_TTL: Duration = Duration::from_secs(300);
+
+static CACHE: LazyLock<Mutex<HashMap<String, (String, Instant)>>> = LazyLock::new(Default::default);
+
+#[derive(serde::Deserialize)]
+struct SecretResponse {
+    value: String,
+}
+
+pub fn is_reference(value: &str) -> bool {
+    value.starts_with(SCHEME)
+}
+
+/// Returns `value` itself, or the secret it references.
+pub fn resolve(
+    value: &str,
+    endpoint: Option<&str>,
+    token: Option<&str>,
+    client: &dyn OutboundClient,
+) -> Result<String> {
+    let Some(name) = value.strip_prefix(SCHEME) else {
+        return Ok(value.to_string());
+    };
+    let endpoint = endpoint.ok_or_else(|| {
+        anyhow::anyhow!("Secret '{name}' is referenced but no secrets_endpoint is set")
+    })?;
+    let url = format!("{}/{name}", endpoint.trim_end_matches('/'));
+
+    if let Some((value, fetched_at)) = CACHE.lock().unwrap().get(&url) {
+        if fetched_at.elapsed() < CACHE_TTL {
+            return Ok(value.clone());
+        }
+    }
+
+    let mut headers = http::HeaderMap::new();
+    if let Some(token) = token {
+        headers.insert(
+            http::header::AUTHORIZATION,
+            format!("Bearer {token}").parse()?,
+        );
+    }
+    let response = client.get(&url, &headers)?;
+    if response.status != 200 {
+        anyhow::bail!(
+            "Failed to resolve secret '{name}': endpoint answered {}",
+            response.status
+        );
+    }
+    let SecretResponse { value } = serde_json::from_slice(&response.body)
+        .map_err(|err| anyhow::anyhow!("Failed to resolve secret '{name}': {err}"))?;
+
+    CACHE
+        .lock()
+        .unwrap()
+        .insert(url, (value.clone(), Instant::now()));
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockClient;
+
+    #[test]
+    fn test_resolve_plain_value() {
+        let client = MockClient::new();
+        let value = resolve("https://hooks.slack.com/x", None, None, &client).unwrap();
+        assert_eq!(value, "https://hooks.slack.com/x");
+        assert!(client.requests().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_reference_and_cache() {
+        let client =
+            MockClient::new().respond_with_body(200, r#"{"value": "https://hooks.slack.com/a"}"#);
+        let endpoint = Some("https://secrets.example.com/v1/");
+
+        let value = resolve("secret://cached", endpoint, Some("t0k"), &client).unwrap();
+        assert_eq!(value, "https://hooks.slack.com/a");
+        let value = resolve("secret://cached", endpoint, Some("t0k"), &client).unwrap();
+        assert_eq!(value, "https://hooks.slack.com/a");
+
+        let requests = client.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, http::Method::GET);
+        assert_eq!(requests[0].url, "https://secrets.example.com/v1/cached");
+        assert_eq!(
+            requests[0]
+                .headers
+                .get(http::header::AUTHORIZATION)
+                .unwrap(),
+            "Bearer t0k"
+        );
+    }
+
+    #[test]
+    fn test_resolve_errors() {
+        let client = MockClient::new()
+            .respond_with(404)
+            .respond_with_body(200, "nope");
+        let endpoint = Some("https://secrets.example.com");
+
+        assert_eq!(
+            resolve("secret://missing", None, None, &client)
+                .unwrap_err()
+                .to_string(),
+            "Secret 'missing' is referenced but no secrets_endpoint is set"
+        );
+        assert_eq!(
+            resolve("secret://unknown", endpoint, None, &client)
+                .unwrap_err()
+                .to_string(),
+            "Failed to resolve secret 'unknown': endpoint answered 404"
+        );
+        assert!(resolve("secret://garbled", endpoint, None, &client)
+            .unwrap_err()
+            .to_string()
+            .starts_with("Failed to resolve secret 'garbled': "));
+    }
+}
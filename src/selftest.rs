@@ -0,0 +1,180 @@
+use anyhow::Result;
+use http::{Request, Response};
+use serde_json::Value;
+
+use crate::client::OutboundClient;
+use crate::helpers::negotiate::Negotiated;
+use crate::helpers::ApiError;
+use crate::request::{MessageBody, NotificationRequest};
+use crate::{auth, secrets, Settings};
+
+// Configuration test
+//
+// `POST /test` posts a canned message naming the component version and the
+// destination it went to, so operators can check delivery end to end right
+// after install. The body may set the `channel`, `channels` and `team_id` of
+// a request, the message itself is always the canned one.
+
+pub const TEST_PATH: &str = "/test";
+
+/// Where messages of `request` go: the webhook URL without its secret part,
+/// or the workspace and channels in bot-token mode.
+pub fn destination(settings: &Settings, request: &NotificationRequest) -> String {
+    if !settings.webhook_url.is_empty() {
+        return match settings.webhook_url.rsplit_once('/') {
+            Some((path, _)) if !secrets::is_reference(&settings.webhook_url) => {
+                format!("{path}/****")
+            }
+            _ => settings.webhook_url.clone(),
+        };
+    }
+
+    let channels = match (&request.channels, &request.channel) {
+        (Some(channels), _) => channels.join(","),
+        (None, Some(channel)) => channel.clone(),
+        (None, None) => settings
+            .default_channel
+            .clone()
+            .or_else(|| settings.defaults.as_ref()?.channel.clone())
+            .unwrap_or_default(),
+    };
+    match request
+        .team_id
+        .as_ref()
+        .or(settings.default_team_id.as_ref())
+    {
+        Some(team_id) if settings.uses_token_store() => format!("{team_id}/{channels}"),
+        _ => channels,
+    }
+}
+
+/// Handles `POST /test`, which needs the `auth_token` as a bearer token.
+pub fn handle(
+    req: &Request<Option<MessageBody>>,
+    settings: &Settings,
+    client: &dyn OutboundClient,
+) -> Result<Response<Negotiated<Value>>> {
+    let Some(expected) = settings.auth_token.as_deref() else {
+        return Err(ApiError::not_found("Configuration tests need an auth_token").into());
+    };
+    if !auth::is_authorized(req.headers(), None, expected) {
+        return Err(ApiError::unauthorized("Unauthorized").into());
+    }
+
+    let target = match req.body() {
+        Some(MessageBody(data, ..)) => NotificationRequest::from_value(data.clone(), false)
+            .map_err(|err| ApiError::bad_request(err.to_string()))?,
+        None => NotificationRequest::default(),
+    };
+    let destination = destination(settings, &target);
+    let request = NotificationRequest {
+        message: Some(format!(
+            ":white_check_mark: Configuration test from the Slack Message component v{}, sent to {destination}",
+            env!("CARGO_PKG_VERSION")
+        )),
+        channel: target.channel,
+        channels: target.channels,
+        team_id: target.team_id,
+        ..Default::default()
+    };
+
+    let response = crate::handle_notification(settings, request, client)?;
+    let mut res = response.into_response(settings.response_format);
+    if let Some(fields) = res.body_mut().0.as_object_mut() {
+        fields.insert("destination".to_string(), destination.into());
+    }
+    Ok(res.map(Negotiated::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockClient;
+    use crate::testing;
+    use serde_json::json;
+
+    fn test_request(token: &str, body: Option<Value>) -> Request<Option<MessageBody>> {
+        let req = testing::RequestFixture::new(http::Method::POST, TEST_PATH)
+            .header("Authorization", &format!("Bearer {token}"));
+        match body {
+            Some(body) => req.json(body).build(),
+            None => req.build(),
+        }
+    }
+
+    fn settings(data: &[(&str, &str)]) -> Settings {
+        let data = data
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        Settings::from_map(&data).unwrap()
+    }
+
+    #[test]
+    fn test_handle_webhook() {
+        let settings = settings(&[
+            ("webhook_url", "https://hooks.slack.com/services/T0/B0/xyz"),
+            ("auth_token", "s3cret"),
+        ]);
+        let client = MockClient::new();
+
+        let res = handle(&test_request("s3cret", None), &settings, &client).unwrap();
+        let body = testing::assert_status(&res, http::StatusCode::OK);
+        assert_eq!(
+            body["destination"],
+            "https://hooks.slack.com/services/T0/B0/****"
+        );
+
+        let posted: Value = serde_json::from_slice(&client.requests()[0].body).unwrap();
+        assert_eq!(
+            posted["text"],
+            format!(
+                ":white_check_mark: Configuration test from the Slack Message component v{}, \
+                 sent to https://hooks.slack.com/services/T0/B0/****",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+    }
+
+    #[test]
+    fn test_handle_bot_token_channel() {
+        let settings = settings(&[
+            ("bot_token", "xoxb-1"),
+            ("default_channel", "#general"),
+            ("auth_token", "s3cret"),
+        ]);
+        let client = MockClient::new()
+            .respond_with_body(200, r#"{"ok": true, "channel": "C1", "ts": "1.1"}"#);
+
+        let body = json!({ "channel": "#ops", "message": "ignored" });
+        let res = handle(&test_request("s3cret", Some(body)), &settings, &client).unwrap();
+        let body = testing::assert_status(&res, http::StatusCode::OK);
+        assert_eq!(body["destination"], "#ops");
+
+        let posted: Value = serde_json::from_slice(&client.requests()[0].body).unwrap();
+        assert_eq!(posted["channel"], "#ops");
+        assert!(posted["text"].as_str().unwrap().ends_with("sent to #ops"));
+    }
+
+    #[test]
+    fn test_handle_requires_auth_token() {
+        let settings = settings(&[("webhook_url", "https://hooks.slack.com/services/T0/B0/xyz")]);
+        let client = MockClient::new();
+        let err = handle(&test_request("s3cret", None), &settings, &client).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ApiError>().unwrap().status,
+            http::StatusCode::NOT_FOUND
+        );
+
+        let settings = Settings {
+            auth_token: Some("s3cret".to_string()),
+            ..settings
+        };
+        let err = handle(&test_request("wrong", None), &settings, &client).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ApiError>().unwrap().status,
+            http::StatusCode::UNAUTHORIZED
+        );
+        assert!(client.requests().is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, Engine, GeneralPurpose, GeneralPurposeConfig};
+use hmac::{Hmac, Mac};
+use http::HeaderMap;
+use sha1::Sha1;
+use sha2::Sha256;
+
+// Slack request signatures
+//
+// Requests from Slack carry `X-Slack-Signature: v0=<hex>`, the HMAC-SHA256
+// of `v0:<X-Slack-Request-Timestamp>:<body>` keyed with the app's signing
+// secret. Vendor webhooks signed the same way (Shopify, or Twilio with
+// SHA-1) reuse the HMAC with base64 signatures.
+
+/// Requests signed longer ago are rejected as possible replays.
+const MAX_AGE: Duration = Duration::from_secs(300);
+
+/// Whether `body` was signed by Slack with `secret` less than five minutes
+/// ago.
+pub fn verify(headers: &HeaderMap, body: &[u8], secret: &str) -> bool {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    verify_at(headers, body, secret, now)
+}
+
+fn verify_at(headers: &HeaderMap, body: &[u8], secret: &str, now: Duration) -> bool {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let (Some(timestamp), Some(signature)) = (
+        header("x-slack-request-timestamp"),
+        header("x-slack-signature"),
+    ) else {
+        return false;
+    };
+    let Ok(signed_at) = timestamp.parse::<u64>() else {
+        return false;
+    };
+    if now.as_secs().abs_diff(signed_at) > MAX_AGE.as_secs() {
+        return false;
+    }
+
+    let expected = sign(secret, signed_at, body);
+    crate::auth::constant_time_eq(expected.as_bytes(), signature.as_bytes())
+}
+
+/// `X-Slack-Signature` value of `body` sent at `timestamp`.
+pub fn sign(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    let mut base = format!("v0:{timestamp}:").into_bytes();
+    base.extend_from_slice(body);
+    format!("v0={}", hex(&hmac_sha256(secret.as_bytes(), &base)))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+// Vendors differ on padding, and wrap long values like email attachments
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Standard, padded base64 of `bytes`.
+pub fn base64(bytes: &[u8]) -> String {
+    BASE64.encode(bytes)
+}
+
+/// Bytes of standard base64 `text`, padded or not, line breaks ignored.
+/// `None` when it isn't base64.
+pub fn from_base64(text: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = text
+        .bytes()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect();
+    BASE64.decode(digits).ok()
+}
+
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any size");
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
+/// Only kept for vendors still signing with it (Twilio).
+pub fn hmac_sha1(key: &[u8], message: &[u8]) -> [u8; 20] {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC takes keys of any size");
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231, test cases 2 and 6
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hex(&hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn test_hmac_sha1() {
+        // RFC 2202, test case 2
+        assert_eq!(
+            hex(&hmac_sha1(b"Jefe", b"what do ya want for nothing?")),
+            "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79"
+        );
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[0xfb, 0xff]), "+/8=");
+    }
+
+    #[test]
+    fn test_from_base64() {
+        assert_eq!(from_base64("").unwrap(), b"");
+        assert_eq!(from_base64("Zg==").unwrap(), b"f");
+        assert_eq!(from_base64("Zm8").unwrap(), b"fo");
+        assert_eq!(from_base64("Zm9v\r\nYmFy").unwrap(), b"foobar");
+        assert_eq!(from_base64("+/8=").unwrap(), [0xfb, 0xff]);
+        assert_eq!(from_base64("Zm9v!"), None);
+        assert_eq!(from_base64("Zm9vY"), None);
+    }
+
+    #[test]
+    fn test_verify() {
+        // Example from Slack's "Verifying requests from Slack" guide
+        let body = b"token=xyzz0WbapA4vBCDEFasx0q6G&team_id=T1DC2JH3J&team_domain=testteamnow&channel_id=G8PSS9T3V&channel_name=foobar&user_id=U2CERLKJA&user_name=roadrunner&command=%2Fwebhook-collect&text=&response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2FT1DC2JH3J%2F397700885554%2F96rGlfmibIGlgcZRskXaIFfN&trigger_id=398738663015.47445629121.803a0bc887a14d10d2c447fce8b6703c";
+        let secret = "8f742231b10e8888abcd99yyyzzz85a5";
+        let mut headers = HeaderMap::new();
+        headers.insert("x-slack-request-timestamp", "1531420618".parse().unwrap());
+        headers.insert(
+            "x-slack-signature",
+            "v0=a2114d57b48eac39b9ad189dd8316235a7b4a8d21a10bd27519666489c69b503"
+                .parse()
+                .unwrap(),
+        );
+
+        let now = Duration::from_secs(1531420618 + 60);
+        assert!(verify_at(&headers, body, secret, now));
+        assert!(!verify_at(&headers, b"tampered", secret, now));
+        assert!(!verify_at(&headers, body, "other", now));
+        assert!(!verify_at(
+            &headers,
+            body,
+            secret,
+            now + Duration::from_secs(600)
+        ));
+        assert!(!verify_at(&HeaderMap::new(), body, secret, now));
+    }
+}
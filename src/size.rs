@@ -0,0 +1,140 @@
+use anyhow::Result;
+
+use crate::request::Upload;
+use crate::SlackMessagePayload;
+
+// Payload size guard
+//
+// Slack cuts message texts past 40,000 characters and refuses larger
+// payloads. Payloads whose JSON is over `MAX_PAYLOAD_BYTES` get their text
+// shortened before sending: in bot-token mode the full text is uploaded as
+// a snippet in the thread of the message, which keeps its start, in
+// webhook mode it is truncated. The strategy applied is reported in the
+// response as `size_strategy`.
+
+const MAX_PAYLOAD_BYTES: usize = 40_000;
+
+// Start of the text kept in the message when the rest is uploaded
+const UPLOADED_TEXT_CHARS: usize = 3_000;
+
+const SNIPPET_NAME: &str = "message.txt";
+
+/// How an oversized payload was brought under the limit.
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    /// Full text uploaded as a snippet, the message keeping its start.
+    Upload,
+    /// Text cut to fit.
+    Truncate,
+}
+
+/// Shortens the text of `payload` when it is over the size limit. The full
+/// text is added to `uploads` when there are some, i.e. in bot-token mode.
+pub fn guard(
+    payload: &mut SlackMessagePayload,
+    uploads: Option<&mut Vec<Upload>>,
+) -> Result<Option<Strategy>> {
+    let size = serde_json::to_vec(&*payload)?.len();
+    if size <= MAX_PAYLOAD_BYTES {
+        return Ok(None);
+    }
+
+    // Room left for the text, which is all the guard can shorten
+    let text_size = serde_json::to_vec(&payload.text)?.len();
+    let Some(budget) = MAX_PAYLOAD_BYTES.checked_sub(size - text_size) else {
+        return Ok(None);
+    };
+    let text = std::mem::take(&mut payload.text);
+    let Some(uploads) = uploads else {
+        payload.text = cut(&text, usize::MAX, budget, "… (truncated)")?;
+        return Ok(Some(Strategy::Truncate));
+    };
+    payload.text = cut(
+        &text,
+        UPLOADED_TEXT_CHARS,
+        budget,
+        "… (full message attached in the thread)",
+    )?;
+    uploads.push(Upload {
+        filename: SNIPPET_NAME.to_string(),
+        data: text.into_bytes(),
+    });
+    Ok(Some(Strategy::Upload))
+}
+
+// Longest start of `text`, at most `chars` characters, whose JSON with
+// `note` fits in `budget` bytes
+fn cut(text: &str, chars: usize, budget: usize, note: &str) -> Result<String> {
+    let fits = |end: usize| -> Result<bool> {
+        let candidate = format!("{}{note}", &text[..end]);
+        Ok(serde_json::to_vec(&candidate)?.len() <= budget)
+    };
+    let ends: Vec<usize> = text
+        .char_indices()
+        .map(|(index, _)| index)
+        .chain(std::iter::once(text.len()))
+        .take(chars.saturating_add(1))
+        .collect();
+
+    // Escapes make the JSON grow faster than the text, hence the search
+    let (mut low, mut high) = (0, ends.len() - 1);
+    while low < high {
+        let middle = (low + high).div_ceil(2);
+        match fits(ends[middle])? {
+            true => low = middle,
+            false => high = middle - 1,
+        }
+    }
+    Ok(format!("{}{note}", &text[..ends[low]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guard_leaves_small_payloads() {
+        let mut payload = SlackMessagePayload::new("Disk full".to_string());
+        assert_eq!(guard(&mut payload, Some(&mut Vec::new())).unwrap(), None);
+        assert_eq!(payload.text, "Disk full");
+    }
+
+    #[test]
+    fn test_guard_truncates() {
+        let text = "é\"".repeat(20_000);
+        let mut payload = SlackMessagePayload::new(text);
+        assert_eq!(guard(&mut payload, None).unwrap(), Some(Strategy::Truncate));
+        assert!(payload.text.ends_with("… (truncated)"));
+        let size = serde_json::to_vec(&payload).unwrap().len();
+        assert!(
+            size <= MAX_PAYLOAD_BYTES && size > MAX_PAYLOAD_BYTES - 10,
+            "{size}"
+        );
+    }
+
+    #[test]
+    fn test_guard_uploads() {
+        let text = "a".repeat(50_000);
+        let mut payload = SlackMessagePayload::new(text.clone());
+        let mut uploads = Vec::new();
+        assert_eq!(
+            guard(&mut payload, Some(&mut uploads)).unwrap(),
+            Some(Strategy::Upload)
+        );
+        assert_eq!(
+            uploads,
+            [Upload {
+                filename: "message.txt".to_string(),
+                data: text.into_bytes(),
+            }]
+        );
+        assert_eq!(
+            payload.text,
+            format!(
+                "{}… (full message attached in the thread)",
+                "a".repeat(3_000)
+            )
+        );
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::client::{ClientResponse, OutboundClient, OutboundRequest};
+use crate::request::{Bookmark, Upload};
+
+// Slack Web API helpers, used in bot-token mode
+//
+// Methods are called with a JSON body and answer 200 with an `ok` flag, the
+// failure reason being in `error`.
+
+pub const API_URL: &str = "https://slack.com/api";
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ApiResponse {
+    pub ok: bool,
+    #[serde(default)]
+    pub ts: Option<String>,
+    #[serde(default)]
+    pub channel: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+fn api_request(token: &str, method: &str, body: &serde_json::Value) -> Result<OutboundRequest> {
+    let mut headers = http::HeaderMap::new();
+    headers.insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static("application/json; charset=utf-8"),
+    );
+    headers.insert(
+        http::header::AUTHORIZATION,
+        format!("Bearer {token}").parse()?,
+    );
+
+    Ok(OutboundRequest {
+        method: http::Method::POST,
+        url: format!("{API_URL}/{method}"),
+        headers,
+        body: serde_json::to_vec(body)?,
+    })
+}
+
+fn parse_response(method: &str, response: &ClientResponse) -> Result<ApiResponse> {
+    if response.status != 200 {
+        anyhow::bail!("Slack API {method} answered {}", response.status);
+    }
+    serde_json::from_slice(&response.body)
+        .map_err(|err| anyhow::anyhow!("Invalid Slack API {method} response: {err}"))
+}
+
+/// Calls a Web API method, failing when Slack answers `ok: false`.
+pub fn call(
+    client: &dyn OutboundClient,
+    token: &str,
+    method: &str,
+    body: &serde_json::Value,
+) -> Result<serde_json::Value> {
+    let request = api_request(token, method, body)?;
+    let response = client.send(request.method, &request.url, &request.headers, request.body)?;
+    checked(method, &response)
+}
+
+fn checked(method: &str, response: &ClientResponse) -> Result<serde_json::Value> {
+    if response.status != 200 {
+        anyhow::bail!("Slack API {method} answered {}", response.status);
+    }
+
+    let response: serde_json::Value = serde_json::from_slice(&response.body)
+        .map_err(|err| anyhow::anyhow!("Invalid Slack API {method} response: {err}"))?;
+    if response["ok"] != true {
+        let error = response["error"].as_str().unwrap_or("unknown_error");
+        anyhow::bail!("Slack API {method} failed: {error}");
+    }
+    Ok(response)
+}
+
+/// Uploads a file in the thread of the message `ts`: an upload URL is asked
+/// for, the bytes are sent to it and the file is then shared.
+pub fn upload_file(
+    client: &dyn OutboundClient,
+    token: &str,
+    channel_id: &str,
+    ts: &str,
+    upload: &Upload,
+) -> Result<()> {
+    // Unlike other methods, files.getUploadURLExternal only takes a form
+    let method = "files.getUploadURLExternal";
+    let mut headers = http::HeaderMap::new();
+    headers.insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static("application/x-www-form-urlencoded"),
+    );
+    headers.insert(
+        http::header::AUTHORIZATION,
+        format!("Bearer {token}").parse()?,
+    );
+    let form = serde_urlencoded::to_string([
+        ("filename", upload.filename.clone()),
+        ("length", upload.data.len().to_string()),
+    ])?;
+    let response = client.post(&format!("{API_URL}/{method}"), &headers, form.into_bytes())?;
+    let response = checked(method, &response)?;
+    let (Some(upload_url), Some(file_id)) = (
+        response["upload_url"].as_str(),
+        response["file_id"].as_str(),
+    ) else {
+        anyhow::bail!("Invalid Slack API {method} response: missing upload_url or file_id");
+    };
+
+    let response = client.post(upload_url, &http::HeaderMap::new(), upload.data.clone())?;
+    if response.status != 200 {
+        anyhow::bail!("Slack file upload answered {}", response.status);
+    }
+
+    let body = serde_json::json!({
+        "files": [{ "id": file_id, "title": upload.filename }],
+        "channel_id": channel_id,
+        "thread_ts": ts,
+    });
+    call(client, token, "files.completeUploadExternal", &body)?;
+    Ok(())
+}
+
+/// Adds a link bookmark to a channel, by channel ID.
+pub fn add_bookmark(
+    client: &dyn OutboundClient,
+    token: &str,
+    channel_id: &str,
+    bookmark: &Bookmark,
+) -> Result<()> {
+    let body = serde_json::json!({
+        "channel_id": channel_id,
+        "title": bookmark.title,
+        "type": "link",
+        "link": bookmark.link,
+    });
+    call(client, token, "bookmarks.add", &body)?;
+    Ok(())
+}
+
+/// Pins a message to its channel.
+pub fn add_pin(client: &dyn OutboundClient, token: &str, channel_id: &str, ts: &str) -> Result<()> {
+    let body = serde_json::json!({ "channel": channel_id, "timestamp": ts });
+    call(client, token, "pins.add", &body)?;
+    Ok(())
+}
+
+/// Outcome of posting to one channel.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct PostedMessage {
+    pub channel: String,
+    /// Channel ID resolved by Slack, needed by follow-up calls.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ts: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Posts the same payload to every channel, `max_concurrency` calls at a
+/// time. Fails on the first channel Slack could not be reached for.
+pub fn post_messages(
+    client: &dyn OutboundClient,
+    token: &str,
+    channels: &[String],
+    payload: &impl serde::Serialize,
+    max_concurrency: usize,
+) -> Result<Vec<PostedMessage>> {
+    post_messages_in_threads(
+        client,
+        token,
+        channels,
+        payload,
+        &HashMap::new(),
+        max_concurrency,
+    )
+}
+
+/// Same as `post_messages`, channels found in `threads` getting the message
+/// as a reply in the thread of the ts they map to.
+pub fn post_messages_in_threads(
+    client: &dyn OutboundClient,
+    token: &str,
+    channels: &[String],
+    payload: &impl serde::Serialize,
+    threads: &HashMap<String, String>,
+    max_concurrency: usize,
+) -> Result<Vec<PostedMessage>> {
+    let payload = serde_json::to_value(payload)?;
+    let requests = channels
+        .iter()
+        .map(|channel| {
+            let mut body = payload.clone();
+            body["channel"] = channel.as_str().into();
+            if let Some(thread_ts) = threads.get(channel) {
+                body["thread_ts"] = thread_ts.as_str().into();
+            }
+            api_request(token, "chat.postMessage", &body)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    client
+        .send_all(requests, max_concurrency)
+        .into_iter()
+        .zip(channels)
+        .map(|(response, channel)| {
+            let response = parse_response("chat.postMessage", &response?)?;
+            Ok(posted(channel, response))
+        })
+        .collect()
+}
+
+fn posted(channel: &str, response: ApiResponse) -> PostedMessage {
+    PostedMessage {
+        channel: channel.to_string(),
+        channel_id: response.channel,
+        ts: response.ts,
+        error: match response.ok {
+            true => None,
+            false => Some(
+                response
+                    .error
+                    .unwrap_or_else(|| "unknown_error".to_string()),
+            ),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockClient;
+    use serde_json::json;
+
+    fn channels(channel: &str) -> Vec<String> {
+        vec![channel.to_string()]
+    }
+
+    #[test]
+    fn test_post_message() {
+        let client = MockClient::new()
+            .respond_with_body(200, r#"{"ok": true, "channel": "C1", "ts": "1700.0001"}"#);
+
+        let posted = post_messages(
+            &client,
+            "xoxb-1",
+            &channels("#alerts"),
+            &json!({ "text": "hi" }),
+            1,
+        )
+        .unwrap()
+        .remove(0);
+        assert_eq!(
+            posted,
+            PostedMessage {
+                channel: "#alerts".to_string(),
+                channel_id: Some("C1".to_string()),
+                ts: Some("1700.0001".to_string()),
+                error: None,
+            }
+        );
+
+        let requests = client.requests();
+        assert_eq!(requests[0].url, "https://slack.com/api/chat.postMessage");
+        assert_eq!(
+            requests[0]
+                .headers
+                .get(http::header::AUTHORIZATION)
+                .unwrap(),
+            "Bearer xoxb-1"
+        );
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&requests[0].body).unwrap(),
+            json!({ "text": "hi", "channel": "#alerts" })
+        );
+    }
+
+    #[test]
+    fn test_post_message_api_error() {
+        let client =
+            MockClient::new().respond_with_body(200, r#"{"ok": false, "error": "not_in_channel"}"#);
+
+        let posted = post_messages(
+            &client,
+            "xoxb-1",
+            &channels("#private"),
+            &json!({ "text": "hi" }),
+            1,
+        )
+        .unwrap()
+        .remove(0);
+        assert_eq!(posted.ts, None);
+        assert_eq!(posted.error.as_deref(), Some("not_in_channel"));
+    }
+
+    #[test]
+    fn test_call() {
+        let client = MockClient::new()
+            .respond_with_body(200, r#"{"ok": true, "sections": []}"#)
+            .respond_with_body(200, r#"{"ok": false, "error": "canvas_not_found"}"#);
+
+        let response = call(&client, "xoxb-1", "canvases.sections.lookup", &json!({})).unwrap();
+        assert_eq!(response["sections"], json!([]));
+        let err = call(&client, "xoxb-1", "canvases.edit", &json!({})).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Slack API canvases.edit failed: canvas_not_found"
+        );
+        assert_eq!(
+            client.requests()[1].url,
+            "https://slack.com/api/canvases.edit"
+        );
+    }
+
+    #[test]
+    fn test_upload_file() {
+        let client = MockClient::new()
+            .respond_with_body(
+                200,
+                r#"{"ok": true, "upload_url": "https://files.slack.com/upload/v1/abc", "file_id": "F1"}"#,
+            )
+            .respond_with(200)
+            .respond_with_body(200, r#"{"ok": true}"#);
+        let upload = Upload {
+            filename: "report.pdf".to_string(),
+            data: b"%PDF".to_vec(),
+        };
+
+        upload_file(&client, "xoxb-1", "C1", "1.1", &upload).unwrap();
+        let requests = client.requests();
+        assert_eq!(requests[0].body, b"filename=report.pdf&length=4");
+        assert_eq!(requests[1].url, "https://files.slack.com/upload/v1/abc");
+        assert_eq!(requests[1].body, b"%PDF");
+        let completed: serde_json::Value = serde_json::from_slice(&requests[2].body).unwrap();
+        assert_eq!(
+            completed,
+            json!({
+                "files": [{ "id": "F1", "title": "report.pdf" }],
+                "channel_id": "C1",
+                "thread_ts": "1.1",
+            })
+        );
+    }
+
+    #[test]
+    fn test_post_messages_http_error() {
+        let client = MockClient::new().respond_with(500);
+        let err =
+            post_messages(&client, "xoxb-1", &channels("#alerts"), &json!({}), 1).unwrap_err();
+        assert_eq!(err.to_string(), "Slack API chat.postMessage answered 500");
+    }
+
+    #[test]
+    fn test_post_messages_keeps_channel_order() {
+        let client = MockClient::new()
+            .respond_with_body(200, r#"{"ok": true, "channel": "C1", "ts": "1.1"}"#)
+            .respond_with_body(200, r#"{"ok": false, "error": "channel_not_found"}"#);
+        let channels = vec!["#alerts".to_string(), "#gone".to_string()];
+
+        let posted =
+            post_messages(&client, "xoxb-1", &channels, &json!({ "text": "hi" }), 4).unwrap();
+        assert_eq!(posted[0].ts.as_deref(), Some("1.1"));
+        assert_eq!(posted[1].channel, "#gone");
+        assert_eq!(posted[1].error.as_deref(), Some("channel_not_found"));
+
+        let requests = client.requests();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&requests[1].body).unwrap(),
+            json!({ "text": "hi", "channel": "#gone" })
+        );
+    }
+}
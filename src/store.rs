@@ -0,0 +1,136 @@
+use anyhow::Result;
+
+use crate::client::OutboundClient;
+use crate::Settings;
+
+// Key-value state kept between requests
+//
+// Bot tokens are stored by team ID and tracked messages under
+// `messages/<channel>/<ts>`. The edge-function world only includes
+// `wasi:http/proxy`, hosts refuse components importing `wasi:keyvalue`, so
+// values go to the `token_store_endpoint` setting instead, an HTTP service
+// answering `GET`/`PUT <endpoint>/<key>` with `{"value": "..."}`, the same
+// contract as the secrets endpoint. Every stateful feature (OAuth tokens,
+// threads, rotation stats, digests, dedup) shares it. `KeyValue` is where a
+// `wasi:keyvalue` backend would plug in once the world has it.
+
+pub trait KeyValue {
+    fn get(&self, key: &str) -> Result<Option<String>>;
+    fn put(&self, key: &str, value: &str) -> Result<()>;
+}
+
+/// Percent-encodes a key segment holding a caller-provided value, like a
+/// `#channel` name.
+pub fn segment(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Store behind the `token_store_endpoint` setting.
+pub struct HttpStore<'a> {
+    endpoint: &'a str,
+    token: Option<&'a str>,
+    client: &'a dyn OutboundClient,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct StoredValue {
+    value: String,
+}
+
+impl<'a> HttpStore<'a> {
+    pub fn from_settings(settings: &'a Settings, client: &'a dyn OutboundClient) -> Result<Self> {
+        let endpoint = settings
+            .token_store_endpoint
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("Missing token_store_endpoint setting"))?;
+        Ok(Self {
+            endpoint,
+            token: settings.token_store_token.as_deref(),
+            client,
+        })
+    }
+
+    fn request(&self, key: &str) -> Result<(String, http::HeaderMap)> {
+        let url = format!("{}/{key}", self.endpoint.trim_end_matches('/'));
+        let mut headers = http::HeaderMap::new();
+        if let Some(token) = self.token {
+            headers.insert(
+                http::header::AUTHORIZATION,
+                format!("Bearer {token}").parse()?,
+            );
+        }
+        Ok((url, headers))
+    }
+}
+
+impl KeyValue for HttpStore<'_> {
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        let (url, headers) = self.request(key)?;
+        let response = self.client.get(&url, &headers)?;
+        match response.status {
+            200 => {
+                let StoredValue { value } = serde_json::from_slice(&response.body)
+                    .map_err(|err| anyhow::anyhow!("Invalid token store response: {err}"))?;
+                Ok(Some(value))
+            }
+            404 => Ok(None),
+            status => anyhow::bail!("Token store answered {status}"),
+        }
+    }
+
+    fn put(&self, key: &str, value: &str) -> Result<()> {
+        let (url, mut headers) = self.request(key)?;
+        headers.insert(
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::from_static("application/json"),
+        );
+        let body = serde_json::to_vec(&StoredValue {
+            value: value.to_string(),
+        })?;
+        let response = self.client.send(http::Method::PUT, &url, &headers, body)?;
+        if !(200..300).contains(&response.status) {
+            anyhow::bail!("Token store answered {}", response.status);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockClient;
+
+    #[test]
+    fn test_http_store() {
+        let data = std::collections::HashMap::from([(
+            "token_store_endpoint".to_string(),
+            "https://kv.example.com/slack/".to_string(),
+        )]);
+        let settings = Settings::from_map(&data).unwrap();
+        let client = MockClient::new()
+            .respond_with_body(200, r#"{"value": "xoxb-t1"}"#)
+            .respond_with(404)
+            .respond_with(204);
+        let store = HttpStore::from_settings(&settings, &client).unwrap();
+
+        assert_eq!(store.get("T1").unwrap().as_deref(), Some("xoxb-t1"));
+        assert_eq!(store.get("T2").unwrap(), None);
+        store.put("messages/C1/1.1", "{}").unwrap();
+
+        let requests = client.requests();
+        assert_eq!(requests[1].url, "https://kv.example.com/slack/T2");
+        assert_eq!(requests[2].method, http::Method::PUT);
+        assert_eq!(
+            requests[2].url,
+            "https://kv.example.com/slack/messages/C1/1.1"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use crate::request::Table;
+
+// Tables
+//
+// Slack has no table block, a request `table` is appended to the message as
+// a code block, columns padded to the widest cell so they line up in the
+// monospace font. Cells longer than `MAX_CELL_WIDTH` characters are cut with
+// an ellipsis to keep rows from wrapping.
+
+const MAX_CELL_WIDTH: usize = 32;
+
+fn value_cell(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(text) => cell(text),
+        serde_json::Value::Null => String::new(),
+        value => cell(&value.to_string()),
+    }
+}
+
+fn cell(text: &str) -> String {
+    // Line breaks would split the row, backticks could close the block
+    let text = text.replace(['\n', '\r'], " ").replace("```", "'''");
+    match text.chars().count() > MAX_CELL_WIDTH {
+        true => {
+            let mut cut: String = text.chars().take(MAX_CELL_WIDTH - 1).collect();
+            cut.push('…');
+            cut
+        }
+        false => text,
+    }
+}
+
+/// `table` as a column-aligned code block, or `None` when it has no cells.
+pub fn render(table: &Table) -> Option<String> {
+    let headers: Vec<String> = table.headers.iter().map(|header| cell(header)).collect();
+    let rows: Vec<Vec<String>> = table
+        .rows
+        .iter()
+        .map(|row| row.iter().map(value_cell).collect())
+        .collect();
+
+    let columns = rows
+        .iter()
+        .map(Vec::len)
+        .chain([headers.len()])
+        .max()
+        .unwrap_or_default();
+    if columns == 0 {
+        return None;
+    }
+    let mut widths = vec![0; columns];
+    for row in rows.iter().chain([&headers]) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    // Short rows end early rather than with empty cells
+    let line = |cells: &[String]| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell}{}", " ".repeat(width - cell.chars().count())))
+            .collect();
+        padded.join(" | ").trim_end().to_string()
+    };
+
+    let mut lines = Vec::new();
+    if !headers.is_empty() {
+        lines.push(line(&headers));
+        let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+        lines.push(rule.join("-+-"));
+    }
+    lines.extend(rows.iter().map(|row| line(row)));
+    Some(format!("```\n{}\n```", lines.join("\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn table(value: serde_json::Value) -> Table {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        let table = table(json!({
+            "headers": ["host", "usage"],
+            "rows": [["db-1", 97], ["cache-eu-west-1", "12%"], ["web"]],
+        }));
+        assert_eq!(
+            render(&table).unwrap(),
+            "```\n\
+             host            | usage\n\
+             ----------------+------\n\
+             db-1            | 97\n\
+             cache-eu-west-1 | 12%\n\
+             web\n\
+             ```"
+        );
+    }
+
+    #[test]
+    fn test_render_truncates_wide_cells() {
+        let table = table(json!({ "rows": [["a".repeat(40), "line\nbreak"]] }));
+        assert_eq!(
+            render(&table).unwrap(),
+            format!("```\n{}… | line break\n```", "a".repeat(31))
+        );
+    }
+
+    #[test]
+    fn test_render_empty() {
+        assert_eq!(render(&table(json!({ "headers": [], "rows": [] }))), None);
+    }
+}
@@ -0,0 +1,166 @@
+use anyhow::Result;
+use regex::{Captures, Regex};
+use serde_json::Value;
+use std::sync::LazyLock;
+
+use crate::dates;
+use crate::helpers::ApiError;
+use crate::request::{NotificationRequest, RawField};
+
+// Settings templates
+//
+// `{{name}}` in a template is replaced with the request field of that name,
+// `{{fields.host}}` reaching into objects, so one setting can read e.g.
+// `[{{env}}]` and tell staging and production messages apart. Missing
+// fields and objects are replaced with nothing. `{{started_at | date}}`
+// shows an ISO-8601 timestamp or unix time as a Slack date token, in each
+// reader's own timezone.
+//
+// With the `interpolated_fields` setting, the `channel`, `channels`,
+// `username` and attachment titles of requests are expanded too, e.g.
+// `alerts-{{env}}`. Only the listed fields may be used there, a request
+// naming its channel after any of its fields could post anywhere.
+
+static VARIABLE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*([A-Za-z0-9_.-]+)\s*(?:\|\s*(date)\s*)?\}\}").unwrap());
+
+/// Expands the `{{variables}}` of `template` with the fields of `data`.
+pub fn expand(template: &str, data: &Value) -> String {
+    VARIABLE
+        .replace_all(template, |caps: &Captures| {
+            let value = field(data, &caps[1]).unwrap_or_default();
+            match caps.get(2) {
+                Some(_) => date(&value),
+                None => value,
+            }
+        })
+        .into_owned()
+}
+
+// The `date` filter
+fn date(value: &str) -> String {
+    match value.parse::<i64>() {
+        Ok(seconds) => dates::token(seconds),
+        Err(_) => dates::format(value),
+    }
+}
+
+/// Text of the field `name` of `data`, dots reaching into objects. `None`
+/// for missing fields, objects and arrays.
+pub fn field(data: &Value, name: &str) -> Option<String> {
+    let pointer = format!("/{}", name.replace('.', "/"));
+    match data.pointer(&pointer)? {
+        Value::String(text) => Some(text.clone()),
+        value @ (Value::Number(_) | Value::Bool(_)) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Expands the `{{variables}}` of the channels, username and attachment
+/// titles of `request`, which may only use the `allowed` fields.
+pub fn interpolate(request: &mut NotificationRequest, allowed: &[String]) -> Result<()> {
+    let data = serde_json::to_value(&*request)?;
+    let expand_field = |text: &str, field: &str| -> Result<String> {
+        if let Some(caps) = VARIABLE
+            .captures_iter(text)
+            .find(|caps| !allowed.iter().any(|name| *name == caps[1]))
+        {
+            return Err(ApiError::bad_request(format!(
+                "Variable '{}' in '{field}' field is not allowed by the interpolated_fields setting",
+                &caps[1]
+            ))
+            .into());
+        }
+        Ok(expand(text, &data))
+    };
+
+    if let Some(channel) = &request.channel {
+        request.channel = Some(expand_field(channel, "channel")?);
+    }
+    for channel in request.channels.iter_mut().flatten() {
+        *channel = expand_field(channel, "channels")?;
+    }
+    if let Some(username) = &request.username {
+        request.username = Some(expand_field(username, "username")?);
+    }
+    if let Some(attachments) = &request.attachments {
+        let mut value = attachments.to_value()?;
+        for attachment in value.as_array_mut().into_iter().flatten() {
+            if let Some(Value::String(title)) = attachment.get("title") {
+                attachment["title"] = expand_field(title, "attachments")?.into();
+            }
+        }
+        request.attachments = Some(RawField::from_value(&value)?);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_expand() {
+        let data = json!({ "env": "prod", "fields": { "host": "db-1", "usage": 97 } });
+        assert_eq!(
+            expand("[{{env}}] {{ fields.host }} at {{fields.usage}}%", &data),
+            "[prod] db-1 at 97%"
+        );
+        assert_eq!(expand("[{{region}}]{{fields}}", &data), "[]");
+        assert_eq!(expand("{single}", &data), "{single}");
+    }
+
+    #[test]
+    fn test_expand_date() {
+        let data = json!({ "at": "2024-01-31T10:30:00Z", "ts": 1706697000, "host": "db-1" });
+        let token = "<!date^1706697000^{date_short} {time}|2024-01-31T10:30:00Z>";
+        assert_eq!(
+            expand("since {{at | date}}", &data),
+            format!("since {token}")
+        );
+        assert_eq!(expand("since {{ts|date}}", &data), format!("since {token}"));
+        assert_eq!(expand("{{host | date}}{{region | date}}", &data), "db-1");
+        assert_eq!(expand("{{at | upper}}", &data), "{{at | upper}}");
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let mut request = NotificationRequest::from_value(
+            json!({
+                "message": "Disk full on {{env}}",
+                "channel": "alerts-{{env}}",
+                "username": "{{ region }} monitor",
+                "attachments": [{ "title": "{{env}} / {{region}}", "text": "{{env}}" }],
+                "env": "prod",
+                "region": "eu",
+            }),
+            false,
+        )
+        .unwrap();
+        let allowed = ["env".to_string(), "region".to_string()];
+
+        interpolate(&mut request, &allowed).unwrap();
+        assert_eq!(request.channel.as_deref(), Some("alerts-prod"));
+        assert_eq!(request.username.as_deref(), Some("eu monitor"));
+        assert_eq!(
+            request.attachments.unwrap().to_value().unwrap(),
+            json!([{ "title": "prod / eu", "text": "{{env}}" }])
+        );
+        assert_eq!(request.message.as_deref(), Some("Disk full on {{env}}"));
+    }
+
+    #[test]
+    fn test_interpolate_rejects_unlisted_fields() {
+        let mut request = NotificationRequest::from_value(
+            json!({ "message": "Hi", "channels": ["ops", "{{team}}"], "team": "general" }),
+            false,
+        )
+        .unwrap();
+        let err = interpolate(&mut request, &["env".to_string()]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Variable 'team' in 'channels' field is not allowed by the interpolated_fields setting"
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! Fixtures and assertions for exercising the component without a WASI
+//! runtime, enabled with the `testing` feature.
+
+use bytes::Bytes;
+use http::{Method, Request, Response, StatusCode};
+use serde_json::Value;
+
+use crate::helpers::body::FromBody;
+use crate::request::MessageBody;
+
+const SETTINGS_HEADER: &str = "x-edgee-component-settings";
+
+/// Builds the `http::Request` a handler receives once `helpers::run` has
+/// parsed the incoming body.
+#[derive(Debug)]
+pub struct RequestFixture {
+    builder: http::request::Builder,
+    body: Option<MessageBody>,
+}
+
+impl Default for RequestFixture {
+    fn default() -> Self {
+        Self::new(Method::POST, "/")
+    }
+}
+
+impl RequestFixture {
+    pub fn new(method: Method, uri: &str) -> Self {
+        Self {
+            builder: Request::builder().method(method).uri(uri),
+            body: None,
+        }
+    }
+
+    pub fn post() -> Self {
+        Self::new(Method::POST, "/")
+    }
+
+    pub fn get(uri: &str) -> Self {
+        Self::new(Method::GET, uri)
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.builder = self.builder.header(name, value);
+        self
+    }
+
+    /// Sets the component settings header from a JSON object.
+    pub fn settings(self, settings: Value) -> Self {
+        self.header(SETTINGS_HEADER, &settings.to_string())
+    }
+
+    pub fn json(mut self, body: Value) -> Self {
+        self.body = Some(MessageBody(body, None, Vec::new()));
+        self
+    }
+
+    /// Parses `data` the way it would be for the given Content-Type.
+    pub fn body(self, content_type: &str, data: impl Into<Bytes>) -> anyhow::Result<Self> {
+        let body = MessageBody::from_content(data.into(), Some(content_type))?;
+        let mut fixture = self.header(http::header::CONTENT_TYPE.as_str(), content_type);
+        fixture.body = Some(body);
+        Ok(fixture)
+    }
+
+    pub fn build(self) -> Request<Option<MessageBody>> {
+        self.builder.body(self.body).unwrap()
+    }
+}
+
+/// Asserts the response status and returns its JSON body.
+#[track_caller]
+pub fn assert_status<B: AsRef<Value>>(response: &Response<B>, status: StatusCode) -> &Value {
+    let body = response.body().as_ref();
+    assert_eq!(response.status(), status, "body: {body}");
+    body
+}
+
+/// Asserts both the response status and its JSON body.
+#[track_caller]
+pub fn assert_json<B: AsRef<Value>>(response: &Response<B>, status: StatusCode, expected: Value) {
+    assert_eq!(assert_status(response, status), &expected);
+}
+
+/// Asserts the JSON body is an error carrying `message`.
+#[track_caller]
+pub fn assert_error<B: AsRef<Value>>(response: &Response<B>, status: StatusCode, message: &str) {
+    assert_eq!(assert_status(response, status)["error"], message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::body::Json;
+    use serde_json::json;
+
+    #[test]
+    fn test_request_fixture_settings_and_json() {
+        let req = RequestFixture::post()
+            .settings(json!({ "webhook_url": "http://example.com/webhook" }))
+            .json(json!({ "message": "Hello" }))
+            .build();
+
+        assert_eq!(req.method(), Method::POST);
+        assert_eq!(
+            req.headers().get(SETTINGS_HEADER).unwrap(),
+            r#"{"webhook_url":"http://example.com/webhook"}"#
+        );
+        assert_eq!(
+            req.body().as_ref().unwrap().0,
+            json!({ "message": "Hello" })
+        );
+    }
+
+    #[test]
+    fn test_request_fixture_parses_body_by_content_type() {
+        let req = RequestFixture::post()
+            .body("text/plain", "disk full\n")
+            .unwrap()
+            .build();
+
+        assert_eq!(
+            req.headers().get(http::header::CONTENT_TYPE).unwrap(),
+            "text/plain"
+        );
+        assert_eq!(
+            req.body().as_ref().unwrap().0,
+            json!({ "message": "disk full" })
+        );
+    }
+
+    #[test]
+    fn test_request_fixture_get_without_body() {
+        let req = RequestFixture::get("/send?message=hi").build();
+        assert_eq!(req.method(), Method::GET);
+        assert_eq!(req.uri().query(), Some("message=hi"));
+        assert!(req.body().is_none());
+    }
+
+    #[test]
+    fn test_assert_helpers() {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(Json(json!({ "error": "nope" })))
+            .unwrap();
+
+        assert_error(&response, StatusCode::BAD_REQUEST, "nope");
+        assert_json(
+            &response,
+            StatusCode::BAD_REQUEST,
+            json!({ "error": "nope" }),
+        );
+    }
+}
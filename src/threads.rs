@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::client::OutboundClient;
+use crate::slack_api::PostedMessage;
+use crate::store::{self, HttpStore, KeyValue};
+use crate::Settings;
+
+// Threads by correlation ID
+//
+// The first message sent with a `correlation_id` starts a thread in each of
+// its channels, the ts being stored under `threads/<channel>/<id>`. Later
+// messages with the same ID are posted as replies in those threads. Needs
+// the key-value store, correlation IDs are ignored without it.
+
+fn thread_key(channel: &str, correlation_id: &str) -> String {
+    format!(
+        "threads/{}/{}",
+        store::segment(channel),
+        store::segment(correlation_id)
+    )
+}
+
+/// Threads already started for `correlation_id`, by channel.
+pub fn find(
+    settings: &Settings,
+    client: &dyn OutboundClient,
+    channels: &[String],
+    correlation_id: Option<&str>,
+) -> Result<HashMap<String, String>> {
+    let (Some(correlation_id), Some(_)) = (correlation_id, &settings.token_store_endpoint) else {
+        return Ok(HashMap::new());
+    };
+
+    let store = HttpStore::from_settings(settings, client)?;
+    let mut threads = HashMap::new();
+    for channel in channels {
+        if let Some(ts) = store.get(&thread_key(channel, correlation_id))? {
+            threads.insert(channel.clone(), ts);
+        }
+    }
+    Ok(threads)
+}
+
+/// Stores the messages that started a thread for `correlation_id`.
+pub fn remember(
+    settings: &Settings,
+    client: &dyn OutboundClient,
+    messages: &[PostedMessage],
+    threads: &HashMap<String, String>,
+    correlation_id: Option<&str>,
+) -> Result<()> {
+    let (Some(correlation_id), Some(_)) = (correlation_id, &settings.token_store_endpoint) else {
+        return Ok(());
+    };
+
+    let store = HttpStore::from_settings(settings, client)?;
+    for message in messages {
+        let Some(ts) = &message.ts else {
+            continue;
+        };
+        if !threads.contains_key(&message.channel) {
+            store.put(&thread_key(&message.channel, correlation_id), ts)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockClient;
+
+    fn settings() -> Settings {
+        let data = HashMap::from([
+            ("bot_token".to_string(), "xoxb-1".to_string()),
+            (
+                "token_store_endpoint".to_string(),
+                "https://kv.example.com/slack".to_string(),
+            ),
+        ]);
+        Settings::from_map(&data).unwrap()
+    }
+
+    #[test]
+    fn test_find() {
+        let client = MockClient::new()
+            .respond_with_body(200, r#"{"value": "1.1"}"#)
+            .respond_with(404);
+        let channels = vec!["#alerts".to_string(), "#ops".to_string()];
+
+        let threads = find(&settings(), &client, &channels, Some("deploy/42")).unwrap();
+        assert_eq!(
+            threads,
+            HashMap::from([("#alerts".to_string(), "1.1".to_string())])
+        );
+        assert_eq!(
+            client.requests()[0].url,
+            "https://kv.example.com/slack/threads/%23alerts/deploy%2F42"
+        );
+
+        let client = MockClient::new();
+        assert!(find(&settings(), &client, &channels, None)
+            .unwrap()
+            .is_empty());
+        assert!(client.requests().is_empty());
+    }
+
+    #[test]
+    fn test_remember_new_threads() {
+        let client = MockClient::new().respond_with(204);
+        let messages = vec![
+            PostedMessage {
+                channel: "#alerts".to_string(),
+                channel_id: Some("C1".to_string()),
+                ts: Some("2.2".to_string()),
+                error: None,
+            },
+            PostedMessage {
+                channel: "#ops".to_string(),
+                channel_id: Some("C2".to_string()),
+                ts: Some("3.3".to_string()),
+                error: None,
+            },
+        ];
+        let threads = HashMap::from([("#alerts".to_string(), "1.1".to_string())]);
+
+        remember(&settings(), &client, &messages, &threads, Some("deploy-42")).unwrap();
+        let requests = client.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].url,
+            "https://kv.example.com/slack/threads/%23ops/deploy-42"
+        );
+    }
+}
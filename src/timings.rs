@@ -0,0 +1,70 @@
+use std::fmt;
+use std::time::Duration;
+
+// Delivery timings
+//
+// How long a message took to parse, to render and send (token lookups,
+// secrets and storage included) and to get through Slack, telling latency in
+// the component from latency in Slack. Logged for every message, and added
+// to structured responses under `timings`.
+
+/// Durations of the steps of a message, in milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize)]
+pub struct Timings {
+    pub parse_ms: f64,
+    pub render_ms: f64,
+    pub slack_ms: f64,
+}
+
+impl Timings {
+    /// Timings of a message parsed after `parse` and done after `total`,
+    /// `slack` of which were spent waiting on Slack.
+    pub fn new(parse: Duration, total: Duration, slack: Duration) -> Self {
+        Self {
+            parse_ms: millis(parse),
+            render_ms: millis(total.saturating_sub(parse).saturating_sub(slack)),
+            slack_ms: millis(slack),
+        }
+    }
+}
+
+// Kept to the microsecond
+fn millis(duration: Duration) -> f64 {
+    duration.as_micros() as f64 / 1000.0
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "parse {} ms, render {} ms, slack {} ms",
+            self.parse_ms, self.render_ms, self.slack_ms
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timings() {
+        let timings = Timings::new(
+            Duration::from_micros(1500),
+            Duration::from_millis(320),
+            Duration::from_millis(300),
+        );
+        assert_eq!(
+            timings,
+            Timings {
+                parse_ms: 1.5,
+                render_ms: 18.5,
+                slack_ms: 300.0,
+            }
+        );
+        assert_eq!(
+            timings.to_string(),
+            "parse 1.5 ms, render 18.5 ms, slack 300 ms"
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use anyhow::Result;
+use http::Request;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use crate::helpers::router::PathParams;
+use crate::helpers::ApiError;
+use crate::request::{MessageBody, NotificationRequest};
+use crate::{template, Settings};
+
+mod datadog;
+mod email;
+mod feed;
+mod shopify;
+mod supabase;
+mod twilio;
+mod uptime;
+
+// Vendor payload transformers
+//
+// Requests to `/transform/<source>`, or carrying an `X-Source: <source>`
+// header, hold the payload of a vendor webhook rather than a notification.
+// The transformer registered for the source checks the request comes from
+// the vendor, then turns its payload into a notification, sent like any
+// other. Adding a vendor takes a `Transformer` and an entry in
+// `TRANSFORMERS`, routing is left as is.
+//
+// The `sources` setting configures sources by name, so one instance can
+// serve many producers: a message template expanded with the payload, a
+// severity mapping and a webhook of their own. A source with a template
+// needs no transformer, the template taking the place of its render.
+
+pub const TRANSFORM_PATH: &str = "/transform/:source";
+
+pub const SOURCE_HEADER: &str = "x-source";
+
+/// Configuration of a source in the `sources` setting.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct SourceSettings {
+    /// Message, `{{field}}` being replaced with the payload field.
+    pub template: Option<String>,
+    /// Payload field holding the severity, the transformer's severity being
+    /// used without it.
+    pub severity_field: Option<String>,
+    /// Severities by payload value, e.g. `{"warn": "warning"}`, other values
+    /// being kept.
+    #[serde(default)]
+    pub severities: HashMap<String, String>,
+    /// Webhook the messages of the source are sent to instead of
+    /// `webhook_url`.
+    pub webhook_url: Option<String>,
+}
+
+pub trait Transformer: Sync {
+    /// Name of the source, as found in paths and the `X-Source` header.
+    fn source(&self) -> &'static str;
+
+    /// Rejects requests not sent by the vendor, e.g. with a wrong signature.
+    /// Every request is accepted by default.
+    fn verify(&self, req: &Request<Option<MessageBody>>, settings: &Settings) -> Result<()> {
+        let _ = (req, settings);
+        Ok(())
+    }
+
+    /// Notification standing for the vendor payload.
+    fn render(&self, body: &Value, settings: &Settings) -> Result<NotificationRequest>;
+}
+
+static TRANSFORMERS: &[&dyn Transformer] = &[
+    &datadog::Datadog,
+    &email::Email,
+    &feed::Feed,
+    &shopify::Shopify,
+    &supabase::Supabase,
+    &twilio::Twilio,
+    &uptime::Uptime,
+];
+
+// Text of a payload field, numbers included, blank strings being missing
+fn text(body: &Value, key: &str) -> Option<String> {
+    match &body[key] {
+        Value::String(text) if !text.trim().is_empty() => Some(text.trim().to_string()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+static TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?is)<(script|style)\b.*?</(script|style)>|<br\s*/?>|</p>|<[^>]*>").unwrap()
+});
+
+// Text of an HTML body, paragraphs and line breaks kept
+fn strip_html(html: &str) -> String {
+    let text = TAG.replace_all(html, |caps: &regex::Captures| {
+        let tag = caps[0].to_ascii_lowercase();
+        match tag.starts_with("<br") || tag == "</p>" {
+            true => "\n",
+            false => "",
+        }
+    });
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Transformer registered for `source`.
+pub fn find(source: &str) -> Option<&'static dyn Transformer> {
+    TRANSFORMERS
+        .iter()
+        .copied()
+        .find(|transformer| transformer.source().eq_ignore_ascii_case(source))
+}
+
+/// Source the request payload comes from, the path taking precedence over
+/// the header.
+pub fn source<B>(req: &Request<B>) -> Option<String> {
+    PathParams::from_req(req)
+        .and_then(|params| params.get("source"))
+        .or_else(|| req.headers().get(SOURCE_HEADER)?.to_str().ok())
+        .map(str::to_string)
+}
+
+// Configuration of `source` in the `sources` setting
+fn configured<'a>(settings: &'a Settings, source: &str) -> Option<&'a SourceSettings> {
+    settings
+        .sources
+        .iter()
+        .flatten()
+        .find(|(name, _)| name.eq_ignore_ascii_case(source))
+        .map(|(_, config)| config)
+}
+
+/// Settings the messages of `source` are sent with, when the source has a
+/// webhook of its own.
+pub fn settings_for(settings: &Settings, source: &str) -> Option<Settings> {
+    let webhook_url = configured(settings, source)?.webhook_url.clone()?;
+    // Not rotated, the secondary webhook standing in for `webhook_url`
+    Some(Settings {
+        webhook_url,
+        secondary_webhook_url: None,
+        ..settings.clone()
+    })
+}
+
+/// Verifies and renders a payload of `source`, unknown sources being a 404.
+pub fn transform(
+    source: &str,
+    req: &Request<Option<MessageBody>>,
+    settings: &Settings,
+    body: &Value,
+) -> Result<NotificationRequest> {
+    let config = configured(settings, source);
+    let mut request = match (find(source), config) {
+        (Some(transformer), _) => apply(transformer, req, settings, body)?,
+        (
+            None,
+            Some(SourceSettings {
+                template: Some(_), ..
+            }),
+        ) => NotificationRequest::default(),
+        _ => return Err(ApiError::not_found(format!("Unknown source '{source}'")).into()),
+    };
+    // Named in footers, and by templates as `{{source}}`
+    request
+        .extra
+        .entry("source")
+        .or_insert_with(|| source.to_ascii_lowercase().into());
+    let Some(config) = config else {
+        return Ok(request);
+    };
+
+    if let Some(template) = &config.template {
+        request.message = Some(template::expand(template, body));
+    }
+    let severity = match &config.severity_field {
+        Some(field) => text(body, field),
+        None => request.severity.take(),
+    };
+    request.severity = severity.map(|severity| {
+        config
+            .severities
+            .get(&severity)
+            .cloned()
+            .unwrap_or(severity)
+    });
+    Ok(request)
+}
+
+fn apply(
+    transformer: &dyn Transformer,
+    req: &Request<Option<MessageBody>>,
+    settings: &Settings,
+    body: &Value,
+) -> Result<NotificationRequest> {
+    transformer.verify(req, settings)?;
+    transformer.render(body, settings)
+}
+
+#[cfg(test)]
+fn webhook_settings() -> Settings {
+    let data = std::collections::HashMap::from([(
+        "webhook_url".to_string(),
+        "https://hooks.slack.com/services/test".to_string(),
+    )]);
+    Settings::from_map(&data).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use serde_json::json;
+
+    struct Deploys;
+
+    impl Transformer for Deploys {
+        fn source(&self) -> &'static str {
+            "deploys"
+        }
+
+        fn verify(&self, req: &Request<Option<MessageBody>>, _settings: &Settings) -> Result<()> {
+            match req.headers().contains_key("x-deploy-token") {
+                true => Ok(()),
+                false => Err(ApiError::unauthorized("Unauthorized").into()),
+            }
+        }
+
+        fn render(&self, body: &Value, _settings: &Settings) -> Result<NotificationRequest> {
+            Ok(NotificationRequest {
+                message: Some(format!("Deployed {}", body["version"])),
+                ..Default::default()
+            })
+        }
+    }
+
+    #[test]
+    fn test_source() {
+        let req = testing::RequestFixture::post()
+            .header("X-Source", "deploys")
+            .build();
+        assert_eq!(source(&req).as_deref(), Some("deploys"));
+        assert_eq!(source(&testing::RequestFixture::post().build()), None);
+    }
+
+    #[test]
+    fn test_apply() {
+        let body = json!({ "version": "1.2.0" });
+        let req = testing::RequestFixture::post()
+            .header("x-deploy-token", "t0ken")
+            .json(body.clone())
+            .build();
+        let request = apply(&Deploys, &req, &webhook_settings(), &body).unwrap();
+        assert_eq!(request.message.as_deref(), Some("Deployed \"1.2.0\""));
+
+        let req = testing::RequestFixture::post().json(body.clone()).build();
+        let err = apply(&Deploys, &req, &webhook_settings(), &body).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ApiError>().unwrap().status,
+            http::StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[test]
+    fn test_transform_configured_source() {
+        let mut settings = webhook_settings();
+        settings.sources = Some(HashMap::from([(
+            "Sentry".to_string(),
+            SourceSettings {
+                template: Some("{{project}}: {{title}}".to_string()),
+                severity_field: Some("level".to_string()),
+                severities: HashMap::from([("fatal".to_string(), "critical".to_string())]),
+                webhook_url: Some("https://hooks.slack.com/services/sentry".to_string()),
+            },
+        )]));
+        let body = json!({ "project": "api", "title": "Panic in handler", "level": "fatal" });
+        let req = testing::RequestFixture::post().json(body.clone()).build();
+
+        let request = transform("sentry", &req, &settings, &body).unwrap();
+        assert_eq!(request.message.as_deref(), Some("api: Panic in handler"));
+        assert_eq!(request.severity.as_deref(), Some("critical"));
+        assert_eq!(request.extra["source"], "sentry");
+        assert_eq!(
+            settings_for(&settings, "sentry").unwrap().webhook_url,
+            "https://hooks.slack.com/services/sentry"
+        );
+        assert!(settings_for(&settings, "datadog").is_none());
+    }
+
+    #[test]
+    fn test_transform_maps_transformer_severity() {
+        let mut settings = webhook_settings();
+        settings.sources = Some(HashMap::from([(
+            "uptimerobot".to_string(),
+            SourceSettings {
+                severities: HashMap::from([("error".to_string(), "critical".to_string())]),
+                ..Default::default()
+            },
+        )]));
+        let body = json!({ "monitor": "API", "status": "down" });
+        let req = testing::RequestFixture::post().json(body.clone()).build();
+
+        let request = transform("uptimerobot", &req, &settings, &body).unwrap();
+        assert_eq!(request.message.as_deref(), Some("API is DOWN"));
+        assert_eq!(request.severity.as_deref(), Some("critical"));
+    }
+
+    #[test]
+    fn test_transform_unknown_source() {
+        let req = testing::RequestFixture::post().build();
+        let err = transform("nagios", &req, &webhook_settings(), &json!({})).unwrap_err();
+        let err = err.downcast_ref::<ApiError>().unwrap();
+        assert_eq!(err.status, http::StatusCode::NOT_FOUND);
+        assert_eq!(err.message, "Unknown source 'nagios'");
+    }
+}
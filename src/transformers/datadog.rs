@@ -0,0 +1,159 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use super::{text, Transformer};
+use crate::helpers::ApiError;
+use crate::request::{NotificationRequest, RawField};
+use crate::Settings;
+
+// Datadog monitor webhooks
+//
+// Datadog posts the JSON template of the webhook integration, whose default
+// one sends `title` ($EVENT_TITLE), `body` ($EVENT_MSG), `last_updated` and
+// `id`. Templates also adding `alert_transition`, `alert_type`, `link`,
+// `alert_id`, `hostname`, `priority` and `tags` get a colored message with a
+// link back to the monitor.
+
+pub struct Datadog;
+
+const MONITORS_URL: &str = "https://app.datadoghq.com/monitors";
+
+// Severity and attachment color of an alert, the transition telling more
+// than the alert type when both are sent
+fn status(body: &Value) -> (&'static str, &'static str) {
+    let transition = body["alert_transition"].as_str().unwrap_or_default();
+    let alert_type = body["alert_type"].as_str().unwrap_or_default();
+    match (transition.to_ascii_lowercase().as_str(), alert_type) {
+        ("recovered", _) | ("", "success") => ("success", "good"),
+        ("warn", _) | ("", "warning") => ("warning", "warning"),
+        ("no data", _) | ("", "info") => ("info", "#439FE0"),
+        ("triggered" | "re-triggered" | "renotify", _) | (_, "error") => ("error", "danger"),
+        _ => ("info", "#439FE0"),
+    }
+}
+
+impl Transformer for Datadog {
+    fn source(&self) -> &'static str {
+        "datadog"
+    }
+
+    fn render(&self, body: &Value, _settings: &Settings) -> Result<NotificationRequest> {
+        let title = text(body, "title").ok_or_else(|| {
+            ApiError::bad_request("Invalid Datadog payload, a 'title' field is needed")
+        })?;
+        let (severity, color) = status(body);
+        let link = text(body, "link").or_else(|| {
+            text(body, "alert_id").map(|monitor_id| format!("{MONITORS_URL}/{monitor_id}"))
+        });
+
+        // Event messages are wrapped in %%% markdown markers
+        let message = text(body, "body").map(|message| {
+            message
+                .lines()
+                .filter(|line| line.trim() != "%%%")
+                .collect::<Vec<_>>()
+                .join("\n")
+                .trim()
+                .to_string()
+        });
+        let fields: Vec<Value> = [
+            ("Host", "hostname"),
+            ("Priority", "priority"),
+            ("Tags", "tags"),
+        ]
+        .into_iter()
+        .filter_map(|(title, key)| {
+            let value = text(body, key)?;
+            Some(json!({ "title": title, "value": value, "short": key != "tags" }))
+        })
+        .collect();
+
+        // The title is the message, the attachment adds the colored details
+        let mut attachment = json!({ "color": color, "footer": "Datadog" });
+        if let Some(message) = message.filter(|message| !message.is_empty()) {
+            attachment["text"] = json!(message);
+        }
+        if !fields.is_empty() {
+            attachment["fields"] = json!(fields);
+        }
+
+        Ok(NotificationRequest {
+            message: Some(match &link {
+                Some(link) => format!("<{link}|{title}>"),
+                None => title,
+            }),
+            severity: Some(severity.to_string()),
+            attachments: Some(RawField::from_value(&json!([attachment]))?),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformers::webhook_settings;
+
+    #[test]
+    fn test_render() {
+        let request = Datadog
+            .render(
+                &json!({
+                    "title": "[Triggered] CPU high on db-1",
+                    "body": "%%%\nCPU usage is **97%**\n%%%",
+                    "alert_transition": "Triggered",
+                    "alert_id": 1234,
+                    "hostname": "db-1",
+                    "tags": "env:prod,team:db",
+                }),
+                &webhook_settings(),
+            )
+            .unwrap();
+        assert_eq!(
+            request.message.as_deref(),
+            Some("<https://app.datadoghq.com/monitors/1234|[Triggered] CPU high on db-1>")
+        );
+        assert_eq!(request.severity.as_deref(), Some("error"));
+        assert_eq!(
+            request.attachments.unwrap().to_value().unwrap(),
+            json!([{
+                "color": "danger",
+                "text": "CPU usage is **97%**",
+                "fields": [
+                    { "title": "Host", "value": "db-1", "short": true },
+                    { "title": "Tags", "value": "env:prod,team:db", "short": false },
+                ],
+                "footer": "Datadog",
+            }])
+        );
+    }
+
+    #[test]
+    fn test_status() {
+        let status_of = |body: Value| status(&body);
+        assert_eq!(
+            status_of(json!({ "alert_transition": "Recovered", "alert_type": "error" })),
+            ("success", "good")
+        );
+        assert_eq!(
+            status_of(json!({ "alert_type": "warning" })),
+            ("warning", "warning")
+        );
+        assert_eq!(
+            status_of(json!({ "alert_transition": "No Data" })),
+            ("info", "#439FE0")
+        );
+        assert_eq!(status_of(json!({})), ("info", "#439FE0"));
+    }
+
+    #[test]
+    fn test_render_requires_title() {
+        let err = Datadog
+            .render(&json!({ "body": "CPU high" }), &webhook_settings())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid Datadog payload, a 'title' field is needed"
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use anyhow::Result;
+use serde_json::Value;
+
+use super::{strip_html, text, Transformer};
+use crate::helpers::ApiError;
+use crate::request::{NotificationRequest, Upload};
+use crate::signature;
+use crate::Settings;
+
+// Inbound email webhooks
+//
+// Postmark posts `Subject`, `From`, `TextBody`, `HtmlBody` and
+// `Attachments`, whose `Content` is base64. SendGrid Inbound Parse posts
+// `subject`, `from`, `text` and `html`, its attachments being described by
+// the `attachment-info` JSON string. The subject and the start of the body
+// are posted, HTML bodies only being used, stripped of their tags, when
+// there is no text one. Postmark attachments are uploaded in the thread of
+// the message in bot-token mode, SendGrid ones are only listed, their
+// content never reaching the component.
+
+pub struct Email;
+
+const MAX_BODY: usize = 500;
+
+// Start of `body`, blank lines dropped
+fn excerpt(body: &str) -> String {
+    let lines: Vec<&str> = body
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let body = lines.join("\n");
+    match body.chars().count() > MAX_BODY {
+        true => body.chars().take(MAX_BODY - 1).collect::<String>() + "…",
+        false => body,
+    }
+}
+
+fn postmark_uploads(body: &Value) -> Result<Vec<Upload>> {
+    let Some(attachments) = body["Attachments"].as_array() else {
+        return Ok(Vec::new());
+    };
+    attachments
+        .iter()
+        .map(|attachment| {
+            let upload = text(attachment, "Name").and_then(|filename| {
+                let data = signature::from_base64(attachment["Content"].as_str()?)?;
+                Some(Upload { filename, data })
+            });
+            upload.ok_or_else(|| {
+                ApiError::bad_request(
+                    "Invalid email attachment, a 'Name' and a base64 'Content' are needed",
+                )
+                .into()
+            })
+        })
+        .collect()
+}
+
+// Names of the attachments described by SendGrid
+fn sendgrid_attachments(body: &Value) -> Vec<String> {
+    let info = match &body["attachment-info"] {
+        Value::String(info) => serde_json::from_str(info).unwrap_or_default(),
+        info => info.clone(),
+    };
+    let Value::Object(info) = info else {
+        return Vec::new();
+    };
+    info.iter()
+        .map(|(key, file)| text(file, "filename").unwrap_or_else(|| key.clone()))
+        .collect()
+}
+
+impl Transformer for Email {
+    fn source(&self) -> &'static str {
+        "email"
+    }
+
+    fn render(&self, body: &Value, _settings: &Settings) -> Result<NotificationRequest> {
+        let first = |keys: &[&str]| keys.iter().find_map(|key| text(body, key));
+        let Some(from) = first(&["From", "from"]) else {
+            return Err(ApiError::bad_request(
+                "Invalid email payload, a 'From' or 'from' field is needed",
+            )
+            .into());
+        };
+        let subject = first(&["Subject", "subject"]).unwrap_or_else(|| "(no subject)".to_string());
+        let content = first(&["TextBody", "text"])
+            .or_else(|| first(&["HtmlBody", "html"]).map(|html| strip_html(&html)))
+            .map(|content| excerpt(&content))
+            .filter(|content| !content.is_empty());
+
+        let uploads = postmark_uploads(body)?;
+        let attachments: Vec<String> = match uploads.is_empty() {
+            true => sendgrid_attachments(body),
+            false => uploads
+                .iter()
+                .map(|upload| upload.filename.clone())
+                .collect(),
+        };
+
+        let mut message = format!(":email: *{subject}* from {from}");
+        if let Some(content) = content {
+            let quoted: Vec<String> = content.lines().map(|line| format!(">{line}")).collect();
+            message = format!("{message}\n{}", quoted.join("\n"));
+        }
+        if !attachments.is_empty() {
+            message = format!("{message}\n:paperclip: {}", attachments.join(", "));
+        }
+
+        Ok(NotificationRequest {
+            message: Some(message),
+            severity: Some("info".to_string()),
+            uploads,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformers::webhook_settings;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_postmark() {
+        let request = Email
+            .render(&json!({
+                "From": "jane@example.com",
+                "Subject": "Invoice 42",
+                "TextBody": "Hello,\n\nThe invoice is attached.\n",
+                "HtmlBody": "<p>Hello,</p>",
+                "Attachments": [
+                    { "Name": "invoice.pdf", "Content": "JVBERg==", "ContentType": "application/pdf" },
+                ],
+            }), &webhook_settings())
+            .unwrap();
+        assert_eq!(
+            request.message.as_deref(),
+            Some(
+                ":email: *Invoice 42* from jane@example.com\n\
+                 >Hello,\n>The invoice is attached.\n\
+                 :paperclip: invoice.pdf"
+            )
+        );
+        assert_eq!(
+            request.uploads,
+            [Upload {
+                filename: "invoice.pdf".to_string(),
+                data: b"%PDF".to_vec(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_render_sendgrid() {
+        let request = Email
+            .render(&json!({
+                "from": "Jane <jane@example.com>",
+                "subject": "Outage",
+                "html": "<html><style>p { color: red }</style><p>API &amp; web</p>down<br>since 10:00</html>",
+                "attachment-info": "{\"attachment1\": {\"filename\": \"graph.png\", \"type\": \"image/png\"}}",
+            }), &webhook_settings())
+            .unwrap();
+        assert_eq!(
+            request.message.as_deref(),
+            Some(
+                ":email: *Outage* from Jane <jane@example.com>\n\
+                 >API & web\n>down\n>since 10:00\n\
+                 :paperclip: graph.png"
+            )
+        );
+        assert!(request.uploads.is_empty());
+    }
+
+    #[test]
+    fn test_render_truncates_body() {
+        let request = Email
+            .render(
+                &json!({ "from": "jane@example.com", "text": "a".repeat(600) }),
+                &webhook_settings(),
+            )
+            .unwrap();
+        assert_eq!(
+            request.message.unwrap(),
+            format!(
+                ":email: *(no subject)* from jane@example.com\n>{}…",
+                "a".repeat(499)
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_invalid_attachment() {
+        let err = Email
+            .render(
+                &json!({
+                    "From": "jane@example.com",
+                    "Attachments": [{ "Name": "invoice.pdf", "Content": "not base64!" }],
+                }),
+                &webhook_settings(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ApiError>().unwrap().status,
+            http::StatusCode::BAD_REQUEST
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::Result;
+use serde_json::Value;
+
+use super::{strip_html, text, Transformer};
+use crate::helpers::ApiError;
+use crate::request::NotificationRequest;
+use crate::Settings;
+use crate::{dates, is_http_url};
+
+// RSS and Atom feed items
+//
+// Feed-polling services post one item per request, with its `title`,
+// `link`, `summary` and `published` date (`description`, `content`,
+// `pubDate` and `updated` being read too). The item is posted as a link
+// Slack unfurls, followed by the start of its summary. Items are sent once
+// by `guid`, `id` or link when the key-value store is configured, polling
+// services posting the same item again after a restart.
+
+pub struct Feed;
+
+const MAX_SUMMARY: usize = 300;
+
+impl Transformer for Feed {
+    fn source(&self) -> &'static str {
+        "feed"
+    }
+
+    fn render(&self, body: &Value, _settings: &Settings) -> Result<NotificationRequest> {
+        // Some services wrap the item
+        let item = match &body["item"] {
+            Value::Object(_) => &body["item"],
+            _ => body,
+        };
+        let first = |keys: &[&str]| keys.iter().find_map(|key| text(item, key));
+        let (Some(title), Some(link)) = (first(&["title"]), first(&["link", "url"])) else {
+            return Err(ApiError::bad_request(
+                "Invalid feed item, a 'title' and a 'link' are needed",
+            )
+            .into());
+        };
+        if !is_http_url(&link) {
+            return Err(ApiError::bad_request(
+                "Invalid feed item, the 'link' must be an http(s) URL",
+            )
+            .into());
+        }
+
+        // `<`, `>` and `|` would end the link
+        let title = title
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        let mut message = format!(":newspaper: *<{link}|{}>*", title.replace('|', "-"));
+        let summary = first(&["summary", "description", "content"])
+            .map(|summary| {
+                strip_html(&summary)
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|summary| !summary.is_empty());
+        if let Some(summary) = summary {
+            let summary = match summary.chars().count() > MAX_SUMMARY {
+                true => summary.chars().take(MAX_SUMMARY - 1).collect::<String>() + "…",
+                false => summary,
+            };
+            message = format!("{message}\n{summary}");
+        }
+        if let Some(published) = first(&["published", "pubDate", "updated"]) {
+            message = format!("{message}\n_{}_", dates::format(&published));
+        }
+
+        let guid = first(&["guid", "id"]).unwrap_or_else(|| link.clone());
+        Ok(NotificationRequest {
+            message: Some(message),
+            severity: Some("info".to_string()),
+            unfurl_links: Some(true),
+            unfurl_media: Some(true),
+            dedup_key: Some(format!("feed/{guid}")),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformers::webhook_settings;
+    use serde_json::json;
+
+    #[test]
+    fn test_render() {
+        let request = Feed
+            .render(
+                &json!({
+                    "title": "Release <2.0> | notes",
+                    "link": "https://blog.example.com/2-0",
+                    "summary": "<p>Faster   builds,</p><p>new <b>API</b>.</p>",
+                    "published": "2024-01-31T10:30:00Z",
+                    "guid": "tag:blog.example.com,2024:2-0",
+                }),
+                &webhook_settings(),
+            )
+            .unwrap();
+        assert_eq!(
+            request.message.as_deref(),
+            Some(
+                ":newspaper: *<https://blog.example.com/2-0|Release &lt;2.0&gt; - notes>*\n\
+                 Faster builds, new API.\n\
+                 _<!date^1706697000^{date_short} {time}|2024-01-31T10:30:00Z>_"
+            )
+        );
+        assert_eq!(request.unfurl_links, Some(true));
+        assert_eq!(
+            request.dedup_key.as_deref(),
+            Some("feed/tag:blog.example.com,2024:2-0")
+        );
+    }
+
+    #[test]
+    fn test_render_wrapped_item() {
+        let request = Feed
+            .render(
+                &json!({
+                    "feed": "Example blog",
+                    "item": { "title": "Hello", "link": "https://blog.example.com/hello" },
+                }),
+                &webhook_settings(),
+            )
+            .unwrap();
+        assert_eq!(
+            request.message.as_deref(),
+            Some(":newspaper: *<https://blog.example.com/hello|Hello>*")
+        );
+        assert_eq!(
+            request.dedup_key.as_deref(),
+            Some("feed/https://blog.example.com/hello")
+        );
+    }
+
+    #[test]
+    fn test_render_requires_link() {
+        let err = Feed
+            .render(
+                &json!({ "title": "Hello", "link": "javascript:alert(1)" }),
+                &webhook_settings(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid feed item, the 'link' must be an http(s) URL"
+        );
+    }
+}
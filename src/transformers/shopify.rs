@@ -0,0 +1,191 @@
+use anyhow::Result;
+use http::Request;
+use serde_json::Value;
+
+use super::{text, Transformer};
+use crate::helpers::ApiError;
+use crate::request::{MessageBody, NotificationRequest};
+use crate::{auth, signature, Settings};
+
+// Shopify order webhooks
+//
+// `orders/create` and `orders/paid` webhooks are signed with
+// `X-Shopify-Hmac-Sha256`, the base64 HMAC-SHA256 of the body keyed with the
+// `shopify_secret` setting, and posted as the order number, total and a line
+// per item. Customer details are left out of the message.
+
+pub struct Shopify;
+
+const TOPICS: [&str; 2] = ["orders/create", "orders/paid"];
+
+/// Lines shown before the rest are summed up.
+const MAX_ITEMS: usize = 10;
+
+fn line_item(item: &Value) -> String {
+    let title = text(item, "title").unwrap_or_else(|| "Item".to_string());
+    let quantity = text(item, "quantity").unwrap_or_else(|| "1".to_string());
+    match text(item, "variant_title") {
+        Some(variant) => format!("• {quantity} × {title} ({variant})"),
+        None => format!("• {quantity} × {title}"),
+    }
+}
+
+impl Transformer for Shopify {
+    fn source(&self) -> &'static str {
+        "shopify"
+    }
+
+    fn verify(&self, req: &Request<Option<MessageBody>>, settings: &Settings) -> Result<()> {
+        let Some(secret) = settings.shopify_secret.as_deref() else {
+            return Err(
+                ApiError::not_found("Shopify webhooks need the shopify_secret setting").into(),
+            );
+        };
+        let header = |name: &str| {
+            req.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+
+        // Signed over the bytes as sent, which JSON bodies keep
+        let signed = match (req.body(), header("x-shopify-hmac-sha256")) {
+            (Some(MessageBody(_, Some(raw), _)), Some(hmac)) => {
+                let expected = signature::base64(&signature::hmac_sha256(secret.as_bytes(), raw));
+                auth::constant_time_eq(expected.as_bytes(), hmac.trim().as_bytes())
+            }
+            _ => false,
+        };
+        if !signed {
+            return Err(ApiError::unauthorized("Invalid Shopify webhook signature").into());
+        }
+
+        match header("x-shopify-topic") {
+            Some(topic) if TOPICS.contains(&topic) => Ok(()),
+            topic => Err(ApiError::unprocessable(format!(
+                "Unsupported Shopify webhook topic '{}', orders/create and orders/paid are",
+                topic.unwrap_or_default()
+            ))
+            .into()),
+        }
+    }
+
+    fn render(&self, body: &Value, _settings: &Settings) -> Result<NotificationRequest> {
+        let order = text(body, "name")
+            .or_else(|| text(body, "order_number").map(|number| format!("#{number}")))
+            .ok_or_else(|| {
+                ApiError::bad_request("Invalid Shopify order, a 'name' or 'order_number' is needed")
+            })?;
+        let total = match (text(body, "total_price"), text(body, "currency")) {
+            (Some(total), Some(currency)) => format!("{total} {currency}"),
+            (Some(total), None) => total,
+            _ => "unknown total".to_string(),
+        };
+
+        // orders/paid sends the same order, paid
+        let mut message = match body["financial_status"].as_str() {
+            Some("paid") => format!(":moneybag: Order *{order}* paid: {total}"),
+            _ => format!(":shopping_trolley: New order *{order}*: {total}"),
+        };
+        let items = body["line_items"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for item in items.iter().take(MAX_ITEMS) {
+            message.push('\n');
+            message.push_str(&line_item(item));
+        }
+        if items.len() > MAX_ITEMS {
+            message.push_str(&format!("\n• and {} more", items.len() - MAX_ITEMS));
+        }
+
+        Ok(NotificationRequest {
+            message: Some(message),
+            severity: Some("info".to_string()),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use serde_json::json;
+
+    fn settings() -> Settings {
+        let data = std::collections::HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            ("shopify_secret".to_string(), "s3cret".to_string()),
+        ]);
+        Settings::from_map(&data).unwrap()
+    }
+
+    fn webhook(body: &str, hmac: &str, topic: &str) -> Request<Option<MessageBody>> {
+        testing::RequestFixture::post()
+            .header("X-Shopify-Hmac-Sha256", hmac)
+            .header("X-Shopify-Topic", topic)
+            .body("application/json", body.to_string())
+            .unwrap()
+            .build()
+    }
+
+    #[test]
+    fn test_verify() {
+        let body = r##"{"name": "#1001"}"##;
+        let hmac = signature::base64(&signature::hmac_sha256(b"s3cret", body.as_bytes()));
+        assert!(Shopify
+            .verify(&webhook(body, &hmac, "orders/paid"), &settings())
+            .is_ok());
+
+        let err = Shopify
+            .verify(&webhook(body, "Zm9v", "orders/paid"), &settings())
+            .unwrap_err();
+        let err = err.downcast_ref::<ApiError>().unwrap();
+        assert_eq!(err.status, http::StatusCode::UNAUTHORIZED);
+
+        let err = Shopify
+            .verify(&webhook(body, &hmac, "products/create"), &settings())
+            .unwrap_err();
+        let err = err.downcast_ref::<ApiError>().unwrap();
+        assert_eq!(err.status, http::StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[test]
+    fn test_render() {
+        let mut items = vec![json!({ "title": "T-shirt", "variant_title": "M", "quantity": 2 })];
+        items.extend(vec![json!({ "title": "Mug", "quantity": 1 }); 11]);
+        let request = Shopify
+            .render(
+                &json!({
+                    "name": "#1001",
+                    "total_price": "59.00",
+                    "currency": "EUR",
+                    "financial_status": "pending",
+                    "line_items": items,
+                    "customer": { "email": "jane@example.com" },
+                }),
+                &settings(),
+            )
+            .unwrap();
+        let message = request.message.unwrap();
+        assert!(message.starts_with(
+            ":shopping_trolley: New order *#1001*: 59.00 EUR\n• 2 × T-shirt (M)\n• 1 × Mug\n"
+        ));
+        assert!(message.ends_with("• 1 × Mug\n• and 2 more"));
+        assert!(!message.contains("jane"));
+    }
+
+    #[test]
+    fn test_render_paid() {
+        let request = Shopify
+            .render(&json!({ "order_number": 1002, "total_price": "12.50", "financial_status": "paid" }), &settings())
+            .unwrap();
+        assert_eq!(
+            request.message.as_deref(),
+            Some(":moneybag: Order *#1002* paid: 12.50")
+        );
+    }
+}
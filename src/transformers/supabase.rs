@@ -0,0 +1,188 @@
+use anyhow::Result;
+use serde_json::{Map, Value};
+
+use super::{text, Transformer};
+use crate::helpers::ApiError;
+use crate::request::NotificationRequest;
+use crate::Settings;
+
+// Database change events
+//
+// Supabase database webhooks post the `type` of change (`INSERT`, `UPDATE`
+// or `DELETE`), the `schema` and `table`, and the `record` and
+// `old_record` rows. Other Postgres change feeds sending `operation`, `new`
+// and `old` are read the same way. The message lists the columns added,
+// changed or removed as a diff. Rows may hold secrets, only the columns
+// listed in the `change_columns` setting, as `column` or `table.column`,
+// have their values shown, other changed columns being named only.
+
+pub struct Supabase;
+
+// Values are cut to keep one line per column
+const MAX_VALUE: usize = 80;
+
+fn value(value: &Value) -> String {
+    let text = match value {
+        Value::String(text) => text.replace(['\n', '\r'], " "),
+        value => value.to_string(),
+    }
+    .replace("```", "'''");
+    match text.chars().count() > MAX_VALUE {
+        true => text.chars().take(MAX_VALUE - 1).collect::<String>() + "…",
+        false => text,
+    }
+}
+
+fn row<'a>(body: &'a Value, keys: &[&str]) -> Option<&'a Map<String, Value>> {
+    keys.iter().find_map(|key| body[key].as_object())
+}
+
+impl Transformer for Supabase {
+    fn source(&self) -> &'static str {
+        "supabase"
+    }
+
+    fn render(&self, body: &Value, settings: &Settings) -> Result<NotificationRequest> {
+        let operation = text(body, "type")
+            .or_else(|| text(body, "operation"))
+            .map(|operation| operation.to_ascii_uppercase());
+        let (Some(operation), Some(table)) = (operation, text(body, "table")) else {
+            return Err(ApiError::bad_request(
+                "Invalid change event, a 'table' and a 'type' are needed",
+            )
+            .into());
+        };
+        let new = row(body, &["record", "new"]);
+        let old = row(body, &["old_record", "old"]);
+        let (emoji, rows) = match operation.as_str() {
+            "INSERT" => (":heavy_plus_sign:", (None, new)),
+            "UPDATE" => (":pencil2:", (old, new)),
+            "DELETE" => (":wastebasket:", (old, None)),
+            _ => {
+                return Err(ApiError::bad_request(format!(
+                    "Invalid change event type '{operation}', INSERT, UPDATE or DELETE is needed"
+                ))
+                .into())
+            }
+        };
+
+        let allowed = |column: &str| {
+            settings
+                .change_columns
+                .iter()
+                .any(|allowed| allowed == column || *allowed == format!("{table}.{column}"))
+        };
+        let empty = Map::new();
+        let (old, new) = (rows.0.unwrap_or(&empty), rows.1.unwrap_or(&empty));
+        let mut columns: Vec<&String> = old.keys().chain(new.keys()).collect();
+        columns.sort();
+        columns.dedup();
+
+        let mut lines = Vec::new();
+        let mut hidden = Vec::new();
+        for column in columns {
+            let (before, after) = (old.get(column), new.get(column));
+            if before.is_some() && after.is_some() && before == after {
+                continue;
+            }
+            if !allowed(column) {
+                hidden.push(column.as_str());
+                continue;
+            }
+            if let Some(before) = before {
+                lines.push(format!("- {column}: {}", value(before)));
+            }
+            if let Some(after) = after {
+                lines.push(format!("+ {column}: {}", value(after)));
+            }
+        }
+
+        let name = match text(body, "schema") {
+            Some(schema) => format!("{schema}.{table}"),
+            None => table,
+        };
+        let mut message = format!("{emoji} *{operation}* on `{name}`");
+        if !lines.is_empty() {
+            message = format!("{message}\n```\n{}\n```", lines.join("\n"));
+        }
+        if !hidden.is_empty() {
+            message = format!("{message}\nAlso changed: {}", hidden.join(", "));
+        }
+
+        Ok(NotificationRequest {
+            message: Some(message),
+            severity: Some("info".to_string()),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn settings() -> Settings {
+        let data = HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            ("change_columns".to_string(), "id,users.plan".to_string()),
+        ]);
+        Settings::from_map(&data).unwrap()
+    }
+
+    #[test]
+    fn test_render_update() {
+        let request = Supabase
+            .render(
+                &json!({
+                    "type": "UPDATE",
+                    "schema": "public",
+                    "table": "users",
+                    "record": { "id": 7, "plan": "pro", "email": "new@example.com", "name": "Jane" },
+                    "old_record": { "id": 7, "plan": "free", "email": "old@example.com", "name": "Jane" },
+                }),
+                &settings(),
+            )
+            .unwrap();
+        assert_eq!(
+            request.message.as_deref(),
+            Some(
+                ":pencil2: *UPDATE* on `public.users`\n\
+                 ```\n- plan: free\n+ plan: pro\n```\n\
+                 Also changed: email"
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_insert() {
+        let request = Supabase
+            .render(
+                &json!({ "operation": "insert", "table": "teams", "new": { "id": 3, "plan": "pro" } }),
+                &settings(),
+            )
+            .unwrap();
+        assert_eq!(
+            request.message.as_deref(),
+            Some(":heavy_plus_sign: *INSERT* on `teams`\n```\n+ id: 3\n```\nAlso changed: plan")
+        );
+    }
+
+    #[test]
+    fn test_render_invalid_type() {
+        let err = Supabase
+            .render(
+                &json!({ "type": "TRUNCATE", "table": "users" }),
+                &settings(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid change event type 'TRUNCATE', INSERT, UPDATE or DELETE is needed"
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use anyhow::Result;
+use http::Request;
+use serde_json::Value;
+
+use super::{text, Transformer};
+use crate::helpers::ApiError;
+use crate::request::{MessageBody, NotificationRequest};
+use crate::{auth, signature, Settings};
+
+// Twilio inbound SMS webhooks
+//
+// Twilio posts a form with `From`, `To` and `Body`, signed with
+// `X-Twilio-Signature`: the base64 HMAC-SHA1, keyed with the
+// `twilio_auth_token` setting, of the URL Twilio called followed by every
+// parameter name and value, sorted by name. The URL is rebuilt from the
+// `Host` header and the request path, over https.
+
+pub struct Twilio;
+
+fn signed_url(req: &Request<Option<MessageBody>>) -> Option<String> {
+    let host = match req.headers().get(http::header::HOST) {
+        Some(host) => host.to_str().ok()?,
+        None => req.uri().authority()?.as_str(),
+    };
+    let path = req.uri().path_and_query().map_or("/", |path| path.as_str());
+    Some(format!("https://{host}{path}"))
+}
+
+impl Transformer for Twilio {
+    fn source(&self) -> &'static str {
+        "twilio"
+    }
+
+    fn verify(&self, req: &Request<Option<MessageBody>>, settings: &Settings) -> Result<()> {
+        let Some(token) = settings.twilio_auth_token.as_deref() else {
+            return Err(
+                ApiError::not_found("Twilio webhooks need the twilio_auth_token setting").into(),
+            );
+        };
+        let header = req
+            .headers()
+            .get("x-twilio-signature")
+            .and_then(|value| value.to_str().ok());
+
+        // Form fields come parsed, in name order
+        let signed = match (req.body(), header, signed_url(req)) {
+            (Some(MessageBody(Value::Object(params), ..)), Some(header), Some(mut base)) => {
+                for (name, value) in params {
+                    base.push_str(name);
+                    base.push_str(value.as_str().unwrap_or_default());
+                }
+                let expected =
+                    signature::base64(&signature::hmac_sha1(token.as_bytes(), base.as_bytes()));
+                auth::constant_time_eq(expected.as_bytes(), header.trim().as_bytes())
+            }
+            _ => false,
+        };
+        match signed {
+            true => Ok(()),
+            false => Err(ApiError::unauthorized("Invalid Twilio webhook signature").into()),
+        }
+    }
+
+    fn render(&self, body: &Value, _settings: &Settings) -> Result<NotificationRequest> {
+        let (Some(from), Some(sms)) = (text(body, "From"), text(body, "Body")) else {
+            return Err(ApiError::bad_request(
+                "Invalid Twilio webhook, 'From' and 'Body' fields are needed",
+            )
+            .into());
+        };
+        let quoted: Vec<String> = sms.lines().map(|line| format!(">{line}")).collect();
+        let message = match text(body, "To") {
+            Some(to) => format!(":speech_balloon: SMS from {from} to {to}:"),
+            None => format!(":speech_balloon: SMS from {from}:"),
+        };
+
+        Ok(NotificationRequest {
+            message: Some(format!("{message}\n{}", quoted.join("\n"))),
+            severity: Some("info".to_string()),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn settings() -> Settings {
+        let data = std::collections::HashMap::from([
+            (
+                "webhook_url".to_string(),
+                "https://hooks.slack.com/services/test".to_string(),
+            ),
+            ("twilio_auth_token".to_string(), "12345".to_string()),
+        ]);
+        Settings::from_map(&data).unwrap()
+    }
+
+    fn webhook(signature: &str) -> Request<Option<MessageBody>> {
+        testing::RequestFixture::new(http::Method::POST, "/transform/twilio?x=1")
+            .header("Host", "edge.example.com")
+            .header("X-Twilio-Signature", signature)
+            .body(
+                "application/x-www-form-urlencoded",
+                "To=%2B15550002&From=%2B15550001&Body=Code+42",
+            )
+            .unwrap()
+            .build()
+    }
+
+    #[test]
+    fn test_verify() {
+        let base = "https://edge.example.com/transform/twilio?x=1\
+                    BodyCode 42From+15550001To+15550002";
+        let signature = signature::base64(&signature::hmac_sha1(b"12345", base.as_bytes()));
+        assert!(Twilio.verify(&webhook(&signature), &settings()).is_ok());
+
+        let err = Twilio.verify(&webhook("Zm9v"), &settings()).unwrap_err();
+        let err = err.downcast_ref::<ApiError>().unwrap();
+        assert_eq!(err.status, http::StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_render() {
+        let request = Twilio
+            .render(
+                &serde_json::json!({ "From": "+15550001", "Body": "Disk full\nOn db-1" }),
+                &settings(),
+            )
+            .unwrap();
+        assert_eq!(
+            request.message.as_deref(),
+            Some(":speech_balloon: SMS from +15550001:\n>Disk full\n>On db-1")
+        );
+    }
+}
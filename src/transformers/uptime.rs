@@ -0,0 +1,165 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use super::{text, Transformer};
+use crate::helpers::ApiError;
+use crate::request::{NotificationRequest, RawField};
+use crate::Settings;
+
+// Uptime monitor webhooks
+//
+// UptimeRobot sends `monitorFriendlyName`, `monitorURL`, `alertType` (1 for
+// down, 2 for up), `alertDetails` and `alertDuration` in seconds, as JSON or
+// a form. Healthcheck services sending `monitor`, `url`, `status` (`up` or
+// `down`), `details` and `duration` are handled the same way.
+
+pub struct Uptime;
+
+// First of the fields a payload may name a value with
+fn first(body: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|key| text(body, key))
+}
+
+fn is_up(body: &Value) -> Option<bool> {
+    match first(body, &["alertType", "status", "alertTypeFriendlyName"])?
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "2" | "up" => Some(true),
+        "1" | "down" => Some(false),
+        _ => None,
+    }
+}
+
+/// `seconds` in its two largest units, e.g. "2h 5m".
+fn format_duration(seconds: u64) -> String {
+    let units = [("d", 86400), ("h", 3600), ("m", 60), ("s", 1)];
+    let mut rest = seconds;
+    let parts: Vec<String> = units
+        .iter()
+        .filter_map(|(unit, size)| {
+            let count = rest / size;
+            rest %= size;
+            (count > 0).then(|| format!("{count}{unit}"))
+        })
+        .take(2)
+        .collect();
+    match parts.is_empty() {
+        true => "0s".to_string(),
+        false => parts.join(" "),
+    }
+}
+
+impl Transformer for Uptime {
+    fn source(&self) -> &'static str {
+        "uptimerobot"
+    }
+
+    fn render(&self, body: &Value, _settings: &Settings) -> Result<NotificationRequest> {
+        let invalid = || {
+            ApiError::bad_request(
+                "Invalid uptime payload, a monitor name and an up or down alert type are needed",
+            )
+        };
+        let name = first(body, &["monitorFriendlyName", "monitor", "name"]).ok_or_else(invalid)?;
+        let up = is_up(body).ok_or_else(invalid)?;
+        let url = first(body, &["monitorURL", "url"]);
+        let details = first(body, &["alertDetails", "details"]);
+        let duration = first(body, &["alertDuration", "duration"])
+            .and_then(|duration| duration.parse::<u64>().ok())
+            .map(format_duration);
+
+        let monitor = match &url {
+            Some(url) => format!("<{url}|{name}>"),
+            None => name,
+        };
+        let (message, severity, color) = match (up, duration) {
+            (true, Some(duration)) => (
+                format!("{monitor} is UP, after {duration} of downtime"),
+                "info",
+                "good",
+            ),
+            (true, None) => (format!("{monitor} is UP"), "info", "good"),
+            (false, _) => (format!("{monitor} is DOWN"), "error", "danger"),
+        };
+        // The colored bar holds the details, the message already saying the rest
+        let attachments = details
+            .map(|details| RawField::from_value(&json!([{ "color": color, "text": details }])))
+            .transpose()?;
+
+        Ok(NotificationRequest {
+            message: Some(message),
+            severity: Some(severity.to_string()),
+            attachments,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformers::webhook_settings;
+
+    #[test]
+    fn test_render_down() {
+        let request = Uptime
+            .render(
+                &json!({
+                    "monitorFriendlyName": "API",
+                    "monitorURL": "https://api.example.com",
+                    "alertType": "1",
+                    "alertDetails": "Connection Timeout",
+                }),
+                &webhook_settings(),
+            )
+            .unwrap();
+        assert_eq!(
+            request.message.as_deref(),
+            Some("<https://api.example.com|API> is DOWN")
+        );
+        assert_eq!(request.severity.as_deref(), Some("error"));
+        assert_eq!(
+            request.attachments.unwrap().to_value().unwrap(),
+            json!([{ "color": "danger", "text": "Connection Timeout" }])
+        );
+    }
+
+    #[test]
+    fn test_render_up() {
+        let request = Uptime
+            .render(
+                &json!({ "monitor": "Checkout", "status": "UP", "duration": 7530 }),
+                &webhook_settings(),
+            )
+            .unwrap();
+        assert_eq!(
+            request.message.as_deref(),
+            Some("Checkout is UP, after 2h 5m of downtime")
+        );
+        assert_eq!(request.severity.as_deref(), Some("info"));
+        assert!(request.attachments.is_none());
+    }
+
+    #[test]
+    fn test_render_requires_state() {
+        let err = Uptime
+            .render(
+                &json!({ "monitor": "Checkout", "alertType": "3" }),
+                &webhook_settings(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid uptime payload, a monitor name and an up or down alert type are needed"
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(45), "45s");
+        assert_eq!(format_duration(3605), "1h 5s");
+        assert_eq!(format_duration(90061), "1d 1h");
+    }
+}
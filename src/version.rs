@@ -0,0 +1,57 @@
+use anyhow::Result;
+
+// API versioning by path prefix
+//
+// `/v1/...` requests are pinned to a version, unprefixed ones follow the
+// `default_api_version` setting. A version's behavior is frozen once
+// released, request schema changes go to the next one.
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiVersion {
+    #[default]
+    V1,
+}
+
+impl ApiVersion {
+    /// Version named by the first path segment, `None` for unprefixed paths.
+    pub fn from_path(path: &str) -> Option<Result<Self>> {
+        let segment = path.trim_start_matches('/').split('/').next()?;
+        let number = segment.strip_prefix('v')?;
+        if number.is_empty() || !number.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+
+        Some(match number {
+            "1" => Ok(Self::V1),
+            _ => Err(anyhow::anyhow!("Unsupported API version {segment}")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(
+            ApiVersion::from_path("/v1/edgee/slack").unwrap().unwrap(),
+            ApiVersion::V1
+        );
+        assert_eq!(
+            ApiVersion::from_path("/v1").unwrap().unwrap(),
+            ApiVersion::V1
+        );
+        assert_eq!(
+            ApiVersion::from_path("/v2/edgee/slack")
+                .unwrap()
+                .unwrap_err()
+                .to_string(),
+            "Unsupported API version v2"
+        );
+        assert!(ApiVersion::from_path("/edgee/slack").is_none());
+        assert!(ApiVersion::from_path("/videos").is_none());
+        assert!(ApiVersion::from_path("/").is_none());
+    }
+}